@@ -0,0 +1,80 @@
+//! Benchmarks for the interpreter's hot paths, run with `cargo bench`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use flake::{
+    chip8::{Chip8, OpCodes},
+    platform::VirtualClock,
+};
+use std::{hint::black_box, sync::Arc, time::Duration};
+
+const BREAKOUT: &[u8] = include_bytes!("../roms/breakout.ch8");
+
+fn chip(program: &[u16]) -> Chip8 {
+    let mut chip = Chip8::with_clock(Arc::new(VirtualClock::default()));
+    chip.trace = false;
+    let rom: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    chip.load_bytes(&rom).unwrap();
+    chip
+}
+
+fn decode(c: &mut Criterion) {
+    c.bench_function("decode every word", |b| {
+        b.iter(|| {
+            for word in 0..=u16::MAX {
+                black_box(OpCodes::try_from(black_box(word)).unwrap());
+            }
+        })
+    });
+}
+
+fn tick(c: &mut Criterion) {
+    // A loop of the register and skip instructions most programs are made of
+    let mut chip = chip(&[
+        0x6005, 0x7101, 0x8014, 0x8125, 0x3100, 0xA300, 0xF11E, 0x1200,
+    ]);
+    c.bench_function("tick", |b| b.iter(|| chip.tick()));
+}
+
+fn draw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("draw");
+    // Sprites crossing the bottom right corner, to include clipping
+    let mut chip = chip(&[0xD01F]);
+    chip.set_v(0, 60);
+    chip.set_v(1, 20);
+    group.bench_function("8x15", |b| {
+        b.iter(|| {
+            chip.set_pc(0x200);
+            chip.tick();
+        })
+    });
+    let mut chip = self::chip(&[0x00FF, 0xD010]);
+    chip.tick();
+    chip.set_v(0, 120);
+    chip.set_v(1, 56);
+    group.bench_function("16x16 hires", |b| {
+        b.iter(|| {
+            chip.set_pc(0x202);
+            chip.tick();
+        })
+    });
+    group.finish();
+}
+
+/// As much emulated time as `step_with_time` catches up in one go, at a speed
+/// like the debugger's turbo mode.
+fn step_with_time(c: &mut Criterion) {
+    let clock = Arc::new(VirtualClock::default());
+    let mut chip = Chip8::with_clock(clock.clone());
+    chip.trace = false;
+    chip.load_bytes(BREAKOUT).unwrap();
+    chip.instructions_per_frame = 1000;
+    c.bench_function("step_with_time turbo 100ms", |b| {
+        b.iter(|| {
+            clock.advance(Duration::from_millis(100));
+            chip.step_with_time();
+        })
+    });
+}
+
+criterion_group!(benches, decode, tick, draw, step_with_time);
+criterion_main!(benches);
@@ -0,0 +1,21 @@
+#![no_main]
+
+use flake::{asm, chip8::OpCodes, disasm, symbols::Symbols};
+use libfuzzer_sys::fuzz_target;
+
+// Treats the input as a ROM: every word has to survive encoding and a trip
+// through the disassembler and assembler, and the whole ROM has to disassemble.
+fuzz_target!(|rom: &[u8]| {
+    let symbols = Symbols::default();
+    for pair in rom.chunks_exact(2) {
+        let word = u16::from_be_bytes([pair[0], pair[1]]);
+        let op = OpCodes::try_from(word).unwrap();
+        if !matches!(op, OpCodes::Unkn(_)) {
+            assert_eq!(u16::from(op), word, "{:04X} decoded as {:?}", word, op);
+        }
+        let text = disasm::mnemonic(op, &symbols);
+        let assembled = asm::assemble(&text).unwrap();
+        assert_eq!(assembled.rom, pair, "{:04X} as '{}'", word, text);
+    }
+    disasm::listing(rom, &symbols);
+});
@@ -0,0 +1,466 @@
+//! The libretro API, so the core can run in RetroArch and other libretro
+//! frontends. The frontend paces frames, so each `retro_run` is one 60Hz frame of
+//! emulated time.
+
+use flake::{
+    buzzer::{Buzzer, Tone, Volume, Waveform},
+    chip8::{Chip8, DEFAULT_INSTRUCTIONS_PER_FRAME},
+    palette::Palette,
+    platform::VirtualClock,
+    romdb,
+};
+use std::{
+    ffi::{c_char, c_uint, c_void, CStr},
+    ptr,
+    sync::{Arc, Mutex},
+};
+
+const RETRO_API_VERSION: c_uint = 1;
+const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+const RETRO_ENVIRONMENT_GET_VARIABLE: c_uint = 15;
+const RETRO_ENVIRONMENT_SET_VARIABLES: c_uint = 16;
+const RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE: c_uint = 17;
+const RETRO_PIXEL_FORMAT_XRGB8888: c_uint = 1;
+const RETRO_DEVICE_JOYPAD: c_uint = 1;
+const RETRO_DEVICE_KEYBOARD: c_uint = 3;
+const RETRO_REGION_NTSC: c_uint = 0;
+
+const SAMPLE_RATE: f64 = 44100.;
+const SAMPLES_PER_FRAME: usize = 735;
+const VOLUME: f32 = 4000.;
+
+/// Core options for the frontend's menu, as a key and then a description and the
+/// values, the first being the default.
+const VARIABLES: [(&CStr, &CStr); 5] = [
+    (c"flake_waveform", c"Buzzer waveform; square|sine|triangle"),
+    (
+        c"flake_tone",
+        c"Buzzer frequency (Hz); 4000|2000|1000|500|440",
+    ),
+    (c"flake_duty", c"Buzzer duty cycle (%); 50|25|12.5|75"),
+    (
+        c"flake_volume",
+        c"Buzzer volume (%); 100|90|80|70|60|50|40|30|20|10",
+    ),
+    (c"flake_mute", c"Mute buzzer; off|on"),
+];
+
+/// The CHIP-8 key for each RetroPad button, by button ID (B, Y, Select, Start,
+/// Up, Down, Left, Right, A, X, L, R, L2, R2, L3, R3). The D-pad and A cover the
+/// 2/4/6/8 and 5 most games use.
+const JOYPAD_KEYS: [usize; 16] = [
+    0xA, 0x7, 0x0, 0xF, 0x2, 0x8, 0x4, 0x6, 0x5, 0x9, 0x1, 0x3, 0xC, 0xD, 0xB, 0xE,
+];
+
+/// The keyboard layout the standalone frontend uses, as libretro key codes, in
+/// CHIP-8 key order.
+const KEYBOARD_KEYS: [u8; 16] = [
+    b'x', b'1', b'2', b'3', b'q', b'w', b'e', b'a', b's', b'd', b'z', b'c', b'4', b'r', b'f', b'v',
+];
+
+#[repr(C)]
+pub struct RetroSystemInfo {
+    library_name: *const c_char,
+    library_version: *const c_char,
+    valid_extensions: *const c_char,
+    need_fullpath: bool,
+    block_extract: bool,
+}
+
+#[repr(C)]
+pub struct RetroGameGeometry {
+    base_width: c_uint,
+    base_height: c_uint,
+    max_width: c_uint,
+    max_height: c_uint,
+    aspect_ratio: f32,
+}
+
+#[repr(C)]
+pub struct RetroSystemTiming {
+    fps: f64,
+    sample_rate: f64,
+}
+
+#[repr(C)]
+pub struct RetroSystemAvInfo {
+    geometry: RetroGameGeometry,
+    timing: RetroSystemTiming,
+}
+
+#[repr(C)]
+pub struct RetroVariable {
+    key: *const c_char,
+    value: *const c_char,
+}
+
+#[repr(C)]
+pub struct RetroGameInfo {
+    path: *const c_char,
+    data: *const c_void,
+    size: usize,
+    meta: *const c_char,
+}
+
+type EnvironmentFn = extern "C" fn(cmd: c_uint, data: *mut c_void) -> bool;
+type VideoRefreshFn =
+    extern "C" fn(data: *const c_void, width: c_uint, height: c_uint, pitch: usize);
+type AudioSampleFn = extern "C" fn(left: i16, right: i16);
+type AudioSampleBatchFn = extern "C" fn(data: *const i16, frames: usize) -> usize;
+type InputPollFn = extern "C" fn();
+type InputStateFn = extern "C" fn(port: c_uint, device: c_uint, index: c_uint, id: c_uint) -> i16;
+
+struct Callbacks {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+
+struct Core {
+    chip: Chip8,
+    palette: [u32; 4],
+    frame: Vec<u32>,
+    buzzer: Buzzer,
+    audio: Vec<i16>,
+}
+
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+impl Core {
+    fn new(chip: Chip8, buzzer: Buzzer) -> Core {
+        let palette = Palette::default().0.map(|color| {
+            let [r, g, b, _] = color
+                .to_array()
+                .map(|c| (c.clamp(0., 1.) * 255.).round() as u32);
+            (r << 16) | (g << 8) | b
+        });
+        Core {
+            chip,
+            palette,
+            frame: vec![],
+            buzzer,
+            audio: vec![0; SAMPLES_PER_FRAME * 2],
+        }
+    }
+
+    fn read_keys(&mut self, input_state: InputStateFn) {
+        self.chip.keys = [false; 16];
+        for (id, &key) in JOYPAD_KEYS.iter().enumerate() {
+            if input_state(0, RETRO_DEVICE_JOYPAD, 0, id as c_uint) != 0 {
+                self.chip.keys[key] = true;
+            }
+        }
+        for (key, &code) in KEYBOARD_KEYS.iter().enumerate() {
+            if input_state(0, RETRO_DEVICE_KEYBOARD, 0, code as c_uint) != 0 {
+                self.chip.keys[key] = true;
+            }
+        }
+    }
+
+    fn render(&mut self) -> (usize, usize) {
+        let display = &self.chip.display;
+        let (width, height) = display.size();
+        self.frame.clear();
+        self.frame.extend(
+            display
+                .pixels()
+                .iter()
+                .map(|&pixel| self.palette[pixel as usize & 3]),
+        );
+        (width, height)
+    }
+
+    /// Fills `audio` with a frame of the buzzer, silent while it's off.
+    fn mix(&mut self) {
+        for frame in self.audio.chunks_mut(2) {
+            let sample = self.buzzer.next_sample(&self.chip, SAMPLE_RATE as f32);
+            frame.fill((sample * VOLUME) as i16);
+        }
+    }
+}
+
+/// The buzzer's tone and volume as set in the core options.
+fn buzzer(environment: EnvironmentFn) -> Buzzer {
+    let mut buzzer = Buzzer::new(tone(environment));
+    buzzer.volume = volume(environment);
+    buzzer
+}
+
+/// The value the frontend has for the core option `key`.
+fn variable(environment: EnvironmentFn, key: &CStr) -> Option<String> {
+    let mut variable = RetroVariable {
+        key: key.as_ptr(),
+        value: ptr::null(),
+    };
+    if !environment(
+        RETRO_ENVIRONMENT_GET_VARIABLE,
+        ptr::addr_of_mut!(variable).cast(),
+    ) || variable.value.is_null()
+    {
+        return None;
+    }
+    // SAFETY: the frontend hands back a string it keeps until the next call
+    let value = unsafe { CStr::from_ptr(variable.value) };
+    value.to_str().ok().map(str::to_string)
+}
+
+/// The buzzer's tone as set in the core options, with the defaults for any that
+/// aren't.
+fn tone(environment: EnvironmentFn) -> Tone {
+    let default = Tone::default();
+    Tone {
+        waveform: variable(environment, c"flake_waveform")
+            .and_then(|value| Waveform::parse(&value).ok())
+            .unwrap_or(default.waveform),
+        frequency: variable(environment, c"flake_tone")
+            .and_then(|value| Tone::parse_frequency(&value).ok())
+            .unwrap_or(default.frequency),
+        duty: variable(environment, c"flake_duty")
+            .and_then(|value| value.parse::<f32>().ok())
+            .map_or(default.duty, |percent| percent / 100.),
+    }
+}
+
+fn volume(environment: EnvironmentFn) -> Volume {
+    let default = Volume::default();
+    Volume {
+        level: variable(environment, c"flake_volume")
+            .and_then(|value| value.parse::<f32>().ok())
+            .map_or(default.level, |percent| percent / 100.),
+        muted: variable(environment, c"flake_mute").is_some_and(|value| value == "on"),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+/// # Safety
+/// `info` must point to a `retro_system_info` the frontend owns.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
+    *info = RetroSystemInfo {
+        library_name: c"flake".as_ptr(),
+        library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast(),
+        valid_extensions: c"ch8|sc8|xo8|8o".as_ptr(),
+        // Octo sources are compiled from the file, and flags are saved next to it
+        need_fullpath: true,
+        block_extract: false,
+    };
+}
+
+/// # Safety
+/// `info` must point to a `retro_system_av_info` the frontend owns.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo) {
+    *info = RetroSystemAvInfo {
+        geometry: RetroGameGeometry {
+            base_width: 64,
+            base_height: 32,
+            max_width: 128,
+            max_height: 64,
+            aspect_ratio: 2.,
+        },
+        timing: RetroSystemTiming {
+            fps: 60.,
+            sample_rate: SAMPLE_RATE,
+        },
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+    CALLBACKS.lock().unwrap().environment = Some(callback);
+    let mut variables: Vec<RetroVariable> = VARIABLES
+        .iter()
+        .map(|(key, value)| RetroVariable {
+            key: key.as_ptr(),
+            value: value.as_ptr(),
+        })
+        .collect();
+    variables.push(RetroVariable {
+        key: ptr::null(),
+        value: ptr::null(),
+    });
+    callback(
+        RETRO_ENVIRONMENT_SET_VARIABLES,
+        variables.as_mut_ptr().cast(),
+    );
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+    CALLBACKS.lock().unwrap().video_refresh = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_callback: AudioSampleFn) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    CALLBACKS.lock().unwrap().audio_sample_batch = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+    CALLBACKS.lock().unwrap().input_poll = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+    CALLBACKS.lock().unwrap().input_state = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *CORE.lock().unwrap() = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    if let Some(core) = CORE.lock().unwrap().as_mut() {
+        core.chip.reset();
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let callbacks = CALLBACKS.lock().unwrap();
+    let mut core = CORE.lock().unwrap();
+    let Some(core) = core.as_mut() else {
+        return;
+    };
+    if let (Some(input_poll), Some(input_state)) = (callbacks.input_poll, callbacks.input_state) {
+        input_poll();
+        core.read_keys(input_state);
+    }
+    if let Some(environment) = callbacks.environment {
+        let mut updated = false;
+        environment(
+            RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE,
+            ptr::addr_of_mut!(updated).cast(),
+        );
+        if updated {
+            core.buzzer.tone = tone(environment);
+            core.buzzer.volume = volume(environment);
+        }
+    }
+    core.chip.step_frame();
+
+    let (width, height) = core.render();
+    if let Some(video_refresh) = callbacks.video_refresh {
+        video_refresh(
+            core.frame.as_ptr().cast(),
+            width as c_uint,
+            height as c_uint,
+            width * 4,
+        );
+    }
+    core.mix();
+    if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+        audio_sample_batch(core.audio.as_ptr(), SAMPLES_PER_FRAME);
+    }
+}
+
+/// # Safety
+/// `game` must be null or point to a `retro_game_info` with a valid `path`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool {
+    let callbacks = CALLBACKS.lock().unwrap();
+    let Some(environment) = callbacks.environment else {
+        return false;
+    };
+    let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+    if !environment(
+        RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+        ptr::addr_of_mut!(format).cast(),
+    ) {
+        eprintln!("flake: the frontend doesn't support XRGB8888");
+        return false;
+    }
+    if game.is_null() || (*game).path.is_null() {
+        return false;
+    }
+    let Ok(path) = CStr::from_ptr((*game).path).to_str() else {
+        return false;
+    };
+    // Frames come from the frontend, so the host's clock has nothing to say
+    let mut chip = Chip8::with_clock(Arc::new(VirtualClock::default()));
+    if let Err(e) = chip.load(path) {
+        eprintln!("flake: failed to load {}: {}", path, e);
+        return false;
+    }
+    chip.instructions_per_frame =
+        romdb::apply_profile(&mut chip, true, DEFAULT_INSTRUCTIONS_PER_FRAME).0;
+    // Frontends log to the terminal too, which tracing every instruction would bury
+    chip.trace = false;
+    *CORE.lock().unwrap() = Some(Core::new(chip, buzzer(environment)));
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const RetroGameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    *CORE.lock().unwrap() = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize(_data: *mut c_void, _size: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unserialize(_data: *const c_void, _size: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+/// Brightness, contrast and gamma the display shader applies to the palette's
+/// colors, kept in the preferences. Screenshots, recordings and spectators get the
+/// palette's colors unadjusted.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Adjustments {
+    /// Added to each channel.
+    pub brightness: f32,
+    /// How far each channel is pushed from mid gray.
+    pub contrast: f32,
+    /// Channels are raised to its inverse, so above 1 lightens the darker colors.
+    pub gamma: f32,
+}
+
+impl Default for Adjustments {
+    fn default() -> Self {
+        Adjustments {
+            brightness: 0.,
+            contrast: 1.,
+            gamma: 1.,
+        }
+    }
+}
+
+/// Which of the adjustments the keys turn up and down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Setting {
+    #[default]
+    Brightness,
+    Contrast,
+    Gamma,
+}
+
+impl Setting {
+    pub fn next(self) -> Setting {
+        match self {
+            Setting::Brightness => Setting::Contrast,
+            Setting::Contrast => Setting::Gamma,
+            Setting::Gamma => Setting::Brightness,
+        }
+    }
+
+    /// The range it's kept in and how much a press changes it by.
+    fn range(self) -> (f32, f32, f32) {
+        match self {
+            Setting::Brightness => (-0.5, 0.5, 0.05),
+            Setting::Contrast => (0.25, 2., 0.1),
+            Setting::Gamma => (0.5, 2.5, 0.1),
+        }
+    }
+}
+
+impl Adjustments {
+    fn value_mut(&mut self, setting: Setting) -> &mut f32 {
+        match setting {
+            Setting::Brightness => &mut self.brightness,
+            Setting::Contrast => &mut self.contrast,
+            Setting::Gamma => &mut self.gamma,
+        }
+    }
+
+    /// Turns `setting` up or down a step, returning a message with how it's set.
+    pub fn adjust(&mut self, setting: Setting, up: bool) -> String {
+        let (min, max, step) = setting.range();
+        let value = self.value_mut(setting);
+        let stepped = if up { *value + step } else { *value - step };
+        // Rounded to the step, so repeated presses don't drift
+        *value = ((stepped / step).round() * step).clamp(min, max);
+        self.describe(setting)
+    }
+
+    pub fn describe(&self, setting: Setting) -> String {
+        let value = match setting {
+            Setting::Brightness => self.brightness,
+            Setting::Contrast => self.contrast,
+            Setting::Gamma => self.gamma,
+        };
+        format!("{:?}: {:.2}", setting, value)
+    }
+}
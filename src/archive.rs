@@ -0,0 +1,48 @@
+//! ROMs read straight out of `.zip` and `.gz` archives, since collections are
+//! usually distributed zipped.
+
+use flate2::read::GzDecoder;
+use std::{
+    io::{self, Cursor, Read},
+    path::Path,
+};
+use zip::ZipArchive;
+
+/// Extensions of the ROMs looked for inside zip archives.
+const ROM_EXTENSIONS: [&str; 3] = ["ch8", "sc8", "xo8"];
+
+/// The ROM in `data`, read from `filename`: the first ROM in a zip archive, the
+/// decompressed contents of a gzip file, or otherwise `data` itself.
+pub fn extract(filename: &str, data: Vec<u8>) -> io::Result<Vec<u8>> {
+    let extension = Path::new(filename)
+        .extension()
+        .map(|ext| ext.to_ascii_lowercase());
+    let mut rom = vec![];
+    match extension.as_ref().and_then(|ext| ext.to_str()) {
+        Some("zip") => {
+            let mut archive = ZipArchive::new(Cursor::new(data))?;
+            let index = (0..archive.len())
+                .find(|&index| {
+                    archive.name_for_index(index).is_some_and(|name| {
+                        Path::new(name).extension().is_some_and(|ext| {
+                            ROM_EXTENSIONS
+                                .iter()
+                                .any(|rom| ext.eq_ignore_ascii_case(rom))
+                        })
+                    })
+                })
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        "no .ch8, .sc8 or .xo8 file in the archive",
+                    )
+                })?;
+            archive.by_index(index)?.read_to_end(&mut rom)?;
+        }
+        Some("gz") => {
+            GzDecoder::new(data.as_slice()).read_to_end(&mut rom)?;
+        }
+        _ => return Ok(data),
+    }
+    Ok(rom)
+}
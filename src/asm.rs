@@ -0,0 +1,260 @@
+use crate::{chip8::OpCodes, source_map::SourceMap, symbols::Symbols};
+use alloc::{collections::BTreeMap, format, string::String, vec, vec::Vec};
+use core::fmt;
+
+/// Where assembled programs are loaded, used to resolve labels.
+const ORIGIN: usize = 0x200;
+
+const KEYWORDS: [&str; 10] = ["I", "[I]", "DT", "ST", "K", "F", "HF", "B", "R", "PITCH"];
+
+#[derive(Debug)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// A ROM along with the addresses of the labels in its source, and the line each
+/// instruction came from.
+pub struct Assembled {
+    pub rom: Vec<u8>,
+    pub symbols: Symbols,
+    pub lines: SourceMap,
+}
+
+enum Operand<'a> {
+    V(usize),
+    Key(&'static str),
+    Value(&'a str),
+}
+
+enum Item<'a> {
+    Instruction {
+        mnemonic: String,
+        operands: Vec<&'a str>,
+    },
+    Data {
+        width: usize,
+        values: Vec<&'a str>,
+    },
+}
+
+/// Assembles source written in the mnemonics from the `OpCodes` comments, e.g.
+///
+/// ```text
+/// ; draw a box and stop
+/// start:  LD V0, 10
+///         LD I, box
+///         DRW V0, V0, 4
+/// loop:   JMP loop
+/// box:    .byte 0xF0, 0x90, 0x90, 0xF0
+/// ```
+///
+/// Mnemonics and registers are case-insensitive, labels are not. Numbers can be
+/// decimal, `0x` hex or `0b` binary. `.byte` and `.word` emit raw data.
+pub fn assemble(source: &str) -> Result<Assembled, AsmError> {
+    let mut labels = BTreeMap::new();
+    let mut items = vec![];
+    let mut address = ORIGIN;
+
+    for (index, line) in source.lines().enumerate() {
+        let error = |message: String| AsmError {
+            line: index + 1,
+            message,
+        };
+        let mut text = line.split(';').next().unwrap_or("").trim();
+        while let Some((label, rest)) = text.split_once(':') {
+            if !is_identifier(label.trim()) {
+                return Err(error(format!("invalid label '{}'", label.trim())));
+            }
+            if labels.insert(label.trim(), address).is_some() {
+                return Err(error(format!("duplicate label '{}'", label.trim())));
+            }
+            text = rest.trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let operands: Vec<&str> = if rest.trim().is_empty() {
+            vec![]
+        } else {
+            rest.split(',').map(str::trim).collect()
+        };
+        let item = match mnemonic.to_ascii_lowercase().as_str() {
+            ".byte" => Item::Data {
+                width: 1,
+                values: operands,
+            },
+            ".word" => Item::Data {
+                width: 2,
+                values: operands,
+            },
+            directive if directive.starts_with('.') => {
+                return Err(error(format!("unknown directive '{}'", mnemonic)))
+            }
+            _ => Item::Instruction {
+                mnemonic: mnemonic.to_ascii_uppercase(),
+                operands,
+            },
+        };
+        let start = address;
+        address += match &item {
+            Item::Instruction { .. } => 2,
+            Item::Data { width, values } => width * values.len(),
+        };
+        items.push((index + 1, start, item));
+    }
+
+    let assembler = Assembler { labels };
+    let mut rom = vec![];
+    let mut lines = SourceMap::default();
+    for (line, address, item) in items {
+        let result = match item {
+            Item::Instruction { mnemonic, operands } => {
+                lines.insert(address, line);
+                assembler
+                    .encode(&mnemonic, &operands)
+                    .map(|op| rom.extend_from_slice(&u16::from(op).to_be_bytes()))
+            }
+            Item::Data { width, values } => values.iter().try_for_each(|value| {
+                let value = assembler.value(value, if width == 1 { 0xFF } else { 0xFFFF })?;
+                rom.extend_from_slice(&(value as u16).to_be_bytes()[2 - width..]);
+                Ok(())
+            }),
+        };
+        result.map_err(|message| AsmError { line, message })?;
+    }
+    let mut symbols = Symbols::default();
+    for (name, address) in assembler.labels {
+        symbols.insert(address, name);
+    }
+    Ok(Assembled {
+        rom,
+        symbols,
+        lines,
+    })
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn register(operand: &str) -> Option<usize> {
+    let digit = operand.strip_prefix(['V', 'v'])?;
+    if digit.len() != 1 {
+        return None;
+    }
+    usize::from_str_radix(digit, 16).ok()
+}
+
+fn parse_number(text: &str) -> Option<usize> {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        usize::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = text.strip_prefix("0b").or_else(|| text.strip_prefix("0B")) {
+        usize::from_str_radix(binary, 2).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+struct Assembler<'a> {
+    labels: BTreeMap<&'a str, usize>,
+}
+
+impl Assembler<'_> {
+    fn value(&self, text: &str, max: usize) -> Result<usize, String> {
+        let value = parse_number(text)
+            .or_else(|| self.labels.get(text).copied())
+            .ok_or_else(|| format!("unknown label or number '{}'", text))?;
+        if value > max {
+            return Err(format!("{} is out of range (max {:#x})", text, max));
+        }
+        Ok(value)
+    }
+
+    fn byte(&self, text: &str) -> Result<u8, String> {
+        self.value(text, 0xFF).map(|v| v as u8)
+    }
+
+    fn encode(&self, mnemonic: &str, operands: &[&str]) -> Result<OpCodes, String> {
+        use Operand::*;
+        let parsed: Vec<Operand> = operands
+            .iter()
+            .map(|&text| match register(text) {
+                Some(x) => V(x),
+                None => KEYWORDS
+                    .iter()
+                    .find(|k| k.eq_ignore_ascii_case(text))
+                    .map_or(Value(text), |k| Key(k)),
+            })
+            .collect();
+
+        Ok(match (mnemonic, parsed.as_slice()) {
+            ("CLS", []) => OpCodes::Cls,
+            ("RET", []) => OpCodes::Ret,
+            ("SCD", [Value(n)]) => OpCodes::ScdN(self.value(n, 0xF)?),
+            ("SCR", []) => OpCodes::Scr,
+            ("SCL", []) => OpCodes::Scl,
+            ("LOW", []) => OpCodes::Low,
+            ("HIGH", []) => OpCodes::High,
+            ("JMP", [Value(nnn)]) => OpCodes::Jmp(self.value(nnn, 0xFFF)?),
+            ("JMP", [V(0), Value(nnn)]) => OpCodes::JmpV0Nnn(self.value(nnn, 0xFFF)?),
+            ("CALL", [Value(nnn)]) => OpCodes::Call(self.value(nnn, 0xFFF)?),
+            ("SE", [V(x), V(y)]) => OpCodes::SeVxVy(*x, *y),
+            ("SE", [V(x), Value(nn)]) => OpCodes::SeVxNn(*x, self.byte(nn)?),
+            ("SNE", [V(x), V(y)]) => OpCodes::SneVxVy(*x, *y),
+            ("SNE", [V(x), Value(nn)]) => OpCodes::SneVxNn(*x, self.byte(nn)?),
+            ("LD", [V(x), V(y)]) => OpCodes::LdVxVy(*x, *y),
+            ("LD", [V(x), Value(nn)]) => OpCodes::LdVxNn(*x, self.byte(nn)?),
+            ("LD", [Key("I"), Value(nnn)]) => OpCodes::LdINn(self.value(nnn, 0xFFF)? as u16),
+            ("LD", [V(x), Key("DT")]) => OpCodes::LdVxDt(*x),
+            ("LD", [V(x), Key("K")]) => OpCodes::LdVxK(*x),
+            ("LD", [Key("DT"), V(x)]) => OpCodes::LdDtVx(*x),
+            ("LD", [Key("ST"), V(x)]) => OpCodes::LdStVx(*x),
+            ("LD", [Key("F"), V(x)]) => OpCodes::LdFVx(*x),
+            ("LD", [Key("HF"), V(x)]) => OpCodes::LdHfVx(*x),
+            ("LD", [Key("B"), V(x)]) => OpCodes::LdBVx(*x),
+            ("LD", [Key("[I]"), V(x)]) => OpCodes::LdIVx(*x),
+            ("LD", [V(x), Key("[I]")]) => OpCodes::LdVxI(*x),
+            ("LD", [Key("R"), V(x)]) => OpCodes::LdRVx(*x),
+            ("LD", [V(x), Key("R")]) => OpCodes::LdVxR(*x),
+            ("LD", [Key("PITCH"), V(x)]) => OpCodes::LdPitchVx(*x),
+            ("ADD", [V(x), V(y)]) => OpCodes::AddVxVy(*x, *y),
+            ("ADD", [V(x), Value(nn)]) => OpCodes::AddVxNn(*x, self.byte(nn)?),
+            ("ADD", [Key("I"), V(x)]) => OpCodes::AddIVx(*x),
+            ("OR", [V(x), V(y)]) => OpCodes::OrVxVy(*x, *y),
+            ("AND", [V(x), V(y)]) => OpCodes::AndVxVy(*x, *y),
+            ("XOR", [V(x), V(y)]) => OpCodes::XorVxVy(*x, *y),
+            ("SUB", [V(x), V(y)]) => OpCodes::SubVxVy(*x, *y),
+            ("SUBN", [V(x), V(y)]) => OpCodes::SubnVxVy(*x, *y),
+            // With VY left out the shift reads VX whichever way the quirk is set
+            ("SHR", [V(x)]) => OpCodes::ShrVxVy(*x, *x),
+            ("SHR", [V(x), V(y)]) => OpCodes::ShrVxVy(*x, *y),
+            ("SHL", [V(x)]) => OpCodes::ShlVxVy(*x, *x),
+            ("SHL", [V(x), V(y)]) => OpCodes::ShlVxVy(*x, *y),
+            ("RND", [V(x), Value(nn)]) => OpCodes::RndVxNn(*x, self.byte(nn)?),
+            ("DRW", [V(x), V(y), Value(n)]) => OpCodes::DrawVxVyN(*x, *y, self.value(n, 0xF)?),
+            ("SKP", [V(x)]) => OpCodes::SkpVx(*x),
+            ("SKNP", [V(x)]) => OpCodes::SknpVx(*x),
+            ("PLANE", [Value(n)]) => OpCodes::PlaneN(self.value(n, 3)?),
+            _ => {
+                return Err(format!(
+                    "invalid instruction {} {}",
+                    mnemonic,
+                    operands.join(", ")
+                ))
+            }
+        })
+    }
+}
@@ -0,0 +1,70 @@
+use crate::paths;
+use flake::{
+    chip8::Chip8,
+    romdb,
+    save_state::{self, SaveState, SaveStateError},
+};
+use std::{fs, io, path::PathBuf};
+
+/// Where the chip was when a ROM was last closed, kept in the data directory by
+/// the ROM's hash so that every copy of it resumes from the same place, e.g.
+/// `states/<sha1>.state`.
+fn path(program: &[u8], extension: &str) -> PathBuf {
+    paths::data_dir()
+        .join("states")
+        .join(format!("{}.{}", romdb::sha1(program), extension))
+}
+
+#[derive(Debug)]
+pub enum AutosaveError {
+    IO(io::Error),
+    State(SaveStateError),
+}
+
+impl std::fmt::Display for AutosaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AutosaveError::IO(e) => write!(f, "failed to access saved state: {}", e),
+            AutosaveError::State(e) => write!(f, "failed to read saved state: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for AutosaveError {
+    fn from(error: io::Error) -> Self {
+        AutosaveError::IO(error)
+    }
+}
+
+impl From<SaveStateError> for AutosaveError {
+    fn from(error: SaveStateError) -> Self {
+        AutosaveError::State(error)
+    }
+}
+
+/// Returns `Ok(None)` when nothing has been saved for this ROM. States from
+/// before the versioned format, kept as `.json`, are migrated.
+pub fn load(program: &[u8]) -> Result<Option<SaveState>, AutosaveError> {
+    for extension in ["state", "json"] {
+        match fs::read(path(program, extension)) {
+            Ok(bytes) => return Ok(Some(save_state::decode(&bytes, program)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(None)
+}
+
+pub fn save(chip: &Chip8) -> Result<(), AutosaveError> {
+    let path = path(chip.program(), "state");
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    fs::write(
+        &path,
+        save_state::encode(&chip.save_state(), chip.program()),
+    )?;
+    // Superseded by the versioned state
+    let _ = fs::remove_file(path.with_extension("json"));
+    Ok(())
+}
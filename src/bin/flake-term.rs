@@ -0,0 +1,221 @@
+//! Plays a ROM in the terminal, drawing two display rows per line with half-block
+//! characters, for use over SSH or without a GPU.
+
+use crossterm::{
+    cursor::{Hide, MoveTo, MoveToNextLine, Show},
+    event::{
+        self, Event, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute, queue,
+    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use flake::{
+    chip8::{Chip8, ExecutionState, DEFAULT_INSTRUCTIONS_PER_FRAME},
+    palette::Palette,
+    romdb,
+};
+use std::{
+    io::{self, Write},
+    process,
+    time::{Duration, Instant},
+};
+
+const USAGE: &str = "usage: flake-term [--ipf <instructions per frame>] <rom>";
+
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Terminals that don't report key releases only repeat held keys, so a key counts
+/// as held for this long after each press or repeat.
+const HOLD_DURATION: Duration = Duration::from_millis(200);
+
+/// The same layout as the windowed frontend, by CHIP-8 key.
+const KEYS: [char; 16] = [
+    'x', '1', '2', '3', 'q', 'w', 'e', 'a', 's', 'd', 'z', 'c', '4', 'r', 'f', 'v',
+];
+
+/// Raw mode on an alternate screen, restored when dropped.
+struct Terminal {
+    /// Whether the terminal reports key releases.
+    reports_releases: bool,
+}
+
+impl Terminal {
+    fn enter() -> io::Result<Terminal> {
+        terminal::enable_raw_mode()?;
+        let reports_releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        let mut out = io::stdout();
+        execute!(out, EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+        if reports_releases {
+            execute!(
+                out,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        Ok(Terminal { reports_releases })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let mut out = io::stdout();
+        if self.reports_releases {
+            let _ = execute!(out, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(out, ResetColor, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn draw(out: &mut impl Write, chip: &Chip8, colors: &[Color; 4], status: &str) -> io::Result<()> {
+    let (width, height) = chip.display.size();
+    let pixels = chip.display.pixels();
+    let color = |x: usize, y: usize| colors[pixels[y * width + x] as usize & 3];
+    queue!(out, MoveTo(0, 0))?;
+    for row in 0..height / 2 {
+        let mut current = None;
+        for x in 0..width {
+            // The upper half block shows the top pixel, the background the bottom one
+            let cell = (color(x, row * 2), color(x, row * 2 + 1));
+            if current != Some(cell) {
+                queue!(out, SetForegroundColor(cell.0), SetBackgroundColor(cell.1))?;
+                current = Some(cell);
+            }
+            queue!(out, Print('▀'))?;
+        }
+        queue!(
+            out,
+            ResetColor,
+            Clear(ClearType::UntilNewLine),
+            MoveToNextLine(1)
+        )?;
+    }
+    queue!(out, Print(status), Clear(ClearType::FromCursorDown))?;
+    out.flush()
+}
+
+fn status(rom: &str, chip: &Chip8) -> String {
+    let state = match chip.state() {
+        ExecutionState::Paused => "Paused".to_string(),
+        ExecutionState::Halted(error) => format!("Halted: {}", error),
+        _ => "Running".to_string(),
+    };
+    format!("{} - {}. Space pauses, Esc quits.", rom, state)
+}
+
+fn run(rom: &str, mut chip: Chip8) -> io::Result<()> {
+    let terminal = Terminal::enter()?;
+    let colors = Palette::default().0.map(|color| {
+        let [r, g, b, _] = color
+            .to_array()
+            .map(|c| (c.clamp(0., 1.) * 255.).round() as u8);
+        Color::Rgb { r, g, b }
+    });
+    let mut out = io::stdout();
+    let mut pressed_at: [Option<Instant>; 16] = [None; 16];
+    let mut shown: Option<(Vec<u8>, String)> = None;
+    let mut next_frame = Instant::now();
+
+    loop {
+        while event::poll(next_frame.saturating_duration_since(Instant::now()))? {
+            match event::read()? {
+                Event::Key(key) => match key.code {
+                    KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return Ok(())
+                    }
+                    KeyCode::Char(' ') if key.kind == KeyEventKind::Press => {
+                        if chip.is_paused() {
+                            chip.resume();
+                        } else {
+                            chip.pause();
+                        }
+                    }
+                    KeyCode::Char(c) => {
+                        let c = c.to_ascii_lowercase();
+                        if let Some(index) = KEYS.iter().position(|&key| key == c) {
+                            pressed_at[index] = match key.kind {
+                                KeyEventKind::Release => None,
+                                _ => Some(Instant::now()),
+                            };
+                        }
+                    }
+                    _ => {}
+                },
+                Event::Resize(..) => {
+                    shown = None;
+                    queue!(out, Clear(ClearType::All))?;
+                }
+                _ => {}
+            }
+        }
+        next_frame += FRAME_DURATION;
+
+        for (key, pressed_at) in pressed_at.iter().enumerate() {
+            chip.keys[key] = match pressed_at {
+                Some(_) if terminal.reports_releases => true,
+                Some(at) => at.elapsed() < HOLD_DURATION,
+                None => false,
+            };
+        }
+        let was_playing = chip.is_sound_playing();
+        chip.step_frame();
+        if chip.is_sound_playing() && !was_playing {
+            // The terminal bell is the only sound there is
+            queue!(out, Print('\x07'))?;
+        }
+
+        let status = status(rom, &chip);
+        let changed = shown.as_ref().is_none_or(|(pixels, shown_status)| {
+            pixels != chip.display.pixels() || *shown_status != status
+        });
+        if changed {
+            draw(&mut out, &chip, &colors, &status)?;
+            shown = Some((chip.display.pixels().to_vec(), status));
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut instructions_per_frame = None;
+    let mut rom = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ipf" => {
+                let value = args.next().unwrap_or_else(|| exit_with(USAGE));
+                instructions_per_frame = match value.parse::<u32>() {
+                    Ok(ipf) if ipf > 0 => Some(ipf),
+                    _ => exit_with(&format!("invalid instructions per frame: {}", value)),
+                };
+            }
+            _ if rom.is_none() && !arg.starts_with('-') => rom = Some(arg.clone()),
+            _ => exit_with(USAGE),
+        }
+    }
+    let rom = rom.unwrap_or_else(|| exit_with(USAGE));
+
+    let mut chip = Chip8::new();
+    if let Err(e) = chip.load(&rom) {
+        exit_with(&format!("Failed to load {}: {}", rom, e));
+    }
+    let (default_instructions_per_frame, guess) =
+        romdb::apply_profile(&mut chip, true, DEFAULT_INSTRUCTIONS_PER_FRAME);
+    if let Some(guess) = guess {
+        println!("{}", guess);
+    }
+    chip.instructions_per_frame = instructions_per_frame.unwrap_or(default_instructions_per_frame);
+    // Printing would scroll the display away
+    chip.trace = false;
+
+    if let Err(e) = run(&rom, chip) {
+        exit_with(&format!("Terminal error: {}", e));
+    }
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
@@ -0,0 +1,152 @@
+use crate::{
+    paths,
+    sdf::{Align, SDFFont, SDFText, TextBatcher, VAlign},
+};
+use glam::{Mat4, Vec3};
+use miniquad::KeyCode;
+use std::{fs, io, path::Path};
+
+const VISIBLE_ROWS: usize = 12;
+const MAX_RECENT: usize = 5;
+const TEXT_SCALE: f32 = 0.35;
+const MARGIN: f32 = 20.0;
+
+/// Overlay listing the ROMs in a directory, with the most recently played ones first.
+pub struct RomBrowser<'a> {
+    pub is_open: bool,
+    directory: String,
+    recent: Vec<String>,
+    entries: Vec<String>,
+    selected: usize,
+    rows: Vec<SDFText<'a>>,
+    row_height: f32,
+}
+
+impl<'a> RomBrowser<'a> {
+    pub fn new(font: &'a SDFFont, directory: &str) -> RomBrowser<'a> {
+        RomBrowser {
+            is_open: false,
+            directory: directory.to_string(),
+            recent: load_recent(),
+            entries: vec![],
+            selected: 0,
+            rows: (0..VISIBLE_ROWS)
+                .map(|_| {
+                    let mut text = SDFText::new(font, "");
+                    text.align = Align {
+                        vertical: VAlign::Top,
+                        ..Default::default()
+                    };
+                    text
+                })
+                .collect(),
+            row_height: font.measure("").size().y * TEXT_SCALE,
+        }
+    }
+
+    pub fn add_recent(&mut self, path: &str) {
+        self.recent.retain(|p| p != path);
+        self.recent.insert(0, path.to_string());
+        self.recent.truncate(MAX_RECENT);
+        save_recent(&self.recent);
+    }
+
+    pub fn open(&mut self) {
+        let mut files = list_roms(&self.directory).unwrap_or_else(|e| {
+            eprintln!("Failed to list {}: {}", self.directory, e);
+            vec![]
+        });
+        files.retain(|file| !self.recent.contains(file));
+
+        self.entries = self.recent.iter().cloned().chain(files).collect();
+        self.selected = 0;
+        self.is_open = true;
+        self.update_rows();
+    }
+
+    /// Handles navigation while open, returning the ROM to load once one is picked.
+    pub fn key_down_event(&mut self, keycode: KeyCode) -> Option<String> {
+        match keycode {
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => {
+                self.selected = (self.selected + 1).min(self.entries.len().saturating_sub(1))
+            }
+            KeyCode::Enter => {
+                self.is_open = false;
+                return self.entries.get(self.selected).cloned();
+            }
+            KeyCode::Escape => self.is_open = false,
+            _ => {}
+        }
+        self.update_rows();
+        None
+    }
+
+    fn update_rows(&mut self) {
+        let first = self.selected.saturating_sub(VISIBLE_ROWS - 1);
+        for (row, text) in self.rows.iter_mut().enumerate() {
+            let index = first + row;
+            let label = match self.entries.get(index) {
+                Some(entry) => {
+                    let cursor = if index == self.selected { ">" } else { " " };
+                    let marker = if index < self.recent.len() { "*" } else { " " };
+                    format!("{} {} {}", cursor, marker, entry)
+                }
+                None => String::new(),
+            };
+            text.update_text(&label);
+        }
+    }
+
+    pub fn draw(&mut self, batcher: &mut TextBatcher, window_height: f32) {
+        if !self.is_open {
+            return;
+        }
+        for (row, text) in self.rows.iter_mut().enumerate() {
+            text.model = Mat4::from_translation(Vec3::new(
+                MARGIN,
+                window_height - MARGIN - self.row_height * row as f32,
+                0.,
+            )) * Mat4::from_scale(Vec3::splat(TEXT_SCALE));
+            batcher.add(text);
+        }
+    }
+}
+
+/// The files in `directory` that could be ROMs, sorted by name.
+pub fn list_roms(directory: &str) -> io::Result<Vec<String>> {
+    let mut files: Vec<String> = fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && !is_sidecar(path))
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Files flake writes next to ROMs, which shouldn't be offered for loading.
+fn is_sidecar(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    name.ends_with(".session.json")
+        || name.ends_with(".flags")
+        || name.ends_with(".sym")
+        || name.ends_with(".map")
+}
+
+fn load_recent() -> Vec<String> {
+    match fs::read_to_string(paths::data_dir().join("recent.json")) {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+        Err(_) => vec![],
+    }
+}
+
+fn save_recent(recent: &[String]) {
+    let path = paths::data_dir().join("recent.json");
+    let result = serde_json::to_string_pretty(recent)
+        .map_err(|e| e.to_string())
+        .and_then(|data| fs::write(&path, data).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        eprintln!("Failed to save {}: {}", path.display(), e);
+    }
+}
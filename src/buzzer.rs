@@ -0,0 +1,190 @@
+//! The buzzer's tone, synthesized the same way by everything that plays or
+//! records it: the libretro core and video recordings.
+
+use crate::chip8::Chip8;
+use alloc::{format, string::String};
+use core::f32::consts::TAU;
+use serde::{Deserialize, Serialize};
+
+/// The tone at the default pitch, in Hz, which FX3A moves up and down from.
+pub const DEFAULT_FREQUENCY: f32 = 4000.;
+
+/// The shape of the tone, for matching different machines' buzzers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Waveform {
+    #[default]
+    Square,
+    Sine,
+    Triangle,
+}
+
+impl Waveform {
+    pub fn parse(s: &str) -> Result<Waveform, String> {
+        match s {
+            "square" => Ok(Waveform::Square),
+            "sine" => Ok(Waveform::Sine),
+            "triangle" => Ok(Waveform::Triangle),
+            _ => Err(format!(
+                "invalid waveform: {} (expected square, sine or triangle)",
+                s
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Waveform::Square => "square",
+            Waveform::Sine => "sine",
+            Waveform::Triangle => "triangle",
+        }
+    }
+
+    /// The wave at `phase` of the way through its cycle, from -1 to 1, with its
+    /// first half taking `duty` of the cycle.
+    fn sample(self, phase: f32, duty: f32) -> f32 {
+        let duty = duty.clamp(MIN_DUTY, 1. - MIN_DUTY);
+        let t = if phase < duty {
+            phase / duty * 0.5
+        } else {
+            0.5 + (phase - duty) / (1. - duty) * 0.5
+        };
+        match self {
+            Waveform::Square if t < 0.5 => 1.,
+            Waveform::Square => -1.,
+            Waveform::Sine => libm::sinf(t * TAU),
+            Waveform::Triangle if t < 0.5 => 4. * t - 1.,
+            Waveform::Triangle => 3. - 4. * t,
+        }
+    }
+}
+
+/// Keeps both halves of the wave from vanishing.
+const MIN_DUTY: f32 = 0.01;
+
+/// How the buzzer sounds.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tone {
+    pub waveform: Waveform,
+    /// In Hz at the default pitch.
+    pub frequency: f32,
+    /// How much of each cycle the wave's first half takes, from 0 to 1.
+    pub duty: f32,
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Tone {
+            waveform: Waveform::Square,
+            frequency: DEFAULT_FREQUENCY,
+            duty: 0.5,
+        }
+    }
+}
+
+impl Tone {
+    /// Reads a duty cycle given as a fraction between 0 and 1.
+    pub fn parse_duty(s: &str) -> Result<f32, String> {
+        match s.parse() {
+            Ok(duty) if duty > 0. && duty < 1. => Ok(duty),
+            _ => Err(format!("invalid duty cycle: {} (expected 0 to 1)", s)),
+        }
+    }
+
+    pub fn parse_frequency(s: &str) -> Result<f32, String> {
+        match s.parse() {
+            Ok(frequency) if frequency > 0. => Ok(frequency),
+            _ => Err(format!("invalid frequency: {}", s)),
+        }
+    }
+}
+
+/// How much each volume key turns the buzzer up or down.
+const VOLUME_STEP: f32 = 0.1;
+
+/// How loud the buzzer is, so ST-heavy ROMs can be quietened or silenced.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Volume {
+    /// From 0 to 1.
+    pub level: f32,
+    pub muted: bool,
+}
+
+impl Default for Volume {
+    fn default() -> Self {
+        Volume {
+            level: 1.,
+            muted: false,
+        }
+    }
+}
+
+impl Volume {
+    /// What the buzzer's samples are scaled by.
+    pub fn gain(self) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.level.clamp(0., 1.)
+        }
+    }
+
+    /// E.g. "Volume 80%", or "Muted".
+    pub fn describe(self) -> String {
+        if self.muted {
+            "Muted".into()
+        } else {
+            format!("Volume {:.0}%", self.level * 100.)
+        }
+    }
+
+    /// Turns the volume up a step, or down if not `louder`, unmuting it, and
+    /// describes the result.
+    pub fn adjust(&mut self, louder: bool) -> String {
+        let step = if louder { VOLUME_STEP } else { -VOLUME_STEP };
+        // Rounded so repeated steps land on whole percentages
+        self.level = (libm::roundf((self.level + step) * 100.) / 100.).clamp(0., 1.);
+        self.muted = false;
+        self.describe()
+    }
+
+    pub fn toggle_mute(&mut self) -> String {
+        self.muted = !self.muted;
+        self.describe()
+    }
+}
+
+/// Generates the buzzer's samples while the sound timer runs.
+#[derive(Clone, Debug, Default)]
+pub struct Buzzer {
+    pub tone: Tone,
+    pub volume: Volume,
+    /// Where the wave is in its cycle, from 0 to 1.
+    phase: f32,
+}
+
+impl Buzzer {
+    pub fn new(tone: Tone) -> Buzzer {
+        Buzzer {
+            tone,
+            volume: Volume::default(),
+            phase: 0.,
+        }
+    }
+
+    /// The next sample of the buzzer as `chip` has it now, from -1 to 1 at full
+    /// volume, silent while it's off.
+    pub fn next_sample(&mut self, chip: &Chip8, sample_rate: f32) -> f32 {
+        let sample = if chip.is_sound_playing() {
+            self.tone.waveform.sample(self.phase, self.tone.duty) * self.volume.gain()
+        } else {
+            0.
+        };
+        let frequency = self.tone.frequency * chip.pitch_ratio();
+        let phase = self.phase + frequency / sample_rate;
+        self.phase = phase - libm::floorf(phase);
+        sample
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{
+    chip8::Chip8,
+    sdf::{Align, SDFFont, SDFText, TextBatcher, TextStyle, VAlign},
+};
+use glam::{Mat4, Vec3, Vec4};
+use miniquad::KeyCode;
+
+const VISIBLE_ROWS: usize = 10;
+const TEXT_SCALE: f32 = 0.3;
+const MARGIN: f32 = 20.;
+
+const FROZEN_COLOR: Vec4 = Vec4::new(0.4, 0.8, 1., 1.);
+const HINT_COLOR: Vec4 = Vec4::new(0.6, 0.6, 0.6, 1.);
+
+/// Narrows down which addresses hold a value, e.g. the number of lives, by
+/// searching memory repeatedly while it changes.
+#[derive(Default)]
+pub struct MemorySearch {
+    /// Addresses that matched every search so far, or `None` before the first.
+    candidates: Option<Vec<usize>>,
+    /// Memory as of the last search.
+    snapshot: Vec<u8>,
+}
+
+impl MemorySearch {
+    pub fn candidates(&self) -> &[usize] {
+        self.candidates.as_deref().unwrap_or_default()
+    }
+
+    pub fn clear(&mut self) {
+        self.candidates = None;
+        self.snapshot.clear();
+    }
+
+    /// Keeps the addresses that hold `value` now.
+    pub fn equal_to(&mut self, memory: &[u8], value: u8) {
+        self.keep(memory, |_, now| now == value);
+    }
+
+    /// Keeps the addresses that changed since the last search. The first search
+    /// only remembers memory to compare against.
+    pub fn changed(&mut self, memory: &[u8]) {
+        if self.snapshot.is_empty() {
+            self.snapshot = memory.to_vec();
+            return;
+        }
+        self.keep(memory, |before, now| before != now);
+    }
+
+    fn keep(&mut self, memory: &[u8], matches: impl Fn(u8, u8) -> bool) {
+        let snapshot = std::mem::replace(&mut self.snapshot, memory.to_vec());
+        let before = |address: usize| snapshot.get(address).copied().unwrap_or_default();
+        let candidates = match self.candidates.take() {
+            Some(candidates) => candidates,
+            None => (0..memory.len()).collect(),
+        };
+        self.candidates = Some(
+            candidates
+                .into_iter()
+                .filter(|&address| matches(before(address), memory[address]))
+                .collect(),
+        );
+    }
+}
+
+fn hex_digit(keycode: KeyCode) -> Option<char> {
+    Some(match keycode {
+        KeyCode::Key0 => '0',
+        KeyCode::Key1 => '1',
+        KeyCode::Key2 => '2',
+        KeyCode::Key3 => '3',
+        KeyCode::Key4 => '4',
+        KeyCode::Key5 => '5',
+        KeyCode::Key6 => '6',
+        KeyCode::Key7 => '7',
+        KeyCode::Key8 => '8',
+        KeyCode::Key9 => '9',
+        KeyCode::A => 'A',
+        KeyCode::B => 'B',
+        KeyCode::C => 'C',
+        KeyCode::D => 'D',
+        KeyCode::E => 'E',
+        KeyCode::F => 'F',
+        _ => return None,
+    })
+}
+
+/// Overlay for searching memory and freezing what's found, the classic way to
+/// get infinite lives. Takes over the keyboard while open.
+pub struct CheatPanel<'a> {
+    pub is_open: bool,
+    search: MemorySearch,
+    /// Hex digits typed so far.
+    value: String,
+    /// Index into the search's candidates.
+    selected: usize,
+    text: SDFText<'a>,
+}
+
+impl<'a> CheatPanel<'a> {
+    pub fn new(font: &'a SDFFont) -> CheatPanel<'a> {
+        let mut text = SDFText::new(font, "");
+        text.align = Align {
+            vertical: VAlign::Top,
+            ..Default::default()
+        };
+        text.style = TextStyle {
+            outline_width: 0.15,
+            ..Default::default()
+        };
+        CheatPanel {
+            is_open: false,
+            search: MemorySearch::default(),
+            value: String::new(),
+            selected: 0,
+            text,
+        }
+    }
+
+    /// Starts the search over, e.g. for a new ROM.
+    pub fn reset(&mut self) {
+        self.search.clear();
+        self.value.clear();
+        self.selected = 0;
+    }
+
+    /// Handles a key while open, returning a message for anything that changed
+    /// the chip or the search.
+    pub fn key_down_event(&mut self, keycode: KeyCode, chip: &mut Chip8) -> Option<String> {
+        let candidates = self.search.candidates().len();
+        if let Some(digit) = hex_digit(keycode) {
+            if self.value.len() == 2 {
+                self.value.clear();
+            }
+            self.value.push(digit);
+            return None;
+        }
+        let value = u8::from_str_radix(&self.value, 16).ok();
+        let selected = self.search.candidates().get(self.selected).copied();
+        match keycode {
+            KeyCode::Escape => self.is_open = false,
+            KeyCode::Backspace => {
+                self.value.pop();
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(candidates.saturating_sub(1)),
+            KeyCode::Enter => {
+                self.selected = 0;
+                self.value.clear();
+                return Some(match value {
+                    Some(value) => {
+                        self.search.equal_to(chip.memory(), value);
+                        format!(
+                            "{} address(es) hold {:#04x}",
+                            self.search.candidates().len(),
+                            value
+                        )
+                    }
+                    None if self.search.snapshot.is_empty() => {
+                        self.search.changed(chip.memory());
+                        "Remembered memory, search again once it changes".to_string()
+                    }
+                    None => {
+                        self.search.changed(chip.memory());
+                        let found = self.search.candidates().len();
+                        format!("{} address(es) changed", found)
+                    }
+                });
+            }
+            KeyCode::Delete => {
+                self.reset();
+                return Some("Cleared memory search".to_string());
+            }
+            KeyCode::Space => {
+                let address = selected?;
+                if chip.unfreeze(address) {
+                    return Some(format!("Unfroze {:#06x}", address));
+                }
+                let value = value.unwrap_or(chip.memory()[address]);
+                self.value.clear();
+                chip.freeze(address, value);
+                return Some(format!("Froze {:#06x} at {:#04x}", address, value));
+            }
+            _ => {}
+        }
+        None
+    }
+
+    pub fn draw(&mut self, batcher: &mut TextBatcher, chip: &Chip8, window_height: f32) {
+        if !self.is_open {
+            return;
+        }
+        // Memory changes while it runs, so this is laid out every frame
+        self.text.update_spans(&self.spans(chip));
+        self.text.model = Mat4::from_translation(Vec3 {
+            x: MARGIN,
+            y: window_height - MARGIN,
+            z: 0.,
+        }) * Mat4::from_scale(Vec3::splat(TEXT_SCALE));
+        batcher.add(&self.text);
+    }
+
+    fn spans(&self, chip: &Chip8) -> Vec<(String, Vec4)> {
+        let candidates = self.search.candidates();
+        let found = match self.search.candidates {
+            Some(_) => format!("{} match(es)", candidates.len()),
+            None => "No search yet".to_string(),
+        };
+        let mut spans = vec![(
+            format!("Memory search: {}   Value: {}_\n", found, self.value),
+            Vec4::ONE,
+        )];
+        let first = self.selected.saturating_sub(VISIBLE_ROWS - 1);
+        for (index, &address) in candidates.iter().enumerate().skip(first).take(VISIBLE_ROWS) {
+            let cursor = if index == self.selected { ">" } else { " " };
+            let line = format!(
+                "{} {:#06x} = {:#04x}\n",
+                cursor,
+                address,
+                chip.memory()[address]
+            );
+            let color = if chip.frozen().contains_key(&address) {
+                FROZEN_COLOR
+            } else {
+                Vec4::ONE
+            };
+            spans.push((line, color));
+        }
+        for (address, value) in chip.frozen() {
+            spans.push((
+                format!("Frozen {:#06x} at {:#04x}\n", address, value),
+                FROZEN_COLOR,
+            ));
+        }
+        spans.push((
+            "Type a hex value and Enter to find it, or Enter alone to find changes.\n\
+             Space freezes or unfreezes the selected address, Delete starts over."
+                .to_string(),
+            HINT_COLOR,
+        ));
+        spans
+    }
+}
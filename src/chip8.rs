@@ -1,136 +1,494 @@
-use core::fmt;
-use std::{
-    fs::File,
-    io::Read,
-    time::{Duration, Instant},
+#[cfg(feature = "std")]
+use crate::{
+    archive, disasm, fetch, octo,
+    platform::{FlagFile, SystemClock},
 };
+use crate::{
+    asm::AsmError,
+    display::{self, Display},
+    platform::{Clock, FlagStorage},
+    quirks::Quirks,
+    save_state::{SaveState, SaveStateError},
+    source_map::SourceMap,
+    symbols::Symbols,
+};
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+use core::{fmt, ops::Range, time::Duration};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use std::io;
+
+/// 700 instructions per second at 60 frames per second, rounded.
+pub const DEFAULT_INSTRUCTIONS_PER_FRAME: u32 = 12;
+
+/// 4x5 hexadecimal digits, pointed at by LD F, VX.
+#[rustfmt::skip]
+const FONT: [u8; 16 * 5] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+/// SUPER-CHIP 8x10 decimal digits, pointed at by LD HF, VX. Stored right after `FONT`.
+#[rustfmt::skip]
+const BIG_FONT: [u8; 10 * 10] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+];
+
+/// Conventional location of the font, which some ROMs hard-code.
+pub const DEFAULT_FONT_ADDRESS: usize = 0x50;
+
+const MEMORY_SIZE: usize = 4096;
+const PROGRAM_START: usize = 0x200;
+const DEFAULT_PITCH: u8 = 64;
+/// Largest ROM that fits between `PROGRAM_START` and the end of memory.
+pub const MAX_ROM_SIZE: usize = MEMORY_SIZE - PROGRAM_START;
+
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// Most emulated time run to catch up with the wall clock in one go. Anything
+/// beyond this (after a stall, sleep or long pause) is skipped instead.
+const MAX_CATCH_UP: Duration = Duration::from_millis(100);
 
 pub struct Chip8 {
-    memory: [u8; 4096],
-    pub display: [u8; 64 * 32],
+    memory: [u8; MEMORY_SIZE],
+    program: Vec<u8>,
+    pub display: Display,
     v: [u8; 16],
     pc: usize,
     st: u8,
     dt: u8,
+    /// XO-CHIP buzzer pitch, set by FX3A. The tone is `2^((pitch - 64) / 48)` times
+    /// its frequency at the default of 64.
+    pitch: u8,
     i: u16,
     stack: Vec<usize>,
     mode: Modes,
+    pub quirks: Quirks,
     pub keys: [bool; 16],
+    /// Keys checked by SKP/SKNP during the last timer frame.
+    polled_keys: [bool; 16],
+    /// Keys checked by SKP/SKNP so far in the current timer frame.
+    polling_keys: [bool; 16],
+    /// Register and key of the last LD VX, K to finish.
+    last_key_wait: Option<(usize, usize)>,
+    /// Instructions executed since the ROM was loaded.
+    instruction_count: u64,
+    /// Steps since the ROM was loaded, counting timer decrements and key waits as
+    /// well as instructions, so that every point in a run has a number of its own.
+    tick_count: u64,
+    last_draw: Option<DrawCall>,
+    /// Pixels the last DXYN turned off, setting VF.
+    collided_pixels: Vec<(usize, usize)>,
 
-    pub execution_speed: f32,
-    pub next_tick: Instant,
-    pub next_timers_tick: Instant,
+    /// Number of instructions executed per 60Hz timer frame.
+    pub instructions_per_frame: u32,
+    /// When the next instruction and timer decrement are due, on `clock`.
+    pub next_tick: Duration,
+    pub next_timers_tick: Duration,
+    clock: Arc<dyn Clock>,
 
     sound_playing: bool,
-}
 
-impl Chip8 {
-    pub fn compare(a: &Chip8, b: &Chip8) -> String {
-        let mut s = vec![];
+    paused: bool,
+    key_wait: Option<KeyWait>,
+    halt: Option<Chip8Error>,
 
-        a.memory
-            .iter()
-            .enumerate()
-            .zip(b.memory.iter())
-            .filter(|((_index, x), y)| x != y)
-            .for_each(|((index, x), y)| {
-                s.push(format!("Memory {:#06x}: {:#06x} → {:#06x}", index, x, y))
-            });
+    pub timing: Timing,
+    /// HP48 flag registers, saved and restored by FX75/FX85.
+    flags: [u8; 16],
+    /// Where the flag registers are persisted, e.g. a file next to the loaded ROM.
+    flag_storage: Option<Arc<dyn FlagStorage>>,
+    /// Where `load` places the fonts. Both fonts must fit below 0x200.
+    pub font_address: usize,
+    last_op: Option<OpCodes>,
+    /// Memory the last instruction read or wrote through I.
+    last_access: Option<MemoryAccess>,
+    /// Labels for the loaded ROM, shared between snapshots since they never change.
+    symbols: Arc<Symbols>,
+    source_map: Arc<SourceMap>,
+    /// Source for CXNN, part of the state so that copies draw the same numbers.
+    rng: StdRng,
+    /// Prints every executed instruction and buzzer change, with the `std` feature.
+    pub trace: bool,
+    /// Addresses written back to a fixed value after every instruction, kept in
+    /// snapshots so rewinding past a freeze undoes it.
+    frozen: BTreeMap<usize, u8>,
+}
 
-        a.display
-            .iter()
-            .enumerate()
-            .zip(b.display.iter())
-            .filter(|((_index, x), y)| x != y)
-            .for_each(|((index, x), y)| {
-                s.push(format!("Display {:#06x}: {:#06x} → {:#06x}", index, x, y))
-            });
+/// How long each instruction takes in emulated time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Timing {
+    /// Every instruction takes 1/700s.
+    Flat,
+    /// Each instruction takes roughly as long as it did on the original COSMAC VIP
+    /// interpreter, so games tuned to that hardware run at the intended pace.
+    CosmacVip,
+}
 
-        a.v.iter()
-            .enumerate()
-            .zip(b.v.iter())
-            .filter(|((_index, x), y)| x != y)
-            .for_each(|((index, x), y)| {
-                s.push(format!("V {:#06x}: {:#06x} → {:#06x}", index, x, y))
-            });
+/// An in-progress LD VX, K.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct KeyWait {
+    register: usize,
+    /// The key that was pressed, when waiting for its release.
+    pressed: Option<usize>,
+}
 
-        if a.pc != b.pc {
-            s.push(format!("PC: {:#06x} → {:#06x}", a.pc, b.pc));
-        }
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip8Error {
+    UnknownOpcode {
+        addr: usize,
+        opcode: u16,
+    },
+    /// CALL with `depth` return addresses already on the stack.
+    StackOverflow {
+        addr: usize,
+        depth: usize,
+    },
+    /// RET with nothing on the stack.
+    StackUnderflow {
+        addr: usize,
+    },
+    /// An instruction at `addr` accessing `len` bytes from `i` that run past the end
+    /// of memory.
+    MemoryOutOfBounds {
+        addr: usize,
+        i: u16,
+        len: usize,
+    },
+    /// FX29/FX30 at `addr` with a value that has no character in the font.
+    InvalidFontDigit {
+        addr: usize,
+        digit: u8,
+    },
+    /// An instruction fetched from `addr` that runs past the end of memory.
+    PcOutOfBounds {
+        addr: usize,
+    },
+    /// A jump or skip at `addr` to `target`, where there's no whole instruction.
+    JumpOutOfBounds {
+        addr: usize,
+        target: usize,
+    },
+}
 
-        if a.st != b.st {
-            s.push(format!("ST: {:#06x} → {:#06x}", a.st, b.st));
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::UnknownOpcode { addr, opcode } => {
+                write!(f, "Unknown opcode {:#06x} at {:#06x}", opcode, addr)
+            }
+            Chip8Error::StackOverflow { addr, depth } => {
+                write!(f, "Stack overflow at {:#06x} (limit {})", addr, depth)
+            }
+            Chip8Error::StackUnderflow { addr } => {
+                write!(f, "Return with an empty stack at {:#06x}", addr)
+            }
+            Chip8Error::MemoryOutOfBounds { addr, i, len } => write!(
+                f,
+                "Access of {} byte(s) from I={:#06x} at {:#06x} runs past the end of memory",
+                len, i, addr
+            ),
+            Chip8Error::InvalidFontDigit { addr, digit } => {
+                write!(f, "No font character for {:#04x} at {:#06x}", digit, addr)
+            }
+            Chip8Error::PcOutOfBounds { addr } => {
+                write!(f, "PC {:#06x} runs past the end of memory", addr)
+            }
+            Chip8Error::JumpOutOfBounds { addr, target } => write!(
+                f,
+                "Jump to {:#06x} at {:#06x} runs past the end of memory",
+                target, addr
+            ),
         }
+    }
+}
 
-        if a.dt != b.dt {
-            s.push(format!("DT: {:#06x} → {:#06x}", a.dt, b.dt));
-        }
+#[derive(Debug)]
+pub enum LoadError {
+    #[cfg(feature = "std")]
+    IO(std::io::Error),
+    Compile(AsmError),
+    Empty,
+    TooLarge {
+        size: usize,
+    },
+}
 
-        if a.i != b.i {
-            s.push(format!(" I: {:#06x} → {:#06x}", a.i, b.i));
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            LoadError::IO(e) => write!(f, "failed to read ROM: {}", e),
+            LoadError::Compile(e) => write!(f, "failed to compile ROM source: {}", e),
+            LoadError::Empty => write!(f, "ROM is empty"),
+            LoadError::TooLarge { size } => write!(
+                f,
+                "ROM is {} bytes but only {} fit in memory ({:#05x}-{:#05x})",
+                size,
+                MAX_ROM_SIZE,
+                PROGRAM_START,
+                PROGRAM_START + MAX_ROM_SIZE - 1
+            ),
         }
+    }
+}
 
-        // a.stack
-        //     .iter()
-        //     .enumerate()
-        //     .zip(b.stack.iter())
-        //     .filter(|((_index, x), y)| x != y)
-        //     .for_each(|((index, x), y)| {
-        //         s.push(format!("Stack {:#06x}: {:#06x} → {:#06x}", index, x, y))
-        //     });
+#[cfg(feature = "std")]
+impl From<std::io::Error> for LoadError {
+    fn from(error: std::io::Error) -> Self {
+        LoadError::IO(error)
+    }
+}
+impl From<AsmError> for LoadError {
+    fn from(error: AsmError) -> Self {
+        LoadError::Compile(error)
+    }
+}
 
-        // stack
+/// What the chip is currently doing, as reported by `Chip8::state`. A halted chip
+/// reports `Halted` even while paused, and a paused chip reports `Paused` even
+/// while it is waiting for a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecutionState {
+    Running,
+    Paused,
+    /// Blocked on LD VX, K until a key is pressed; holds X.
+    WaitingForKey(usize),
+    Halted(Chip8Error),
+}
 
-        if a.mode != b.mode {
-            s.push(format!(" mode: {:?} → {:?}", a.mode, b.mode));
-        }
+/// A single changed cell of an indexed piece of state (memory, display, registers, ...).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CellChange<T> {
+    pub index: usize,
+    pub from: T,
+    pub to: T,
+}
 
-        // keys
+/// Structured difference between two Chip8 states, as produced by `Chip8::compare`.
+/// The `Display` impl renders it in the same format the debugger prints while stepping.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    pub memory: Vec<CellChange<u8>>,
+    pub display: Vec<CellChange<u8>>,
+    pub v: Vec<CellChange<u8>>,
+    pub pc: Option<(usize, usize)>,
+    pub st: Option<(u8, u8)>,
+    pub dt: Option<(u8, u8)>,
+    pub pitch: Option<(u8, u8)>,
+    pub i: Option<(u16, u16)>,
+    /// Stack slots that differ, `None` meaning the slot is not in use.
+    pub stack: Vec<CellChange<Option<usize>>>,
+    pub mode: Option<(Modes, Modes)>,
+    pub keys: Vec<CellChange<bool>>,
+    pub next_tick: Option<(Duration, Duration)>,
+    pub next_timers_tick: Option<(Duration, Duration)>,
+    pub sound_playing: Option<(bool, bool)>,
+    pub state: Option<(ExecutionState, ExecutionState)>,
+}
 
-        if a.next_tick != b.next_tick {
-            s.push(format!(" tick: {:?} → {:?}", a.next_tick, b.next_tick));
-        }
+fn diff_cells<T: Copy + PartialEq>(a: &[T], b: &[T]) -> Vec<CellChange<T>> {
+    a.iter()
+        .enumerate()
+        .zip(b.iter())
+        .filter(|((_index, x), y)| x != y)
+        .map(|((index, &from), &to)| CellChange { index, from, to })
+        .collect()
+}
+
+fn diff_value<T: Copy + PartialEq>(a: T, b: T) -> Option<(T, T)> {
+    if a != b {
+        Some((a, b))
+    } else {
+        None
+    }
+}
+
+fn fmt_stack_slot(slot: Option<usize>) -> String {
+    match slot {
+        Some(addr) => format!("{:#06x}", addr),
+        None => "------".to_string(),
+    }
+}
 
-        if a.next_timers_tick != b.next_timers_tick {
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = vec![];
+
+        for c in &self.memory {
             s.push(format!(
-                "timers: {:?} → {:?}",
-                a.next_timers_tick, b.next_timers_tick
+                "Memory {:#06x}: {:#06x} → {:#06x}",
+                c.index, c.from, c.to
             ));
         }
-
-        if a.sound_playing != b.sound_playing {
+        for c in &self.display {
+            s.push(format!(
+                "Display {:#06x}: {:#06x} → {:#06x}",
+                c.index, c.from, c.to
+            ));
+        }
+        for c in &self.v {
             s.push(format!(
-                "sound_playing: {:?} → {:?}",
-                a.sound_playing, b.sound_playing
+                "V {:#06x}: {:#06x} → {:#06x}",
+                c.index, c.from, c.to
             ));
         }
+        if let Some((a, b)) = self.pc {
+            s.push(format!("PC: {:#06x} → {:#06x}", a, b));
+        }
+        if let Some((a, b)) = self.st {
+            s.push(format!("ST: {:#06x} → {:#06x}", a, b));
+        }
+        if let Some((a, b)) = self.dt {
+            s.push(format!("DT: {:#06x} → {:#06x}", a, b));
+        }
+        if let Some((a, b)) = self.pitch {
+            s.push(format!("Pitch: {:#06x} → {:#06x}", a, b));
+        }
+        if let Some((a, b)) = self.i {
+            s.push(format!(" I: {:#06x} → {:#06x}", a, b));
+        }
+        for c in &self.stack {
+            s.push(format!(
+                "Stack {:#06x}: {} → {}",
+                c.index,
+                fmt_stack_slot(c.from),
+                fmt_stack_slot(c.to)
+            ));
+        }
+        if let Some((a, b)) = self.mode {
+            s.push(format!(" mode: {:?} → {:?}", a, b));
+        }
+        for c in &self.keys {
+            s.push(format!("Key {:#03x}: {:?} → {:?}", c.index, c.from, c.to));
+        }
+        if let Some((a, b)) = self.next_tick {
+            s.push(format!(" tick: {:?} → {:?}", a, b));
+        }
+        if let Some((a, b)) = self.next_timers_tick {
+            s.push(format!("timers: {:?} → {:?}", a, b));
+        }
+        if let Some((a, b)) = self.sound_playing {
+            s.push(format!("sound_playing: {:?} → {:?}", a, b));
+        }
+        if let Some((a, b)) = self.state {
+            s.push(format!("state: {:?} → {:?}", a, b));
+        }
 
-        s.join("\n")
+        f.write_str(&s.join("\n"))
     }
 }
 
-impl std::clone::Clone for Chip8 {
+impl Chip8 {
+    pub fn compare(a: &Chip8, b: &Chip8) -> StateDiff {
+        let depth = a.stack.len().max(b.stack.len());
+        let stack = (0..depth)
+            .map(|index| CellChange {
+                index,
+                from: a.stack.get(index).copied(),
+                to: b.stack.get(index).copied(),
+            })
+            .filter(|c| c.from != c.to)
+            .collect();
+
+        StateDiff {
+            memory: diff_cells(&a.memory, &b.memory),
+            display: diff_cells(a.display.pixels(), b.display.pixels()),
+            v: diff_cells(&a.v, &b.v),
+            pc: diff_value(a.pc, b.pc),
+            st: diff_value(a.st, b.st),
+            dt: diff_value(a.dt, b.dt),
+            pitch: diff_value(a.pitch, b.pitch),
+            i: diff_value(a.i, b.i),
+            stack,
+            mode: diff_value(a.mode, b.mode),
+            keys: diff_cells(&a.keys, &b.keys),
+            next_tick: diff_value(a.next_tick, b.next_tick),
+            next_timers_tick: diff_value(a.next_timers_tick, b.next_timers_tick),
+            sound_playing: diff_value(a.sound_playing, b.sound_playing),
+            state: diff_value(a.state(), b.state()),
+        }
+    }
+}
+
+impl Clone for Chip8 {
     fn clone(&self) -> Self {
-        let mut chip8 = Chip8::new();
+        let mut chip8 = Chip8::with_clock(self.clock.clone());
         chip8.clone_from(self);
         chip8
     }
 
     fn clone_from(&mut self, source: &Self) {
         self.memory.copy_from_slice(&source.memory);
-        self.display.copy_from_slice(&source.display);
+        self.program.clone_from(&source.program);
+        self.display.clone_from(&source.display);
         self.v.copy_from_slice(&source.v);
         self.pc = source.pc;
         self.st = source.st;
         self.dt = source.dt;
+        self.pitch = source.pitch;
         self.i = source.i;
         self.stack = source.stack.clone();
         self.mode = source.mode;
         self.keys.copy_from_slice(&source.keys);
-        self.execution_speed = source.execution_speed;
+        self.polled_keys = source.polled_keys;
+        self.polling_keys = source.polling_keys;
+        self.last_key_wait = source.last_key_wait;
+        self.instruction_count = source.instruction_count;
+        self.tick_count = source.tick_count;
+        self.last_draw = source.last_draw;
+        self.collided_pixels.clone_from(&source.collided_pixels);
+        self.instructions_per_frame = source.instructions_per_frame;
         self.next_tick = source.next_tick;
         self.next_timers_tick = source.next_timers_tick;
+        self.clock = source.clock.clone();
         self.sound_playing = source.sound_playing;
+        // `paused` is run control owned by whoever drives the chip, not machine state,
+        // so restoring a snapshot must not resume or pause execution.
+        self.key_wait = source.key_wait;
+        self.quirks = source.quirks;
+        self.timing = source.timing;
+        self.font_address = source.font_address;
+        self.flags = source.flags;
+        self.flag_storage = source.flag_storage.clone();
+        self.last_op = source.last_op;
+        self.last_access = source.last_access;
+        self.halt = source.halt;
+        self.symbols = source.symbols.clone();
+        self.source_map = source.source_map.clone();
+        self.rng = source.rng.clone();
+        self.trace = source.trace;
+        self.frozen.clone_from(&source.frozen);
     }
 }
 
@@ -152,11 +510,17 @@ V[values]: {}
     Stack: {}
        ST: {}
        DT: {}
+    Pitch: {}
+    State: {:?}
+     Tick: {}
 ",
             &self
                 .display
-                .map(|b| if b != 0 { "■" } else { " " })
-                .chunks(64)
+                .pixels()
+                .iter()
+                .map(|&b| if b != 0 { "■" } else { " " })
+                .collect::<Vec<_>>()
+                .chunks(self.display.width())
                 .map(|line| line.join("") + "\n")
                 .collect::<String>(),
             &self.pc,
@@ -172,42 +536,84 @@ V[values]: {}
                 .collect::<String>(),
             &self.st,
             &self.dt,
+            &self.pitch,
+            self.state(),
+            self.tick_count,
         ))
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Modes {
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Modes {
     Chip8,
-    // Chip48,
-    // SuperChip,
+    Chip48,
+    SuperChip,
+}
+
+impl Modes {
+    pub fn name(self) -> &'static str {
+        match self {
+            Modes::Chip8 => "CHIP-8",
+            Modes::Chip48 => "CHIP-48",
+            Modes::SuperChip => "SCHIP",
+        }
+    }
+}
+
+/// The memory an instruction read or wrote through I.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub address: usize,
+    pub length: usize,
+    pub write: bool,
+}
+
+/// A DXYN as it was executed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DrawCall {
+    /// The instruction count when it ran.
+    pub instruction: u64,
+    pub pc: usize,
+    /// Where the sprite went, after wrapping onto the display.
+    pub x: usize,
+    pub y: usize,
+    /// Sprite rows; 16 for DXY0's 16x16 sprites.
+    pub height: usize,
+    pub i: u16,
+    /// Whether it set VF.
+    pub collided: bool,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum OpCodes {
+pub enum OpCodes {
     Unkn(u16),
-    Cls,                    // CLS — 00E0
-    Ret,                    // RET — 00EE
-    Jmp(usize),             // JMP — 1NNN
-    Call(usize),            // CALL NNN — 2NNN
-    SeVxNn(usize, u8),      // SE VX, NN — 3XNN
-    SneVxNn(usize, u8),     // SNE VX, NN — 4XNN
-    SeVxVy(usize, usize),   // SE VX, VY — 5XY0
-    LdVxNn(usize, u8),      // LD VX, NN — 6XNN
-    AddVxNn(usize, u8),     // ADD VX, NN — 7XNN
-    LdVxVy(usize, usize),   // LD VX, VY — 8XY0
-    OrVxVy(usize, usize),   // OR VX, VY — 8XY1
-    AndVxVy(usize, usize),  // AND VX, VY — 8XY2
-    XorVxVy(usize, usize),  // XOR VX, VY — 8XY3
-    AddVxVy(usize, usize),  // ADD VX, VY — 8XY4
-    SubVxVy(usize, usize),  // SUB VX, VY — 8XY5
-    ShrVxVy(usize, usize),  // SHR VX {, VY} — 8XY6
-    SubnVxVy(usize, usize), // SUBN VX, VY — 8XY7
-    ShlVxVy(usize, usize),  // SHL VX {, VY} — 8XYE
-    SneVxVy(usize, usize),  // SNE VX, VY — 9XY0
-    LdINn(u16),             // LD I, NNN — ANNN
-    JmpV0Nnn(usize),        // JMP V0, NNN — BNNN
-    // JmpVxNnn(usize, usize),       // JMP V0, NNN — BNNN
+    Cls,                            // CLS — 00E0
+    Ret,                            // RET — 00EE
+    ScdN(usize),                    // SCD N — 00CN
+    Scr,                            // SCR — 00FB
+    Scl,                            // SCL — 00FC
+    Low,                            // LOW — 00FE
+    High,                           // HIGH — 00FF
+    Jmp(usize),                     // JMP — 1NNN
+    Call(usize),                    // CALL NNN — 2NNN
+    SeVxNn(usize, u8),              // SE VX, NN — 3XNN
+    SneVxNn(usize, u8),             // SNE VX, NN — 4XNN
+    SeVxVy(usize, usize),           // SE VX, VY — 5XY0
+    LdVxNn(usize, u8),              // LD VX, NN — 6XNN
+    AddVxNn(usize, u8),             // ADD VX, NN — 7XNN
+    LdVxVy(usize, usize),           // LD VX, VY — 8XY0
+    OrVxVy(usize, usize),           // OR VX, VY — 8XY1
+    AndVxVy(usize, usize),          // AND VX, VY — 8XY2
+    XorVxVy(usize, usize),          // XOR VX, VY — 8XY3
+    AddVxVy(usize, usize),          // ADD VX, VY — 8XY4
+    SubVxVy(usize, usize),          // SUB VX, VY — 8XY5
+    ShrVxVy(usize, usize),          // SHR VX {, VY} — 8XY6
+    SubnVxVy(usize, usize),         // SUBN VX, VY — 8XY7
+    ShlVxVy(usize, usize),          // SHL VX {, VY} — 8XYE
+    SneVxVy(usize, usize),          // SNE VX, VY — 9XY0
+    LdINn(u16),                     // LD I, NNN — ANNN
+    JmpV0Nnn(usize),                // JMP V0, NNN — BNNN
     RndVxNn(usize, u8),             // RND VX, NN – CXNN
     DrawVxVyN(usize, usize, usize), // DRW VX, VY, N — DXYN
     SkpVx(usize),                   // SKP VX — EX9E
@@ -218,9 +624,14 @@ enum OpCodes {
     LdStVx(usize),                  // LD ST, VX — FX18
     AddIVx(usize),                  // ADD I, VX — FX1E
     LdFVx(usize),                   // LD F, VX — FX29
+    LdHfVx(usize),                  // LD HF, VX — FX30
     LdBVx(usize),                   // LD B, VX — FX33
     LdIVx(usize),                   // LD [I], VX — FX55
     LdVxI(usize),                   // LD VX, [I] — FX65
+    PlaneN(usize),                  // PLANE N — FN01
+    LdRVx(usize),                   // LD R, VX — FX75
+    LdVxR(usize),                   // LD VX, R — FX85
+    LdPitchVx(usize),               // LD PITCH, VX — FX3A
 }
 
 impl TryFrom<u16> for OpCodes {
@@ -241,13 +652,18 @@ impl TryFrom<u16> for OpCodes {
             0x0000 => match v {
                 0x00EE => OpCodes::Ret,
                 0x00E0 => OpCodes::Cls,
+                0x00FB => OpCodes::Scr,
+                0x00FC => OpCodes::Scl,
+                0x00FE => OpCodes::Low,
+                0x00FF => OpCodes::High,
+                _ if v & 0xFFF0 == 0x00C0 => OpCodes::ScdN(nib3),
                 _ => OpCodes::Unkn(v),
             },
             0x1000 => OpCodes::Jmp(nnn),
             0x2000 => OpCodes::Call(nnn),
             0x3000 => OpCodes::SeVxNn(nib1, byte1),
             0x4000 => OpCodes::SneVxNn(nib1, byte1),
-            0x5000 => OpCodes::SeVxVy(nib1, nib2),
+            0x5000 if nib3 == 0 => OpCodes::SeVxVy(nib1, nib2),
             0x6000 => OpCodes::LdVxNn(nib1, byte1),
             0x7000 => OpCodes::AddVxNn(nib1, byte1),
             0x8000 => match v & 0xF00F {
@@ -262,10 +678,9 @@ impl TryFrom<u16> for OpCodes {
                 0x800E => OpCodes::ShlVxVy(nib1, nib2),
                 _ => OpCodes::Unkn(v),
             },
-            0x9000 => OpCodes::SneVxVy(nib1, nib2),
+            0x9000 if nib3 == 0 => OpCodes::SneVxVy(nib1, nib2),
             0xA000 => OpCodes::LdINn(nnn as u16),
             0xB000 => OpCodes::JmpV0Nnn(nnn),
-            // 0xB000 => OpCodes::JmpVxNnn(nib1, nnn),
             0xC000 => OpCodes::RndVxNn(nib1, byte1),
             0xD000 => OpCodes::DrawVxVyN(nib1, nib2, nib3),
             0xE000 => match v & 0xF0FF {
@@ -281,8 +696,13 @@ impl TryFrom<u16> for OpCodes {
                 0xF00A => OpCodes::LdVxK(nib1),
                 0xF018 => OpCodes::LdStVx(nib1),
                 0xF029 => OpCodes::LdFVx(nib1),
+                0xF030 => OpCodes::LdHfVx(nib1),
                 0xF033 => OpCodes::LdBVx(nib1),
                 0xF01E => OpCodes::AddIVx(nib1),
+                0xF001 if nib1 <= 3 => OpCodes::PlaneN(nib1),
+                0xF075 => OpCodes::LdRVx(nib1),
+                0xF085 => OpCodes::LdVxR(nib1),
+                0xF03A => OpCodes::LdPitchVx(nib1),
                 _ => OpCodes::Unkn(v),
             },
             _ => OpCodes::Unkn(v),
@@ -290,55 +710,675 @@ impl TryFrom<u16> for OpCodes {
     }
 }
 
+impl OpCodes {
+    /// Whether the instruction can change what's on the display.
+    pub fn changes_display(self) -> bool {
+        matches!(
+            self,
+            OpCodes::Cls
+                | OpCodes::DrawVxVyN(..)
+                | OpCodes::ScdN(_)
+                | OpCodes::Scr
+                | OpCodes::Scl
+                | OpCodes::Low
+                | OpCodes::High
+        )
+    }
+
+    /// Whether the instruction can change V`register`, including VF as a flag.
+    pub fn writes_register(self, register: usize) -> bool {
+        match self {
+            OpCodes::LdVxNn(x, _)
+            | OpCodes::AddVxNn(x, _)
+            | OpCodes::LdVxVy(x, _)
+            | OpCodes::OrVxVy(x, _)
+            | OpCodes::AndVxVy(x, _)
+            | OpCodes::XorVxVy(x, _)
+            | OpCodes::RndVxNn(x, _)
+            | OpCodes::LdVxDt(x)
+            | OpCodes::LdVxK(x) => x == register,
+            OpCodes::AddVxVy(x, _)
+            | OpCodes::SubVxVy(x, _)
+            | OpCodes::ShrVxVy(x, _)
+            | OpCodes::SubnVxVy(x, _)
+            | OpCodes::ShlVxVy(x, _) => x == register || register == 0xF,
+            OpCodes::DrawVxVyN(..) => register == 0xF,
+            OpCodes::LdVxI(x) | OpCodes::LdVxR(x) => register <= x,
+            _ => false,
+        }
+    }
+}
+
+impl From<OpCodes> for u16 {
+    fn from(op: OpCodes) -> u16 {
+        let xy = |x: usize, y: usize| ((x as u16) << 8) | ((y as u16) << 4);
+        let xnn = |x: usize, nn: u8| ((x as u16) << 8) | nn as u16;
+        let x = |x: usize| (x as u16) << 8;
+        match op {
+            OpCodes::Unkn(v) => v,
+            OpCodes::Cls => 0x00E0,
+            OpCodes::Ret => 0x00EE,
+            OpCodes::ScdN(n) => 0x00C0 | n as u16,
+            OpCodes::Scr => 0x00FB,
+            OpCodes::Scl => 0x00FC,
+            OpCodes::Low => 0x00FE,
+            OpCodes::High => 0x00FF,
+            OpCodes::Jmp(nnn) => 0x1000 | nnn as u16,
+            OpCodes::Call(nnn) => 0x2000 | nnn as u16,
+            OpCodes::SeVxNn(vx, nn) => 0x3000 | xnn(vx, nn),
+            OpCodes::SneVxNn(vx, nn) => 0x4000 | xnn(vx, nn),
+            OpCodes::SeVxVy(vx, vy) => 0x5000 | xy(vx, vy),
+            OpCodes::LdVxNn(vx, nn) => 0x6000 | xnn(vx, nn),
+            OpCodes::AddVxNn(vx, nn) => 0x7000 | xnn(vx, nn),
+            OpCodes::LdVxVy(vx, vy) => 0x8000 | xy(vx, vy),
+            OpCodes::OrVxVy(vx, vy) => 0x8001 | xy(vx, vy),
+            OpCodes::AndVxVy(vx, vy) => 0x8002 | xy(vx, vy),
+            OpCodes::XorVxVy(vx, vy) => 0x8003 | xy(vx, vy),
+            OpCodes::AddVxVy(vx, vy) => 0x8004 | xy(vx, vy),
+            OpCodes::SubVxVy(vx, vy) => 0x8005 | xy(vx, vy),
+            OpCodes::ShrVxVy(vx, vy) => 0x8006 | xy(vx, vy),
+            OpCodes::SubnVxVy(vx, vy) => 0x8007 | xy(vx, vy),
+            OpCodes::ShlVxVy(vx, vy) => 0x800E | xy(vx, vy),
+            OpCodes::SneVxVy(vx, vy) => 0x9000 | xy(vx, vy),
+            OpCodes::LdINn(nnn) => 0xA000 | nnn,
+            OpCodes::JmpV0Nnn(nnn) => 0xB000 | nnn as u16,
+            OpCodes::RndVxNn(vx, nn) => 0xC000 | xnn(vx, nn),
+            OpCodes::DrawVxVyN(vx, vy, n) => 0xD000 | xy(vx, vy) | n as u16,
+            OpCodes::SkpVx(vx) => 0xE09E | x(vx),
+            OpCodes::SknpVx(vx) => 0xE0A1 | x(vx),
+            OpCodes::LdVxDt(vx) => 0xF007 | x(vx),
+            OpCodes::LdVxK(vx) => 0xF00A | x(vx),
+            OpCodes::LdDtVx(vx) => 0xF015 | x(vx),
+            OpCodes::LdStVx(vx) => 0xF018 | x(vx),
+            OpCodes::AddIVx(vx) => 0xF01E | x(vx),
+            OpCodes::LdFVx(vx) => 0xF029 | x(vx),
+            OpCodes::LdHfVx(vx) => 0xF030 | x(vx),
+            OpCodes::LdBVx(vx) => 0xF033 | x(vx),
+            OpCodes::LdIVx(vx) => 0xF055 | x(vx),
+            OpCodes::LdVxI(vx) => 0xF065 | x(vx),
+            OpCodes::PlaneN(n) => 0xF001 | x(n),
+            OpCodes::LdRVx(vx) => 0xF075 | x(vx),
+            OpCodes::LdVxR(vx) => 0xF085 | x(vx),
+            OpCodes::LdPitchVx(vx) => 0xF03A | x(vx),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Chip8 {
+    /// A chip running on the system clock.
+    #[cfg(feature = "std")]
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock::default()))
+    }
+
+    /// A chip scheduled against `clock`. Without the `std` feature CXNN's random
+    /// numbers always start from the same seed, see `seed_rng`.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        let now = clock.now();
         Chip8 {
-            memory: [0; 4096],
+            memory: [0; MEMORY_SIZE],
+            program: vec![],
             v: [0; 16],
-            pc: 0x200,
+            pc: PROGRAM_START,
             st: 0,
             dt: 0,
+            pitch: DEFAULT_PITCH,
             i: 0,
-            display: [0; 64 * 32],
+            display: Display::new(display::LORES),
             stack: vec![],
             mode: Modes::Chip8,
+            quirks: Quirks::for_mode(Modes::Chip8),
             keys: [false; 16],
-            next_tick: Instant::now(),
-            next_timers_tick: Instant::now(),
+            polled_keys: [false; 16],
+            polling_keys: [false; 16],
+            last_key_wait: None,
+            instruction_count: 0,
+            tick_count: 0,
+            last_draw: None,
+            collided_pixels: vec![],
+            next_tick: now,
+            next_timers_tick: now,
+            clock,
             sound_playing: false,
-            execution_speed: 1.0,
+            instructions_per_frame: DEFAULT_INSTRUCTIONS_PER_FRAME,
+            paused: false,
+            key_wait: None,
+            halt: None,
+            timing: Timing::Flat,
+            font_address: DEFAULT_FONT_ADDRESS,
+            flags: [0; 16],
+            flag_storage: None,
+            symbols: Arc::default(),
+            source_map: Arc::default(),
+            last_op: None,
+            last_access: None,
+            #[cfg(feature = "std")]
+            rng: StdRng::from_entropy(),
+            #[cfg(not(feature = "std"))]
+            rng: StdRng::seed_from_u64(0),
+            trace: true,
+            frozen: BTreeMap::new(),
+        }
+    }
+
+    pub fn state(&self) -> ExecutionState {
+        if let Some(error) = self.halt {
+            ExecutionState::Halted(error)
+        } else if self.paused {
+            ExecutionState::Paused
+        } else if let Some(wait) = self.key_wait {
+            ExecutionState::WaitingForKey(wait.register)
+        } else {
+            ExecutionState::Running
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Whether LD VX, K is blocked, even while paused.
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.key_wait.is_some()
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        // Reset timers so that we don't immediately jump ahead
+        self.resync_clock();
+    }
+
+    /// Restarts the program from 0x200 without reloading it, so memory (including any
+    /// changes the program made to itself) is kept. Registers, stack, timers and the
+    /// display are cleared.
+    pub fn reset(&mut self) {
+        self.pc = PROGRAM_START;
+        self.v.fill(0);
+        self.i = 0;
+        self.st = 0;
+        self.dt = 0;
+        self.pitch = DEFAULT_PITCH;
+        self.stack.clear();
+        self.display = Display::new(display::LORES);
+        self.key_wait = None;
+        self.polled_keys = [false; 16];
+        self.polling_keys = [false; 16];
+        self.last_key_wait = None;
+        self.last_draw = None;
+        self.collided_pixels.clear();
+        self.halt = None;
+        self.last_op = None;
+        self.last_access = None;
+        self.resync_clock();
+    }
+
+    /// Rebases the instruction and timer schedules onto the current time, keeping
+    /// the offset between them, so that execution continues without catching up.
+    pub fn resync_clock(&mut self) {
+        self.rebase_clock(self.clock.now());
+    }
+
+    /// Goes back to `snapshot`, an earlier clone of this chip, for rewinding. Its
+    /// schedule is rebased onto the current time, so running on from it plays out
+    /// as it did the first time instead of catching up on the time since it was
+    /// taken, with the buzzer on or off as it was then.
+    pub fn rewind_to(&mut self, snapshot: &Chip8) {
+        self.clone_from(snapshot);
+        self.resync_clock();
+    }
+
+    /// The time on the chip's clock, which `next_tick` and `next_timers_tick` are
+    /// measured on.
+    pub fn now(&self) -> Duration {
+        self.clock.now()
+    }
+
+    /// Drops however much of the schedule is more than `MAX_CATCH_UP` behind `now`.
+    pub fn limit_catch_up(&mut self, now: Duration) {
+        if let Some(start) = now.checked_sub(MAX_CATCH_UP) {
+            if self.next_tick.min(self.next_timers_tick) < start {
+                self.rebase_clock(start);
+            }
+        }
+    }
+
+    fn rebase_clock(&mut self, start: Duration) {
+        let earliest = self.next_tick.min(self.next_timers_tick);
+        self.next_tick = start + (self.next_tick - earliest);
+        self.next_timers_tick = start + (self.next_timers_tick - earliest);
+    }
+
+    pub fn save_state(&self) -> SaveState {
+        let earliest = self.next_tick.min(self.next_timers_tick);
+        SaveState {
+            memory: self.memory.to_vec(),
+            display: self.display.clone(),
+            v: self.v,
+            i: self.i,
+            pc: self.pc,
+            stack: self.stack.clone(),
+            dt: self.dt,
+            st: self.st,
+            pitch: self.pitch,
+            flags: self.flags,
+            mode: self.mode,
+            quirks: self.quirks,
+            timing: self.timing,
+            font_address: self.font_address,
+            instructions_per_frame: self.instructions_per_frame,
+            instruction_count: self.instruction_count,
+            tick_count: self.tick_count,
+            key_wait: self.key_wait.map(|wait| wait.register),
+            next_tick: self.next_tick - earliest,
+            next_timers_tick: self.next_timers_tick - earliest,
+        }
+    }
+
+    /// Picks up from `state`, which should be from a chip running the same ROM. The
+    /// chip is left alone if `state` couldn't have come from one.
+    pub fn restore_state(&mut self, state: &SaveState) -> Result<(), SaveStateError> {
+        let invalid = |reason: &str| Err(SaveStateError::Invalid(reason.to_string()));
+        if state.memory.len() != MEMORY_SIZE {
+            return invalid("memory is the wrong size");
+        }
+        let size = state.display.size();
+        if size != display::LORES && size != display::HIRES
+            || state.display.pixels().len() != size.0 * size.1
+        {
+            return invalid("display is the wrong size");
+        }
+        if state.pc > MEMORY_SIZE - 2
+            || state.stack.iter().any(|&address| address > MEMORY_SIZE - 2)
+        {
+            return invalid("address out of memory");
+        }
+        if state.key_wait.is_some_and(|register| register > 0xF) {
+            return invalid("no such register");
+        }
+        if state.font_address + FONT.len() + BIG_FONT.len() > PROGRAM_START {
+            return invalid("font doesn't fit below 0x200");
+        }
+        // Instruction durations are divided by the speed
+        if state.instructions_per_frame == 0 {
+            return invalid("no instructions per frame");
+        }
+
+        self.memory.copy_from_slice(&state.memory);
+        self.display = state.display.clone();
+        self.v = state.v;
+        self.i = state.i;
+        self.pc = state.pc;
+        self.stack = state.stack.clone();
+        self.dt = state.dt;
+        self.st = state.st;
+        self.pitch = state.pitch;
+        self.flags = state.flags;
+        self.mode = state.mode;
+        self.quirks = state.quirks;
+        self.timing = state.timing;
+        self.font_address = state.font_address;
+        self.instructions_per_frame = state.instructions_per_frame;
+        self.instruction_count = state.instruction_count;
+        self.tick_count = state.tick_count;
+        self.key_wait = state.key_wait.map(|register| KeyWait {
+            register,
+            pressed: None,
+        });
+        self.polled_keys = [false; 16];
+        self.polling_keys = [false; 16];
+        self.last_key_wait = None;
+        self.last_draw = None;
+        self.collided_pixels.clear();
+        self.halt = None;
+        self.last_op = None;
+        self.last_access = None;
+        let now = self.clock.now();
+        self.next_tick = now + state.next_tick;
+        self.next_timers_tick = now + state.next_timers_tick;
+        Ok(())
+    }
+
+    /// A copy that runs on its own from here, without saving its flag registers over
+    /// this one's.
+    pub fn fork(&self) -> Chip8 {
+        let mut chip = self.clone();
+        chip.flag_storage = None;
+        chip
+    }
+
+    /// Restarts CXNN's random numbers from `seed`, e.g. from a hardware RNG.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn mode(&self) -> Modes {
+        self.mode
+    }
+
+    /// Switches platform, resetting the quirks to that platform's profile.
+    pub fn set_mode(&mut self, mode: Modes) {
+        self.mode = mode;
+        self.quirks = Quirks::for_mode(mode);
+    }
+
+    /// The loaded ROM, as it was before the program had a chance to modify itself.
+    pub fn program(&self) -> &[u8] {
+        &self.program
+    }
+
+    /// Where the ROM was loaded in memory.
+    pub fn program_range(&self) -> Range<usize> {
+        PROGRAM_START..PROGRAM_START + self.program.len()
+    }
+
+    /// Where the small and big fonts are in memory.
+    pub fn font_range(&self) -> Range<usize> {
+        self.font_address..self.big_font_address()
+    }
+
+    pub fn big_font_range(&self) -> Range<usize> {
+        self.big_font_address()..self.big_font_address() + BIG_FONT.len()
+    }
+
+    pub fn symbols(&self) -> &Symbols {
+        &self.symbols
+    }
+
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    /// Which keys the program checked with SKP/SKNP during the last 60Hz frame.
+    pub fn polled_keys(&self) -> [bool; 16] {
+        self.polled_keys
+    }
+
+    pub fn instruction_count(&self) -> u64 {
+        self.instruction_count
+    }
+
+    pub fn tick_count(&self) -> u64 {
+        self.tick_count
+    }
+
+    /// The register and key of the most recently completed LD VX, K.
+    pub fn last_key_wait(&self) -> Option<(usize, usize)> {
+        self.last_key_wait
+    }
+
+    pub fn last_draw(&self) -> Option<DrawCall> {
+        self.last_draw
+    }
+
+    pub fn last_access(&self) -> Option<MemoryAccess> {
+        self.last_access
+    }
+
+    pub fn collided_pixels(&self) -> &[(usize, usize)] {
+        &self.collided_pixels
+    }
+
+    /// The opcode at PC, which runs next.
+    pub fn next_opcode(&self) -> u16 {
+        let byte = |address: usize| self.memory.get(address).copied().unwrap_or(0);
+        u16::from_be_bytes([byte(self.pc), byte(self.pc + 1)])
+    }
+
+    /// The most recently executed instruction.
+    pub fn last_op(&self) -> Option<OpCodes> {
+        self.last_op
+    }
+
+    /// Whether the buzzer is on, i.e. the sound timer is running.
+    pub fn is_sound_playing(&self) -> bool {
+        self.sound_playing
+    }
+
+    /// How many times higher than usual the XO-CHIP pitch register has the buzzer.
+    pub fn pitch_ratio(&self) -> f32 {
+        libm::exp2f((self.pitch as f32 - DEFAULT_PITCH as f32) / 48.)
+    }
+
+    pub fn dt(&self) -> u8 {
+        self.dt
+    }
+
+    pub fn st(&self) -> u8 {
+        self.st
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn i(&self) -> u16 {
+        self.i
+    }
+
+    pub fn v(&self) -> [u8; 16] {
+        self.v
+    }
+
+    /// Return addresses, innermost last.
+    pub fn stack(&self) -> &[usize] {
+        &self.stack
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn set_v(&mut self, x: usize, value: u8) {
+        self.v[x] = value;
+    }
+
+    pub fn set_i(&mut self, i: u16) {
+        self.i = i;
+    }
+
+    /// Moves execution to `pc`, kept where a whole instruction can be read.
+    pub fn set_pc(&mut self, pc: usize) {
+        self.pc = pc.min(MEMORY_SIZE - 2);
+    }
+
+    pub fn set_dt(&mut self, dt: u8) {
+        self.dt = dt;
+    }
+
+    pub fn set_st(&mut self, st: u8) {
+        self.st = st;
+    }
+
+    /// Copies `bytes` into memory from `address`, which they must fit after.
+    pub fn write_memory(&mut self, address: usize, bytes: &[u8]) {
+        self.memory[address..address + bytes.len()].copy_from_slice(bytes);
+    }
+
+    /// Holds `address` at `value` from now on, replacing any earlier freeze of it.
+    pub fn freeze(&mut self, address: usize, value: u8) {
+        self.frozen.insert(address, value);
+        self.memory[address] = value;
+    }
+
+    /// Returns whether `address` was frozen.
+    pub fn unfreeze(&mut self, address: usize) -> bool {
+        self.frozen.remove(&address).is_some()
+    }
+
+    pub fn frozen(&self) -> &BTreeMap<usize, u8> {
+        &self.frozen
+    }
+
+    /// Checks that the `len` bytes from I are in memory, unless it wraps, halting the
+    /// current instruction if they aren't.
+    fn check_memory_from_i(&mut self, len: usize) -> bool {
+        if self.i as usize + len <= MEMORY_SIZE || self.quirks.wrap_memory {
+            return true;
         }
+        self.pc -= 2;
+        self.halt = Some(Chip8Error::MemoryOutOfBounds {
+            addr: self.pc,
+            i: self.i,
+            len,
+        });
+        false
+    }
+
+    /// Moves the PC to `target`, halting the current instruction if there's no whole
+    /// instruction there.
+    fn jump(&mut self, target: usize) {
+        if target + 1 < MEMORY_SIZE {
+            self.pc = target;
+            return;
+        }
+        self.pc -= 2;
+        self.halt = Some(Chip8Error::JumpOutOfBounds {
+            addr: self.pc,
+            target,
+        });
+    }
+
+    /// The font character VX selects: its low nibble, like the original interpreters,
+    /// or with `strict_font`, VX itself up to `last`, halting past it.
+    fn font_digit(&mut self, x: usize, last: usize) -> Option<usize> {
+        let digit = self.v[x];
+        if !self.quirks.strict_font {
+            return Some((digit & 0xF) as usize);
+        }
+        if digit as usize <= last {
+            return Some(digit as usize);
+        }
+        self.pc -= 2;
+        self.halt = Some(Chip8Error::InvalidFontDigit {
+            addr: self.pc,
+            digit,
+        });
+        None
+    }
+
+    /// Notes the access of `length` bytes from I, for the script's on_memory hook
+    /// and the heatmap.
+    fn record_access(&mut self, length: usize, write: bool) {
+        self.last_access = Some(MemoryAccess {
+            address: self.i as usize,
+            length,
+            write,
+        });
+    }
+
+    /// The address `offset` bytes from I, wrapped into memory.
+    fn i_address(&self, offset: usize) -> usize {
+        (self.i as usize + offset) % MEMORY_SIZE
+    }
+
+    fn big_font_address(&self) -> usize {
+        self.font_address + FONT.len()
     }
 
-    pub fn load(&mut self, filename: &str) -> Result<(), std::io::Error> {
+    /// Loads a ROM from disk, persisting its flag registers in a `.flags` file next to it.
+    /// Octo source files (`.8o`) are compiled first, and `.zip` and `.gz` archives
+    /// extracted. Labels come from the source and from a `.sym` file next to the ROM,
+    /// source lines from the source or a `.map` file. `filename` can also be `-` for
+    /// stdin or a URL, which have no files next to them.
+    #[cfg(feature = "std")]
+    pub fn load(&mut self, filename: &str) -> Result<(), LoadError> {
+        let data = fetch::read(filename)?;
+        let is_file = fetch::is_file(filename);
+        let (rom, mut symbols, source_map) = if octo::is_source(filename) {
+            let source = String::from_utf8(data)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let assembled = octo::compile(&source)?;
+            let mut lines = assembled.lines;
+            lines.source = filename.to_string();
+            (assembled.rom, assembled.symbols, lines)
+        } else {
+            let source_map = if is_file {
+                SourceMap::load(filename).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    None
+                })
+            } else {
+                None
+            };
+            (
+                archive::extract(filename, data)?,
+                Symbols::default(),
+                source_map.unwrap_or_default(),
+            )
+        };
+        self.load_bytes(&rom)?;
+
+        if is_file {
+            match Symbols::load(filename) {
+                Ok(Some(file)) => symbols.extend(file),
+                Ok(None) => {}
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        self.symbols = Arc::new(symbols);
+        self.source_map = Arc::new(source_map);
+
+        if is_file {
+            self.set_flag_storage(Some(Arc::new(FlagFile {
+                path: format!("{}.flags", filename).into(),
+            })));
+        }
+        Ok(())
+    }
+
+    /// Loads a ROM from memory. Flag registers start cleared and are not persisted.
+    pub fn load_bytes(&mut self, rom: &[u8]) -> Result<(), LoadError> {
+        if rom.is_empty() {
+            return Err(LoadError::Empty);
+        }
+        if rom.len() > MAX_ROM_SIZE {
+            return Err(LoadError::TooLarge { size: rom.len() });
+        }
         self.memory.fill(0);
 
-        self.memory[0..(16 * 5)].copy_from_slice(&[
-            0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-            0x20, 0x60, 0x20, 0x20, 0x70, // 1
-            0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-            0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-            0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-            0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-            0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-            0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-            0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-            0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-            0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-            0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-            0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-            0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-            0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-            0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-        ]);
-        let mut file = File::open(filename)?;
-        let file_length = file.metadata().unwrap().len() as usize;
-        file.read_exact(&mut self.memory[0x200..0x200 + file_length])
-            .expect("Failed to read file");
+        let font = self.font_address;
+        self.memory[font..font + FONT.len()].copy_from_slice(&FONT);
+        let big_font = self.big_font_address();
+        self.memory[big_font..big_font + BIG_FONT.len()].copy_from_slice(&BIG_FONT);
+
+        self.memory[PROGRAM_START..PROGRAM_START + rom.len()].copy_from_slice(rom);
+        self.program = rom.to_vec();
+
+        self.set_flag_storage(None);
+        self.symbols = Arc::default();
+        self.source_map = Arc::default();
         Ok(())
     }
 
+    /// Persists the flag registers to `storage` from now on, starting from what it
+    /// has saved. `load` keeps them in a file next to the ROM.
+    pub fn set_flag_storage(&mut self, storage: Option<Arc<dyn FlagStorage>>) {
+        self.flags.fill(0);
+        if let Some(storage) = &storage {
+            storage.load(&mut self.flags);
+        }
+        self.flag_storage = storage;
+    }
+
+    fn save_flags(&self) {
+        if let Some(storage) = &self.flag_storage {
+            storage.save(&self.flags);
+        }
+    }
+
     pub fn step_debug(&mut self) {
+        self.tick_count += 1;
         if self.next_timers_tick < self.next_tick {
             if self.st > 0 {
                 self.st -= 1;
@@ -346,88 +1386,272 @@ impl Chip8 {
             if self.dt > 0 {
                 self.dt -= 1;
             }
-            self.next_timers_tick += Duration::from_secs_f32(1.0 / (60.0 * self.execution_speed));
+            self.polled_keys = core::mem::take(&mut self.polling_keys);
+            self.next_timers_tick += FRAME_DURATION;
         } else {
             self.tick();
-            self.next_tick += Duration::from_secs_f32(1.0 / (700.0 * self.execution_speed));
+            self.next_tick += self.last_op_duration();
+            for (&address, &value) in &self.frozen {
+                self.memory[address] = value;
+            }
         }
         if self.st > 0 && !self.sound_playing {
-            // TODO
-            // play sound
             self.sound_playing = true;
-            println!("Start sound");
+            #[cfg(feature = "std")]
+            if self.trace {
+                println!("[{}] start sound", self.tick_count);
+            }
         } else if self.st == 0 && self.sound_playing {
-            println!("stop sound");
+            #[cfg(feature = "std")]
+            if self.trace {
+                println!("[{}] stop sound", self.tick_count);
+            }
             self.sound_playing = false;
-            // stop sound
         }
     }
 
+    /// Emulated time taken by the instruction executed in the last `tick`.
+    fn last_op_duration(&self) -> Duration {
+        let op = match (self.timing, self.last_op) {
+            (Timing::CosmacVip, Some(op)) => op,
+            // Polling for keys (and the flat model) spreads instructions evenly over a frame.
+            _ => return FRAME_DURATION / self.instructions_per_frame,
+        };
+        // Approximate execution times of the COSMAC VIP interpreter, in microseconds.
+        let micros = match op {
+            OpCodes::Unkn(_) => 0,
+            OpCodes::Cls => 109,
+            OpCodes::Ret | OpCodes::Jmp(_) | OpCodes::Call(_) | OpCodes::JmpV0Nnn(_) => 105,
+            OpCodes::SeVxNn(..) | OpCodes::SneVxNn(..) | OpCodes::LdINn(_) => 55,
+            OpCodes::SeVxVy(..) | OpCodes::SneVxVy(..) => 73,
+            OpCodes::SkpVx(_) | OpCodes::SknpVx(_) => 73,
+            OpCodes::LdVxNn(..) => 27,
+            OpCodes::AddVxNn(..) => 45,
+            OpCodes::LdVxVy(..)
+            | OpCodes::OrVxVy(..)
+            | OpCodes::AndVxVy(..)
+            | OpCodes::XorVxVy(..)
+            | OpCodes::AddVxVy(..)
+            | OpCodes::SubVxVy(..)
+            | OpCodes::ShrVxVy(..)
+            | OpCodes::SubnVxVy(..)
+            | OpCodes::ShlVxVy(..) => 200,
+            OpCodes::RndVxNn(..) => 164,
+            OpCodes::DrawVxVyN(..) => 22734,
+            OpCodes::LdVxDt(_) | OpCodes::LdDtVx(_) | OpCodes::LdStVx(_) => 45,
+            OpCodes::LdVxK(_) => 0,
+            OpCodes::AddIVx(_) => 86,
+            OpCodes::LdFVx(_) | OpCodes::LdHfVx(_) => 91,
+            OpCodes::LdBVx(_) => 927,
+            OpCodes::LdIVx(_) | OpCodes::LdVxI(_) => 605,
+            // Not part of the COSMAC VIP interpreter.
+            OpCodes::LdRVx(_) | OpCodes::LdVxR(_) => 605,
+            OpCodes::ScdN(_) | OpCodes::Scr | OpCodes::Scl => 109,
+            OpCodes::Low | OpCodes::High | OpCodes::PlaneN(_) => 109,
+            OpCodes::LdPitchVx(_) => 45,
+        };
+        // Scaled so that changing the speed also applies to this model.
+        Duration::from_micros(micros) * DEFAULT_INSTRUCTIONS_PER_FRAME / self.instructions_per_frame
+    }
+
+    /// Runs one 60Hz frame of emulated time, up to and including the next timer
+    /// decrement, for frontends that pace frames themselves.
+    pub fn step_frame(&mut self) {
+        if self.paused {
+            return;
+        }
+        let frame_end = self.next_timers_tick;
+        while self.next_timers_tick == frame_end {
+            self.step_debug();
+        }
+    }
+
+    /// Runs everything due by now on the clock, skipping ahead if it's fallen
+    /// more than `MAX_CATCH_UP` behind.
     pub fn step_with_time(&mut self) {
-        let t = Instant::now();
+        let t = self.clock.now();
+        self.limit_catch_up(t);
+        self.step_until(t);
+    }
+
+    /// Runs everything due by `t` on the clock, however far behind that is.
+    pub fn step_until(&mut self, t: Duration) {
+        if self.paused || self.halt.is_some() {
+            return;
+        }
         while t > self.next_tick && t > self.next_timers_tick {
             self.step_debug();
         }
     }
 
+    fn update_key_wait(&mut self, wait: KeyWait) {
+        match wait.pressed {
+            None => {
+                if let Some(key) = self.keys.iter().position(|&b| b) {
+                    if self.quirks.key_wait_release {
+                        self.key_wait = Some(KeyWait {
+                            pressed: Some(key),
+                            ..wait
+                        });
+                    } else {
+                        self.v[wait.register] = key as u8;
+                        self.key_wait = None;
+                        self.last_key_wait = Some((wait.register, key));
+                    }
+                }
+            }
+            Some(key) => {
+                if !self.keys[key] {
+                    self.v[wait.register] = key as u8;
+                    self.key_wait = None;
+                    self.last_key_wait = Some((wait.register, key));
+                }
+            }
+        }
+    }
+
     pub fn tick(&mut self) {
+        self.last_op = None;
+        self.last_access = None;
+        if self.halt.is_some() {
+            return;
+        }
+        if let Some(wait) = self.key_wait {
+            self.update_key_wait(wait);
+            return;
+        }
+        if self.pc + 1 >= MEMORY_SIZE {
+            self.halt = Some(Chip8Error::PcOutOfBounds { addr: self.pc });
+            return;
+        }
+
         let next_instruction: u16 =
             u16::from_be_bytes(self.memory[self.pc..self.pc + 2].try_into().unwrap());
         self.pc += 2;
 
         let op = OpCodes::try_from(next_instruction).unwrap();
-        println!("{:#06x}: {:?}", next_instruction, op);
+        self.instruction_count += 1;
+        #[cfg(feature = "std")]
+        if self.trace {
+            if let Some(label) = self.symbols.name(self.pc - 2) {
+                println!("{}:", label);
+            }
+            println!(
+                "[{}] {:#05x}: {:04X}  {}",
+                self.tick_count,
+                self.pc - 2,
+                next_instruction,
+                disasm::mnemonic(op, &self.symbols)
+            );
+        }
+        self.last_op = Some(op);
         // println!("{:?}", self);
 
         match op {
             OpCodes::Unkn(c) => {
-                panic!("Unknwon opcode {}", c);
+                self.pc -= 2;
+                self.halt = Some(Chip8Error::UnknownOpcode {
+                    addr: self.pc,
+                    opcode: c,
+                });
             }
             OpCodes::Cls => {
-                self.display.fill(0);
+                self.display.clear();
+            }
+            // Scroll amounts are in display pixels, like Octo and modern SUPER-CHIP
+            OpCodes::ScdN(n) => {
+                self.display.scroll_down(n);
+            }
+            OpCodes::Scr => {
+                self.display.scroll_right(4);
+            }
+            OpCodes::Scl => {
+                self.display.scroll_left(4);
+            }
+            OpCodes::Low => {
+                self.display.resize(display::LORES);
+            }
+            OpCodes::High => {
+                self.display.resize(display::HIRES);
+            }
+            OpCodes::PlaneN(n) => {
+                self.display.selected_planes = n as u8;
             }
             OpCodes::LdINn(n) => {
                 self.i = n;
             }
             OpCodes::RndVxNn(x, n) => {
-                self.v[x] = n & rand::random::<u8>();
+                self.v[x] = n & self.rng.gen::<u8>();
             }
             OpCodes::LdVxNn(x, n) => {
                 self.v[x] = n;
             }
             OpCodes::DrawVxVyN(vx, vy, n) => {
+                // DXY0 draws a 16x16 sprite, two bytes per row
+                let (rows, bytes_per_row) = if n == 0 { (16, 2) } else { (n, 1) };
+                let planes = self.display.selected_planes.count_ones() as usize;
+                if !self.check_memory_from_i(rows * bytes_per_row * planes) {
+                    return;
+                }
+                self.record_access(rows * bytes_per_row * planes, false);
                 self.v[0xf] = 0;
-                let x = (self.v[vx] as usize) % 64; // wrap
-                let y = (self.v[vy] as usize) % 32; // wrap
-                for dy in 0..n {
-                    if (y + dy) >= 32 {
-                        break; // clip
+                self.collided_pixels.clear();
+                let (width, height) = self.display.size();
+                let x = (self.v[vx] as usize) % width; // wrap
+                let y = (self.v[vy] as usize) % height; // wrap
+                let wrap = self.quirks.wrap_sprites;
+                let mut addr = self.i as usize;
+                for plane in [1u8, 2] {
+                    if self.display.selected_planes & plane == 0 {
+                        continue;
                     }
-                    let line: u8 = self.memory[self.i as usize + dy];
-                    for dx in 0..8usize {
-                        if (x + dx) >= 64 {
+                    for dy in 0..rows {
+                        if (y + dy) >= height && !wrap {
                             break; // clip
                         }
-                        let loc = x + dx + (y + dy) * 64;
-                        let cur = self.display[loc];
-                        if ((0b10000000 >> dx) & line) != 0 {
-                            self.display[loc] ^= 255;
-                        }
-                        if cur == 255 && self.display[loc] == 0 {
-                            self.v[0xf] = 1;
+                        let py = (y + dy) % height;
+                        for dx in 0..8 * bytes_per_row {
+                            if (x + dx) >= width && !wrap {
+                                break; // clip
+                            }
+                            let px = (x + dx) % width;
+                            let line =
+                                self.memory[(addr + dy * bytes_per_row + dx / 8) % MEMORY_SIZE];
+                            if ((0b10000000 >> (dx % 8)) & line) != 0
+                                && self.display.toggle(px, py, plane)
+                            {
+                                self.v[0xf] = 1;
+                                self.collided_pixels.push((px, py));
+                            }
                         }
                     }
+                    // With both planes selected, the second plane's sprite follows the first
+                    addr += rows * bytes_per_row;
                 }
+                self.last_draw = Some(DrawCall {
+                    instruction: self.instruction_count,
+                    pc: self.pc - 2,
+                    x,
+                    y,
+                    height: rows,
+                    i: self.i,
+                    collided: self.v[0xf] == 1,
+                });
             }
 
             OpCodes::SkpVx(x) => {
-                if self.keys[self.v[x] as usize] {
-                    self.pc += 2;
+                // Only the low nibble names a key
+                let key = (self.v[x] & 0xF) as usize;
+                self.polling_keys[key] = true;
+                if self.keys[key] {
+                    self.jump(self.pc + 2);
                 }
             }
             OpCodes::SknpVx(x) => {
-                if !self.keys[self.v[x] as usize] {
-                    self.pc += 2;
+                let key = (self.v[x] & 0xF) as usize;
+                self.polling_keys[key] = true;
+                if !self.keys[key] {
+                    self.jump(self.pc + 2);
                 }
             }
             OpCodes::AddVxNn(x, n) => {
@@ -436,37 +1660,50 @@ impl Chip8 {
             OpCodes::Jmp(n) => {
                 self.pc = n;
             }
-            // OpCodes::JmpVxNnn(x, n) => {
-            //     self.pc = n + self.v[x] as usize;
-            // }
             OpCodes::JmpV0Nnn(n) => {
-                self.pc = n + self.v[0] as usize;
+                // BXNN: X is the high nibble of NNN
+                let x = if self.quirks.jump_uses_vx { n >> 8 } else { 0 };
+                self.jump(n + self.v[x] as usize);
             }
             OpCodes::SeVxNn(x, n) => {
                 if self.v[x] == n {
-                    self.pc += 2;
+                    self.jump(self.pc + 2);
                 }
             }
             OpCodes::SneVxNn(x, n) => {
                 if self.v[x] != n {
-                    self.pc += 2;
+                    self.jump(self.pc + 2);
                 }
             }
             OpCodes::SeVxVy(x, y) => {
                 if self.v[x] == self.v[y] {
-                    self.pc += 2;
+                    self.jump(self.pc + 2);
                 }
             }
             OpCodes::SneVxVy(x, y) => {
                 if self.v[x] != self.v[y] {
-                    self.pc += 2;
+                    self.jump(self.pc + 2);
                 }
             }
             OpCodes::Call(n) => {
-                self.stack.push(self.pc);
-                self.pc = n;
+                if self.stack.len() >= self.quirks.stack_depth {
+                    self.pc -= 2;
+                    self.halt = Some(Chip8Error::StackOverflow {
+                        addr: self.pc,
+                        depth: self.quirks.stack_depth,
+                    });
+                } else {
+                    self.stack.push(self.pc);
+                    self.pc = n;
+                }
             }
-            OpCodes::Ret => self.pc = self.stack.pop().unwrap(),
+            OpCodes::Ret => match self.stack.pop() {
+                Some(addr) => self.pc = addr,
+                None => {
+                    self.pc -= 2;
+                    self.halt = Some(Chip8Error::StackUnderflow { addr: self.pc });
+                }
+            },
             OpCodes::LdVxVy(x, y) => {
                 self.v[x] = self.v[y];
             }
@@ -485,49 +1722,68 @@ impl Chip8 {
                 self.v[0xf] = if did_overflow { 1 } else { 0 };
             }
             OpCodes::SubVxVy(x, y) => {
-                let (result, did_overflow) = self.v[x].overflowing_sub(self.v[y]);
+                // VF is set when there's no borrow
+                let (result, did_borrow) = self.v[x].overflowing_sub(self.v[y]);
                 self.v[x] = result;
-                self.v[0xf] = if did_overflow { 1 } else { 0 };
+                self.v[0xf] = if did_borrow { 0 } else { 1 };
             }
             OpCodes::SubnVxVy(x, y) => {
-                let (result, did_overflow) = self.v[y].overflowing_sub(self.v[x]);
+                let (result, did_borrow) = self.v[y].overflowing_sub(self.v[x]);
                 self.v[x] = result;
-                self.v[0xf] = if did_overflow { 1 } else { 0 };
+                self.v[0xf] = if did_borrow { 0 } else { 1 };
             }
             OpCodes::ShrVxVy(x, y) => {
-                if self.mode == Modes::Chip8 {
+                if self.quirks.shift_uses_vy {
                     self.v[x] = self.v[y];
                 }
-                self.v[0xf] = self.v[x] & 1;
+                // Like the other flags, VF ends up holding the flag even as VX
+                let flag = self.v[x] & 1;
                 self.v[x] >>= 1;
+                self.v[0xf] = flag;
             }
             OpCodes::ShlVxVy(x, y) => {
-                if self.mode == Modes::Chip8 {
+                if self.quirks.shift_uses_vy {
                     self.v[x] = self.v[y];
                 }
-                self.v[0xf] = self.v[x] >> 7;
+                let flag = self.v[x] >> 7;
                 self.v[x] <<= 1;
+                self.v[0xf] = flag;
             }
             OpCodes::LdIVx(x) => {
-                for dx in 0..x + 1 {
-                    self.memory[(self.i as usize) + dx] = self.v[dx];
+                if self.check_memory_from_i(x + 1) {
+                    self.record_access(x + 1, true);
+                    for dx in 0..x + 1 {
+                        self.memory[self.i_address(dx)] = self.v[dx];
+                    }
                 }
             }
             OpCodes::LdVxI(x) => {
-                for dx in 0..x + 1 {
-                    self.v[dx] = self.memory[(self.i as usize) + dx];
+                if self.check_memory_from_i(x + 1) {
+                    self.record_access(x + 1, false);
+                    for dx in 0..x + 1 {
+                        self.v[dx] = self.memory[self.i_address(dx)];
+                    }
                 }
             }
+            OpCodes::LdRVx(x) => {
+                self.flags[..=x].copy_from_slice(&self.v[..=x]);
+                self.save_flags();
+            }
+            OpCodes::LdVxR(x) => {
+                self.v[..=x].copy_from_slice(&self.flags[..=x]);
+            }
             OpCodes::LdVxK(x) => {
-                if let Some(key) = self.keys.iter().position(|&b| b) {
-                    self.v[x] = key as u8;
-                } else {
-                    self.pc -= 2;
-                }
+                self.key_wait = Some(KeyWait {
+                    register: x,
+                    pressed: None,
+                });
             }
             OpCodes::LdStVx(x) => {
                 self.st = self.v[x];
             }
+            OpCodes::LdPitchVx(x) => {
+                self.pitch = self.v[x];
+            }
             OpCodes::LdDtVx(x) => {
                 self.dt = self.v[x];
             }
@@ -535,15 +1791,25 @@ impl Chip8 {
                 self.v[x] = self.dt;
             }
             OpCodes::LdFVx(x) => {
-                self.i = (self.v[x] * 0x5) as u16;
+                if let Some(digit) = self.font_digit(x, 0xF) {
+                    self.i = (self.font_address + digit * 5) as u16;
+                }
+            }
+            OpCodes::LdHfVx(x) => {
+                if let Some(digit) = self.font_digit(x, BIG_FONT.len() / 10 - 1) {
+                    self.i = (self.big_font_address() + digit * 10) as u16;
+                }
             }
             OpCodes::AddIVx(x) => {
-                self.i += self.v[x] as u16;
+                self.i = self.i.wrapping_add(self.v[x] as u16);
             }
             OpCodes::LdBVx(x) => {
-                self.memory[(self.i as usize)] = self.v[x] / 100;
-                self.memory[(self.i as usize) + 1] = (self.v[x] / 10) % 10;
-                self.memory[(self.i as usize) + 2] = self.v[x] % 10;
+                if self.check_memory_from_i(3) {
+                    self.record_access(3, true);
+                    self.memory[self.i_address(0)] = self.v[x] / 100;
+                    self.memory[self.i_address(1)] = (self.v[x] / 10) % 10;
+                    self.memory[self.i_address(2)] = self.v[x] % 10;
+                }
             }
         }
     }
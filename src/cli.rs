@@ -0,0 +1,474 @@
+use crate::{
+    buzzer::{Tone, Waveform},
+    input::KeyRepeat,
+    netplay::NetplayRole,
+    op_breakpoint::OpBreakpoint,
+    palette::Palette,
+    quirks::QuirkOverrides,
+    regions::Region,
+    scaling::{DisplayFilter, DisplayScaling, ScaleMode},
+};
+use std::{path::Path, time::Duration};
+
+const USAGE: &str = "usage: flake [--ipf <instructions per frame>] [--stack-depth <entries>] [--wrap-memory] [--strict-font] [--palette <default|okabe-ito|ibm|grayscale|RRGGBB,RRGGBB,RRGGBB,RRGGBB>] [--scale <fit|integer|stretch>] [--margin <pixels>] [--filter <nearest|linear|sharp>] [--vertex-shader <glsl>] [--fragment-shader <glsl>] [--rom-dir <dir>] [--region <name=start-end>]... [--break <label, region or 0xaddress>]... [--watch <label, region or 0xaddress>]... [--break-op <mnemonic|write:VX|opcode pattern>]... [--break-on-st] [--break-on-beep] [--compare <quirk=value,...>] [--gdb <port>] [--dap <port>] [--control <port>] [--debugger-window] [--script <file.rhai>] [--font <bmfont.json>] [--repeat-delay <ms>] [--repeat-rate <steps per second>] [--run-in-background] [--threaded] [--record <video|dir>] [--waveform <square|sine|triangle>] [--tone <hz>] [--duty <0-1>] [--spectate <port>] [--host <port>] [--join <host:port>] [--hash-after <instructions>] [rom|-|url|dir]...
+       flake asm <input> [-o <output>]
+       flake disasm <rom> [-o <output>]
+       flake trace <rom> [-n <instructions>] [-o <output>] [--compare <reference trace>]
+       flake monitor <host:port>";
+
+pub enum Command {
+    Run(Box<Options>),
+    /// Assembles (or compiles, for Octo sources) `input` into `output` without
+    /// opening a window.
+    Asm {
+        input: String,
+        output: String,
+    },
+    /// Prints a disassembly of `rom`, or writes it to `output`.
+    Disasm {
+        rom: String,
+        output: Option<String>,
+    },
+    /// Runs `rom` headless and prints its execution trace, or writes it to
+    /// `output`. With `reference`, checks the trace against that one instead, for
+    /// as many instructions as it has.
+    Trace {
+        rom: String,
+        instructions: usize,
+        output: Option<String>,
+        reference: Option<String>,
+    },
+    /// Opens a window showing the debugger of the flake whose control server is
+    /// at `address`, as started by `--debugger-window`.
+    Monitor {
+        address: String,
+    },
+}
+
+pub struct Options {
+    /// ROMs to play through, starting with the first. Directories stand for the
+    /// ROMs in them.
+    pub roms: Vec<String>,
+    /// Overrides the ROM database and the default speed when set.
+    pub instructions_per_frame: Option<u32>,
+    /// Applied on top of the ROM database's quirks.
+    pub quirks: QuirkOverrides,
+    pub palette: Option<Palette>,
+    pub scaling: DisplayScaling,
+    /// Replacements for the built-in display shaders, reloaded when changed.
+    pub vertex_shader: Option<String>,
+    pub fragment_shader: Option<String>,
+    /// Directory listed by the ROM browser.
+    pub rom_dir: String,
+    /// Named parts of memory, shown in the memory view and usable as locations.
+    pub regions: Vec<Region>,
+    /// Breakpoints to set on start, as symbol names, region names or `0x`
+    /// addresses. Regions break on entering them.
+    pub breakpoints: Vec<String>,
+    /// Watchpoints to set on start, the same way, covering all of a region.
+    pub watchpoints: Vec<String>,
+    /// Breakpoints on the instruction about to run, wherever it is.
+    pub op_breakpoints: Vec<OpBreakpoint>,
+    /// Break when the sound timer is set, or when the beep starts or stops.
+    pub break_on_sound_timer: bool,
+    pub break_on_beep: bool,
+    /// Quirks to change in a second chip run alongside, stopping where they diverge.
+    pub compare: Option<QuirkOverrides>,
+    /// Port to serve the GDB remote protocol on, for attaching debuggers.
+    pub gdb_port: Option<u16>,
+    /// Port to serve the Debug Adapter Protocol on, for debugging from editors.
+    pub dap_port: Option<u16>,
+    /// Port to accept JSON commands on, for scripts and other programs.
+    pub control_port: Option<u16>,
+    /// Shows the debugger in a window of its own, run by `flake monitor`.
+    pub debugger_window: bool,
+    /// Rhai script with hooks to run alongside the program.
+    pub script: Option<String>,
+    /// BMFont JSON to use instead of the built-in font.
+    pub font: Option<String>,
+    /// How stepping repeats while J or K is held.
+    pub key_repeat: KeyRepeat,
+    /// Whether to pause while the window is minimized. Losing focus isn't
+    /// noticed, since miniquad doesn't report it.
+    pub pause_in_background: bool,
+    /// Runs the chip on its own thread, without the debugger.
+    pub threaded: bool,
+    /// Where to record a video to from the start, as in `Recording::start`.
+    pub record: Option<String>,
+    /// Replace the buzzer's waveform, frequency at the default pitch and duty
+    /// cycle from the preferences.
+    pub waveform: Option<Waveform>,
+    pub tone_frequency: Option<f32>,
+    pub duty: Option<f32>,
+    /// Port to stream the display and beeper on, for watching from a browser.
+    pub spectate_port: Option<u16>,
+    /// Plays with someone else on another flake, without the debugger.
+    pub netplay: Option<NetplayRole>,
+    /// Runs this many instructions without a window, then prints a hash of the
+    /// display and registers and exits.
+    pub hash_after: Option<u64>,
+}
+
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    match args.get(1).map(String::as_str) {
+        Some("asm") => return parse_asm(&args[2..]),
+        Some("disasm") => return parse_disasm(&args[2..]),
+        Some("trace") => return parse_trace(&args[2..]),
+        Some("monitor") => {
+            return match &args[2..] {
+                [address] => Ok(Command::Monitor {
+                    address: address.clone(),
+                }),
+                _ => Err(USAGE.to_string()),
+            }
+        }
+        _ => {}
+    }
+
+    let mut roms = vec![];
+    let mut instructions_per_frame = None;
+    let mut quirks = QuirkOverrides::default();
+    let mut palette = None;
+    let mut scaling = DisplayScaling::default();
+    let mut vertex_shader = None;
+    let mut fragment_shader = None;
+    let mut rom_dir = String::from("roms");
+    let mut regions = vec![];
+    let mut breakpoints = vec![];
+    let mut watchpoints = vec![];
+    let mut op_breakpoints = vec![];
+    let mut break_on_sound_timer = false;
+    let mut break_on_beep = false;
+    let mut compare = None;
+    let mut gdb_port = None;
+    let mut dap_port = None;
+    let mut control_port = None;
+    let mut script = None;
+    let mut font = None;
+    let mut key_repeat = KeyRepeat::default();
+    let mut pause_in_background = true;
+    let mut threaded = false;
+    let mut debugger_window = false;
+    let mut record = None;
+    let mut waveform = None;
+    let mut tone_frequency = None;
+    let mut duty = None;
+    let mut spectate_port = None;
+    let mut netplay = None;
+    let mut hash_after = None;
+
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ipf" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                instructions_per_frame = match value.parse() {
+                    Ok(n) if n > 0 => Some(n),
+                    _ => return Err(format!("invalid instructions per frame: {}", value)),
+                };
+            }
+            "--stack-depth" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                quirks.stack_depth = match value.parse() {
+                    Ok(depth) if depth > 0 => Some(depth),
+                    _ => return Err(format!("invalid stack depth: {}", value)),
+                };
+            }
+            "--wrap-memory" => quirks.wrap_memory = Some(true),
+            "--strict-font" => quirks.strict_font = Some(true),
+            "--palette" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                palette = Some(Palette::parse(value)?);
+            }
+            "--scale" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                scaling.mode = ScaleMode::parse(value)?;
+            }
+            "--filter" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                scaling.filter = DisplayFilter::parse(value)?;
+            }
+            "--margin" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                scaling.margin = match value.parse() {
+                    Ok(margin) if margin >= 0. => margin,
+                    _ => return Err(format!("invalid margin: {}", value)),
+                };
+            }
+            "--vertex-shader" => {
+                vertex_shader = Some(args.next().ok_or_else(|| USAGE.to_string())?.clone());
+            }
+            "--fragment-shader" => {
+                fragment_shader = Some(args.next().ok_or_else(|| USAGE.to_string())?.clone());
+            }
+            "--rom-dir" => {
+                rom_dir = args.next().ok_or_else(|| USAGE.to_string())?.clone();
+            }
+            "--region" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                regions.push(Region::parse(value)?);
+            }
+            "--break" => {
+                breakpoints.push(args.next().ok_or_else(|| USAGE.to_string())?.clone());
+            }
+            "--watch" => {
+                watchpoints.push(args.next().ok_or_else(|| USAGE.to_string())?.clone());
+            }
+            "--break-op" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                op_breakpoints.push(OpBreakpoint::parse(value)?);
+            }
+            "--break-on-st" => break_on_sound_timer = true,
+            "--break-on-beep" => break_on_beep = true,
+            "--compare" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                compare = Some(QuirkOverrides::parse(value)?);
+            }
+            "--gdb" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                gdb_port = match value.parse() {
+                    Ok(port) => Some(port),
+                    _ => return Err(format!("invalid port: {}", value)),
+                };
+            }
+            "--dap" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                dap_port = match value.parse() {
+                    Ok(port) => Some(port),
+                    _ => return Err(format!("invalid port: {}", value)),
+                };
+            }
+            "--control" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                control_port = match value.parse() {
+                    Ok(port) => Some(port),
+                    _ => return Err(format!("invalid port: {}", value)),
+                };
+            }
+            "--script" => {
+                script = Some(args.next().ok_or_else(|| USAGE.to_string())?.clone());
+            }
+            "--font" => {
+                font = Some(args.next().ok_or_else(|| USAGE.to_string())?.clone());
+            }
+            "--repeat-delay" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                key_repeat.delay = match value.parse() {
+                    Ok(ms) => Duration::from_millis(ms),
+                    _ => return Err(format!("invalid repeat delay: {}", value)),
+                };
+            }
+            "--repeat-rate" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                key_repeat.interval = match value.parse::<f64>() {
+                    Ok(rate) if rate > 0. && rate.is_finite() => Duration::from_secs_f64(1. / rate),
+                    _ => return Err(format!("invalid repeat rate: {}", value)),
+                };
+            }
+            "--run-in-background" => pause_in_background = false,
+            "--threaded" => threaded = true,
+            "--debugger-window" => debugger_window = true,
+            "--record" => {
+                record = Some(args.next().ok_or_else(|| USAGE.to_string())?.clone());
+            }
+            "--waveform" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                waveform = Some(Waveform::parse(value)?);
+            }
+            "--tone" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                tone_frequency = Some(Tone::parse_frequency(value)?);
+            }
+            "--duty" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                duty = Some(Tone::parse_duty(value)?);
+            }
+            "--spectate" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                spectate_port = match value.parse() {
+                    Ok(port) => Some(port),
+                    _ => return Err(format!("invalid port: {}", value)),
+                };
+            }
+            "--host" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                if netplay.is_some() {
+                    return Err("--host and --join can't be used together".to_string());
+                }
+                netplay = match value.parse() {
+                    Ok(port) => Some(NetplayRole::Host(port)),
+                    _ => return Err(format!("invalid port: {}", value)),
+                };
+            }
+            "--join" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                if netplay.is_some() {
+                    return Err("--host and --join can't be used together".to_string());
+                }
+                netplay = Some(NetplayRole::Join(value.clone()));
+            }
+            "--hash-after" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                hash_after = match value.parse() {
+                    Ok(n) => Some(n),
+                    _ => return Err(format!("invalid instruction count: {}", value)),
+                };
+            }
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if arg.starts_with('-') && arg != "-" => return Err(USAGE.to_string()),
+            _ => roms.push(arg.clone()),
+        }
+    }
+    if roms.is_empty() {
+        roms.push(String::from("roms/breakout.ch8"));
+    }
+
+    if threaded && compare.is_some() {
+        return Err("--compare needs the debugger, so can't be used with --threaded".to_string());
+    }
+    if threaded && gdb_port.is_some() {
+        return Err("--gdb needs the debugger, so can't be used with --threaded".to_string());
+    }
+    if threaded && dap_port.is_some() {
+        return Err("--dap needs the debugger, so can't be used with --threaded".to_string());
+    }
+    if threaded && script.is_some() {
+        return Err("--script needs the debugger, so can't be used with --threaded".to_string());
+    }
+    if threaded && debugger_window {
+        return Err(
+            "--debugger-window needs the debugger, so can't be used with --threaded".to_string(),
+        );
+    }
+    if threaded && control_port.is_some() {
+        return Err(
+            "--control needs the chip on the main thread, so can't be used with --threaded"
+                .to_string(),
+        );
+    }
+
+    if netplay.is_some() {
+        let conflict = if threaded {
+            Some("--threaded")
+        } else if compare.is_some() {
+            Some("--compare")
+        } else if gdb_port.is_some() {
+            Some("--gdb")
+        } else if dap_port.is_some() {
+            Some("--dap")
+        } else if script.is_some() {
+            Some("--script")
+        } else if control_port.is_some() {
+            Some("--control")
+        } else if debugger_window {
+            Some("--debugger-window")
+        } else {
+            None
+        };
+        if let Some(option) = conflict {
+            return Err(format!(
+                "netplay has to control the chip alone, so can't be used with {}",
+                option
+            ));
+        }
+    }
+
+    Ok(Command::Run(Box::new(Options {
+        roms,
+        instructions_per_frame,
+        quirks,
+        palette,
+        scaling,
+        vertex_shader,
+        fragment_shader,
+        rom_dir,
+        regions,
+        breakpoints,
+        watchpoints,
+        op_breakpoints,
+        break_on_sound_timer,
+        break_on_beep,
+        compare,
+        gdb_port,
+        dap_port,
+        control_port,
+        debugger_window,
+        script,
+        font,
+        key_repeat,
+        pause_in_background,
+        threaded,
+        record,
+        waveform,
+        tone_frequency,
+        duty,
+        spectate_port,
+        netplay,
+        hash_after,
+    })))
+}
+
+/// Parses `<input> [-o <output>]`.
+fn parse_input_output(args: &[String]) -> Result<(String, Option<String>), String> {
+    let mut input = None;
+    let mut output = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => output = Some(args.next().ok_or_else(|| USAGE.to_string())?.clone()),
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if input.is_none() => input = Some(arg.clone()),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    Ok((input.ok_or_else(|| USAGE.to_string())?, output))
+}
+
+fn parse_disasm(args: &[String]) -> Result<Command, String> {
+    let (rom, output) = parse_input_output(args)?;
+    Ok(Command::Disasm { rom, output })
+}
+
+fn parse_trace(args: &[String]) -> Result<Command, String> {
+    let mut rom = None;
+    let mut instructions = 1000;
+    let mut output = None;
+    let mut reference = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-n" => {
+                let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                instructions = value
+                    .parse()
+                    .map_err(|_| format!("invalid instruction count: {}", value))?;
+            }
+            "-o" => output = Some(args.next().ok_or_else(|| USAGE.to_string())?.clone()),
+            "--compare" => reference = Some(args.next().ok_or_else(|| USAGE.to_string())?.clone()),
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if rom.is_none() => rom = Some(arg.clone()),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    Ok(Command::Trace {
+        rom: rom.ok_or_else(|| USAGE.to_string())?,
+        instructions,
+        output,
+        reference,
+    })
+}
+
+fn parse_asm(args: &[String]) -> Result<Command, String> {
+    let (input, output) = parse_input_output(args)?;
+    let output = output.unwrap_or_else(|| {
+        Path::new(&input)
+            .with_extension("ch8")
+            .to_string_lossy()
+            .into_owned()
+    });
+    Ok(Command::Asm { input, output })
+}
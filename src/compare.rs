@@ -0,0 +1,56 @@
+use crate::{
+    chip8::{Chip8, StateDiff},
+    quirks::QuirkOverrides,
+};
+
+/// Runs a second chip in lockstep with the main one, with some quirks changed, to
+/// find the first step where the two disagree. It keeps running after that, so the
+/// two can still be watched side by side.
+pub struct Comparison {
+    /// Applied on top of the main chip's quirks.
+    pub overrides: QuirkOverrides,
+    pub chip: Chip8,
+    /// Set once the chips have diverged, which is only reported once.
+    diverged: bool,
+}
+
+impl Comparison {
+    pub fn new(main: &Chip8, overrides: QuirkOverrides) -> Comparison {
+        let mut comparison = Comparison {
+            overrides,
+            chip: Chip8::new(),
+            diverged: false,
+        };
+        comparison.restart(main);
+        comparison
+    }
+
+    /// Starts over from a copy of `main`, e.g. after it was loaded, reset or rewound.
+    pub fn restart(&mut self, main: &Chip8) {
+        self.chip = main.fork();
+        self.chip.quirks.apply(&self.overrides);
+        // The main chip's trace is enough to follow both
+        self.chip.trace = false;
+        self.diverged = false;
+    }
+
+    /// Steps the comparison chip as `main` was just stepped, with the same keys held,
+    /// returning how they differ the first time they diverge.
+    pub fn follow(&mut self, main: &Chip8) -> Option<StateDiff> {
+        self.chip.keys = main.keys;
+        self.chip.instructions_per_frame = main.instructions_per_frame;
+        self.chip.timing = main.timing;
+        self.chip.step_debug();
+        if self.diverged {
+            return None;
+        }
+        let mut diff = Chip8::compare(main, &self.chip);
+        // Only the main chip is paused by the debugger
+        diff.state = None;
+        if diff == StateDiff::default() {
+            return None;
+        }
+        self.diverged = true;
+        Some(diff)
+    }
+}
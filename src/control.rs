@@ -0,0 +1,240 @@
+use crate::{chip8::ExecutionState, debugger::RunLimit, screenshot, Stage};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+/// What the connection threads have read, tagged with which client sent it.
+enum Incoming {
+    Connected(usize, TcpStream),
+    Line(usize, String),
+    Disconnected(usize),
+}
+
+/// A command, as a JSON object with a `command` field, e.g.
+/// `{"command": "step", "count": 10}`. An `id` field is echoed in the response.
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case", deny_unknown_fields)]
+enum Request {
+    Load {
+        path: String,
+    },
+    Pause,
+    Resume,
+    /// Runs single instructions, like the debugger's step.
+    Step {
+        #[serde(default = "one")]
+        count: u32,
+    },
+    /// Runs flat out for a number of `instructions` or `frames` and then pauses,
+    /// answering straight away rather than once it has.
+    RunFor {
+        instructions: Option<u64>,
+        frames: Option<u64>,
+    },
+    State,
+    /// The state along with the chip's whole save state, for mirroring it.
+    SaveState,
+    /// Saves the display as a PNG, answering with where it went.
+    Screenshot,
+}
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Serialize)]
+struct State {
+    rom: String,
+    state: String,
+    pc: usize,
+    i: u16,
+    v: [u8; 16],
+    stack: Vec<usize>,
+    dt: u8,
+    st: u8,
+    instruction_count: u64,
+    tick_count: u64,
+    instructions_per_frame: u32,
+}
+
+/// Lets other programs drive the emulator over TCP, e.g. scripts, test rigs and
+/// stream overlays. Each request is a line of JSON and gets a line of JSON back,
+/// with `ok` and either the result or an `error`.
+pub struct ControlServer {
+    incoming: Receiver<Incoming>,
+    clients: HashMap<usize, TcpStream>,
+    /// The port listened on, which is picked by the system when asked for 0.
+    pub port: u16,
+}
+
+impl ControlServer {
+    pub fn listen(port: u16) -> io::Result<ControlServer> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let port = listener.local_addr()?.port();
+        println!("Listening for control commands on port {}", port);
+        let (sender, incoming) = mpsc::channel();
+        thread::spawn(move || serve(listener, sender));
+        Ok(ControlServer {
+            incoming,
+            clients: HashMap::new(),
+            port,
+        })
+    }
+
+    fn send(&mut self, client: usize, response: &Value) {
+        let Some(stream) = self.clients.get_mut(&client) else {
+            return;
+        };
+        if let Err(e) = writeln!(stream, "{}", response) {
+            eprintln!("Failed to write to control client: {}", e);
+            self.clients.remove(&client);
+        }
+    }
+}
+
+/// Accepts any number of clients, each read on a thread of its own.
+fn serve(listener: TcpListener, sender: Sender<Incoming>) {
+    for (client, stream) in listener.incoming().enumerate() {
+        let (writer, reader) = match stream.and_then(|stream| Ok((stream.try_clone()?, stream))) {
+            Ok(streams) => streams,
+            Err(e) => {
+                eprintln!("Control connection failed: {}", e);
+                continue;
+            }
+        };
+        if sender.send(Incoming::Connected(client, writer)).is_err() {
+            return;
+        }
+        let sender = sender.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines().map_while(Result::ok) {
+                if sender.send(Incoming::Line(client, line)).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(Incoming::Disconnected(client));
+        });
+    }
+}
+
+fn state(stage: &Stage) -> State {
+    let chip = &stage.chip;
+    let state = match chip.state() {
+        ExecutionState::Running => "running".to_string(),
+        ExecutionState::Paused => "paused".to_string(),
+        ExecutionState::WaitingForKey(_) => "waiting_for_key".to_string(),
+        ExecutionState::Halted(error) => format!("halted: {}", error),
+    };
+    State {
+        rom: stage.rom_path.clone(),
+        state,
+        pc: chip.pc(),
+        i: chip.i(),
+        v: chip.v(),
+        stack: chip.stack().to_vec(),
+        dt: chip.dt(),
+        st: chip.st(),
+        instruction_count: chip.instruction_count(),
+        tick_count: chip.tick_count(),
+        instructions_per_frame: chip.instructions_per_frame,
+    }
+}
+
+/// Carries out a request, returning what to add to the response.
+fn handle(stage: &mut Stage, request: Request) -> Result<Value, String> {
+    match request {
+        Request::Load { path } => {
+            stage.load_rom(&path).map_err(|e| e.to_string())?;
+            stage.notify(&format!("Loaded {} by remote control", path));
+        }
+        Request::Pause => stage.chip.pause(),
+        Request::Resume => stage.chip.resume(),
+        Request::Step { count } => {
+            stage.chip.pause();
+            for _ in 0..count {
+                stage.debugger.step_instruction(&mut stage.chip);
+            }
+        }
+        Request::RunFor {
+            instructions,
+            frames,
+        } => {
+            let limit = match (instructions, frames) {
+                (Some(n), None) => RunLimit::Instructions(n),
+                (None, Some(n)) => RunLimit::Frames(n),
+                _ => return Err("expected one of instructions or frames".to_string()),
+            };
+            stage.debugger.run_for(&mut stage.chip, limit);
+        }
+        Request::State => {}
+        Request::SaveState => {
+            let mut value = serde_json::to_value(state(stage)).map_err(|e| e.to_string())?;
+            value["save_state"] =
+                serde_json::to_value(stage.chip.save_state()).map_err(|e| e.to_string())?;
+            return Ok(value);
+        }
+        Request::Screenshot => {
+            let path = screenshot::save(&stage.chip.display, &stage.palette, &stage.rom_path)
+                .map_err(|e| e.to_string())?;
+            return Ok(json!({ "path": path }));
+        }
+    }
+    // Everything else answers with the state it left the chip in
+    serde_json::to_value(state(stage)).map_err(|e| e.to_string())
+}
+
+/// Parses and carries out one line from a client.
+fn respond(stage: &mut Stage, line: &str) -> Value {
+    let mut message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => return json!({ "ok": false, "error": e.to_string() }),
+    };
+    let id = message
+        .as_object_mut()
+        .and_then(|fields| fields.remove("id"));
+    let result = serde_json::from_value(message)
+        .map_err(|e| e.to_string())
+        .and_then(|request| handle(stage, request));
+    let mut response = match result {
+        Ok(Value::Object(mut fields)) => {
+            fields.insert("ok".to_string(), json!(true));
+            Value::Object(fields)
+        }
+        Ok(_) => json!({ "ok": true }),
+        Err(error) => json!({ "ok": false, "error": error }),
+    };
+    if let Some(id) = id {
+        response["id"] = id;
+    }
+    response
+}
+
+/// Answers every command received since the last frame.
+pub fn update(stage: &mut Stage) {
+    let Some(mut server) = stage.control.take() else {
+        return;
+    };
+    while let Ok(incoming) = server.incoming.try_recv() {
+        match incoming {
+            Incoming::Connected(client, stream) => {
+                server.clients.insert(client, stream);
+            }
+            Incoming::Line(client, line) => {
+                if !line.trim().is_empty() {
+                    let response = respond(stage, &line);
+                    server.send(client, &response);
+                }
+            }
+            Incoming::Disconnected(client) => {
+                server.clients.remove(&client);
+            }
+        }
+    }
+    stage.control = Some(server);
+}
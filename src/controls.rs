@@ -0,0 +1,307 @@
+use crate::{
+    chip8::Chip8,
+    input::Action,
+    sdf::{Align, SDFFont, SDFText, TextBatcher, TextStyle, VAlign},
+};
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use std::time::{Duration, Instant};
+
+const TEXT_SCALE: f32 = 0.35;
+const MARGIN: f32 = 20.;
+/// Space between controls, and between the two rows, in window pixels.
+const GAP: f32 = 14.;
+/// How long the bar stays up after the pointer last moved.
+const SHOW_FOR: Duration = Duration::from_secs(3);
+/// Characters in the speed slider's track, and the timeline's.
+const SPEED_TRACK: usize = 16;
+const TIMELINE_TRACK: usize = 40;
+const TIMELINE_LABEL: &str = "History ";
+
+const IDLE_COLOR: Vec4 = Vec4::new(0.8, 0.8, 0.8, 1.);
+const HOVER_COLOR: Vec4 = Vec4::new(1., 0.85, 0.2, 1.);
+const FILLED_COLOR: Vec4 = Vec4::new(0.3, 1., 0.4, 1.);
+const EMPTY_COLOR: Vec4 = Vec4::new(0.4, 0.4, 0.4, 1.);
+
+/// The fastest the speed slider goes, in instructions per frame, for a ROM that
+/// normally runs at `default`.
+pub fn max_speed(default: u32) -> u32 {
+    (default * 10).max(100)
+}
+
+/// The speed at `fraction` of the way along the slider, which is logarithmic so
+/// the slow end isn't squashed.
+fn speed_at(fraction: f32, max: u32) -> u32 {
+    ((max as f32).powf(fraction).round() as u32).clamp(1, max)
+}
+
+fn speed_fraction(speed: u32, max: u32) -> f32 {
+    ((speed.max(1) as f32).ln() / (max as f32).ln()).clamp(0., 1.)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Control {
+    Rewind,
+    Back,
+    Play,
+    Step,
+    Slower,
+    Speed,
+    Faster,
+    Normal,
+    Timeline,
+}
+
+impl Control {
+    const ALL: [Control; 9] = [
+        Control::Rewind,
+        Control::Back,
+        Control::Play,
+        Control::Step,
+        Control::Slower,
+        Control::Speed,
+        Control::Faster,
+        Control::Normal,
+        Control::Timeline,
+    ];
+
+    /// The action a button stands in for the key of.
+    fn action(self) -> Option<Action> {
+        Some(match self {
+            Control::Rewind => Action::PlayBackward,
+            Control::Back => Action::UndoStepDebug,
+            Control::Play => Action::TogglePlay,
+            Control::Step => Action::StepDebug,
+            Control::Slower => Action::GoSlower,
+            Control::Faster => Action::GoFaster,
+            Control::Normal => Action::GoNormal,
+            Control::Speed | Control::Timeline => return None,
+        })
+    }
+}
+
+/// What a click or drag on the bar asks for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Request {
+    /// Press the action's key, until the pointer lets go.
+    Press(Action),
+    /// Run this many instructions per frame.
+    Speed(u32),
+    /// Go to this position on the debugger's timeline.
+    Scrub(usize),
+}
+
+/// Buttons for rewinding, pausing and stepping, a speed slider and a timeline to
+/// scrub back and forward through what's been run, along the bottom of the
+/// window, so the debugger can be used with the mouse or a finger instead of keys.
+/// It shows up when the pointer moves, and hides again after a while.
+pub struct ControlBar<'a> {
+    font: &'a SDFFont,
+    /// One for each of `Control::ALL`.
+    texts: Vec<SDFText<'a>>,
+    /// Where each control was drawn last, as its bottom left and top right corners
+    /// in window pixels with y up. Sliders only cover their tracks.
+    areas: Vec<(Control, Vec2, Vec2)>,
+    shown_until: Option<Instant>,
+    hovered: Option<Control>,
+    /// The pointer holding a control down, and what it pressed.
+    held: Option<(u64, Control, Option<Action>)>,
+    /// The slider ranges as last drawn, for turning drags into requests.
+    max_speed: u32,
+    timeline_len: usize,
+}
+
+impl<'a> ControlBar<'a> {
+    pub fn new(font: &'a SDFFont) -> ControlBar<'a> {
+        let texts = Control::ALL
+            .iter()
+            .map(|_| {
+                let mut text = SDFText::new(font, "");
+                text.align = Align {
+                    vertical: VAlign::Bottom,
+                    ..Default::default()
+                };
+                text.style = TextStyle {
+                    outline_width: 0.15,
+                    ..Default::default()
+                };
+                text
+            })
+            .collect();
+        ControlBar {
+            font,
+            texts,
+            areas: vec![],
+            shown_until: None,
+            hovered: None,
+            held: None,
+            max_speed: 1,
+            timeline_len: 0,
+        }
+    }
+
+    fn is_shown(&self) -> bool {
+        self.held.is_some()
+            || self.hovered.is_some()
+            || self.shown_until.is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Keeps the bar up for a while longer.
+    pub fn show(&mut self) {
+        self.shown_until = Some(Instant::now() + SHOW_FOR);
+    }
+
+    fn control_at(&self, position: Vec2) -> Option<(Control, f32)> {
+        self.areas.iter().find_map(|&(control, min, max)| {
+            let inside =
+                (min.x..=max.x).contains(&position.x) && (min.y..=max.y).contains(&position.y);
+            inside.then(|| (control, (position.x - min.x) / (max.x - min.x)))
+        })
+    }
+
+    /// What dragging `control` to `fraction` of the way along asks for.
+    fn slide(&self, control: Control, fraction: f32) -> Option<Request> {
+        let fraction = fraction.clamp(0., 1.);
+        match control {
+            Control::Speed => Some(Request::Speed(speed_at(fraction, self.max_speed))),
+            Control::Timeline => Some(Request::Scrub(
+                (fraction * self.timeline_len as f32).round() as usize,
+            )),
+            _ => None,
+        }
+    }
+
+    /// Shows the bar and highlights the control under `position`, in window pixels
+    /// with y up, dragging the slider `pointer` is holding along.
+    pub fn pointer_moved(&mut self, pointer: u64, position: Vec2) -> Option<Request> {
+        self.show();
+        self.hovered = self.control_at(position).map(|(control, _)| control);
+        let (held_by, control, _) = self.held?;
+        if held_by != pointer {
+            return None;
+        }
+        let &(_, min, max) = self.areas.iter().find(|(c, _, _)| *c == control)?;
+        self.slide(control, (position.x - min.x) / (max.x - min.x))
+    }
+
+    /// Presses the control under `position` on behalf of `pointer`, returning what
+    /// it asks for, or `None` if there isn't one.
+    pub fn press(&mut self, pointer: u64, position: Vec2) -> Option<Request> {
+        if !self.is_shown() || self.held.is_some() {
+            return None;
+        }
+        let (control, fraction) = self.control_at(position)?;
+        let action = control.action();
+        self.held = Some((pointer, control, action));
+        match action {
+            Some(action) => Some(Request::Press(action)),
+            None => self.slide(control, fraction),
+        }
+    }
+
+    /// Lets go of what `pointer` is holding, returning the action to release.
+    pub fn release(&mut self, pointer: u64) -> Option<Action> {
+        let (held_by, _, action) = self.held?;
+        if held_by != pointer {
+            return None;
+        }
+        self.held = None;
+        self.show();
+        action
+    }
+
+    /// Draws the bar from `left` along the bottom of the window, with the chip's
+    /// speed and where it is on the debugger's `timeline`.
+    pub fn draw(
+        &mut self,
+        batcher: &mut TextBatcher,
+        chip: &Chip8,
+        default_speed: u32,
+        timeline: (usize, usize),
+        left: f32,
+    ) {
+        self.max_speed = max_speed(default_speed);
+        self.timeline_len = timeline.1;
+        self.areas.clear();
+        if !self.is_shown() {
+            self.hovered = None;
+            return;
+        }
+
+        let speed = chip.instructions_per_frame;
+        let mut x = left + MARGIN;
+        let mut row_height = 0.;
+        for (control, text) in Control::ALL.into_iter().zip(&mut self.texts) {
+            let color = if self.hovered == Some(control) {
+                HOVER_COLOR
+            } else {
+                IDLE_COLOR
+            };
+            // The sliders' tracks are drawn in two colors, for how far along they are
+            let (prefix, track, suffix) = match control {
+                Control::Rewind => ("<<".to_string(), None, String::new()),
+                Control::Back => ("Back".to_string(), None, String::new()),
+                Control::Play if chip.is_paused() => ("Play".to_string(), None, String::new()),
+                Control::Play => ("Pause".to_string(), None, String::new()),
+                Control::Step => ("Step".to_string(), None, String::new()),
+                Control::Slower => ("-".to_string(), None, String::new()),
+                Control::Speed => (
+                    String::new(),
+                    Some((SPEED_TRACK, speed_fraction(speed, self.max_speed))),
+                    String::new(),
+                ),
+                Control::Faster => ("+".to_string(), None, String::new()),
+                Control::Normal => (format!("{}/frame", speed), None, String::new()),
+                Control::Timeline => {
+                    let (position, len) = timeline;
+                    let fraction = if len == 0 {
+                        1.
+                    } else {
+                        position as f32 / len as f32
+                    };
+                    (
+                        TIMELINE_LABEL.to_string(),
+                        Some((TIMELINE_TRACK, fraction)),
+                        format!(" {}/{}", position, len),
+                    )
+                }
+            };
+            let mut spans = vec![(prefix.clone(), color)];
+            let mut track_text = String::new();
+            if let Some((len, fraction)) = track {
+                let filled = (fraction * len as f32).round() as usize;
+                spans.push(("=".repeat(filled), FILLED_COLOR));
+                spans.push(("=".repeat(len - filled), EMPTY_COLOR));
+                track_text = "=".repeat(len);
+            }
+            spans.push((suffix, color));
+            text.update_spans(&spans);
+
+            // The timeline gets a row of its own above the buttons
+            let size = text.size() * TEXT_SCALE;
+            let corner = if control == Control::Timeline {
+                Vec2::new(left + MARGIN, MARGIN + row_height + GAP)
+            } else {
+                row_height = f32::max(row_height, size.y);
+                let corner = Vec2::new(x, MARGIN);
+                x += size.x + GAP;
+                corner
+            };
+            text.model = Mat4::from_translation(corner.extend(0.))
+                * Mat4::from_scale(Vec3::splat(TEXT_SCALE));
+            batcher.add(text);
+
+            let (start, width) = match track {
+                Some(_) => (
+                    self.font.measure(&prefix).size().x * TEXT_SCALE,
+                    self.font.measure(&track_text).size().x * TEXT_SCALE,
+                ),
+                None => (0., size.x),
+            };
+            self.areas.push((
+                control,
+                Vec2::new(corner.x + start, corner.y),
+                Vec2::new(corner.x + start + width, corner.y + size.y),
+            ));
+        }
+    }
+}
@@ -0,0 +1,365 @@
+use crate::{chip8::ExecutionState, Stage};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+/// The chip is the only thread there is.
+const THREAD_ID: i64 = 1;
+/// Variables reference for the registers scope.
+const REGISTERS: i64 = 1;
+
+/// What the connection thread has read.
+enum Incoming {
+    Connected(TcpStream),
+    Request(Value),
+    Disconnected,
+}
+
+/// A Debug Adapter Protocol server, so editors like VS Code can set breakpoints
+/// in the source of the loaded ROM and step it. Source lines are mapped to
+/// addresses with the ROM's source map. Like the GDB server, messages are read on
+/// a thread of their own and answered from `update` on the main thread.
+pub struct DapServer {
+    incoming: Receiver<Incoming>,
+    client: Option<TcpStream>,
+    seq: i64,
+    /// Set while the client waits for the chip to stop after continuing.
+    running: bool,
+    /// Breakpoint addresses set from each source file, replaced as a whole.
+    breakpoints: HashMap<String, Vec<usize>>,
+}
+
+impl DapServer {
+    pub fn listen(port: u16) -> io::Result<DapServer> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        println!("Waiting for a DAP client on port {}", port);
+        let (sender, incoming) = mpsc::channel();
+        thread::spawn(move || serve(listener, sender));
+        Ok(DapServer {
+            incoming,
+            client: None,
+            seq: 0,
+            running: false,
+            breakpoints: HashMap::new(),
+        })
+    }
+
+    fn send(&mut self, mut message: Value) {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        let body = message.to_string();
+        if let Some(client) = &mut self.client {
+            let frame = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+            if let Err(e) = client.write_all(frame.as_bytes()) {
+                eprintln!("Failed to write to the DAP client: {}", e);
+                self.client = None;
+            }
+        }
+    }
+
+    fn event(&mut self, event: &str, body: Value) {
+        self.send(json!({ "type": "event", "event": event, "body": body }));
+    }
+
+    fn stopped(&mut self, reason: &str, description: Option<String>) {
+        self.running = false;
+        self.event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "description": description,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            }),
+        );
+    }
+
+    fn clear_breakpoints(&mut self, stage: &mut Stage) {
+        for (_, addresses) in self.breakpoints.drain() {
+            for address in addresses {
+                stage.debugger.remove_breakpoint(address);
+            }
+        }
+    }
+}
+
+/// Accepts one client at a time, forwarding its requests.
+fn serve(listener: TcpListener, sender: Sender<Incoming>) {
+    for stream in listener.incoming() {
+        let stream = match stream.and_then(|stream| Ok((stream.try_clone()?, stream))) {
+            Ok((writer, reader)) => {
+                if sender.send(Incoming::Connected(writer)).is_err() {
+                    return;
+                }
+                reader
+            }
+            Err(e) => {
+                eprintln!("DAP connection failed: {}", e);
+                continue;
+            }
+        };
+        let mut reader = BufReader::new(stream);
+        while let Some(message) = read_message(&mut reader) {
+            if sender.send(Incoming::Request(message)).is_err() {
+                return;
+            }
+        }
+        if sender.send(Incoming::Disconnected).is_err() {
+            return;
+        }
+    }
+}
+
+/// Reads a `Content-Length` framed message, or `None` once the client is gone.
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    loop {
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).ok()? == 0 {
+                return None;
+            }
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("Content-Length") {
+                    length = value.trim().parse().ok();
+                }
+            }
+        }
+        let mut body = vec![0; length?];
+        reader.read_exact(&mut body).ok()?;
+        match serde_json::from_slice(&body) {
+            Ok(message) => return Some(message),
+            Err(e) => eprintln!("Ignoring malformed DAP message: {}", e),
+        }
+    }
+}
+
+/// Whether two paths name the same file, however they're written.
+fn same_file(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Where `address` is in the source, as a stack frame.
+fn frame(stage: &Stage, id: usize, address: usize) -> Value {
+    let chip = &stage.chip;
+    let name = match chip.symbols().name(address) {
+        Some(name) => format!("{} ({:#05x})", name, address),
+        None => format!("{:#05x}", address),
+    };
+    let source_map = chip.source_map();
+    let mut frame = json!({
+        "id": id,
+        "name": name,
+        "line": 0,
+        "column": 0,
+        "instructionPointerReference": format!("{:#05x}", address),
+    });
+    if let Some(line) = source_map.line(address) {
+        frame["line"] = json!(line);
+        frame["column"] = json!(1);
+        // Editors want absolute paths, and sources loaded directly may be relative
+        let path = fs::canonicalize(&source_map.source)
+            .map_or(source_map.source.clone(), |path| path.display().to_string());
+        frame["source"] = json!({ "path": path });
+    }
+    frame
+}
+
+fn variables(stage: &Stage) -> Value {
+    let chip = &stage.chip;
+    let variables: Vec<Value> = chip
+        .v()
+        .iter()
+        .enumerate()
+        .map(|(x, value)| (format!("V{:X}", x), format!("{:#04x}", value)))
+        .chain([
+            ("I".to_string(), format!("{:#06x}", chip.i())),
+            ("PC".to_string(), format!("{:#06x}", chip.pc())),
+            ("SP".to_string(), chip.stack().len().to_string()),
+            ("DT".to_string(), chip.dt().to_string()),
+            ("ST".to_string(), chip.st().to_string()),
+        ])
+        .map(|(name, value)| json!({ "name": name, "value": value, "variablesReference": 0 }))
+        .collect();
+    json!({ "variables": variables })
+}
+
+/// Replaces the breakpoints in one source file, answering with where they ended
+/// up, since lines without code move to the next line that has some.
+fn set_breakpoints(stage: &mut Stage, server: &mut DapServer, args: &Value) -> Value {
+    let path = args["source"]["path"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    for address in server.breakpoints.remove(&path).unwrap_or_default() {
+        stage.debugger.remove_breakpoint(address);
+    }
+    let source_map = stage.chip.source_map().clone();
+    let is_loaded = !source_map.is_empty() && same_file(&path, &source_map.source);
+    let mut addresses = vec![];
+    let mut breakpoints = vec![];
+    for requested in args["breakpoints"].as_array().into_iter().flatten() {
+        let line = requested["line"].as_u64().unwrap_or_default() as usize;
+        let found = is_loaded.then(|| source_map.address(line)).flatten();
+        breakpoints.push(match found {
+            Some((address, line)) => {
+                stage.debugger.add_breakpoint(&stage.chip, address);
+                addresses.push(address);
+                json!({ "verified": true, "line": line })
+            }
+            None if is_loaded => {
+                json!({ "verified": false, "line": line, "message": "No code on or after this line" })
+            }
+            None => json!({
+                "verified": false,
+                "line": line,
+                "message": "Not the source of the loaded ROM",
+            }),
+        });
+    }
+    server.breakpoints.insert(path, addresses);
+    json!({ "breakpoints": breakpoints })
+}
+
+/// Carries out a request, returning the response body or an error message.
+fn handle(
+    stage: &mut Stage,
+    server: &mut DapServer,
+    command: &str,
+    args: &Value,
+) -> Result<Value, String> {
+    Ok(match command {
+        "initialize" => json!({
+            "supportsConfigurationDoneRequest": true,
+        }),
+        "launch" | "attach" => {
+            if let Some(program) = args["program"].as_str() {
+                server.clear_breakpoints(stage);
+                stage.load_rom(program).map_err(|e| e.to_string())?;
+                stage.chip.pause();
+            }
+            json!({})
+        }
+        "setBreakpoints" => set_breakpoints(stage, server, args),
+        "configurationDone" => {
+            stage.chip.resume();
+            server.running = true;
+            json!({})
+        }
+        "threads" => json!({ "threads": [{ "id": THREAD_ID, "name": "CHIP-8" }] }),
+        "stackTrace" => {
+            // Callers are where each return address was called from
+            let returns = stage
+                .chip
+                .stack()
+                .iter()
+                .rev()
+                .map(|&to| to.saturating_sub(2));
+            let addresses: Vec<usize> = std::iter::once(stage.chip.pc()).chain(returns).collect();
+            let frames: Vec<Value> = addresses
+                .iter()
+                .enumerate()
+                .map(|(id, &address)| frame(stage, id, address))
+                .collect();
+            json!({ "stackFrames": frames, "totalFrames": frames.len() })
+        }
+        "scopes" => json!({
+            "scopes": [{ "name": "Registers", "variablesReference": REGISTERS, "expensive": false }],
+        }),
+        "variables" => variables(stage),
+        "continue" => {
+            stage.chip.resume();
+            server.running = true;
+            json!({ "allThreadsContinued": true })
+        }
+        // Both step a single instruction, calls included. The stopped event goes
+        // out after the response.
+        "next" | "stepIn" => {
+            server.running = false;
+            stage.debugger.step_instruction(&mut stage.chip);
+            json!({})
+        }
+        "pause" => {
+            stage.chip.pause();
+            json!({})
+        }
+        "disconnect" => {
+            server.clear_breakpoints(stage);
+            server.running = false;
+            stage.chip.resume();
+            json!({})
+        }
+        _ => return Err(format!("Unsupported request '{}'", command)),
+    })
+}
+
+/// Answers everything the client sent since the last frame, and tells it when a
+/// chip it continued stops.
+pub fn update(stage: &mut Stage) {
+    let Some(mut server) = stage.dap.take() else {
+        return;
+    };
+    while let Ok(incoming) = server.incoming.try_recv() {
+        match incoming {
+            Incoming::Connected(client) => {
+                server.client = Some(client);
+                server.running = false;
+                stage.chip.pause();
+                stage.notify("Debug adapter client attached");
+            }
+            Incoming::Request(request) => {
+                let command = request["command"].as_str().unwrap_or_default().to_string();
+                let mut response = json!({
+                    "type": "response",
+                    "request_seq": request["seq"],
+                    "command": command,
+                });
+                match handle(stage, &mut server, &command, &request["arguments"]) {
+                    Ok(body) => {
+                        response["success"] = json!(true);
+                        response["body"] = body;
+                    }
+                    Err(message) => {
+                        response["success"] = json!(false);
+                        response["message"] = json!(message);
+                    }
+                }
+                let succeeded = response["success"] == json!(true);
+                server.send(response);
+                match command.as_str() {
+                    "initialize" => server.event("initialized", json!({})),
+                    "next" | "stepIn" if succeeded => server.stopped("step", None),
+                    _ => {}
+                }
+            }
+            Incoming::Disconnected => {
+                server.clear_breakpoints(stage);
+                server.client = None;
+                server.running = false;
+                stage.notify("Debug adapter client detached");
+            }
+        }
+    }
+    if server.running && stage.chip.is_paused() {
+        let pc = stage.chip.pc();
+        match stage.chip.state() {
+            ExecutionState::Halted(error) => server.stopped("exception", Some(error.to_string())),
+            _ if stage.debugger.has_breakpoint(pc) => server.stopped("breakpoint", None),
+            _ => server.stopped("pause", None),
+        }
+    }
+    stage.dap = Some(server);
+}
@@ -1,112 +1,620 @@
-use crate::{Chip8, Stage};
-use miniquad::Context;
-use miniquad::KeyCode;
-use std::{collections::HashMap, process, time::Instant};
-
-pub const KEY_TOGGLE_PLAY: KeyCode = KeyCode::P;
-pub const KEY_PLAY_BACKWARD: KeyCode = KeyCode::H;
-pub const KEY_STEP_DEBUG: KeyCode = KeyCode::J;
-pub const KEY_UNDO_STEP_DEBUG: KeyCode = KeyCode::K;
-pub const KEY_GO_FASTER: KeyCode = KeyCode::Equal;
-pub const KEY_GO_SLOWER: KeyCode = KeyCode::Minus;
-pub const KEY_GO_NORMAL: KeyCode = KeyCode::Key0;
-pub const KEY_TERMINATE: KeyCode = KeyCode::Semicolon;
+use crate::{
+    chip8::{Chip8, ExecutionState, OpCodes, Timing},
+    compare::Comparison,
+    disasm,
+    draw_log::DrawLog,
+    event_log::{Event, EventLog, StepStart},
+    heatmap::Heatmap,
+    input::Action,
+    op_breakpoint::OpBreakpoint,
+    regions::Region,
+    script::Script,
+    session::Session,
+    Stage,
+};
+use std::{
+    collections::BTreeSet,
+    fmt, process,
+    time::{Duration, Instant},
+};
+
+/// Wall-clock time per rendered frame spent emulating while fast-forwarding.
+const TURBO_FRAME_BUDGET: Duration = Duration::from_millis(12);
+
+/// How long to run before pausing, e.g. to get to the instruction a bug report
+/// says something goes wrong at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunLimit {
+    Instructions(u64),
+    /// Counted in timer decrements, at 60 a second.
+    Frames(u64),
+}
+
+impl fmt::Display for RunLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunLimit::Instructions(n) => write!(f, "{} instructions", n),
+            RunLimit::Frames(n) => write!(f, "{} frames", n),
+        }
+    }
+}
 
 pub struct Debugger {
     pub is_enabled: bool,
-    is_playing: bool,
-    keyboard: HashMap<KeyCode, bool>,
-    consumable_keys: HashMap<KeyCode, bool>,
     states: Vec<Chip8>,
+    /// States after the current one, latest first, kept after going back so the
+    /// timeline can go forward again until something else runs.
+    future: Vec<Chip8>,
+    pub breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeSet<usize>,
+    /// Kept across ROMs, since they don't depend on where anything is.
+    pub op_breakpoints: Vec<OpBreakpoint>,
+    /// Stop whenever execution moves into one of these from outside it.
+    region_breakpoints: Vec<Region>,
+    /// The user's names for parts of memory, kept across ROMs like `op_breakpoints`.
+    pub regions: Vec<Region>,
+    /// Stop whenever the program sets the sound timer (LD ST, VX).
+    pub break_on_sound_timer: bool,
+    /// Stop whenever the buzzer starts or stops.
+    pub break_on_beep: bool,
+    /// Set to run until the next instruction that changes the display, once.
+    run_to_draw: bool,
+    /// Set to run flat out for a while and then pause, with how much is left.
+    run_for: Option<(RunLimit, u64)>,
+    pub draw_log: DrawLog,
+    pub events: EventLog,
+    pub heatmap: Heatmap,
+    /// A chip with different quirks to stop at the first divergence from.
+    pub comparison: Option<Comparison>,
+    /// Hooks run on every frame, instruction and memory access.
+    pub script: Option<Script>,
 }
 
 impl Debugger {
     pub fn new() -> Debugger {
         Debugger {
             is_enabled: true,
-            is_playing: false,
-            keyboard: HashMap::new(),
-            consumable_keys: HashMap::new(),
             states: vec![],
+            future: vec![],
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+            op_breakpoints: vec![],
+            region_breakpoints: vec![],
+            regions: vec![],
+            break_on_sound_timer: false,
+            break_on_beep: false,
+            run_to_draw: false,
+            run_for: None,
+            draw_log: DrawLog::default(),
+            events: EventLog::default(),
+            heatmap: Heatmap::default(),
+            comparison: None,
+            script: None,
+        }
+    }
+    /// Forgets everything tied to the previously loaded ROM.
+    pub fn reset(&mut self) {
+        self.states.clear();
+        self.future.clear();
+        self.breakpoints.clear();
+        self.region_breakpoints.clear();
+        self.watchpoints.clear();
+        self.run_to_draw = false;
+        self.run_for = None;
+        self.draw_log.clear();
+        self.events.clear();
+        self.heatmap.clear();
+    }
+
+    pub fn session(&self, chip: &Chip8) -> Session {
+        Session {
+            breakpoints: self.breakpoints.iter().copied().collect(),
+            watchpoints: self.watchpoints.iter().copied().collect(),
+            instructions_per_frame: chip.instructions_per_frame,
+            last_pc: chip.pc(),
+        }
+    }
+
+    /// Sets up the breakpoints and watchpoints from `session`, dropping any outside
+    /// memory, and its speed unless `keep_speed`, returning a message saying what
+    /// was restored.
+    pub fn restore_session(
+        &mut self,
+        session: &Session,
+        chip: &mut Chip8,
+        keep_speed: bool,
+    ) -> String {
+        let memory_size = chip.memory().len();
+        let in_memory = |address: &usize| *address < memory_size;
+        self.breakpoints = session
+            .breakpoints
+            .iter()
+            .copied()
+            .filter(in_memory)
+            .collect();
+        self.watchpoints = session
+            .watchpoints
+            .iter()
+            .copied()
+            .filter(in_memory)
+            .collect();
+        let dropped = session
+            .breakpoints
+            .iter()
+            .chain(&session.watchpoints)
+            .filter(|address| !in_memory(address))
+            .count();
+
+        let mut message = format!(
+            "Restored session: {} breakpoint(s), {} watchpoint(s)",
+            self.breakpoints.len(),
+            self.watchpoints.len()
+        );
+        if !keep_speed && session.instructions_per_frame > 0 {
+            chip.instructions_per_frame = session.instructions_per_frame;
+            message.push_str(&format!(
+                ", {} instructions/frame",
+                session.instructions_per_frame
+            ));
+        }
+        message.push_str(&format!(", last PC {:#06x}", session.last_pc));
+        if dropped > 0 {
+            message.push_str(&format!("; dropped {} outside memory", dropped));
+        }
+        message
+    }
+
+    /// Returns a message saying where the breakpoint went.
+    pub fn add_breakpoint(&mut self, chip: &Chip8, address: usize) -> String {
+        self.breakpoints.insert(address);
+        format!("Added breakpoint at {}", location(chip, address))
+    }
+
+    pub fn add_region_breakpoint(&mut self, region: &Region) -> String {
+        self.region_breakpoints.push(region.clone());
+        format!(
+            "Added breakpoint on entering {} ({:#06x}-{:#06x})",
+            region.name,
+            region.range.start,
+            region.range.end - 1
+        )
+    }
+
+    /// Starts the comparison over from the chip's current state.
+    pub fn restart_comparison(&mut self, chip: &Chip8) {
+        if let Some(comparison) = &mut self.comparison {
+            comparison.restart(chip);
+        }
+    }
+
+    /// Steps the comparison chip along with `chip`, printing how they differ and
+    /// returning a message if they just diverged. `pc` is where `chip` was.
+    fn follow_comparison(&mut self, chip: &Chip8, pc: usize) -> Option<String> {
+        let diff = self.comparison.as_mut()?.follow(chip)?;
+        println!("Differences from the comparison chip:\n{}", diff);
+        Some(format!(
+            "Quirk comparison diverged at {} (instruction {})",
+            location(chip, pc),
+            chip.instruction_count()
+        ))
+    }
+
+    /// Keeps `chip` as it is now for going back to, before it runs on.
+    fn push_state(&mut self, chip: &Chip8) {
+        self.future.clear();
+        self.states.push(chip.clone());
+    }
+
+    /// Goes back to the previous state, keeping the current one to go forward to,
+    /// returning whether there was one.
+    fn go_back(&mut self, chip: &mut Chip8) -> bool {
+        let Some(prev) = self.states.pop() else {
+            return false;
+        };
+        self.future.push(chip.clone());
+        chip.rewind_to(&prev);
+        true
+    }
+
+    /// Where the chip is in the states kept for going back and forward through, and
+    /// how many there are.
+    pub fn timeline(&self) -> (usize, usize) {
+        let position = self.states.len();
+        (position, position + self.future.len())
+    }
+
+    /// Goes back or forward to the state at `position` on the timeline, pausing
+    /// there.
+    pub fn scrub_to(&mut self, chip: &mut Chip8, position: usize) {
+        chip.pause();
+        while self.states.len() > position && self.go_back(chip) {}
+        while self.states.len() < position {
+            let Some(next) = self.future.pop() else {
+                break;
+            };
+            self.states.push(chip.clone());
+            chip.rewind_to(&next);
+        }
+        self.restart_comparison(chip);
+    }
+
+    pub fn has_breakpoint(&self, address: usize) -> bool {
+        self.breakpoints.contains(&address)
+    }
+
+    /// Runs the script's hooks for the step that started at `start`, returning why
+    /// to stop, if the script paused or failed. Failed scripts are dropped.
+    fn run_script(&mut self, chip: &mut Chip8, start: StepStart) -> Option<String> {
+        if chip.instruction_count() == start.instruction_count {
+            return None;
+        }
+        let script = self.script.as_mut()?;
+        match script.step(chip, start.pc) {
+            Ok(false) => None,
+            Ok(true) => Some(format!(
+                "Paused by {} at {}",
+                script.path,
+                location(chip, start.pc)
+            )),
+            Err(e) => {
+                self.script = None;
+                Some(format!("{}; script stopped", e))
+            }
         }
     }
-    pub fn consume_key(&mut self, keycode: KeyCode) -> bool {
-        let result = *self.consumable_keys.get(&keycode).unwrap_or(&false);
-        self.consumable_keys.insert(keycode, false);
-        result
+
+    /// Runs the script's frame hook, returning a message like `run_script`.
+    fn run_script_frame(&mut self, chip: &mut Chip8) -> Option<String> {
+        let script = self.script.as_mut()?;
+        match script.frame(chip) {
+            Ok(false) => None,
+            Ok(true) => Some(format!("Paused by {}", script.path)),
+            Err(e) => {
+                self.script = None;
+                Some(format!("{}; script stopped", e))
+            }
+        }
     }
-    pub fn is_key_down(&mut self, keycode: KeyCode) -> bool {
-        *self.keyboard.get(&keycode).unwrap_or(&false)
+
+    pub fn remove_breakpoint(&mut self, address: usize) -> bool {
+        self.breakpoints.remove(&address)
     }
-    pub fn key_down_event(&mut self, keycode: KeyCode) {
-        self.keyboard.insert(keycode, true);
-        self.consumable_keys.insert(keycode, true);
+
+    pub fn add_watchpoint(&mut self, address: usize) {
+        self.watchpoints.insert(address);
     }
-    pub fn key_up_event(&mut self, keycode: KeyCode) {
-        self.keyboard.insert(keycode, false);
-        self.consumable_keys.insert(keycode, false);
+
+    pub fn remove_watchpoint(&mut self, address: usize) -> bool {
+        self.watchpoints.remove(&address)
+    }
+
+    /// Steps until an instruction runs, or the chip can't make progress on its own,
+    /// keeping the state it started from for undoing.
+    pub fn step_instruction(&mut self, chip: &mut Chip8) {
+        self.push_state(chip);
+        let start = StepStart::of(chip);
+        while chip.instruction_count() == start.instruction_count
+            && !chip.is_waiting_for_key()
+            && !matches!(chip.state(), ExecutionState::Halted(_))
+        {
+            let step = StepStart::of(chip);
+            chip.step_debug();
+            self.draw_log.record(chip);
+            self.events.record_step(chip, step);
+            self.heatmap.record(chip, step);
+            if let Some(message) = self.run_script(chip, step) {
+                println!("{}", message);
+            }
+        }
+    }
+
+    /// Runs the chip as fast as it goes until `limit` is reached, unless something
+    /// else stops it first.
+    pub fn run_for(&mut self, chip: &mut Chip8, limit: RunLimit) {
+        let (RunLimit::Instructions(n) | RunLimit::Frames(n)) = limit;
+        if n == 0 {
+            return;
+        }
+        self.run_for = Some((limit, n));
+        chip.resume();
+    }
+
+    /// Counts a step towards `run_for`, returning the limit once it's reached.
+    fn count_down(&mut self, executed: bool, ticked: bool) -> Option<RunLimit> {
+        let (limit, left) = self.run_for.as_mut()?;
+        let counted = match limit {
+            RunLimit::Instructions(_) => executed,
+            RunLimit::Frames(_) => ticked,
+        };
+        if counted {
+            *left -= 1;
+        }
+        if *left > 0 {
+            return None;
+        }
+        let limit = *limit;
+        self.run_for = None;
+        Some(limit)
+    }
+
+    fn watched_values(&self, chip: &Chip8) -> Vec<u8> {
+        // Watchpoints from the GDB stub aren't checked against the memory size
+        self.watchpoints
+            .iter()
+            .filter_map(|&addr| chip.memory().get(addr).copied())
+            .collect()
+    }
+
+    /// Runs the chip up to the current time like `Chip8::step_with_time`, but stops
+    /// early (and pauses) when a breakpoint is reached or a watched address changes,
+    /// returning why.
+    fn step_with_time_until_break(&mut self, chip: &mut Chip8) -> Option<String> {
+        let t = chip.now();
+        chip.limit_catch_up(t);
+        self.run_until_break(chip, |chip| t > chip.next_tick && t > chip.next_timers_tick)
+    }
+
+    /// Runs as many steps as fit in `TURBO_FRAME_BUDGET` of wall-clock time, regardless
+    /// of the emulated clock, then rebases the clock so normal play continues from there.
+    fn step_turbo_until_break(&mut self, chip: &mut Chip8) -> Option<String> {
+        let end = Instant::now() + TURBO_FRAME_BUDGET;
+        let stopped = self.run_until_break(chip, |_| Instant::now() < end);
+        chip.resync_clock();
+        stopped
+    }
+
+    fn run_until_break(
+        &mut self,
+        chip: &mut Chip8,
+        keep_going: impl Fn(&Chip8) -> bool,
+    ) -> Option<String> {
+        if chip.is_paused() {
+            return None;
+        }
+        while keep_going(chip) {
+            let start = StepStart::of(chip);
+            let pc = start.pc;
+            let watched = self.watched_values(chip);
+            let timers_tick = chip.next_timers_tick;
+            chip.step_debug();
+            self.draw_log.record(chip);
+            self.events.record_step(chip, start);
+            self.heatmap.record(chip, start);
+            let diverged = self.follow_comparison(chip, pc);
+            let scripted = self.run_script(chip, start);
+            let executed = chip.instruction_count() != start.instruction_count;
+            let drawn = executed && chip.last_op().is_some_and(OpCodes::changes_display);
+            let finished = self.count_down(executed, chip.next_timers_tick != timers_tick);
+            let stopped = if let Some(diverged) = diverged {
+                diverged
+            } else if let Some(scripted) = scripted {
+                scripted
+            } else if self.run_to_draw && drawn {
+                self.run_to_draw = false;
+                let op = chip.last_op().unwrap();
+                format!(
+                    "Display changed by {} at {}",
+                    disasm::mnemonic(op, chip.symbols()),
+                    location(chip, pc)
+                )
+            } else if let Some(limit) = finished {
+                format!("Ran {} to {}", limit, location(chip, chip.pc()))
+            } else if chip.pc() != pc && self.breakpoints.contains(&chip.pc()) {
+                format!("Breakpoint hit at {}", location(chip, chip.pc()))
+            } else if let Some(breakpoint) = self
+                .op_breakpoints
+                .iter()
+                .find(|breakpoint| executed && breakpoint.matches(chip))
+            {
+                format!(
+                    "Breakpoint on {} hit at {}",
+                    breakpoint,
+                    location(chip, chip.pc())
+                )
+            } else if let Some(region) = self.region_breakpoints.iter().find(|region| {
+                executed && region.range.contains(&chip.pc()) && !region.range.contains(&pc)
+            }) {
+                format!("Entered {} at {}", region.name, location(chip, chip.pc()))
+            } else if watched != self.watched_values(chip) {
+                format!("Watchpoint triggered at {:#06x}", chip.pc())
+            } else if self.break_on_sound_timer
+                && executed
+                && matches!(chip.last_op(), Some(OpCodes::LdStVx(_)))
+            {
+                format!("Sound timer set at {}", location(chip, pc))
+            } else if self.break_on_beep && chip.is_sound_playing() != start.sound_playing {
+                let change = if start.sound_playing {
+                    "stopped"
+                } else {
+                    "started"
+                };
+                format!("Beep {} at {}", change, location(chip, chip.pc()))
+            } else if let ExecutionState::Halted(error) = chip.state() {
+                format!("Halted: {}", error)
+            } else {
+                continue;
+            };
+            chip.pause();
+            self.run_for = None;
+            self.events.push(
+                chip,
+                Event::Break {
+                    reason: stopped.clone(),
+                },
+            );
+            return Some(stopped);
+        }
+        None
     }
 }
 
-pub fn update(stage: &mut Stage, ctx: &mut Context) {
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// An address along with its label, if it has one.
+pub fn location(chip: &Chip8, address: usize) -> String {
+    match chip.symbols().name(address) {
+        Some(name) => format!("{:#06x} ({})", address, name),
+        None => format!("{:#06x}", address),
+    }
+}
+
+pub fn update(stage: &mut Stage) {
     if !stage.debugger.is_enabled {
         stage.chip.step_with_time();
-        stage.bindings.images[0].update(ctx, &stage.chip.display);
         return;
     }
-    if stage.debugger.consume_key(KEY_TERMINATE) {
+    stage.debugger.events.record_keys(&stage.chip);
+    if stage.input.just_pressed(Action::ExportEvents) {
+        match stage.debugger.events.export(&stage.rom_path) {
+            Ok(path) => stage.notify(&format!("Saved event log to {}", path.display())),
+            Err(e) => eprintln!("Failed to save event log: {}", e),
+        }
+    }
+    if stage.input.just_pressed(Action::Terminate) {
+        stage.save_session();
+        stage.save_resume_state();
+        stage.save_preferences();
         process::exit(0);
     }
-    if stage.debugger.consume_key(KEY_GO_FASTER) {
-        stage.chip.execution_speed += 0.1;
-        println!("Faster! {}", stage.chip.execution_speed);
+    if stage.input.just_pressed(Action::SoftReset) {
+        stage.debugger.push_state(&stage.chip);
+        stage.chip.reset();
+        stage.debugger.restart_comparison(&stage.chip);
+        stage.notify("Reset");
     }
-    if stage.debugger.consume_key(KEY_GO_SLOWER) {
-        stage.chip.execution_speed = 0.1;
-        println!("Slower! {}", stage.chip.execution_speed);
+    if stage.input.just_pressed(Action::Reload) {
+        let path = stage.rom_path.clone();
+        if stage.open_rom(&path) {
+            stage.notify(&format!("Reloaded {}", path));
+        }
     }
-    if stage.debugger.consume_key(KEY_GO_NORMAL) {
-        stage.chip.execution_speed = 1.0;
-        println!("Normal! {}", stage.chip.execution_speed);
+    if stage.input.just_pressed(Action::ToggleBreakpoint) {
+        let pc = stage.chip.pc();
+        if stage.debugger.breakpoints.remove(&pc) {
+            stage.notify(&format!(
+                "Removed breakpoint at {}",
+                location(&stage.chip, pc)
+            ));
+        } else {
+            let message = stage.debugger.add_breakpoint(&stage.chip, pc);
+            stage.notify(&message);
+        }
     }
-    if stage.debugger.consume_key(KEY_TOGGLE_PLAY) {
-        stage.debugger.is_playing = !stage.debugger.is_playing;
-        if stage.debugger.is_playing {
-            // Reset timers so that we don't immediately jump ahead
-            stage.chip.next_tick = Instant::now();
-            stage.chip.next_timers_tick = Instant::now();
-            // TODO: There is a more correct way to resume,
-            //       by getting the duration between the two timers.
+    if stage.input.just_pressed(Action::ToggleWatchpoint) {
+        let addr = stage.chip.i() as usize;
+        if stage.debugger.watchpoints.remove(&addr) {
+            stage.notify(&format!("Removed watchpoint at {:#06x}", addr));
+        } else {
+            stage.debugger.watchpoints.insert(addr);
+            stage.notify(&format!("Added watchpoint at {:#06x}", addr));
         }
     }
-    if stage.debugger.is_playing {
-        stage.debugger.states.push(stage.chip.clone());
-        stage.chip.step_with_time(); // Note: We don't close sub-step states here
+    if stage.input.just_pressed(Action::ToggleSoundTimerBreak) {
+        stage.debugger.break_on_sound_timer = !stage.debugger.break_on_sound_timer;
+        stage.notify(&format!(
+            "Break when ST is set: {}",
+            on_off(stage.debugger.break_on_sound_timer)
+        ));
+    }
+    if stage.input.just_pressed(Action::ToggleBeepBreak) {
+        stage.debugger.break_on_beep = !stage.debugger.break_on_beep;
+        stage.notify(&format!(
+            "Break when the beep starts or stops: {}",
+            on_off(stage.debugger.break_on_beep)
+        ));
+    }
+    if stage.input.just_pressed(Action::GoFaster) {
+        stage.chip.instructions_per_frame += 1;
+        stage.notify(&format!("Faster! {}", stage.chip.instructions_per_frame));
+    }
+    if stage.input.just_pressed(Action::GoSlower) {
+        stage.chip.instructions_per_frame = (stage.chip.instructions_per_frame - 1).max(1);
+        stage.notify(&format!("Slower! {}", stage.chip.instructions_per_frame));
+    }
+    if stage.input.just_pressed(Action::GoNormal) {
+        stage.chip.instructions_per_frame = stage.default_instructions_per_frame;
+        stage.notify(&format!("Normal! {}", stage.chip.instructions_per_frame));
+    }
+    if stage.input.just_pressed(Action::ToggleTiming) {
+        stage.chip.timing = match stage.chip.timing {
+            Timing::Flat => Timing::CosmacVip,
+            Timing::CosmacVip => Timing::Flat,
+        };
+        stage.notify(&format!("Timing: {:?}", stage.chip.timing));
+    }
+    if stage.input.just_pressed(Action::TogglePlay) {
+        if stage.chip.is_paused() {
+            stage.chip.resume();
+        } else {
+            stage.chip.pause();
+            stage.debugger.run_to_draw = false;
+            stage.debugger.run_for = None;
+        }
+    }
+    if stage.input.just_pressed(Action::RunToDraw) {
+        stage.debugger.run_to_draw = true;
+        stage.chip.resume();
+    }
+    if let Some(scripted) = stage.debugger.run_script_frame(&mut stage.chip) {
+        stage.chip.pause();
+        stage.notify(&scripted);
+    }
+    if !stage.chip.is_paused() {
+        stage.debugger.push_state(&stage.chip);
+        // Note: We don't close sub-step states here
+        let stopped =
+            if stage.input.is_pressed(Action::FastForward) || stage.debugger.run_for.is_some() {
+                stage.debugger.step_turbo_until_break(&mut stage.chip)
+            } else {
+                stage.debugger.step_with_time_until_break(&mut stage.chip)
+            };
+        if let Some(reason) = stopped {
+            stage.notify(&reason);
+        }
     } else {
-        if stage.debugger.consume_key(KEY_STEP_DEBUG) {
-            stage.debugger.states.push(stage.chip.clone());
+        for _ in 0..stage.input.repeats(Action::StepDebug) {
+            stage.debugger.push_state(&stage.chip);
             println!("{:?}", stage.debugger.states.last().unwrap());
+            let start = StepStart::of(&stage.chip);
             stage.chip.step_debug();
+            stage.debugger.draw_log.record(&stage.chip);
+            stage.debugger.events.record_step(&stage.chip, start);
+            stage.debugger.heatmap.record(&stage.chip, start);
+            if let Some(diverged) = stage.debugger.follow_comparison(&stage.chip, start.pc) {
+                stage.notify(&diverged);
+            }
+            if let Some(scripted) = stage.debugger.run_script(&mut stage.chip, start) {
+                stage.notify(&scripted);
+            }
+            let diff = Chip8::compare(stage.debugger.states.last().unwrap(), &stage.chip);
             println!(
                 "
 ----------------------------------------------------------
 Changes:
 {}
 ----------------------------------------------------------",
-                Chip8::compare(stage.debugger.states.last().unwrap(), &stage.chip)
+                diff
             );
+            stage.highlight_step(&diff);
         }
-        if stage.debugger.is_key_down(KEY_PLAY_BACKWARD) {
-            if let Some(prev) = stage.debugger.states.pop() {
-                stage.chip.clone_from(&prev);
-            }
+        if stage.input.is_pressed(Action::PlayBackward) && stage.debugger.go_back(&mut stage.chip) {
+            stage.debugger.restart_comparison(&stage.chip);
         }
-        if stage.debugger.consume_key(KEY_UNDO_STEP_DEBUG) {
-            if let Some(prev) = stage.debugger.states.pop() {
-                stage.chip.clone_from(&prev);
-                println!("{:?}", stage.chip);
-            }
+        for _ in 0..stage.input.repeats(Action::UndoStepDebug) {
+            let Some(prev) = stage.debugger.states.last() else {
+                break;
+            };
+            let diff = Chip8::compare(&stage.chip, prev);
+            stage.debugger.go_back(&mut stage.chip);
+            stage.debugger.restart_comparison(&stage.chip);
+            println!("{:?}", stage.chip);
+            stage.highlight_step(&diff);
         }
     }
-    stage.bindings.images[0].update(ctx, &stage.chip.display);
 }
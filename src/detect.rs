@@ -0,0 +1,92 @@
+use crate::{chip8::Modes, chip8::OpCodes, disasm, quirks::QuirkOverrides};
+use alloc::{collections::BTreeMap, format, string::String, vec, vec::Vec};
+
+/// A platform and quirks guessed from the code of a ROM that isn't in the database.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Detection {
+    pub platform: Modes,
+    pub quirks: QuirkOverrides,
+    /// The clues it was based on, empty when nothing suggested anything but CHIP-8.
+    pub reasons: Vec<String>,
+}
+
+impl Detection {
+    pub fn summary(&self) -> String {
+        format!("Guessed {:?}: {}", self.platform, self.reasons.join("; "))
+    }
+}
+
+/// Names the instructions that only exist on SUPER-CHIP and later platforms.
+fn extension_name(op: OpCodes) -> Option<&'static str> {
+    Some(match op {
+        OpCodes::ScdN(_) => "SCD",
+        OpCodes::Scr => "SCR",
+        OpCodes::Scl => "SCL",
+        OpCodes::Low => "LOW",
+        OpCodes::High => "HIGH",
+        OpCodes::DrawVxVyN(_, _, 0) => "DRW 16x16",
+        OpCodes::LdHfVx(_) => "LD HF",
+        OpCodes::LdRVx(_) => "LD R",
+        OpCodes::LdVxR(_) => "LD VX, R",
+        OpCodes::PlaneN(_) => "PLANE",
+        OpCodes::LdPitchVx(_) => "LD PITCH",
+        _ => return None,
+    })
+}
+
+/// Looks at the instructions reachable from 0x200, so that sprite data isn't
+/// mistaken for code.
+pub fn detect(rom: &[u8]) -> Detection {
+    let code = disasm::code(rom);
+    let mut detection = Detection {
+        platform: Modes::Chip8,
+        quirks: QuirkOverrides::default(),
+        reasons: vec![],
+    };
+
+    let mut extensions = BTreeMap::new();
+    for &(_, op) in &code {
+        if let Some(name) = extension_name(op) {
+            *extensions.entry(name).or_insert(0) += 1;
+        }
+    }
+    if !extensions.is_empty() {
+        let uses: Vec<String> = extensions
+            .iter()
+            .map(|(name, count)| format!("{} x{}", name, count))
+            .collect();
+        detection.platform = Modes::SuperChip;
+        detection.reasons.push(format!("uses {}", uses.join(", ")));
+        // The platform's profile already covers the quirks below
+        return detection;
+    }
+
+    // SUPER-CHIP programs often leave VY as 0 since it's ignored there, which would
+    // shift V0 into VX on the COSMAC VIP
+    let shift_in_place = code
+        .iter()
+        .find(|(_, op)| matches!(op, OpCodes::ShrVxVy(x, 0) | OpCodes::ShlVxVy(x, 0) if *x != 0));
+    if let Some(&(address, op)) = shift_in_place {
+        detection.quirks.shift_uses_vy = Some(false);
+        detection.reasons.push(format!(
+            "{} at {:#05x} ignores VY",
+            disasm::mnemonic(op, &Default::default()),
+            address
+        ));
+    }
+    // BNNN jumping into the interpreter only makes sense as BXNN, adding VX
+    let jump_with_vx = code
+        .iter()
+        .find(|(_, op)| matches!(op, OpCodes::JmpV0Nnn(nnn) if *nnn < 0x200));
+    if let Some(&(address, OpCodes::JmpV0Nnn(nnn))) = jump_with_vx {
+        detection.quirks.jump_uses_vx = Some(true);
+        detection.reasons.push(format!(
+            "B{:03X} at {:#05x} jumps to {:#05x} + V{:X}",
+            nnn,
+            address,
+            nnn & 0xff,
+            nnn >> 8
+        ));
+    }
+    detection
+}
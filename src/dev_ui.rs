@@ -0,0 +1,285 @@
+//! Windows of real widgets for the debugger, drawn with egui when flake is built
+//! with the `egui` feature: the registers, an editable memory grid, the breakpoints
+//! and a speed slider. The SDF text HUD stays for the lightweight overlays.
+
+use crate::Stage;
+use miniquad::{Context, KeyCode, KeyMods, MouseButton};
+
+#[cfg(feature = "egui")]
+use crate::{chip8::Chip8, controls, debugger, egui_backend::EguiBackend};
+#[cfg(feature = "egui")]
+use std::collections::BTreeSet;
+
+#[cfg(feature = "egui")]
+const BYTES_PER_ROW: usize = 16;
+
+#[derive(Default)]
+pub struct DevUi {
+    #[cfg(feature = "egui")]
+    is_open: bool,
+    /// Made the first time the windows are opened, since it needs the context.
+    #[cfg(feature = "egui")]
+    backend: Option<EguiBackend>,
+    /// Every breakpoint listed, including unchecked ones so they can be checked
+    /// again.
+    #[cfg(feature = "egui")]
+    breakpoints: BTreeSet<usize>,
+    /// Label or address typed for a new breakpoint.
+    #[cfg(feature = "egui")]
+    new_breakpoint: String,
+}
+
+/// Draws the windows over everything else, when they're open.
+pub fn draw(ctx: &mut Context, stage: &mut Stage) {
+    let mut dev_ui = std::mem::take(&mut stage.dev_ui);
+    dev_ui.draw(ctx, stage);
+    stage.dev_ui = dev_ui;
+}
+
+#[cfg(feature = "egui")]
+impl DevUi {
+    /// Shows or hides the windows, returning why not when they can't be shown.
+    pub fn toggle(&mut self) -> Result<(), String> {
+        self.is_open = !self.is_open;
+        Ok(())
+    }
+
+    fn open_backend(&mut self) -> Option<&mut EguiBackend> {
+        self.backend.as_mut().filter(|_| self.is_open)
+    }
+
+    /// These pass input on to the windows, returning whether they took it, so the
+    /// emulator should ignore it.
+    pub fn mouse_motion_event(&mut self, x: f32, y: f32) -> bool {
+        let Some(backend) = self.open_backend() else {
+            return false;
+        };
+        backend.mouse_motion(x, y);
+        backend.wants_pointer()
+    }
+
+    pub fn mouse_button_event(
+        &mut self,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+        pressed: bool,
+    ) -> bool {
+        let Some(backend) = self.open_backend() else {
+            return false;
+        };
+        // Releases always go through, so drags that leave a window still end
+        let wanted = backend.wants_pointer();
+        backend.mouse_button(button, x, y, pressed);
+        wanted
+    }
+
+    pub fn mouse_wheel_event(&mut self, x: f32, y: f32) -> bool {
+        let Some(backend) = self.open_backend() else {
+            return false;
+        };
+        backend.mouse_wheel(x, y);
+        backend.wants_pointer()
+    }
+
+    pub fn key_event(&mut self, keycode: KeyCode, keymods: KeyMods, pressed: bool) -> bool {
+        let Some(backend) = self.open_backend() else {
+            return false;
+        };
+        backend.key(keycode, keymods, pressed);
+        backend.wants_keyboard()
+    }
+
+    pub fn char_event(&mut self, character: char) -> bool {
+        let Some(backend) = self.open_backend() else {
+            return false;
+        };
+        backend.char(character);
+        backend.wants_keyboard()
+    }
+
+    fn draw(&mut self, ctx: &mut Context, stage: &mut Stage) {
+        if !self.is_open {
+            return;
+        }
+        let backend = self.backend.get_or_insert_with(|| EguiBackend::new(ctx));
+        let (breakpoints, new_breakpoint) = (&mut self.breakpoints, &mut self.new_breakpoint);
+        breakpoints.extend(&stage.debugger.breakpoints);
+        backend.draw(ctx, |egui| {
+            egui::Window::new("Registers")
+                .default_pos([20., 20.])
+                .show(egui, |ui| registers(ui, &mut stage.chip));
+            egui::Window::new("Memory")
+                .default_pos([240., 20.])
+                .default_height(300.)
+                .show(egui, |ui| memory(ui, &mut stage.chip));
+            egui::Window::new("Breakpoints")
+                .default_pos([20., 420.])
+                .show(egui, |ui| {
+                    breakpoint_list(ui, stage, breakpoints, new_breakpoint)
+                });
+            egui::Window::new("Speed")
+                .default_pos([240., 420.])
+                .show(egui, |ui| speed(ui, stage));
+        });
+    }
+}
+
+/// A byte or word that's changed when `value` is edited.
+#[cfg(feature = "egui")]
+fn hex_field(ui: &mut egui::Ui, value: u16, digits: usize, max: u16) -> Option<u16> {
+    let mut edited = value;
+    ui.add(
+        egui::DragValue::new(&mut edited)
+            .hexadecimal(digits, false, true)
+            .range(0..=max)
+            .speed(0.),
+    );
+    (edited != value).then_some(edited)
+}
+
+#[cfg(feature = "egui")]
+fn registers(ui: &mut egui::Ui, chip: &mut Chip8) {
+    egui::Grid::new("registers").num_columns(4).show(ui, |ui| {
+        ui.label("PC");
+        if let Some(pc) = hex_field(ui, chip.pc() as u16, 4, 0xFFF) {
+            chip.set_pc(pc as usize);
+        }
+        ui.label("I");
+        if let Some(i) = hex_field(ui, chip.i(), 4, 0xFFFF) {
+            chip.set_i(i);
+        }
+        ui.end_row();
+        for row in 0..8 {
+            for x in [row, row + 8] {
+                ui.label(format!("V{:X}", x));
+                if let Some(value) = hex_field(ui, chip.v()[x] as u16, 2, 0xFF) {
+                    chip.set_v(x, value as u8);
+                }
+            }
+            ui.end_row();
+        }
+        ui.label("DT");
+        if let Some(dt) = hex_field(ui, chip.dt() as u16, 2, 0xFF) {
+            chip.set_dt(dt as u8);
+        }
+        ui.label("ST");
+        if let Some(st) = hex_field(ui, chip.st() as u16, 2, 0xFF) {
+            chip.set_st(st as u8);
+        }
+        ui.end_row();
+    });
+    ui.label(format!("Stack: {:03x?}", chip.stack()));
+}
+
+#[cfg(feature = "egui")]
+fn memory(ui: &mut egui::Ui, chip: &mut Chip8) {
+    let rows = chip.memory().len() / BYTES_PER_ROW;
+    let row_height = ui.spacing().interact_size.y;
+    egui::ScrollArea::vertical().show_rows(ui, row_height, rows, |ui, visible| {
+        for row in visible {
+            ui.horizontal(|ui| {
+                let start = row * BYTES_PER_ROW;
+                ui.monospace(format!("{:#06x}", start));
+                for address in start..start + BYTES_PER_ROW {
+                    let byte = chip.memory()[address] as u16;
+                    if let Some(byte) = hex_field(ui, byte, 2, 0xFF) {
+                        chip.write_memory(address, &[byte as u8]);
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// Lists the breakpoints with checkboxes to turn them on and off, and a field to
+/// add more by label or address.
+#[cfg(feature = "egui")]
+fn breakpoint_list(
+    ui: &mut egui::Ui,
+    stage: &mut Stage,
+    breakpoints: &mut BTreeSet<usize>,
+    new_breakpoint: &mut String,
+) {
+    if breakpoints.is_empty() {
+        ui.label("No breakpoints");
+    }
+    for &address in breakpoints.iter() {
+        let mut enabled = stage.debugger.has_breakpoint(address);
+        let label = debugger::location(&stage.chip, address);
+        if ui.checkbox(&mut enabled, label).changed() {
+            if enabled {
+                stage.debugger.add_breakpoint(&stage.chip, address);
+            } else {
+                stage.debugger.remove_breakpoint(address);
+            }
+        }
+    }
+    ui.horizontal(|ui| {
+        let field = ui.add(
+            egui::TextEdit::singleline(new_breakpoint)
+                .hint_text("label or 0xaddress")
+                .desired_width(120.),
+        );
+        let entered = field.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+        if ui.button("Add").clicked() || entered {
+            match stage.chip.symbols().resolve(new_breakpoint.trim()) {
+                Some(address) => {
+                    let message = stage.debugger.add_breakpoint(&stage.chip, address);
+                    stage.notify(&message);
+                    breakpoints.insert(address);
+                    new_breakpoint.clear();
+                }
+                None => stage.notify(&format!("Unknown breakpoint location {}", new_breakpoint)),
+            }
+        }
+    });
+}
+
+#[cfg(feature = "egui")]
+fn speed(ui: &mut egui::Ui, stage: &mut Stage) {
+    let max = controls::max_speed(stage.default_instructions_per_frame);
+    ui.add(
+        egui::Slider::new(&mut stage.chip.instructions_per_frame, 1..=max)
+            .logarithmic(true)
+            .text("instructions per frame"),
+    );
+    if ui.button("Normal").clicked() {
+        stage.chip.instructions_per_frame = stage.default_instructions_per_frame;
+    }
+}
+
+#[cfg(not(feature = "egui"))]
+impl DevUi {
+    pub fn toggle(&mut self) -> Result<(), String> {
+        Err("flake was built without the egui feature".to_string())
+    }
+
+    pub fn mouse_motion_event(&mut self, _x: f32, _y: f32) -> bool {
+        false
+    }
+
+    pub fn mouse_button_event(
+        &mut self,
+        _button: MouseButton,
+        _x: f32,
+        _y: f32,
+        _pressed: bool,
+    ) -> bool {
+        false
+    }
+
+    pub fn mouse_wheel_event(&mut self, _x: f32, _y: f32) -> bool {
+        false
+    }
+
+    pub fn key_event(&mut self, _keycode: KeyCode, _keymods: KeyMods, _pressed: bool) -> bool {
+        false
+    }
+
+    pub fn char_event(&mut self, _character: char) -> bool {
+        false
+    }
+
+    fn draw(&mut self, _ctx: &mut Context, _stage: &mut Stage) {}
+}
@@ -0,0 +1,182 @@
+use crate::{chip8::OpCodes, symbols::Symbols};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::Write;
+
+/// Where ROMs are loaded, and so the address of their first byte.
+const ORIGIN: usize = 0x200;
+
+/// Formats an instruction in the assembler's syntax, naming addresses found in
+/// `symbols`.
+pub fn mnemonic(op: OpCodes, symbols: &Symbols) -> String {
+    let address = |nnn: usize| match symbols.name(nnn) {
+        Some(label) => label.to_string(),
+        None => format!("{:#05x}", nnn),
+    };
+    match op {
+        OpCodes::Unkn(v) => format!(".word {:#06x}", v),
+        OpCodes::Cls => "CLS".to_string(),
+        OpCodes::Ret => "RET".to_string(),
+        OpCodes::ScdN(n) => format!("SCD {}", n),
+        OpCodes::Scr => "SCR".to_string(),
+        OpCodes::Scl => "SCL".to_string(),
+        OpCodes::Low => "LOW".to_string(),
+        OpCodes::High => "HIGH".to_string(),
+        OpCodes::Jmp(nnn) => format!("JMP {}", address(nnn)),
+        OpCodes::Call(nnn) => format!("CALL {}", address(nnn)),
+        OpCodes::SeVxNn(x, nn) => format!("SE V{:X}, {:#04x}", x, nn),
+        OpCodes::SneVxNn(x, nn) => format!("SNE V{:X}, {:#04x}", x, nn),
+        OpCodes::SeVxVy(x, y) => format!("SE V{:X}, V{:X}", x, y),
+        OpCodes::LdVxNn(x, nn) => format!("LD V{:X}, {:#04x}", x, nn),
+        OpCodes::AddVxNn(x, nn) => format!("ADD V{:X}, {:#04x}", x, nn),
+        OpCodes::LdVxVy(x, y) => format!("LD V{:X}, V{:X}", x, y),
+        OpCodes::OrVxVy(x, y) => format!("OR V{:X}, V{:X}", x, y),
+        OpCodes::AndVxVy(x, y) => format!("AND V{:X}, V{:X}", x, y),
+        OpCodes::XorVxVy(x, y) => format!("XOR V{:X}, V{:X}", x, y),
+        OpCodes::AddVxVy(x, y) => format!("ADD V{:X}, V{:X}", x, y),
+        OpCodes::SubVxVy(x, y) => format!("SUB V{:X}, V{:X}", x, y),
+        OpCodes::ShrVxVy(x, y) => format!("SHR V{:X}, V{:X}", x, y),
+        OpCodes::SubnVxVy(x, y) => format!("SUBN V{:X}, V{:X}", x, y),
+        OpCodes::ShlVxVy(x, y) => format!("SHL V{:X}, V{:X}", x, y),
+        OpCodes::SneVxVy(x, y) => format!("SNE V{:X}, V{:X}", x, y),
+        OpCodes::LdINn(nnn) => format!("LD I, {}", address(nnn as usize)),
+        OpCodes::JmpV0Nnn(nnn) => format!("JMP V0, {}", address(nnn)),
+        OpCodes::RndVxNn(x, nn) => format!("RND V{:X}, {:#04x}", x, nn),
+        OpCodes::DrawVxVyN(x, y, n) => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        OpCodes::SkpVx(x) => format!("SKP V{:X}", x),
+        OpCodes::SknpVx(x) => format!("SKNP V{:X}", x),
+        OpCodes::LdVxDt(x) => format!("LD V{:X}, DT", x),
+        OpCodes::LdVxK(x) => format!("LD V{:X}, K", x),
+        OpCodes::LdDtVx(x) => format!("LD DT, V{:X}", x),
+        OpCodes::LdStVx(x) => format!("LD ST, V{:X}", x),
+        OpCodes::AddIVx(x) => format!("ADD I, V{:X}", x),
+        OpCodes::LdFVx(x) => format!("LD F, V{:X}", x),
+        OpCodes::LdHfVx(x) => format!("LD HF, V{:X}", x),
+        OpCodes::LdBVx(x) => format!("LD B, V{:X}", x),
+        OpCodes::LdIVx(x) => format!("LD [I], V{:X}", x),
+        OpCodes::LdVxI(x) => format!("LD V{:X}, [I]", x),
+        OpCodes::PlaneN(n) => format!("PLANE {}", n),
+        OpCodes::LdRVx(x) => format!("LD R, V{:X}", x),
+        OpCodes::LdVxR(x) => format!("LD V{:X}, R", x),
+        OpCodes::LdPitchVx(x) => format!("LD PITCH, V{:X}", x),
+    }
+}
+
+/// The instruction at `address`, if both of its bytes are inside the ROM.
+fn decode_at(rom: &[u8], address: usize) -> Option<(u16, OpCodes)> {
+    let index = address.checked_sub(ORIGIN)?;
+    let bytes = rom.get(index..index + 2)?;
+    let opcode = u16::from_be_bytes([bytes[0], bytes[1]]);
+    // Every opcode decodes, if only to `Unkn`
+    Some((opcode, OpCodes::try_from(opcode).unwrap()))
+}
+
+/// Follows every path the program can take from 0x200, returning which ROM offsets
+/// start an instruction. Whatever isn't reached is treated as data, since sprites
+/// are usually interleaved with code. `JMP V0, NNN` only follows the base address.
+fn find_code(rom: &[u8]) -> Vec<bool> {
+    let mut is_code = vec![false; rom.len()];
+    let mut pending = vec![ORIGIN];
+    while let Some(address) = pending.pop() {
+        let Some((_, op)) = decode_at(rom, address) else {
+            continue;
+        };
+        if is_code[address - ORIGIN] || matches!(op, OpCodes::Unkn(_)) {
+            continue;
+        }
+        is_code[address - ORIGIN] = true;
+
+        let next = address + 2;
+        match op {
+            OpCodes::Ret => {}
+            OpCodes::Jmp(nnn) | OpCodes::JmpV0Nnn(nnn) => pending.push(nnn),
+            OpCodes::Call(nnn) => pending.extend([nnn, next]),
+            OpCodes::SeVxNn(..)
+            | OpCodes::SneVxNn(..)
+            | OpCodes::SeVxVy(..)
+            | OpCodes::SneVxVy(..)
+            | OpCodes::SkpVx(_)
+            | OpCodes::SknpVx(_) => pending.extend([next, next + 2]),
+            _ => pending.push(next),
+        }
+    }
+    is_code
+}
+
+/// The instructions reachable from 0x200, with their addresses, in order.
+pub fn code(rom: &[u8]) -> Vec<(usize, OpCodes)> {
+    let is_code = find_code(rom);
+    (0..rom.len())
+        .filter(|&index| is_code[index])
+        .map(|index| (ORIGIN + index, decode_at(rom, ORIGIN + index).unwrap().1))
+        .collect()
+}
+
+/// A listing of a ROM with addresses, raw opcodes and mnemonics for the code
+/// reachable from 0x200, and `.byte` lines for everything else. Jump and call
+/// targets get `L` labels and `LD I` targets in data get `D` labels, though names
+/// in `symbols` take precedence.
+pub fn listing(rom: &[u8], symbols: &Symbols) -> String {
+    let is_code = find_code(rom);
+    let mut labels = Symbols::default();
+    for (index, _) in is_code.iter().enumerate().filter(|(_, &code)| code) {
+        let (_, op) = decode_at(rom, ORIGIN + index).unwrap();
+        let (target, prefix) = match op {
+            OpCodes::Jmp(nnn) | OpCodes::Call(nnn) => (nnn, "L"),
+            OpCodes::LdINn(nnn) => (nnn as usize, "D"),
+            _ => continue,
+        };
+        let is_target_code = target
+            .checked_sub(ORIGIN)
+            .and_then(|i| is_code.get(i))
+            .copied();
+        match (is_target_code, prefix) {
+            (Some(true), _) => labels.insert(target, &format!("L{:03X}", target)),
+            (Some(false), "D") => labels.insert(target, &format!("D{:03X}", target)),
+            _ => {}
+        }
+    }
+    labels.extend(symbols.clone());
+
+    let mut out = String::new();
+    let mut index = 0;
+    while index < rom.len() {
+        let address = ORIGIN + index;
+        if let Some(label) = labels.name(address) {
+            writeln!(out, "{}:", label).unwrap();
+        }
+        if is_code[index] {
+            let (opcode, op) = decode_at(rom, address).unwrap();
+            writeln!(
+                out,
+                "{:#05x}  {:04X}  {}",
+                address,
+                opcode,
+                mnemonic(op, &labels)
+            )
+            .unwrap();
+            index += 2;
+            continue;
+        }
+        // Up to 8 bytes of data per line, stopping at code and labels
+        let mut end = index + 1;
+        while end < rom.len()
+            && end - index < 8
+            && !is_code[end]
+            && labels.name(ORIGIN + end).is_none()
+        {
+            end += 1;
+        }
+        let bytes: Vec<String> = rom[index..end]
+            .iter()
+            .map(|byte| format!("{:#04x}", byte))
+            .collect();
+        writeln!(out, "{:#05x}        .byte {}", address, bytes.join(", ")).unwrap();
+        index = end;
+    }
+    out
+}
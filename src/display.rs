@@ -0,0 +1,115 @@
+use alloc::{vec, vec::Vec};
+use serde::{Deserialize, Serialize};
+
+/// The chip's framebuffer. Each pixel is a byte whose bits are the pixel's value
+/// in each bit-plane (bit 0 for plane 1, bit 1 for plane 2), so a plain CHIP-8
+/// display only ever holds 0 and 1 while XO-CHIP can use up to 4 colors.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Display {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+    /// Bitmask of the planes that drawing, clearing and scrolling affect.
+    pub selected_planes: u8,
+}
+
+pub const LORES: (usize, usize) = (64, 32);
+pub const HIRES: (usize, usize) = (128, 64);
+
+impl Display {
+    pub fn new((width, height): (usize, usize)) -> Display {
+        Display {
+            width,
+            height,
+            pixels: vec![0; width * height],
+            selected_planes: 1,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Changes the resolution, clearing every plane.
+    pub fn resize(&mut self, (width, height): (usize, usize)) {
+        self.width = width;
+        self.height = height;
+        self.pixels.clear();
+        self.pixels.resize(width * height, 0);
+    }
+
+    /// Clears the selected planes.
+    pub fn clear(&mut self) {
+        let mask = !self.selected_planes;
+        self.pixels.iter_mut().for_each(|p| *p &= mask);
+    }
+
+    /// XORs `plane` (a single-bit mask) at the given pixel, returning true if it
+    /// was lit and got turned off.
+    pub fn toggle(&mut self, x: usize, y: usize, plane: u8) -> bool {
+        let pixel = &mut self.pixels[x + y * self.width];
+        let collided = *pixel & plane != 0;
+        *pixel ^= plane;
+        collided
+    }
+
+    pub fn scroll_down(&mut self, n: usize) {
+        let n = n.min(self.height);
+        for y in (0..self.height).rev() {
+            for x in 0..self.width {
+                let from = if y >= n {
+                    self.pixels[x + (y - n) * self.width]
+                } else {
+                    0
+                };
+                self.set_selected(x, y, from);
+            }
+        }
+    }
+
+    pub fn scroll_right(&mut self, n: usize) {
+        let n = n.min(self.width);
+        for y in 0..self.height {
+            for x in (0..self.width).rev() {
+                let from = if x >= n {
+                    self.pixels[x - n + y * self.width]
+                } else {
+                    0
+                };
+                self.set_selected(x, y, from);
+            }
+        }
+    }
+
+    pub fn scroll_left(&mut self, n: usize) {
+        let n = n.min(self.width);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let from = if x + n < self.width {
+                    self.pixels[x + n + y * self.width]
+                } else {
+                    0
+                };
+                self.set_selected(x, y, from);
+            }
+        }
+    }
+
+    /// Copies the selected planes of `value` into the pixel, leaving other planes alone.
+    fn set_selected(&mut self, x: usize, y: usize, value: u8) {
+        let pixel = &mut self.pixels[x + y * self.width];
+        *pixel = (*pixel & !self.selected_planes) | (value & self.selected_planes);
+    }
+}
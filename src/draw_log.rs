@@ -0,0 +1,129 @@
+use crate::{
+    chip8::{Chip8, DrawCall},
+    layout::{Dock, Panel},
+    sdf::{Align, HAlign, SDFFont, SDFText, TextBatcher, TextStyle, VAlign},
+};
+use glam::Vec4;
+use std::collections::VecDeque;
+
+/// Oldest draws are dropped beyond this.
+const MAX_ENTRIES: usize = 1000;
+const VISIBLE_ROWS: usize = 16;
+const TEXT_SCALE: f32 = 0.3;
+
+const COLLISION_COLOR: Vec4 = Vec4::new(1., 0.4, 0.3, 1.);
+
+/// Every sprite the debugger has seen drawn, newest last.
+#[derive(Default)]
+pub struct DrawLog {
+    calls: VecDeque<DrawCall>,
+    /// Bumped on every change, so views know when to lay themselves out again.
+    version: u64,
+}
+
+impl DrawLog {
+    /// Logs the chip's last draw if it was the instruction just executed and isn't
+    /// logged yet.
+    pub fn record(&mut self, chip: &Chip8) {
+        let Some(call) = chip.last_draw() else {
+            return;
+        };
+        let is_new = self
+            .calls
+            .back()
+            .is_none_or(|last| last.instruction != call.instruction);
+        if call.instruction == chip.instruction_count() && is_new {
+            if self.calls.len() == MAX_ENTRIES {
+                self.calls.pop_front();
+            }
+            self.calls.push_back(call);
+            self.version += 1;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.calls.clear();
+        self.version += 1;
+    }
+}
+
+/// Shows a page of the draw log, scrolled back from the newest draw. Draws that set VF are highlighted.
+pub struct DrawLogView<'a> {
+    pub is_visible: bool,
+    /// Rows back from the newest draw.
+    scroll: usize,
+    text: SDFText<'a>,
+    shown: Option<(u64, usize)>,
+}
+
+impl<'a> DrawLogView<'a> {
+    pub fn new(font: &'a SDFFont) -> DrawLogView<'a> {
+        let mut text = SDFText::new(font, "");
+        text.align = Align {
+            horizontal: HAlign::Left,
+            vertical: VAlign::Top,
+        };
+        text.style = TextStyle {
+            outline_width: 0.15,
+            ..Default::default()
+        };
+        DrawLogView {
+            is_visible: false,
+            scroll: 0,
+            text,
+            shown: None,
+        }
+    }
+
+    /// Scrolls a page towards older (positive) or newer (negative) draws.
+    pub fn scroll_pages(&mut self, pages: isize) {
+        let rows = pages * VISIBLE_ROWS as isize;
+        self.scroll = self.scroll.saturating_add_signed(rows);
+    }
+
+    pub fn draw(&mut self, batcher: &mut TextBatcher, log: &DrawLog, dock: &mut Dock) {
+        if !self.is_visible {
+            return;
+        }
+        self.scroll = self
+            .scroll
+            .min(log.calls.len().saturating_sub(VISIBLE_ROWS));
+        if self.shown != Some((log.version, self.scroll)) {
+            self.text.update_spans(&log_spans(log, self.scroll));
+            self.shown = Some((log.version, self.scroll));
+        }
+        dock.place_text(Panel::DrawLog, &mut self.text, TEXT_SCALE);
+        batcher.add(&self.text);
+    }
+}
+
+fn log_spans(log: &DrawLog, scroll: usize) -> Vec<(String, Vec4)> {
+    if log.calls.is_empty() {
+        return vec![("No draws logged".to_string(), Vec4::ONE)];
+    }
+    let end = log.calls.len() - scroll;
+    let start = end.saturating_sub(VISIBLE_ROWS);
+    let mut spans = vec![(
+        format!("Draws {}-{} of {}\n", start + 1, end, log.calls.len()),
+        Vec4::ONE,
+    )];
+    for call in log.calls.range(start..end) {
+        let line = format!(
+            "#{} {:#06x}  ({}, {}) x{}  I={:#06x}{}\n",
+            call.instruction,
+            call.pc,
+            call.x,
+            call.y,
+            call.height,
+            call.i,
+            if call.collided { "  VF" } else { "" }
+        );
+        let color = if call.collided {
+            COLLISION_COLOR
+        } else {
+            Vec4::ONE
+        };
+        spans.push((line, color));
+    }
+    spans
+}
@@ -0,0 +1,334 @@
+use egui::{
+    epaint::{ImageDelta, Primitive},
+    Event, Key, Modifiers, MouseWheelUnit, Pos2, RawInput, Rect, TextureFilter, TextureId, Vec2,
+};
+use miniquad::*;
+use std::{collections::HashMap, time::Instant};
+
+/// Vertices and indices there's room for before the buffers grow.
+const MIN_CAPACITY: usize = 4096;
+
+/// An egui vertex with its color unpacked, since miniquad 0.3 only passes bytes to
+/// shaders unnormalized.
+#[repr(C)]
+struct EguiVertex {
+    pos: [f32; 2],
+    uv: [f32; 2],
+    color: [f32; 4],
+}
+
+/// Feeds miniquad's input to egui and draws what it lays out, with a pipeline and
+/// textures of its own. Positions are in window pixels, one per egui point.
+pub struct EguiBackend {
+    egui: egui::Context,
+    /// Events since the last frame.
+    events: Vec<Event>,
+    modifiers: Modifiers,
+    pointer: Pos2,
+    start: Instant,
+    pipeline: Pipeline,
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    /// How many vertices and indices the buffers have room for.
+    capacity: usize,
+    textures: HashMap<TextureId, Texture>,
+}
+
+impl EguiBackend {
+    pub fn new(ctx: &mut Context) -> EguiBackend {
+        let shader = Shader::new(ctx, shader::VERTEX, shader::FRAGMENT, shader::meta()).unwrap();
+        let pipeline = Pipeline::with_params(
+            ctx,
+            &[BufferLayout::default()],
+            &[
+                VertexAttribute::new("pos", VertexFormat::Float2),
+                VertexAttribute::new("uv", VertexFormat::Float2),
+                VertexAttribute::new("color", VertexFormat::Float4),
+            ],
+            shader,
+            PipelineParams {
+                // egui's colors and textures are premultiplied
+                color_blend: Some(BlendState::new(
+                    Equation::Add,
+                    BlendFactor::One,
+                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                )),
+                ..Default::default()
+            },
+        );
+        let (vertex_buffer, index_buffer) = mesh_buffers(ctx, MIN_CAPACITY);
+        EguiBackend {
+            egui: egui::Context::default(),
+            events: vec![],
+            modifiers: Modifiers::default(),
+            pointer: Pos2::ZERO,
+            start: Instant::now(),
+            pipeline,
+            vertex_buffer,
+            index_buffer,
+            capacity: MIN_CAPACITY,
+            textures: HashMap::new(),
+        }
+    }
+
+    /// Whether egui is using the keyboard, e.g. for a text field, so the emulator
+    /// shouldn't.
+    pub fn wants_keyboard(&self) -> bool {
+        self.egui.wants_keyboard_input()
+    }
+
+    /// Whether the pointer is over or dragging something of egui's.
+    pub fn wants_pointer(&self) -> bool {
+        self.egui.is_pointer_over_area() || self.egui.is_using_pointer()
+    }
+
+    pub fn mouse_motion(&mut self, x: f32, y: f32) {
+        self.pointer = Pos2::new(x, y);
+        self.events.push(Event::PointerMoved(self.pointer));
+    }
+
+    pub fn mouse_button(&mut self, button: MouseButton, x: f32, y: f32, pressed: bool) {
+        let button = match button {
+            MouseButton::Left => egui::PointerButton::Primary,
+            MouseButton::Right => egui::PointerButton::Secondary,
+            MouseButton::Middle => egui::PointerButton::Middle,
+            MouseButton::Unknown => return,
+        };
+        self.pointer = Pos2::new(x, y);
+        self.events.push(Event::PointerButton {
+            pos: self.pointer,
+            button,
+            pressed,
+            modifiers: self.modifiers,
+        });
+    }
+
+    pub fn mouse_wheel(&mut self, x: f32, y: f32) {
+        self.events.push(Event::MouseWheel {
+            unit: MouseWheelUnit::Line,
+            delta: Vec2::new(x, y),
+            modifiers: self.modifiers,
+        });
+    }
+
+    pub fn key(&mut self, keycode: KeyCode, keymods: KeyMods, pressed: bool) {
+        self.modifiers = Modifiers {
+            alt: keymods.alt,
+            ctrl: keymods.ctrl,
+            shift: keymods.shift,
+            mac_cmd: false,
+            command: keymods.ctrl,
+        };
+        if let Some(key) = egui_key(keycode) {
+            self.events.push(Event::Key {
+                key,
+                physical_key: None,
+                pressed,
+                repeat: false,
+                modifiers: self.modifiers,
+            });
+        }
+    }
+
+    pub fn char(&mut self, character: char) {
+        if !character.is_control() {
+            self.events.push(Event::Text(character.to_string()));
+        }
+    }
+
+    /// Lays out a frame with `ui` and draws it over whatever's been drawn so far.
+    pub fn draw(&mut self, ctx: &mut Context, ui: impl FnMut(&egui::Context)) {
+        let (width, height) = ctx.screen_size();
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, Vec2::new(width, height))),
+            time: Some(self.start.elapsed().as_secs_f64()),
+            modifiers: self.modifiers,
+            events: std::mem::take(&mut self.events),
+            focused: true,
+            ..Default::default()
+        };
+        let output = self.egui.run(input, ui);
+
+        for (id, delta) in &output.textures_delta.set {
+            self.update_texture(ctx, *id, delta);
+        }
+        let primitives = self.egui.tessellate(output.shapes, output.pixels_per_point);
+        ctx.apply_pipeline(&self.pipeline);
+        for primitive in primitives {
+            let Primitive::Mesh(mesh) = primitive.primitive else {
+                continue;
+            };
+            // Points are pixels, and GL's scissor starts from the bottom
+            let clip = primitive.clip_rect;
+            let x = clip.min.x.clamp(0., width);
+            let y = clip.min.y.clamp(0., height);
+            let clip_width = clip.max.x.clamp(x, width) - x;
+            let clip_height = clip.max.y.clamp(y, height) - y;
+            ctx.apply_scissor_rect(
+                x as i32,
+                (height - y - clip_height) as i32,
+                clip_width as i32,
+                clip_height as i32,
+            );
+            for mesh in mesh.split_to_u16() {
+                let Some(&texture) = self.textures.get(&mesh.texture_id) else {
+                    continue;
+                };
+                self.draw_mesh(ctx, &mesh, texture, Vec2::new(width, height));
+            }
+        }
+        ctx.apply_scissor_rect(0, 0, width as i32, height as i32);
+
+        for id in &output.textures_delta.free {
+            if let Some(texture) = self.textures.remove(id) {
+                texture.delete();
+            }
+        }
+    }
+
+    fn draw_mesh(
+        &mut self,
+        ctx: &mut Context,
+        mesh: &egui::epaint::Mesh16,
+        texture: Texture,
+        screen_size: Vec2,
+    ) {
+        let needed = mesh.vertices.len().max(mesh.indices.len());
+        if needed > self.capacity {
+            self.vertex_buffer.delete();
+            self.index_buffer.delete();
+            self.capacity = needed.next_power_of_two();
+            (self.vertex_buffer, self.index_buffer) = mesh_buffers(ctx, self.capacity);
+        }
+        let vertices: Vec<EguiVertex> = mesh
+            .vertices
+            .iter()
+            .map(|vertex| EguiVertex {
+                pos: [vertex.pos.x, vertex.pos.y],
+                uv: [vertex.uv.x, vertex.uv.y],
+                color: vertex.color.to_array().map(|c| c as f32 / 255.),
+            })
+            .collect();
+        self.vertex_buffer.update(ctx, &vertices);
+        self.index_buffer.update(ctx, &mesh.indices);
+        ctx.apply_bindings(&Bindings {
+            vertex_buffers: vec![self.vertex_buffer],
+            index_buffer: self.index_buffer,
+            images: vec![texture],
+        });
+        ctx.apply_uniforms(&shader::Uniforms {
+            screen_size: glam::Vec2::new(screen_size.x, screen_size.y),
+        });
+        ctx.draw(0, mesh.indices.len() as i32, 1);
+    }
+
+    fn update_texture(&mut self, ctx: &mut Context, id: TextureId, delta: &ImageDelta) {
+        let egui::ImageData::Color(image) = &delta.image;
+        let [width, height] = image.size;
+        let bytes: Vec<u8> = image
+            .pixels
+            .iter()
+            .flat_map(|pixel| pixel.to_array())
+            .collect();
+        match (delta.pos, self.textures.get(&id)) {
+            (Some([x, y]), Some(texture)) => texture.update_texture_part(
+                ctx,
+                x as i32,
+                y as i32,
+                width as i32,
+                height as i32,
+                &bytes,
+            ),
+            _ => {
+                let filter = match delta.options.magnification {
+                    TextureFilter::Nearest => FilterMode::Nearest,
+                    TextureFilter::Linear => FilterMode::Linear,
+                };
+                let texture = Texture::from_data_and_format(
+                    ctx,
+                    &bytes,
+                    TextureParams {
+                        format: TextureFormat::RGBA8,
+                        wrap: TextureWrap::Clamp,
+                        filter,
+                        width: width as u32,
+                        height: height as u32,
+                    },
+                );
+                if let Some(old) = self.textures.insert(id, texture) {
+                    old.delete();
+                }
+            }
+        }
+    }
+}
+
+impl Drop for EguiBackend {
+    fn drop(&mut self) {
+        self.vertex_buffer.delete();
+        self.index_buffer.delete();
+        for texture in self.textures.values() {
+            texture.delete();
+        }
+    }
+}
+
+fn mesh_buffers(ctx: &mut Context, capacity: usize) -> (Buffer, Buffer) {
+    let vertex_buffer = Buffer::stream(
+        ctx,
+        BufferType::VertexBuffer,
+        capacity * std::mem::size_of::<EguiVertex>(),
+    );
+    let index_buffer = Buffer::stream(
+        ctx,
+        BufferType::IndexBuffer,
+        capacity * std::mem::size_of::<u16>(),
+    );
+    (vertex_buffer, index_buffer)
+}
+
+/// The egui key for the keys its widgets use, for editing and moving around.
+fn egui_key(keycode: KeyCode) -> Option<Key> {
+    Some(match keycode {
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Enter | KeyCode::KpEnter => Key::Enter,
+        KeyCode::Escape => Key::Escape,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Left => Key::ArrowLeft,
+        KeyCode::Right => Key::ArrowRight,
+        KeyCode::Up => Key::ArrowUp,
+        KeyCode::Down => Key::ArrowDown,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::A => Key::A,
+        KeyCode::C => Key::C,
+        KeyCode::V => Key::V,
+        KeyCode::X => Key::X,
+        KeyCode::Z => Key::Z,
+        _ => return None,
+    })
+}
+
+mod shader {
+    use miniquad::*;
+
+    pub const VERTEX: &str = include_str!("egui_vert.glsl");
+    pub const FRAGMENT: &str = include_str!("egui_frag.glsl");
+
+    pub fn meta() -> ShaderMeta {
+        ShaderMeta {
+            images: vec!["tex".to_string()],
+            uniforms: UniformBlockLayout {
+                uniforms: vec![UniformDesc::new("screen_size", UniformType::Float2)],
+            },
+        }
+    }
+
+    #[repr(C)]
+    pub struct Uniforms {
+        pub screen_size: glam::Vec2,
+    }
+}
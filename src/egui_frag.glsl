@@ -0,0 +1,9 @@
+#version 100
+precision mediump float;
+varying lowp vec2 texcoord;
+varying lowp vec4 tint;
+uniform sampler2D tex;
+
+void main() {
+    gl_FragColor = tint * texture2D(tex, texcoord);
+}
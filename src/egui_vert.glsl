@@ -0,0 +1,13 @@
+#version 100
+attribute vec2 pos;
+attribute vec2 uv;
+attribute vec4 color;
+uniform vec2 screen_size;
+varying lowp vec2 texcoord;
+varying lowp vec4 tint;
+void main() {
+    // egui lays out in pixels with y down
+    gl_Position = vec4(2.0 * pos.x / screen_size.x - 1.0, 1.0 - 2.0 * pos.y / screen_size.y, 0, 1);
+    texcoord = uv;
+    tint = color;
+}
@@ -0,0 +1,197 @@
+use crate::{
+    chip8::{Chip8, ExecutionState, OpCodes},
+    paths,
+};
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Oldest events are dropped beyond this.
+const MAX_EVENTS: usize = 100_000;
+
+/// Something that happened while the program ran, as exported.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    KeyDown {
+        key: usize,
+    },
+    KeyUp {
+        key: usize,
+    },
+    Draw {
+        pc: usize,
+        x: usize,
+        y: usize,
+        height: usize,
+        i: u16,
+        collided: bool,
+    },
+    TimerLoad {
+        pc: usize,
+        /// `dt` or `st`.
+        timer: &'static str,
+        value: u8,
+    },
+    SoundStart,
+    SoundStop,
+    Call {
+        pc: usize,
+        target: usize,
+    },
+    Return {
+        pc: usize,
+        to: usize,
+    },
+    /// The debugger stopped, for a breakpoint, watchpoint, halt, ...
+    Break {
+        reason: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+struct TimedEvent {
+    /// The instruction and tick counts when it happened.
+    instruction: u64,
+    tick: u64,
+    #[serde(flatten)]
+    event: Event,
+}
+
+#[derive(Serialize)]
+struct Export<'a> {
+    rom: &'a str,
+    events: &'a VecDeque<TimedEvent>,
+}
+
+/// The chip as a step started, to tell what the step did.
+#[derive(Clone, Copy, Debug)]
+pub struct StepStart {
+    pub pc: usize,
+    pub instruction_count: u64,
+    pub sound_playing: bool,
+}
+
+impl StepStart {
+    pub fn of(chip: &Chip8) -> StepStart {
+        StepStart {
+            pc: chip.pc(),
+            instruction_count: chip.instruction_count(),
+            sound_playing: chip.is_sound_playing(),
+        }
+    }
+}
+
+/// High level events recorded by the debugger, for analysis in other tools.
+#[derive(Default)]
+pub struct EventLog {
+    events: VecDeque<TimedEvent>,
+    /// Keys as of the last `record_keys`.
+    keys: [bool; 16],
+}
+
+impl EventLog {
+    pub fn push(&mut self, chip: &Chip8, event: Event) {
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(TimedEvent {
+            instruction: chip.instruction_count(),
+            tick: chip.tick_count(),
+            event,
+        });
+    }
+
+    /// Logs the keys pressed or released since the last call.
+    pub fn record_keys(&mut self, chip: &Chip8) {
+        for key in 0..chip.keys.len() {
+            if chip.keys[key] != self.keys[key] {
+                let event = if chip.keys[key] {
+                    Event::KeyDown { key }
+                } else {
+                    Event::KeyUp { key }
+                };
+                self.push(chip, event);
+            }
+        }
+        self.keys = chip.keys;
+    }
+
+    /// Logs what the chip's last step did.
+    pub fn record_step(&mut self, chip: &Chip8, start: StepStart) {
+        if chip.is_sound_playing() != start.sound_playing {
+            let event = if start.sound_playing {
+                Event::SoundStop
+            } else {
+                Event::SoundStart
+            };
+            self.push(chip, event);
+        }
+        let executed = chip.instruction_count() != start.instruction_count;
+        // Halted instructions didn't take effect
+        if !executed || matches!(chip.state(), ExecutionState::Halted(_)) {
+            return;
+        }
+        let pc = start.pc;
+        let event = match chip.last_op() {
+            Some(OpCodes::DrawVxVyN(..)) => match chip.last_draw() {
+                Some(draw) => Event::Draw {
+                    pc: draw.pc,
+                    x: draw.x,
+                    y: draw.y,
+                    height: draw.height,
+                    i: draw.i,
+                    collided: draw.collided,
+                },
+                None => return,
+            },
+            Some(OpCodes::LdDtVx(_)) => Event::TimerLoad {
+                pc,
+                timer: "dt",
+                value: chip.dt(),
+            },
+            Some(OpCodes::LdStVx(_)) => Event::TimerLoad {
+                pc,
+                timer: "st",
+                value: chip.st(),
+            },
+            Some(OpCodes::Call(_)) => Event::Call {
+                pc,
+                target: chip.pc(),
+            },
+            Some(OpCodes::Ret) => Event::Return { pc, to: chip.pc() },
+            _ => return,
+        };
+        self.push(chip, event);
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// Writes the log as JSON to the events directory, named after the ROM and the
+    /// time, returning where it went.
+    pub fn export(&self, rom_path: &str) -> Result<PathBuf, String> {
+        let directory = paths::data_dir().join("events");
+        fs::create_dir_all(&directory).map_err(|e| e.to_string())?;
+        let rom_name = Path::new(rom_path)
+            .file_stem()
+            .map_or("flake".into(), |stem| stem.to_string_lossy());
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let path = directory.join(format!("{}-{}.json", rom_name, seconds));
+        let writer = BufWriter::new(File::create(&path).map_err(|e| e.to_string())?);
+        let export = Export {
+            rom: rom_path,
+            events: &self.events,
+        };
+        serde_json::to_writer_pretty(writer, &export).map_err(|e| e.to_string())?;
+        Ok(path)
+    }
+}
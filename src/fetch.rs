@@ -0,0 +1,50 @@
+//! Where ROMs are read from besides files: stdin, given as `-`, for piping them
+//! from build scripts, and http(s) URLs with the `http` feature.
+
+use std::{
+    io::{self, Read},
+    sync::OnceLock,
+};
+
+/// Whether `rom` names a file, and so can have sidecar files next to it.
+pub fn is_file(rom: &str) -> bool {
+    rom != "-" && !is_url(rom)
+}
+
+pub fn is_url(rom: &str) -> bool {
+    rom.starts_with("http://") || rom.starts_with("https://")
+}
+
+/// Reads the bytes of `rom`. Stdin can only be read once, so what it gave is kept
+/// for reloading.
+pub fn read(rom: &str) -> io::Result<Vec<u8>> {
+    static STDIN: OnceLock<Vec<u8>> = OnceLock::new();
+    if rom == "-" {
+        if let Some(data) = STDIN.get() {
+            return Ok(data.clone());
+        }
+        let mut data = vec![];
+        io::stdin().read_to_end(&mut data)?;
+        return Ok(STDIN.get_or_init(|| data).clone());
+    }
+    if is_url(rom) {
+        return get(rom);
+    }
+    std::fs::read(rom)
+}
+
+#[cfg(feature = "http")]
+fn get(url: &str) -> io::Result<Vec<u8>> {
+    let response = ureq::get(url).call().map_err(io::Error::other)?;
+    let mut data = vec![];
+    response.into_reader().read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(not(feature = "http"))]
+fn get(_url: &str) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "flake was built without the http feature",
+    ))
+}
@@ -1,8 +1,60 @@
 #version 100
-precision lowp float;
+precision mediump float;
 varying lowp vec2 texcoord;
 uniform sampler2D tex;
+// Debugging tints, with their strength in alpha
+uniform sampler2D highlight;
+uniform vec4 palette[4];
+uniform vec2 texture_size;
+// Window pixels per display pixel
+uniform float pixel_scale;
+// 0 nearest, 1 linear, 2 sharp bilinear
+uniform float filter_mode;
+// Added to each channel, how far channels are pushed from mid gray, and the inverse
+// of the power they're raised to
+uniform float brightness;
+uniform float contrast;
+uniform float gamma;
+
+// Pixels hold a bitmask of lit planes, used as an index into the palette. They're
+// filtered after the lookup, since blending indices would give the wrong colors.
+vec4 texel(vec2 p) {
+    float c = floor(texture2D(tex, (p + 0.5) / texture_size).r * 255.0 + 0.5);
+    if (c < 0.5) {
+        return palette[0];
+    } else if (c < 1.5) {
+        return palette[1];
+    } else if (c < 2.5) {
+        return palette[2];
+    }
+    return palette[3];
+}
+
+vec4 filtered() {
+    vec2 p = texcoord * texture_size - 0.5;
+    if (filter_mode < 0.5) {
+        return texel(floor(p + 0.5));
+    }
+    vec2 base = floor(p);
+    vec2 f = p - base;
+    if (filter_mode > 1.5) {
+        // Only blend across the last window pixel before each edge
+        f = clamp((f - 0.5) * pixel_scale + 0.5, 0.0, 1.0);
+    }
+    vec4 bottom = mix(texel(base), texel(base + vec2(1.0, 0.0)), f.x);
+    vec4 top = mix(texel(base + vec2(0.0, 1.0)), texel(base + vec2(1.0, 1.0)), f.x);
+    return mix(bottom, top, f.y);
+}
+
+vec3 adjusted(vec3 color) {
+    color = (color - 0.5) * contrast + 0.5 + brightness;
+    return pow(clamp(color, 0.0, 1.0), vec3(1.0 / gamma));
+}
+
 void main() {
-    float c = texture2D(tex, vec2(texcoord.x, texcoord.y)).r;
-    gl_FragColor = vec4(c, c, 0.5, 1.0);
+    vec4 color = filtered();
+    color.rgb = adjusted(color.rgb);
+    // Tints go on top, so they look the same however the display is adjusted
+    vec4 tint = texture2D(highlight, texcoord);
+    gl_FragColor = mix(color, vec4(tint.rgb, 1.0), tint.a * 0.75);
 }
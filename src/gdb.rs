@@ -0,0 +1,340 @@
+use crate::{chip8::Chip8, Stage};
+use std::{
+    io::{self, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+/// Register numbers in `p`/`P` packets, after V0 to VF.
+const REGISTER_I: usize = 16;
+const REGISTER_PC: usize = 17;
+/// Stack depth, read only.
+const REGISTER_SP: usize = 18;
+const REGISTER_DT: usize = 19;
+const REGISTER_ST: usize = 20;
+
+/// Names the registers for clients that ask, in `g` packet order. Multi-byte
+/// registers are little endian there, as GDB assumes without an architecture.
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.flake.chip8">
+    <reg name="v0" bitsize="8" regnum="0"/>
+    <reg name="v1" bitsize="8"/>
+    <reg name="v2" bitsize="8"/>
+    <reg name="v3" bitsize="8"/>
+    <reg name="v4" bitsize="8"/>
+    <reg name="v5" bitsize="8"/>
+    <reg name="v6" bitsize="8"/>
+    <reg name="v7" bitsize="8"/>
+    <reg name="v8" bitsize="8"/>
+    <reg name="v9" bitsize="8"/>
+    <reg name="va" bitsize="8"/>
+    <reg name="vb" bitsize="8"/>
+    <reg name="vc" bitsize="8"/>
+    <reg name="vd" bitsize="8"/>
+    <reg name="ve" bitsize="8"/>
+    <reg name="vf" bitsize="8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="8"/>
+    <reg name="dt" bitsize="8"/>
+    <reg name="st" bitsize="8"/>
+  </feature>
+</target>
+"#;
+
+/// What the connection thread has read.
+enum Incoming {
+    Connected(TcpStream),
+    Packet(String),
+    /// A packet with a bad checksum, to be sent again.
+    Corrupt,
+    /// Ctrl-C in the client.
+    Interrupt,
+    Disconnected,
+}
+
+enum Reply {
+    Packet(String),
+    /// Continuing, which is answered once the chip stops.
+    Deferred,
+}
+
+/// A GDB remote serial protocol server, so GDB and other tools speaking it can
+/// attach to the chip. Packets are read on a thread of their own, but answered
+/// from `update` on the main thread, where the chip is.
+pub struct GdbServer {
+    incoming: Receiver<Incoming>,
+    client: Option<TcpStream>,
+    /// Set while the client waits for the chip to stop after continuing.
+    running: bool,
+}
+
+impl GdbServer {
+    pub fn listen(port: u16) -> io::Result<GdbServer> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        println!("Waiting for GDB on port {}", port);
+        let (sender, incoming) = mpsc::channel();
+        thread::spawn(move || serve(listener, sender));
+        Ok(GdbServer {
+            incoming,
+            client: None,
+            running: false,
+        })
+    }
+
+    fn send(&mut self, data: &str) {
+        if let Some(client) = &mut self.client {
+            if let Err(e) = client.write_all(data.as_bytes()) {
+                eprintln!("Failed to write to GDB: {}", e);
+                self.client = None;
+            }
+        }
+    }
+
+    /// Tells a client waiting on `c` that the chip stopped.
+    fn report_stop(&mut self, signal: u8) {
+        self.running = false;
+        self.send(&packet(&format!("S{:02x}", signal)));
+    }
+}
+
+fn packet(payload: &str) -> String {
+    let checksum = payload
+        .bytes()
+        .fold(0u8, |sum, byte| sum.wrapping_add(byte));
+    format!("${}#{:02x}", payload, checksum)
+}
+
+/// Accepts one client at a time, forwarding its packets.
+fn serve(listener: TcpListener, sender: Sender<Incoming>) {
+    for stream in listener.incoming() {
+        let stream = match stream.and_then(|stream| Ok((stream.try_clone()?, stream))) {
+            Ok((writer, reader)) => {
+                if sender.send(Incoming::Connected(writer)).is_err() {
+                    return;
+                }
+                reader
+            }
+            Err(e) => {
+                eprintln!("GDB connection failed: {}", e);
+                continue;
+            }
+        };
+        let mut bytes = BufReader::new(stream).bytes().map_while(Result::ok);
+        while let Some(byte) = bytes.next() {
+            let incoming = match byte {
+                b'$' => read_packet(&mut bytes),
+                0x03 => Incoming::Interrupt,
+                // Acknowledgements, which replies aren't resent for anyway
+                _ => continue,
+            };
+            if sender.send(incoming).is_err() {
+                return;
+            }
+        }
+        if sender.send(Incoming::Disconnected).is_err() {
+            return;
+        }
+    }
+}
+
+/// Reads the rest of a packet after its `$`.
+fn read_packet(bytes: &mut impl Iterator<Item = u8>) -> Incoming {
+    let payload: Vec<u8> = bytes.by_ref().take_while(|&byte| byte != b'#').collect();
+    let checksum: String = bytes.take(2).map(char::from).collect();
+    let sum = payload
+        .iter()
+        .fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+    match u8::from_str_radix(&checksum, 16) {
+        Ok(expected) if expected == sum => {
+            Incoming::Packet(String::from_utf8_lossy(&payload).into_owned())
+        }
+        _ => Incoming::Corrupt,
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn parse_hex(s: &str) -> Option<usize> {
+    usize::from_str_radix(s, 16).ok()
+}
+
+fn parse_hex_bytes(s: &str) -> Option<Vec<u8>> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// `addr,length` as in `m` and `Z` packets.
+fn parse_range(s: &str) -> Option<(usize, usize)> {
+    let (address, length) = s.split_once(',')?;
+    Some((parse_hex(address)?, parse_hex(length)?))
+}
+
+/// A register's value in target byte order.
+fn read_register(chip: &Chip8, register: usize) -> Option<Vec<u8>> {
+    Some(match register {
+        0..=15 => vec![chip.v()[register]],
+        REGISTER_I => chip.i().to_le_bytes().to_vec(),
+        REGISTER_PC => (chip.pc() as u16).to_le_bytes().to_vec(),
+        REGISTER_SP => vec![chip.stack().len() as u8],
+        REGISTER_DT => vec![chip.dt()],
+        REGISTER_ST => vec![chip.st()],
+        _ => return None,
+    })
+}
+
+fn write_register(chip: &mut Chip8, register: usize, bytes: &[u8]) -> Option<()> {
+    let byte = *bytes.first()?;
+    let word = || Some(u16::from_le_bytes(bytes.get(..2)?.try_into().ok()?));
+    match register {
+        0..=15 => chip.set_v(register, byte),
+        REGISTER_I => chip.set_i(word()?),
+        REGISTER_PC => chip.set_pc(word()? as usize),
+        REGISTER_DT => chip.set_dt(byte),
+        REGISTER_ST => chip.set_st(byte),
+        _ => return None,
+    }
+    Some(())
+}
+
+/// Answers a packet, returning `None` for ones that aren't supported, which GDB
+/// expects an empty reply to.
+fn handle(stage: &mut Stage, data: &str) -> Option<Reply> {
+    let chip = &mut stage.chip;
+    let command = data.get(..1)?;
+    let args = &data[1..];
+    Some(Reply::Packet(match command {
+        "?" => "S05".to_string(),
+        "g" => (0..=REGISTER_ST)
+            .filter_map(|register| read_register(chip, register))
+            .map(|bytes| hex(&bytes))
+            .collect(),
+        "p" => hex(&read_register(chip, parse_hex(args)?)?),
+        "P" => {
+            let (register, value) = args.split_once('=')?;
+            write_register(chip, parse_hex(register)?, &parse_hex_bytes(value)?)?;
+            "OK".to_string()
+        }
+        "m" => {
+            let (address, length) = parse_range(args)?;
+            let memory = chip.memory();
+            let end = address.saturating_add(length).min(memory.len());
+            hex(memory.get(address..end)?)
+        }
+        "M" => {
+            let (range, data) = args.split_once(':')?;
+            let (address, length) = parse_range(range)?;
+            let bytes = parse_hex_bytes(data)?;
+            if bytes.len() != length || address.saturating_add(length) > chip.memory().len() {
+                return Some(Reply::Packet("E01".to_string()));
+            }
+            chip.write_memory(address, &bytes);
+            "OK".to_string()
+        }
+        "c" => {
+            chip.resume();
+            return Some(Reply::Deferred);
+        }
+        "s" => {
+            stage.debugger.step_instruction(chip);
+            "S05".to_string()
+        }
+        "D" => {
+            chip.resume();
+            "OK".to_string()
+        }
+        "H" => "OK".to_string(),
+        "Z" | "z" => {
+            let (kind, range) = args.split_once(',')?;
+            let (address, _) = parse_range(range)?;
+            let insert = command == "Z";
+            let debugger = &mut stage.debugger;
+            match (kind, insert) {
+                // Software and hardware breakpoints are the same here
+                ("0" | "1", true) => {
+                    debugger.add_breakpoint(chip, address);
+                }
+                ("0" | "1", false) => {
+                    debugger.remove_breakpoint(address);
+                }
+                ("2", true) => debugger.add_watchpoint(address),
+                ("2", false) => {
+                    debugger.remove_watchpoint(address);
+                }
+                _ => return None,
+            }
+            "OK".to_string()
+        }
+        _ => return handle_query(data).map(Reply::Packet),
+    }))
+}
+
+fn handle_query(packet: &str) -> Option<String> {
+    if packet.starts_with("qSupported") {
+        return Some("PacketSize=4000;qXfer:features:read+".to_string());
+    }
+    if packet == "qAttached" {
+        return Some("1".to_string());
+    }
+    let args = packet.strip_prefix("qXfer:features:read:target.xml:")?;
+    let (offset, length) = parse_range(args)?;
+    let start = offset.min(TARGET_XML.len());
+    let end = offset.saturating_add(length).min(TARGET_XML.len());
+    let more = if end < TARGET_XML.len() { "m" } else { "l" };
+    Some(format!("{}{}", more, &TARGET_XML[start..end]))
+}
+
+/// Answers everything the client sent since the last frame, and tells it when a
+/// chip it continued stops.
+pub fn update(stage: &mut Stage) {
+    let Some(mut server) = stage.gdb.take() else {
+        return;
+    };
+    while let Ok(incoming) = server.incoming.try_recv() {
+        match incoming {
+            Incoming::Connected(client) => {
+                server.client = Some(client);
+                server.running = false;
+                stage.chip.pause();
+                stage.notify("GDB attached");
+            }
+            Incoming::Packet(data) => {
+                let reply = match handle(stage, &data) {
+                    Some(Reply::Packet(payload)) => payload,
+                    Some(Reply::Deferred) => {
+                        server.running = true;
+                        server.send("+");
+                        continue;
+                    }
+                    None => String::new(),
+                };
+                server.send(&format!("+{}", packet(&reply)));
+            }
+            Incoming::Corrupt => server.send("-"),
+            Incoming::Interrupt => {
+                stage.chip.pause();
+                if server.running {
+                    // SIGINT
+                    server.report_stop(2);
+                }
+            }
+            Incoming::Disconnected => {
+                server.client = None;
+                server.running = false;
+                stage.notify("GDB detached");
+            }
+        }
+    }
+    if server.running && stage.chip.is_paused() {
+        // SIGTRAP
+        server.report_stop(5);
+    }
+    stage.gdb = Some(server);
+}
@@ -0,0 +1,252 @@
+use crate::{
+    chip8::Chip8,
+    event_log::StepStart,
+    layout::{Dock, Panel},
+    sdf::{Align, HAlign, SDFFont, SDFText, TextBatcher, TextStyle, VAlign},
+    Vertex,
+};
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use miniquad::*;
+
+/// Cells along each side of the grid, one per byte of the 4K of memory.
+const GRID: usize = 64;
+/// Window pixels per cell.
+const CELL_SIZE: f32 = 4.;
+const TEXT_SCALE: f32 = 0.3;
+/// Space between the legend and the grid.
+const LEGEND_GAP: f32 = 10.;
+
+const EXECUTE_COLOR: Vec3 = Vec3::new(0.3, 1., 0.4);
+const READ_COLOR: Vec3 = Vec3::new(0.3, 0.6, 1.);
+const WRITE_COLOR: Vec3 = Vec3::new(1., 0.35, 0.3);
+/// Bytes nothing has touched, dim enough that used memory stands out.
+const UNUSED_COLOR: Vec3 = Vec3::new(0.08, 0.08, 0.1);
+
+/// How often each byte of memory has been executed, read and written through I
+/// since the ROM was loaded, as seen by the debugger.
+pub struct Heatmap {
+    executed: Vec<u32>,
+    read: Vec<u32>,
+    written: Vec<u32>,
+    /// Bumped on every change, so views know when to upload again.
+    version: u64,
+}
+
+impl Default for Heatmap {
+    fn default() -> Heatmap {
+        Heatmap {
+            executed: vec![0; GRID * GRID],
+            read: vec![0; GRID * GRID],
+            written: vec![0; GRID * GRID],
+            version: 0,
+        }
+    }
+}
+
+impl Heatmap {
+    /// Counts what the chip's last step did.
+    pub fn record(&mut self, chip: &Chip8, start: StepStart) {
+        if chip.instruction_count() == start.instruction_count {
+            return;
+        }
+        let len = self.executed.len();
+        for address in [start.pc, start.pc + 1] {
+            bump(&mut self.executed[address % len]);
+        }
+        if let Some(access) = chip.last_access() {
+            let counts = if access.write {
+                &mut self.written
+            } else {
+                &mut self.read
+            };
+            for offset in 0..access.length {
+                bump(&mut counts[(access.address + offset) % len]);
+            }
+        }
+        self.version += 1;
+    }
+
+    pub fn clear(&mut self) {
+        for counts in [&mut self.executed, &mut self.read, &mut self.written] {
+            counts.fill(0);
+        }
+        self.version += 1;
+    }
+
+    /// The grid as texture data, a row of 64 bytes at a time from address 0. Each
+    /// kind of access adds its color, brighter the more often it happened relative
+    /// to the busiest byte, on a log scale so rarely touched bytes still show.
+    pub fn rgba(&self) -> Vec<u8> {
+        let scale = |counts: &[u32]| {
+            let max = counts.iter().copied().max().unwrap_or(0);
+            let log_max = (max as f32).ln_1p().max(f32::EPSILON);
+            move |count: u32| (count as f32).ln_1p() / log_max
+        };
+        let (executed, read, written) = (
+            scale(&self.executed),
+            scale(&self.read),
+            scale(&self.written),
+        );
+        (0..self.executed.len())
+            .flat_map(|address| {
+                let heat = EXECUTE_COLOR * executed(self.executed[address])
+                    + READ_COLOR * read(self.read[address])
+                    + WRITE_COLOR * written(self.written[address]);
+                let color = UNUSED_COLOR.max(heat);
+                let [r, g, b] = color.to_array().map(|c| (c.clamp(0., 1.) * 255.) as u8);
+                [r, g, b, 255]
+            })
+            .collect()
+    }
+}
+
+fn bump(count: &mut u32) {
+    *count = count.saturating_add(1);
+}
+
+/// The heatmap as a grid, with a legend above it.
+pub struct HeatmapView<'a> {
+    pub is_visible: bool,
+    pipeline: Pipeline,
+    bindings: Bindings,
+    legend: SDFText<'a>,
+    shown: Option<u64>,
+}
+
+impl<'a> HeatmapView<'a> {
+    pub fn new(ctx: &mut Context, font: &'a SDFFont) -> HeatmapView<'a> {
+        let shader = Shader::new(ctx, shader::VERTEX, shader::FRAGMENT, shader::meta()).unwrap();
+        let pipeline = Pipeline::new(
+            ctx,
+            &[BufferLayout::default()],
+            &[
+                VertexAttribute::new("pos", VertexFormat::Float2),
+                VertexAttribute::new("uv", VertexFormat::Float2),
+            ],
+            shader,
+        );
+
+        #[rustfmt::skip]
+        let vertices: [Vertex; 4] = [
+            Vertex { pos : Vec2 { x: 0.0, y: 0. }, uv: Vec2 { x: 0., y: 1. } },
+            Vertex { pos : Vec2 { x: 1.0, y: 0. }, uv: Vec2 { x: 1., y: 1. } },
+            Vertex { pos : Vec2 { x: 1.0, y: 1.0 }, uv: Vec2 { x: 1., y: 0. } },
+            Vertex { pos : Vec2 { x: 0.0, y: 1.0 }, uv: Vec2 { x: 0., y: 0. } },
+        ];
+        let vertex_buffer = Buffer::immutable(ctx, BufferType::VertexBuffer, &vertices);
+        let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
+        let index_buffer = Buffer::immutable(ctx, BufferType::IndexBuffer, &indices);
+        let texture = Texture::from_data_and_format(
+            ctx,
+            &Heatmap::default().rgba(),
+            TextureParams {
+                format: TextureFormat::RGBA8,
+                wrap: TextureWrap::Clamp,
+                filter: FilterMode::Nearest,
+                width: GRID as u32,
+                height: GRID as u32,
+            },
+        );
+
+        let mut legend = SDFText::new(font, "");
+        legend.update_spans(&[
+            ("Memory: ".to_string(), Vec4::ONE),
+            ("execute ".to_string(), EXECUTE_COLOR.extend(1.)),
+            ("read ".to_string(), READ_COLOR.extend(1.)),
+            ("write".to_string(), WRITE_COLOR.extend(1.)),
+        ]);
+        legend.align = Align {
+            horizontal: HAlign::Left,
+            vertical: VAlign::Top,
+        };
+        legend.style = TextStyle {
+            outline_width: 0.15,
+            ..Default::default()
+        };
+        HeatmapView {
+            is_visible: false,
+            pipeline,
+            bindings: Bindings {
+                index_buffer,
+                vertex_buffers: vec![vertex_buffer],
+                images: vec![texture],
+            },
+            legend,
+            shown: None,
+        }
+    }
+
+    /// Draws the grid straight away, and queues the legend on `batcher`.
+    pub fn draw(
+        &mut self,
+        ctx: &mut Context,
+        batcher: &mut TextBatcher,
+        heatmap: &Heatmap,
+        dock: &mut Dock,
+        projection: Mat4,
+        view: Mat4,
+    ) {
+        if !self.is_visible {
+            return;
+        }
+        if self.shown != Some(heatmap.version) {
+            self.bindings.images[0].update(ctx, &heatmap.rgba());
+            self.shown = Some(heatmap.version);
+        }
+        let scale = dock.scale(Panel::Heatmap);
+        let size = GRID as f32 * CELL_SIZE * scale;
+        let text_scale = TEXT_SCALE * scale;
+        let legend_size = self.legend.size() * text_scale;
+        let top_left = dock.place(
+            Panel::Heatmap,
+            Vec2::new(
+                size.max(legend_size.x),
+                legend_size.y + LEGEND_GAP * scale + size,
+            ),
+        );
+        self.legend.model =
+            Mat4::from_translation(top_left.extend(0.)) * Mat4::from_scale(Vec3::splat(text_scale));
+        batcher.add(&self.legend);
+
+        let corner = Vec3::new(
+            top_left.x,
+            top_left.y - legend_size.y - LEGEND_GAP * scale - size,
+            0.,
+        );
+        ctx.apply_pipeline(&self.pipeline);
+        ctx.apply_bindings(&self.bindings);
+        ctx.apply_uniforms(&shader::Uniforms {
+            projection,
+            view,
+            model: Mat4::from_translation(corner) * Mat4::from_scale(Vec3::new(size, size, 1.)),
+        });
+        ctx.draw(0, 6, 1);
+    }
+}
+
+mod shader {
+    use miniquad::*;
+
+    pub const VERTEX: &str = include_str!("vert.glsl");
+    pub const FRAGMENT: &str = include_str!("heatmap_frag.glsl");
+
+    pub fn meta() -> ShaderMeta {
+        ShaderMeta {
+            images: vec!["tex".to_string()],
+            uniforms: UniformBlockLayout {
+                uniforms: vec![
+                    UniformDesc::new("model", UniformType::Mat4),
+                    UniformDesc::new("view", UniformType::Mat4),
+                    UniformDesc::new("projection", UniformType::Mat4),
+                ],
+            },
+        }
+    }
+
+    #[repr(C)]
+    pub struct Uniforms {
+        pub model: glam::Mat4,
+        pub view: glam::Mat4,
+        pub projection: glam::Mat4,
+    }
+}
@@ -0,0 +1,8 @@
+#version 100
+precision mediump float;
+varying lowp vec2 texcoord;
+uniform sampler2D tex;
+
+void main() {
+    gl_FragColor = texture2D(tex, texcoord);
+}
@@ -0,0 +1,104 @@
+use crate::{
+    input::{key_name, Action, Input},
+    keypad,
+    sdf::{Align, SDFFont, SDFText, TextBatcher, TextStyle, VAlign},
+};
+use glam::{Mat4, Vec3, Vec4};
+
+const TEXT_SCALE: f32 = 0.25;
+const MARGIN: f32 = 20.;
+/// Gap between the key and description columns, in font pixels.
+const COLUMN_GAP: f32 = 40.;
+const KEY_COLOR: Vec4 = Vec4::new(1., 0.85, 0.2, 1.);
+
+/// Every key binding and what it does, in the order they're listed.
+fn bindings(input: &Input) -> Vec<(String, &'static str)> {
+    let keys = |action| {
+        input
+            .keys(action)
+            .into_iter()
+            .map(key_name)
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let mut bindings: Vec<(String, &str)> = Action::ALL
+        .into_iter()
+        .map(|action| (keys(action), action.description()))
+        .collect();
+    // The keypad goes in its own layout rather than one line per key
+    for (row, chip_keys) in keypad::LAYOUT.into_iter().enumerate() {
+        let row_keys: Vec<String> = chip_keys
+            .into_iter()
+            .map(|key| keys(Action::ChipKey(key as u8)))
+            .collect();
+        let description = if row == 0 { "CHIP-8 keypad" } else { "" };
+        bindings.push((row_keys.join(" "), description));
+    }
+    bindings
+}
+
+/// Lists every key binding over the top left of the window.
+pub struct HelpOverlay<'a> {
+    pub is_visible: bool,
+    keys: SDFText<'a>,
+    actions: SDFText<'a>,
+    /// Offset of the action column from the key column, in font pixels.
+    actions_offset: f32,
+    /// Height of the whole list, in font pixels.
+    height: f32,
+}
+
+impl<'a> HelpOverlay<'a> {
+    pub fn new(font: &'a SDFFont, input: &Input) -> HelpOverlay<'a> {
+        let bindings = bindings(input);
+        let keys: Vec<&str> = bindings.iter().map(|(key, _)| key.as_str()).collect();
+        let actions: Vec<&str> = bindings.iter().map(|(_, action)| *action).collect();
+
+        let text = |lines: &[&str], color: Vec4| {
+            let mut text = SDFText::new(font, &lines.join("\n"));
+            text.align = Align {
+                vertical: VAlign::Top,
+                ..Default::default()
+            };
+            text.style = TextStyle {
+                color,
+                outline_width: 0.2,
+                ..Default::default()
+            };
+            text
+        };
+        let key_width = keys
+            .iter()
+            .map(|key| font.measure(key).size().x)
+            .fold(0., f32::max);
+        HelpOverlay {
+            is_visible: false,
+            keys: text(&keys, KEY_COLOR),
+            actions: text(&actions, Vec4::ONE),
+            actions_offset: key_width + COLUMN_GAP,
+            height: font.measure(&keys.join("\n")).size().y,
+        }
+    }
+
+    pub fn draw(&mut self, batcher: &mut TextBatcher, window_height: f32) {
+        if !self.is_visible {
+            return;
+        }
+        // Shrinks to fit short windows
+        let scale = TEXT_SCALE.min((window_height - 2. * MARGIN).max(0.) / self.height);
+        let origin = Mat4::from_translation(Vec3 {
+            x: MARGIN,
+            y: window_height - MARGIN,
+            z: 0.,
+        }) * Mat4::from_scale(Vec3::splat(scale));
+        self.keys.model = origin;
+        self.actions.model = origin
+            * Mat4::from_translation(Vec3 {
+                x: self.actions_offset,
+                y: 0.,
+                z: 0.,
+            });
+        batcher.add(&self.keys);
+        batcher.add(&self.actions);
+    }
+}
@@ -0,0 +1,56 @@
+use glam::Vec3;
+
+/// Frames a highlight takes to fade out completely.
+const FADE_FRAMES: f32 = 20.;
+
+/// Tints over individual display pixels, fading out a little every frame. Drawn by
+/// the display shader from the `highlight` texture that `rgba` fills.
+pub struct Highlights {
+    width: usize,
+    height: usize,
+    /// Color and remaining strength, from 1 down to 0, of every pixel.
+    pixels: Vec<(Vec3, f32)>,
+}
+
+impl Highlights {
+    pub fn new((width, height): (usize, usize)) -> Highlights {
+        Highlights {
+            width,
+            height,
+            pixels: vec![(Vec3::ZERO, 0.); width * height],
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Starts the pixel at full strength in `color`.
+    pub fn mark(&mut self, x: usize, y: usize, color: Vec3) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = (color, 1.);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels.fill((Vec3::ZERO, 0.));
+    }
+
+    /// Fades every pixel by one frame.
+    pub fn fade(&mut self) {
+        for (_, strength) in &mut self.pixels {
+            *strength = (*strength - 1. / FADE_FRAMES).max(0.);
+        }
+    }
+
+    /// Texture data, with the strength in alpha.
+    pub fn rgba(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|&(color, strength)| {
+                let [r, g, b] = color.to_array().map(|c| (c.clamp(0., 1.) * 255.) as u8);
+                [r, g, b, (strength * 255.) as u8]
+            })
+            .collect()
+    }
+}
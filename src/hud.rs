@@ -0,0 +1,406 @@
+use crate::{
+    buzzer::Volume,
+    chip8::{Chip8, ExecutionState},
+    layout::{Dock, Panel},
+    sdf::{Align, HAlign, SDFFont, SDFText, TextBatcher, TextStyle, VAlign},
+};
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use std::time::{Duration, Instant};
+
+/// How often the stats overlay's counters are averaged and redrawn.
+const STATS_INTERVAL: Duration = Duration::from_millis(500);
+
+const TEXT_SCALE: f32 = 0.35;
+const MARGIN: f32 = 20.;
+
+const RELEASED_COLOR: Vec4 = Vec4::new(0.5, 0.5, 0.5, 1.);
+const PRESSED_COLOR: Vec4 = Vec4::new(0.3, 1., 0.4, 1.);
+
+/// What the input overlay was last laid out for.
+#[derive(Clone, Copy, PartialEq)]
+struct InputState {
+    keys: [bool; 16],
+    waiting: Option<usize>,
+    last_key_wait: Option<(usize, usize)>,
+}
+
+/// Shows which of the 16 keys are held, along with the LD VX, K in progress or
+/// the last one to finish.
+pub struct InputOverlay<'a> {
+    pub is_visible: bool,
+    text: SDFText<'a>,
+    shown: Option<InputState>,
+}
+
+impl<'a> InputOverlay<'a> {
+    pub fn new(font: &'a SDFFont) -> InputOverlay<'a> {
+        let mut text = SDFText::new(font, "");
+        text.align = Align {
+            horizontal: HAlign::Left,
+            vertical: VAlign::Top,
+        };
+        text.style = TextStyle {
+            outline_width: 0.15,
+            ..Default::default()
+        };
+        InputOverlay {
+            is_visible: false,
+            text,
+            shown: None,
+        }
+    }
+
+    pub fn draw(&mut self, batcher: &mut TextBatcher, chip: &Chip8, dock: &mut Dock) {
+        if !self.is_visible {
+            return;
+        }
+        let state = InputState {
+            keys: chip.keys,
+            waiting: match chip.state() {
+                ExecutionState::WaitingForKey(x) => Some(x),
+                _ => None,
+            },
+            last_key_wait: chip.last_key_wait(),
+        };
+        if self.shown != Some(state) {
+            self.text.update_spans(&input_spans(&state));
+            self.shown = Some(state);
+        }
+        dock.place_text(Panel::Input, &mut self.text, TEXT_SCALE);
+        batcher.add(&self.text);
+    }
+}
+
+fn input_spans(state: &InputState) -> Vec<(String, Vec4)> {
+    let mut spans = vec![("Keys".to_string(), Vec4::ONE)];
+    spans.extend(state.keys.iter().enumerate().map(|(key, &pressed)| {
+        let color = if pressed {
+            PRESSED_COLOR
+        } else {
+            RELEASED_COLOR
+        };
+        (format!(" {:X}", key), color)
+    }));
+    let wait = match (state.waiting, state.last_key_wait) {
+        (Some(x), _) => format!("\nFX0A waiting (V{:X})", x),
+        (None, Some((x, key))) => format!("\nFX0A V{:X} = {:X}", x, key),
+        (None, None) => "\nFX0A none yet".to_string(),
+    };
+    spans.push((wait, Vec4::ONE));
+    spans
+}
+
+/// Frames and instructions per second, averaged over `STATS_INTERVAL`, along with
+/// the speed setting, the chip's tick count and whether execution is paused.
+pub struct StatsOverlay<'a> {
+    pub is_visible: bool,
+    text: SDFText<'a>,
+    interval_start: Instant,
+    frames: u32,
+    start_count: u64,
+    /// The averages as of the last interval.
+    rates: String,
+    /// The tick count, paused state and volume the text was last laid out for.
+    shown: Option<(u64, bool, Volume)>,
+}
+
+impl<'a> StatsOverlay<'a> {
+    pub fn new(font: &'a SDFFont) -> StatsOverlay<'a> {
+        let mut text = SDFText::new(font, "");
+        text.align = Align {
+            horizontal: HAlign::Left,
+            vertical: VAlign::Top,
+        };
+        text.style = TextStyle {
+            outline_width: 0.15,
+            ..Default::default()
+        };
+        StatsOverlay {
+            is_visible: false,
+            text,
+            interval_start: Instant::now(),
+            frames: 0,
+            start_count: 0,
+            rates: String::new(),
+            shown: None,
+        }
+    }
+
+    /// Counts a rendered frame, refreshing the text once per interval. `speed` is
+    /// the ratio of the current instructions per frame to the ROM's default.
+    pub fn draw(
+        &mut self,
+        batcher: &mut TextBatcher,
+        chip: &Chip8,
+        speed: f32,
+        volume: Volume,
+        dock: &mut Dock,
+    ) {
+        self.frames += 1;
+        let elapsed = self.interval_start.elapsed();
+        if elapsed >= STATS_INTERVAL {
+            let seconds = elapsed.as_secs_f32();
+            // Rewinding lowers the count, which shows up as no progress
+            let instructions = chip.instruction_count().saturating_sub(self.start_count);
+            self.rates = format!(
+                "{:.0} FPS\n{:.0} IPS\nSpeed x{:.2}",
+                self.frames as f32 / seconds,
+                instructions as f32 / seconds,
+                speed
+            );
+            self.shown = None;
+            self.interval_start = Instant::now();
+            self.frames = 0;
+            self.start_count = chip.instruction_count();
+        }
+
+        if !self.is_visible {
+            return;
+        }
+        // The tick count is kept current, so it can be read off while stepping
+        let shown = (chip.tick_count(), chip.is_paused(), volume);
+        if self.shown != Some(shown) {
+            let mut stats = format!(
+                "{}\nTick {}\n{}",
+                self.rates,
+                chip.tick_count(),
+                volume.describe()
+            );
+            if chip.is_paused() {
+                stats.push_str("\nPaused");
+            }
+            self.text.update_text(&stats);
+            self.shown = Some(shown);
+        }
+        dock.place_text(Panel::Stats, &mut self.text, TEXT_SCALE);
+        batcher.add(&self.text);
+    }
+}
+
+/// Timer value that fills a bar, i.e. one second.
+const TIMER_BAR_FULL: u8 = 60;
+const TIMER_BAR_WIDTH: usize = 20;
+
+const TIMER_BAR_COLOR: Vec4 = Vec4::new(0.3, 0.8, 1., 1.);
+const SOUND_BAR_COLOR: Vec4 = Vec4::new(1., 0.85, 0.2, 1.);
+
+/// What the timers overlay was last laid out for.
+#[derive(Clone, Copy, PartialEq)]
+struct TimersState {
+    dt: u8,
+    st: u8,
+    /// Tenths of a millisecond of emulated time until the timers next decrement.
+    next_decrement: u64,
+}
+
+/// DT and ST as bars, with the emulated time left until they next count down.
+pub struct TimersOverlay<'a> {
+    pub is_visible: bool,
+    text: SDFText<'a>,
+    shown: Option<TimersState>,
+}
+
+impl<'a> TimersOverlay<'a> {
+    pub fn new(font: &'a SDFFont) -> TimersOverlay<'a> {
+        let mut text = SDFText::new(font, "");
+        text.align = Align {
+            horizontal: HAlign::Left,
+            vertical: VAlign::Top,
+        };
+        text.style = TextStyle {
+            outline_width: 0.15,
+            ..Default::default()
+        };
+        TimersOverlay {
+            is_visible: false,
+            text,
+            shown: None,
+        }
+    }
+
+    pub fn draw(&mut self, batcher: &mut TextBatcher, chip: &Chip8, dock: &mut Dock) {
+        if !self.is_visible {
+            return;
+        }
+        // Measured on the emulated clock, so it's meaningful while paused too
+        let next_decrement = chip.next_timers_tick.saturating_sub(chip.next_tick);
+        let state = TimersState {
+            dt: chip.dt(),
+            st: chip.st(),
+            next_decrement: (next_decrement.as_micros() / 100) as u64,
+        };
+        if self.shown != Some(state) {
+            self.text.update_spans(&timers_spans(&state));
+            self.shown = Some(state);
+        }
+        dock.place_text(Panel::Timers, &mut self.text, TEXT_SCALE);
+        batcher.add(&self.text);
+    }
+}
+
+fn timers_spans(state: &TimersState) -> Vec<(String, Vec4)> {
+    let mut spans = vec![];
+    for (name, value, color) in [
+        ("DT", state.dt, TIMER_BAR_COLOR),
+        ("ST", state.st, SOUND_BAR_COLOR),
+    ] {
+        let filled = value.min(TIMER_BAR_FULL) as usize * TIMER_BAR_WIDTH / TIMER_BAR_FULL as usize;
+        let overflow = if value > TIMER_BAR_FULL { "+" } else { "" };
+        spans.push((format!("{} {:3} ", name, value), Vec4::ONE));
+        spans.push(("|".repeat(filled), color));
+        spans.push((
+            format!("{}{}\n", ".".repeat(TIMER_BAR_WIDTH - filled), overflow),
+            RELEASED_COLOR,
+        ));
+    }
+    spans.push((
+        format!(
+            "Next decrement in {:.1}ms",
+            state.next_decrement as f32 / 10.
+        ),
+        Vec4::ONE,
+    ));
+    spans
+}
+
+/// How long a toast stays up, including its fade out.
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+const TOAST_FADE: Duration = Duration::from_millis(500);
+const MAX_TOASTS: usize = 5;
+
+/// Short notifications stacked at the bottom of the window, newest at the bottom,
+/// that fade out after a few seconds.
+pub struct Toasts<'a> {
+    font: &'a SDFFont,
+    toasts: Vec<(SDFText<'a>, Instant)>,
+    line_height: f32,
+}
+
+impl<'a> Toasts<'a> {
+    pub fn new(font: &'a SDFFont) -> Toasts<'a> {
+        Toasts {
+            font,
+            toasts: vec![],
+            line_height: font.measure("").size().y * TEXT_SCALE,
+        }
+    }
+
+    pub fn push(&mut self, message: &str) {
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+        let mut text = SDFText::new(self.font, message);
+        text.align = Align {
+            horizontal: HAlign::Center,
+            vertical: VAlign::Bottom,
+        };
+        self.toasts.push((text, Instant::now()));
+    }
+
+    pub fn draw(&mut self, batcher: &mut TextBatcher, window_width: f32) {
+        self.toasts
+            .retain(|(_, shown_at)| shown_at.elapsed() < TOAST_DURATION);
+        for (row, (text, shown_at)) in self.toasts.iter_mut().rev().enumerate() {
+            let remaining = TOAST_DURATION.saturating_sub(shown_at.elapsed());
+            let alpha = (remaining.as_secs_f32() / TOAST_FADE.as_secs_f32()).min(1.);
+            text.style = TextStyle {
+                color: Vec4::new(1., 1., 1., alpha),
+                shadow_color: Vec4::new(0., 0., 0., 0.8 * alpha),
+                shadow_offset: Vec2::new(3., -3.),
+                ..Default::default()
+            };
+            text.model = Mat4::from_translation(Vec3 {
+                x: window_width / 2.,
+                y: MARGIN + row as f32 * self.line_height,
+                z: 0.,
+            }) * Mat4::from_scale(Vec3::splat(TEXT_SCALE));
+            batcher.add(text);
+        }
+    }
+}
+
+/// Lines a script shows with `hud`, in the bottom left corner.
+pub struct ScriptHud<'a> {
+    text: SDFText<'a>,
+    shown: Vec<String>,
+}
+
+impl<'a> ScriptHud<'a> {
+    pub fn new(font: &'a SDFFont) -> ScriptHud<'a> {
+        let mut text = SDFText::new(font, "");
+        text.align = Align {
+            horizontal: HAlign::Left,
+            vertical: VAlign::Bottom,
+        };
+        text.style = TextStyle {
+            outline_width: 0.15,
+            ..Default::default()
+        };
+        ScriptHud {
+            text,
+            shown: vec![],
+        }
+    }
+
+    pub fn draw(&mut self, batcher: &mut TextBatcher, lines: &[String]) {
+        if lines.is_empty() {
+            return;
+        }
+        if self.shown != lines {
+            self.text.update_text(&lines.join("\n"));
+            self.shown = lines.to_vec();
+        }
+        self.text.model = Mat4::from_translation(Vec3 {
+            x: MARGIN,
+            y: MARGIN,
+            z: 0.,
+        }) * Mat4::from_scale(Vec3::splat(TEXT_SCALE));
+        batcher.add(&self.text);
+    }
+}
+
+/// The playing ROM's title and where it is in the playlist, in the top middle.
+pub struct TitleOverlay<'a> {
+    text: SDFText<'a>,
+    shown: String,
+}
+
+impl<'a> TitleOverlay<'a> {
+    pub fn new(font: &'a SDFFont) -> TitleOverlay<'a> {
+        let mut text = SDFText::new(font, "");
+        text.align = Align {
+            horizontal: HAlign::Center,
+            vertical: VAlign::Top,
+        };
+        text.style = TextStyle {
+            outline_width: 0.15,
+            ..Default::default()
+        };
+        TitleOverlay {
+            text,
+            shown: String::new(),
+        }
+    }
+
+    pub fn draw(
+        &mut self,
+        batcher: &mut TextBatcher,
+        title: &str,
+        window_width: f32,
+        window_height: f32,
+    ) {
+        if title.is_empty() {
+            return;
+        }
+        if self.shown != title {
+            self.text.update_text(title);
+            self.shown = title.to_string();
+        }
+        self.text.model = Mat4::from_translation(Vec3 {
+            x: window_width / 2.,
+            y: window_height - MARGIN,
+            z: 0.,
+        }) * Mat4::from_scale(Vec3::splat(TEXT_SCALE));
+        batcher.add(&self.text);
+    }
+}
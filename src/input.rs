@@ -0,0 +1,416 @@
+use crate::layout::Panel;
+use miniquad::KeyCode;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+/// Repeats older than this are dropped instead of all firing at once, e.g. after
+/// the window was stalled.
+const MAX_REPEAT_BACKLOG: Duration = Duration::from_millis(100);
+
+/// Something a key can be bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// A key on the emulated CHIP-8 keypad.
+    ChipKey(u8),
+    ToggleHelp,
+    TogglePlay,
+    StepDebug,
+    UndoStepDebug,
+    RunToDraw,
+    RunFor,
+    PlayBackward,
+    FastForward,
+    GoFaster,
+    GoSlower,
+    GoNormal,
+    ToggleTiming,
+    ToggleBreakpoint,
+    ToggleWatchpoint,
+    ToggleSoundTimerBreak,
+    ToggleBeepBreak,
+    ToggleQuirkDetection,
+    SoftReset,
+    Reload,
+    PreviousRom,
+    NextRom,
+    ToggleBrowser,
+    ToggleCheats,
+    ToggleMemory,
+    ToggleKeypad,
+    ToggleInput,
+    ToggleStats,
+    ToggleTimers,
+    ToggleDrawLog,
+    ToggleHeatmap,
+    MovePanel,
+    ResizePanel,
+    ToggleDevUi,
+    ExportEvents,
+    ToggleCollisions,
+    ScrollLogBack,
+    ScrollLogForward,
+    ToggleFullscreen,
+    CycleFilter,
+    CyclePalette,
+    ChooseAdjustment,
+    DecreaseAdjustment,
+    IncreaseAdjustment,
+    ResetAdjustments,
+    ToggleMute,
+    VolumeDown,
+    VolumeUp,
+    Screenshot,
+    ToggleRecording,
+    Terminate,
+}
+
+impl Action {
+    /// Every action except the CHIP-8 keys, in the order the help lists them.
+    pub const ALL: [Action; 50] = [
+        Action::ToggleHelp,
+        Action::TogglePlay,
+        Action::StepDebug,
+        Action::UndoStepDebug,
+        Action::RunToDraw,
+        Action::RunFor,
+        Action::PlayBackward,
+        Action::FastForward,
+        Action::GoFaster,
+        Action::GoSlower,
+        Action::GoNormal,
+        Action::ToggleTiming,
+        Action::ToggleBreakpoint,
+        Action::ToggleWatchpoint,
+        Action::ToggleSoundTimerBreak,
+        Action::ToggleBeepBreak,
+        Action::ToggleQuirkDetection,
+        Action::SoftReset,
+        Action::Reload,
+        Action::PreviousRom,
+        Action::NextRom,
+        Action::ToggleBrowser,
+        Action::ToggleCheats,
+        Action::ToggleMemory,
+        Action::ToggleKeypad,
+        Action::ToggleInput,
+        Action::ToggleStats,
+        Action::ToggleTimers,
+        Action::ToggleDrawLog,
+        Action::ScrollLogBack,
+        Action::ScrollLogForward,
+        Action::ToggleHeatmap,
+        Action::MovePanel,
+        Action::ResizePanel,
+        Action::ToggleDevUi,
+        Action::ExportEvents,
+        Action::ToggleCollisions,
+        Action::ToggleFullscreen,
+        Action::CycleFilter,
+        Action::CyclePalette,
+        Action::ChooseAdjustment,
+        Action::DecreaseAdjustment,
+        Action::IncreaseAdjustment,
+        Action::ResetAdjustments,
+        Action::ToggleMute,
+        Action::VolumeDown,
+        Action::VolumeUp,
+        Action::Screenshot,
+        Action::ToggleRecording,
+        Action::Terminate,
+    ];
+
+    /// The panel this action shows or hides, if it's one the layout manages.
+    pub fn panel(self) -> Option<Panel> {
+        match self {
+            Action::ToggleStats => Some(Panel::Stats),
+            Action::ToggleTimers => Some(Panel::Timers),
+            Action::ToggleMemory => Some(Panel::Memory),
+            Action::ToggleInput => Some(Panel::Input),
+            Action::ToggleDrawLog => Some(Panel::DrawLog),
+            Action::ToggleHeatmap => Some(Panel::Heatmap),
+            _ => None,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::ChipKey(_) => "Press a CHIP-8 key",
+            Action::ToggleHelp => "Show or hide this help",
+            Action::TogglePlay => "Pause or resume",
+            Action::StepDebug => "Step while paused, repeating when held",
+            Action::UndoStepDebug => "Undo a step while paused, repeating when held",
+            Action::RunToDraw => "Run until the display changes",
+            Action::RunFor => "Run a number of instructions or frames, then pause",
+            Action::PlayBackward => "Rewind while held and paused",
+            Action::FastForward => "Fast forward while held",
+            Action::GoFaster => "Run faster",
+            Action::GoSlower => "Run slower",
+            Action::GoNormal => "Run at normal speed",
+            Action::ToggleTiming => "Switch timing model",
+            Action::ToggleBreakpoint => "Toggle a breakpoint at PC",
+            Action::ToggleWatchpoint => "Toggle a watchpoint at I",
+            Action::ToggleSoundTimerBreak => "Toggle breaking when ST is set",
+            Action::ToggleBeepBreak => "Toggle breaking when the beep starts or stops",
+            Action::ToggleQuirkDetection => "Toggle guessing quirks for unknown ROMs",
+            Action::SoftReset => "Restart the program",
+            Action::Reload => "Reload the ROM",
+            Action::PreviousRom => "Load the previous ROM in the playlist",
+            Action::NextRom => "Load the next ROM in the playlist",
+            Action::ToggleBrowser => "Open a ROM",
+            Action::ToggleCheats => "Search memory and freeze values",
+            Action::ToggleMemory => "Browse and search memory and its disassembly",
+            Action::ToggleKeypad => "Show or hide the on-screen keypad",
+            Action::ToggleInput => "Show or hide held keys",
+            Action::ToggleStats => "Show or hide FPS, speed and volume",
+            Action::ToggleTimers => "Show or hide the delay and sound timers",
+            Action::ToggleDrawLog => "Show or hide the sprite draw log",
+            Action::ScrollLogBack => "Scroll the draw log back",
+            Action::ScrollLogForward => "Scroll the draw log forward",
+            Action::ToggleHeatmap => "Show or hide how often memory is executed, read and written",
+            Action::MovePanel => "Dock the last panel shown at the next edge",
+            Action::ResizePanel => "Resize the last panel shown",
+            Action::ToggleDevUi => "Show or hide the debugger windows (with the egui feature)",
+            Action::ExportEvents => "Save the event log as JSON",
+            Action::ToggleCollisions => "Show or hide where sprites collide",
+            Action::ToggleFullscreen => "Toggle fullscreen",
+            Action::CycleFilter => "Switch display filter",
+            Action::CyclePalette => "Switch to the next built-in palette",
+            Action::ChooseAdjustment => "Choose brightness, contrast or gamma to adjust",
+            Action::DecreaseAdjustment => "Turn the chosen display adjustment down",
+            Action::IncreaseAdjustment => "Turn the chosen display adjustment up",
+            Action::ResetAdjustments => "Reset brightness, contrast and gamma",
+            Action::ToggleMute => "Mute or unmute the buzzer",
+            Action::VolumeDown => "Turn the buzzer down",
+            Action::VolumeUp => "Turn the buzzer up",
+            Action::Screenshot => "Save a screenshot",
+            Action::ToggleRecording => "Start or stop recording a video",
+            Action::Terminate => "Save the session and quit",
+        }
+    }
+}
+
+fn default_bindings() -> Vec<(KeyCode, Action)> {
+    vec![
+        (KeyCode::Key1, Action::ChipKey(0x1)),
+        (KeyCode::Key2, Action::ChipKey(0x2)),
+        (KeyCode::Key3, Action::ChipKey(0x3)),
+        (KeyCode::Key4, Action::ChipKey(0xc)),
+        (KeyCode::Q, Action::ChipKey(0x4)),
+        (KeyCode::W, Action::ChipKey(0x5)),
+        (KeyCode::E, Action::ChipKey(0x6)),
+        (KeyCode::R, Action::ChipKey(0xd)),
+        (KeyCode::A, Action::ChipKey(0x7)),
+        (KeyCode::S, Action::ChipKey(0x8)),
+        (KeyCode::D, Action::ChipKey(0x9)),
+        (KeyCode::F, Action::ChipKey(0xe)),
+        (KeyCode::Z, Action::ChipKey(0xa)),
+        (KeyCode::X, Action::ChipKey(0x0)),
+        (KeyCode::C, Action::ChipKey(0xb)),
+        (KeyCode::V, Action::ChipKey(0xf)),
+        (KeyCode::F1, Action::ToggleHelp),
+        (KeyCode::P, Action::TogglePlay),
+        (KeyCode::J, Action::StepDebug),
+        (KeyCode::K, Action::UndoStepDebug),
+        (KeyCode::G, Action::RunToDraw),
+        (KeyCode::Period, Action::RunFor),
+        (KeyCode::H, Action::PlayBackward),
+        (KeyCode::Tab, Action::FastForward),
+        (KeyCode::Equal, Action::GoFaster),
+        (KeyCode::Minus, Action::GoSlower),
+        (KeyCode::Key0, Action::GoNormal),
+        (KeyCode::T, Action::ToggleTiming),
+        (KeyCode::B, Action::ToggleBreakpoint),
+        (KeyCode::N, Action::ToggleWatchpoint),
+        (KeyCode::F7, Action::ToggleSoundTimerBreak),
+        (KeyCode::F8, Action::ToggleBeepBreak),
+        (KeyCode::F9, Action::ToggleQuirkDetection),
+        (KeyCode::Backspace, Action::SoftReset),
+        (KeyCode::F5, Action::Reload),
+        (KeyCode::LeftBracket, Action::PreviousRom),
+        (KeyCode::RightBracket, Action::NextRom),
+        (KeyCode::O, Action::ToggleBrowser),
+        (KeyCode::F10, Action::ToggleCheats),
+        (KeyCode::GraveAccent, Action::ToggleMemory),
+        (KeyCode::U, Action::ToggleKeypad),
+        (KeyCode::I, Action::ToggleInput),
+        (KeyCode::F3, Action::ToggleStats),
+        (KeyCode::F4, Action::ToggleTimers),
+        (KeyCode::L, Action::ToggleDrawLog),
+        (KeyCode::PageUp, Action::ScrollLogBack),
+        (KeyCode::PageDown, Action::ScrollLogForward),
+        (KeyCode::Comma, Action::ToggleHeatmap),
+        (KeyCode::Slash, Action::MovePanel),
+        (KeyCode::Apostrophe, Action::ResizePanel),
+        (KeyCode::Backslash, Action::ToggleDevUi),
+        (KeyCode::M, Action::ExportEvents),
+        (KeyCode::F2, Action::ToggleCollisions),
+        (KeyCode::F11, Action::ToggleFullscreen),
+        (KeyCode::F6, Action::CycleFilter),
+        (KeyCode::Key5, Action::CyclePalette),
+        (KeyCode::Key6, Action::ChooseAdjustment),
+        (KeyCode::Key7, Action::DecreaseAdjustment),
+        (KeyCode::Key8, Action::IncreaseAdjustment),
+        (KeyCode::Key9, Action::ResetAdjustments),
+        (KeyCode::Insert, Action::ToggleMute),
+        (KeyCode::End, Action::VolumeDown),
+        (KeyCode::Home, Action::VolumeUp),
+        (KeyCode::F12, Action::Screenshot),
+        (KeyCode::Y, Action::ToggleRecording),
+        (KeyCode::Semicolon, Action::Terminate),
+    ]
+}
+
+/// How actions that auto-repeat fire while held.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyRepeat {
+    /// Time held before the first repeat.
+    pub delay: Duration,
+    pub interval: Duration,
+}
+
+impl Default for KeyRepeat {
+    fn default() -> KeyRepeat {
+        KeyRepeat {
+            delay: Duration::from_millis(400),
+            interval: Duration::from_millis(50),
+        }
+    }
+}
+
+pub fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Key0 => "0".to_string(),
+        KeyCode::Key1 => "1".to_string(),
+        KeyCode::Key2 => "2".to_string(),
+        KeyCode::Key3 => "3".to_string(),
+        KeyCode::Key4 => "4".to_string(),
+        KeyCode::Key5 => "5".to_string(),
+        KeyCode::Key6 => "6".to_string(),
+        KeyCode::Key7 => "7".to_string(),
+        KeyCode::Key8 => "8".to_string(),
+        KeyCode::Key9 => "9".to_string(),
+        KeyCode::Equal => "=".to_string(),
+        KeyCode::Minus => "-".to_string(),
+        KeyCode::Semicolon => ";".to_string(),
+        KeyCode::Period => ".".to_string(),
+        KeyCode::Comma => ",".to_string(),
+        KeyCode::GraveAccent => "`".to_string(),
+        KeyCode::Slash => "/".to_string(),
+        KeyCode::Apostrophe => "'".to_string(),
+        KeyCode::Backslash => "\\".to_string(),
+        KeyCode::LeftBracket => "[".to_string(),
+        KeyCode::RightBracket => "]".to_string(),
+        _ => format!("{:?}", key),
+    }
+}
+
+/// Maps keys to actions and tracks which actions are held. Presses are
+/// remembered until the end of the frame, so `just_pressed` works the same
+/// whether it's checked from an event or from `update`.
+pub struct Input {
+    bindings: Vec<(KeyCode, Action)>,
+    held: HashSet<Action>,
+    pressed: HashSet<Action>,
+    /// When each held action that's been checked with `repeats` fires next.
+    next_repeats: HashMap<Action, Instant>,
+    pub repeat: KeyRepeat,
+}
+
+impl Input {
+    pub fn new() -> Input {
+        Input {
+            bindings: default_bindings(),
+            held: HashSet::new(),
+            pressed: HashSet::new(),
+            next_repeats: HashMap::new(),
+            repeat: KeyRepeat::default(),
+        }
+    }
+
+    pub fn action(&self, keycode: KeyCode) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(key, _)| *key == keycode)
+            .map(|&(_, action)| action)
+    }
+
+    /// The keys bound to `action`.
+    pub fn keys(&self, action: Action) -> Vec<KeyCode> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|&(key, _)| key)
+            .collect()
+    }
+
+    /// Returns the newly pressed action, ignoring the OS's key repeat.
+    pub fn key_down(&mut self, keycode: KeyCode, repeat: bool) -> Option<Action> {
+        let action = self.action(keycode)?;
+        if repeat {
+            return None;
+        }
+        self.held.insert(action);
+        self.pressed.insert(action);
+        Some(action)
+    }
+
+    /// Returns the released action.
+    pub fn key_up(&mut self, keycode: KeyCode) -> Option<Action> {
+        let action = self.action(keycode)?;
+        self.held.remove(&action);
+        self.next_repeats.remove(&action);
+        Some(action)
+    }
+
+    /// Holds `action` down as if its key was pressed, e.g. for a button clicked on
+    /// screen, until `release`.
+    pub fn press(&mut self, action: Action) {
+        self.held.insert(action);
+        self.pressed.insert(action);
+    }
+
+    pub fn release(&mut self, action: Action) {
+        self.held.remove(&action);
+        self.next_repeats.remove(&action);
+    }
+
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.held.contains(&action)
+    }
+
+    /// Whether `action` was pressed this frame.
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    /// How many times `action` fires this frame: once when pressed, then every
+    /// `repeat.interval` once it's been held for `repeat.delay`.
+    pub fn repeats(&mut self, action: Action) -> u32 {
+        let now = Instant::now();
+        if self.just_pressed(action) {
+            self.next_repeats.insert(action, now + self.repeat.delay);
+            return 1;
+        }
+        if !self.is_pressed(action) {
+            return 0;
+        }
+        let Some(next) = self.next_repeats.get_mut(&action) else {
+            return 0;
+        };
+        if now.saturating_duration_since(*next) > MAX_REPEAT_BACKLOG {
+            *next = now;
+        }
+        let mut count = 0;
+        while *next <= now {
+            count += 1;
+            *next += self.repeat.interval;
+        }
+        count
+    }
+
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{
+    chip8::{Chip8, ExecutionState},
+    sdf::{Align, HAlign, SDFFont, SDFText, TextBatcher, TextStyle, VAlign},
+};
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use std::collections::HashMap;
+
+/// Pointer id used for the mouse, which can't clash with touch ids in practice.
+pub const MOUSE_POINTER: u64 = u64::MAX;
+
+/// The keys as laid out on the COSMAC VIP's hex keypad.
+pub const LAYOUT: [[usize; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+const KEY_SIZE: f32 = 48.;
+const MARGIN: f32 = 20.;
+/// How far the keypad reaches from the left of the window.
+pub const WIDTH: f32 = MARGIN + 4. * KEY_SIZE;
+const TEXT_SCALE: f32 = 0.4;
+
+const IDLE_COLOR: Vec4 = Vec4::new(0.6, 0.6, 0.6, 0.8);
+const POLLED_COLOR: Vec4 = Vec4::new(1., 0.85, 0.2, 1.);
+const PRESSED_COLOR: Vec4 = Vec4::ONE;
+
+/// A clickable hex keypad in the bottom left corner. Keys the program is polling
+/// are highlighted, and held keys are drawn outlined.
+pub struct Keypad<'a> {
+    pub is_visible: bool,
+    labels: Vec<SDFText<'a>>,
+    /// Which key each mouse button or touch is holding down.
+    pointers: HashMap<u64, usize>,
+}
+
+impl<'a> Keypad<'a> {
+    pub fn new(font: &'a SDFFont) -> Keypad<'a> {
+        Keypad {
+            is_visible: false,
+            labels: (0..16)
+                .map(|key| {
+                    let mut text = SDFText::new(font, &format!("{:X}", key));
+                    text.align = Align {
+                        horizontal: HAlign::Center,
+                        vertical: VAlign::Baseline,
+                    };
+                    text
+                })
+                .collect(),
+            pointers: HashMap::new(),
+        }
+    }
+
+    /// The key under `position`, in window pixels with y up.
+    fn key_at(&self, position: Vec2) -> Option<usize> {
+        let column = ((position.x - MARGIN) / KEY_SIZE).floor();
+        let row = ((MARGIN + 4. * KEY_SIZE - position.y) / KEY_SIZE).floor();
+        if !(0. ..4.).contains(&column) || !(0. ..4.).contains(&row) {
+            return None;
+        }
+        Some(LAYOUT[row as usize][column as usize])
+    }
+
+    /// Presses the key under `position` on behalf of `pointer`, returning whether
+    /// there was one.
+    pub fn press(&mut self, chip: &mut Chip8, pointer: u64, position: Vec2) -> bool {
+        if !self.is_visible {
+            return false;
+        }
+        let Some(key) = self.key_at(position) else {
+            return false;
+        };
+        chip.keys[key] = true;
+        self.pointers.insert(pointer, key);
+        true
+    }
+
+    pub fn release(&mut self, chip: &mut Chip8, pointer: u64) {
+        if let Some(key) = self.pointers.remove(&pointer) {
+            if !self.pointers.values().any(|&k| k == key) {
+                chip.keys[key] = false;
+            }
+        }
+    }
+
+    pub fn draw(&mut self, batcher: &mut TextBatcher, chip: &Chip8) {
+        if !self.is_visible {
+            return;
+        }
+        let polled = chip.polled_keys();
+        let waiting = matches!(chip.state(), ExecutionState::WaitingForKey(_));
+        for (row, keys) in LAYOUT.iter().enumerate() {
+            for (column, &key) in keys.iter().enumerate() {
+                let text = &mut self.labels[key];
+                let pressed = chip.keys[key];
+                text.style = TextStyle {
+                    color: if pressed {
+                        PRESSED_COLOR
+                    } else if waiting || polled[key] {
+                        POLLED_COLOR
+                    } else {
+                        IDLE_COLOR
+                    },
+                    outline_width: if pressed { 0.2 } else { 0. },
+                    ..Default::default()
+                };
+                let center = Vec3 {
+                    x: MARGIN + (column as f32 + 0.5) * KEY_SIZE,
+                    y: MARGIN + (3.5 - row as f32) * KEY_SIZE - KEY_SIZE * 0.25,
+                    z: 0.,
+                };
+                text.model =
+                    Mat4::from_translation(center) * Mat4::from_scale(Vec3::splat(TEXT_SCALE));
+                batcher.add(text);
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::sdf::SDFText;
+use glam::{Mat4, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const MARGIN: f32 = 20.;
+/// Space between panels docked to the same edge.
+const GAP: f32 = 10.;
+/// Leaves room for the status line under panels docked to the bottom.
+const BOTTOM_OFFSET: f32 = 60.;
+/// The sizes a panel can be drawn at, relative to its usual size.
+const SCALES: [f32; 4] = [0.75, 1., 1.25, 1.5];
+
+/// Which edge of the window a panel is docked to. Panels on the left and right
+/// stack down from the top, and ones on the bottom stack leftwards from the right
+/// above the status line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Edge {
+    Left,
+    Right,
+    Bottom,
+}
+
+impl Edge {
+    fn name(self) -> &'static str {
+        match self {
+            Edge::Left => "left",
+            Edge::Right => "right",
+            Edge::Bottom => "bottom",
+        }
+    }
+
+    fn next(self) -> Edge {
+        match self {
+            Edge::Left => Edge::Right,
+            Edge::Right => Edge::Bottom,
+            Edge::Bottom => Edge::Left,
+        }
+    }
+}
+
+/// The debugger panels the layout manages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Panel {
+    Stats,
+    Timers,
+    Memory,
+    Input,
+    DrawLog,
+    Heatmap,
+}
+
+impl Panel {
+    pub fn name(self) -> &'static str {
+        match self {
+            Panel::Stats => "Stats",
+            Panel::Timers => "Timers",
+            Panel::Memory => "Memory",
+            Panel::Input => "Input",
+            Panel::DrawLog => "Draw log",
+            Panel::Heatmap => "Heatmap",
+        }
+    }
+}
+
+/// Where a panel is docked and how big it's drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Placement {
+    pub edge: Edge,
+    pub scale: f32,
+}
+
+/// Where every panel goes, kept in the preferences. Panels missing from them get
+/// their default placement.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Layout {
+    panels: BTreeMap<Panel, Placement>,
+}
+
+impl Layout {
+    pub fn placement(&self, panel: Panel) -> Placement {
+        self.panels.get(&panel).copied().unwrap_or(Placement {
+            edge: match panel {
+                Panel::Stats | Panel::Timers | Panel::Memory => Edge::Left,
+                Panel::Input | Panel::DrawLog => Edge::Right,
+                Panel::Heatmap => Edge::Bottom,
+            },
+            scale: 1.,
+        })
+    }
+
+    /// Docks `panel` at the next edge round, returning a message saying where.
+    pub fn move_panel(&mut self, panel: Panel) -> String {
+        let mut placement = self.placement(panel);
+        placement.edge = placement.edge.next();
+        self.panels.insert(panel, placement);
+        format!("{} docked {}", panel.name(), placement.edge.name())
+    }
+
+    /// Draws `panel` at the next size up, going back to the smallest after the
+    /// largest, returning a message saying how big.
+    pub fn resize_panel(&mut self, panel: Panel) -> String {
+        let mut placement = self.placement(panel);
+        placement.scale = SCALES
+            .into_iter()
+            .find(|&scale| scale > placement.scale + f32::EPSILON)
+            .unwrap_or(SCALES[0]);
+        self.panels.insert(panel, placement);
+        format!("{} at {:.0}%", panel.name(), placement.scale * 100.)
+    }
+}
+
+/// Hands out room along the edges of the window to the panels drawn in a frame,
+/// in the order they ask for it.
+pub struct Dock<'l> {
+    layout: &'l Layout,
+    window_size: Vec2,
+    /// How far along each edge is taken, indexed by `Edge`.
+    used: [f32; 3],
+}
+
+impl<'l> Dock<'l> {
+    pub fn new(layout: &'l Layout, window_width: f32, window_height: f32) -> Dock<'l> {
+        Dock {
+            layout,
+            window_size: Vec2::new(window_width, window_height),
+            used: [0.; 3],
+        }
+    }
+
+    /// How much bigger than usual `panel` is drawn.
+    pub fn scale(&self, panel: Panel) -> f32 {
+        self.layout.placement(panel).scale
+    }
+
+    /// Takes room for `panel`, `size` in window pixels, on the edge it's docked to,
+    /// returning where its top left corner goes.
+    pub fn place(&mut self, panel: Panel, size: Vec2) -> Vec2 {
+        let edge = self.layout.placement(panel).edge;
+        let used = &mut self.used[edge as usize];
+        let Vec2 {
+            x: width,
+            y: height,
+        } = self.window_size;
+        let corner = match edge {
+            Edge::Left => Vec2::new(MARGIN, height - MARGIN - *used),
+            Edge::Right => Vec2::new(width - MARGIN - size.x, height - MARGIN - *used),
+            Edge::Bottom => Vec2::new(width - MARGIN - *used - size.x, BOTTOM_OFFSET + size.y),
+        };
+        *used += match edge {
+            Edge::Left | Edge::Right => size.y,
+            Edge::Bottom => size.x,
+        } + GAP;
+        corner
+    }
+
+    /// Places `panel`'s `text`, aligned to its top left, drawn at `scale` times the
+    /// panel's scale.
+    pub fn place_text(&mut self, panel: Panel, text: &mut SDFText, scale: f32) {
+        let scale = scale * self.scale(panel);
+        let corner = self.place(panel, text.size() * scale);
+        text.model =
+            Mat4::from_translation(corner.extend(0.)) * Mat4::from_scale(Vec3::splat(scale));
+    }
+}
@@ -0,0 +1,30 @@
+//! The emulator core, shared by the `flake` frontend and the libretro core in
+//! `libretro/`, which RetroArch and other frontends load. Without the default
+//! `std` feature it builds with no_std and alloc, for running on microcontrollers;
+//! see `platform` for what the embedder provides.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod archive;
+pub mod asm;
+pub mod buzzer;
+pub mod chip8;
+pub mod detect;
+pub mod disasm;
+pub mod display;
+#[cfg(feature = "std")]
+pub mod fetch;
+pub mod octo;
+#[cfg(feature = "std")]
+pub mod palette;
+pub mod platform;
+pub mod quirks;
+#[cfg(feature = "std")]
+pub mod romdb;
+pub mod save_state;
+pub mod source_map;
+pub mod symbols;
+pub mod trace;
@@ -1,12 +1,93 @@
-mod chip8;
+use flake::{
+    asm, buzzer, chip8, disasm, display, fetch, octo, palette, quirks, romdb, save_state, symbols,
+    trace,
+};
+
+mod adjustments;
+mod autosave;
+mod browser;
+mod cheats;
+mod cli;
+mod compare;
+mod control;
+mod controls;
+mod dap;
 mod debugger;
+mod dev_ui;
+mod draw_log;
+#[cfg(feature = "egui")]
+mod egui_backend;
+mod event_log;
+mod gdb;
+mod heatmap;
+mod help;
+mod highlight;
+mod hud;
+mod input;
+mod keypad;
+mod layout;
+mod memory_view;
+mod monitor;
+mod netplay;
+mod op_breakpoint;
+mod paths;
+mod playlist;
+mod preferences;
+mod recording;
+mod regions;
+mod runner;
+mod scaling;
+mod screenshot;
+mod script;
 mod sdf;
+mod session;
+mod spectate;
+mod user_shader;
+mod watcher;
 
-use chip8::Chip8;
-use debugger::Debugger;
-use glam::{Mat4, Quat, Vec2, Vec3};
+use adjustments::{Adjustments, Setting};
+use browser::RomBrowser;
+use buzzer::Tone;
+use cheats::CheatPanel;
+use chip8::{Chip8, ExecutionState, LoadError, StateDiff, DEFAULT_INSTRUCTIONS_PER_FRAME};
+use cli::{Command, Options};
+use compare::Comparison;
+use control::ControlServer;
+use controls::ControlBar;
+use dap::DapServer;
+use debugger::{Debugger, RunLimit};
+use dev_ui::DevUi;
+use display::Display;
+use draw_log::DrawLogView;
+use gdb::GdbServer;
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+use heatmap::HeatmapView;
+use help::HelpOverlay;
+use highlight::Highlights;
+use hud::{InputOverlay, ScriptHud, StatsOverlay, TimersOverlay, TitleOverlay, Toasts};
+use input::{Action, Input};
+use keypad::Keypad;
+use layout::{Dock, Panel};
+use memory_view::MemoryView;
 use miniquad::*;
-use sdf::{SDFFont, SDFText};
+use monitor::Monitor;
+use netplay::{Netplay, NetplayRole};
+use palette::Palette;
+use playlist::Playlist;
+use preferences::Preferences;
+use quirks::QuirkOverrides;
+use recording::Recording;
+use runner::Runner;
+use save_state::SaveState;
+use scaling::DisplayScaling;
+use script::Script;
+use sdf::{Align, HAlign, SDFFont, SDFText, TextBatcher, TextStyle, VAlign};
+use session::Session;
+use spectate::SpectateServer;
+use std::path::Path;
+use symbols::Symbols;
+use user_shader::UserShader;
+use watcher::FileWatcher;
 
 #[repr(C)]
 struct Vertex {
@@ -17,145 +98,1186 @@ struct Vertex {
 pub struct Stage<'a> {
     pipeline: Pipeline,
     bindings: Bindings,
+    /// The comparison chip's display, drawn to the right of the main one.
+    comparison_bindings: Bindings,
     chip: Chip8,
+    rom_path: String,
+    playlist: Playlist,
+    /// The ROM's title and place in the playlist, when there's more than one.
+    title: String,
+    instructions_per_frame_override: Option<u32>,
+    default_instructions_per_frame: u32,
+    quirk_overrides: QuirkOverrides,
+    /// Whether ROMs missing from the database get a platform and quirks guessed
+    /// from their code.
+    detect_quirks: bool,
+    palette: Palette,
+    /// The preferences' tone with the command line's changes.
+    tone: Tone,
+    /// The display adjustment the keys turn up and down.
+    adjusting: Setting,
+    scaling: DisplayScaling,
     size: (i32, i32),
+    preferences: Preferences,
     debugger: Debugger,
-    text_test: SDFText<'a>,
-    text_test_2: SDFText<'a>,
+    input: Input,
+    status_text: SDFText<'a>,
+    /// What `status_text` was last laid out with.
+    shown_status: Vec<(String, Vec4)>,
+    font: &'a SDFFont,
+    /// Shown at the top, e.g. when a ROM fails to load or to offer resuming it.
+    message_text: Option<SDFText<'a>>,
+    /// Where the ROM was when it was last closed, offered until it's resumed or
+    /// dismissed.
+    resume: Option<SaveState>,
+    /// The count typed so far for running a number of instructions or frames.
+    run_for_input: Option<String>,
+    browser: RomBrowser<'a>,
+    cheats: CheatPanel<'a>,
+    memory_view: MemoryView<'a>,
+    keypad: Keypad<'a>,
+    controls: ControlBar<'a>,
+    input_overlay: InputOverlay<'a>,
+    stats_overlay: StatsOverlay<'a>,
+    timers_overlay: TimersOverlay<'a>,
+    draw_log_view: DrawLogView<'a>,
+    heatmap_view: HeatmapView<'a>,
+    /// The panel the layout keys move and resize, the last one toggled.
+    last_panel: Option<Panel>,
+    dev_ui: DevUi,
+    highlights: Highlights,
+    show_collisions: bool,
+    /// Instruction count of the last draw whose collisions were highlighted.
+    highlighted_draw: Option<u64>,
+    toasts: Toasts<'a>,
+    script_hud: ScriptHud<'a>,
+    title_overlay: TitleOverlay<'a>,
+    help: HelpOverlay<'a>,
+    text_batcher: TextBatcher<'a>,
+    pause_in_background: bool,
+    /// Set when minimizing the window paused the chip, so restoring it resumes.
+    paused_in_background: bool,
+    watcher: Option<FileWatcher>,
+    user_shader: Option<UserShader>,
+    gdb: Option<GdbServer>,
+    dap: Option<DapServer>,
+    control: Option<ControlServer>,
+    spectators: Option<SpectateServer>,
+    recording: Option<Recording>,
+    /// Set with `--threaded`, in which case `chip` mirrors the one it runs.
+    runner: Option<Runner>,
+    threaded: bool,
+    /// Set with `--host` or `--join`, in which case it runs the chip instead of the
+    /// debugger, and nothing else may change it.
+    netplay: Option<Netplay>,
 }
 
 impl<'a> Stage<'a> {
-    pub fn new(ctx: &mut Context, filename: &str, font: &'a SDFFont) -> Stage<'a> {
-        let mut chip = Chip8::new();
-        chip.execution_speed = 1.0;
-        // chip.load("roms/test_opcode.ch8")
-        //     .expect("Failed to load file");
-        chip.load(filename).expect("Failed to load file");
+    pub fn new(
+        ctx: &mut Context,
+        options: &Options,
+        preferences: Preferences,
+        font: &'a SDFFont,
+    ) -> Stage<'a> {
+        let chip = Chip8::new();
 
         #[rustfmt::skip]
         let vertices: [Vertex; 4] = [
             Vertex { pos : Vec2 { x: 0.0, y: 0. }, uv: Vec2 { x: 0., y: 1. } },
-            Vertex { pos : Vec2 { x: 64.0, y: 0. }, uv: Vec2 { x: 1., y: 1. } },
-            Vertex { pos : Vec2 { x: 64.0, y: 32.0 }, uv: Vec2 { x: 1., y: 0. } },
-            Vertex { pos : Vec2 { x: 0.0, y:  32.0}, uv: Vec2 { x: 0., y: 0. } },
+            Vertex { pos : Vec2 { x: 1.0, y: 0. }, uv: Vec2 { x: 1., y: 1. } },
+            Vertex { pos : Vec2 { x: 1.0, y: 1.0 }, uv: Vec2 { x: 1., y: 0. } },
+            Vertex { pos : Vec2 { x: 0.0, y: 1.0 }, uv: Vec2 { x: 0., y: 0. } },
         ];
         let vertex_buffer = Buffer::immutable(ctx, BufferType::VertexBuffer, &vertices);
 
         let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
         let index_buffer = Buffer::immutable(ctx, BufferType::IndexBuffer, &indices);
 
-        let pixels: [u8; 64 * 32] = [0; 64 * 32];
-        let texture = Texture::from_data_and_format(
-            ctx,
-            &pixels,
-            TextureParams {
-                format: TextureFormat::Alpha,
-                wrap: TextureWrap::Clamp,
-                filter: FilterMode::Nearest,
-                width: 64,
-                height: 32,
-            },
-        );
+        let texture = display_texture(ctx, &chip.display);
+        let highlights = Highlights::new(chip.display.size());
+        let highlight_texture = highlight_texture(ctx, &highlights);
 
         let bindings = Bindings {
             index_buffer,
             vertex_buffers: vec![vertex_buffer],
-            images: vec![texture],
+            images: vec![texture, highlight_texture],
+        };
+        // Only the main display gets highlights
+        let no_highlights = self::highlight_texture(ctx, &Highlights::new((1, 1)));
+        let comparison_bindings = Bindings {
+            images: vec![display_texture(ctx, &chip.display), no_highlights],
+            ..bindings.clone()
         };
 
-        let shader = Shader::new(ctx, shader::VERTEX, shader::FRAGMENT, shader::meta()).unwrap();
-
-        let pipeline = Pipeline::new(
-            ctx,
-            &[BufferLayout::default()],
-            &[
-                VertexAttribute::new("pos", VertexFormat::Float2),
-                VertexAttribute::new("uv", VertexFormat::Float2),
-            ],
-            shader,
+        let user_shader = UserShader::new(
+            options.vertex_shader.as_deref(),
+            options.fragment_shader.as_deref(),
         );
+        let pipeline = user_shader
+            .as_ref()
+            .and_then(|user_shader| user_pipeline(ctx, user_shader))
+            .unwrap_or_else(|| display_pipeline(ctx, shader::VERTEX, shader::FRAGMENT).unwrap());
 
-        let stage = {
-            let mut text = SDFText::new(ctx, font, "Hello World");
-            text.update_text(ctx, "Goodbye World".to_string());
+        let mut stage = {
+            let mut status_text = SDFText::new(font, "");
+            status_text.update_spans(&status_spans(chip.state()));
+            // Outlined so it stays readable over the display
+            status_text.style = TextStyle {
+                outline_width: 0.15,
+                ..Default::default()
+            };
+            status_text.align = Align {
+                horizontal: HAlign::Right,
+                vertical: VAlign::Bottom,
+            };
 
-            let mut text2 = SDFText::new(ctx, font, "Hello World");
-            text2.model *= Mat4::from_translation(Vec3 {
-                x: 0.,
-                y: 100.,
-                z: 0.,
+            let palette = options.palette.unwrap_or_else(|| {
+                preferences
+                    .palette
+                    .as_deref()
+                    .and_then(|palette| Palette::parse(palette).ok())
+                    .unwrap_or_default()
+            });
+            let (width, height) = ctx.screen_size();
+
+            let mut input = Input::new();
+            input.repeat = options.key_repeat;
+            let help = HelpOverlay::new(font, &input);
+            let mut debugger = Debugger::new();
+            // The debugger steps the chip itself, so it can't drive a threaded one, or
+            // one kept in step with another player's
+            debugger.is_enabled = !options.threaded && options.netplay.is_none();
+            debugger.op_breakpoints = options.op_breakpoints.clone();
+            debugger.regions = options.regions.clone();
+            debugger.break_on_sound_timer = options.break_on_sound_timer;
+            debugger.break_on_beep = options.break_on_beep;
+            debugger.comparison = options
+                .compare
+                .map(|overrides| Comparison::new(&chip, overrides));
+            debugger.script = options.script.as_deref().and_then(|path| {
+                Script::load(path)
+                    .map_err(|e| eprintln!("Failed to load script {}", e))
+                    .ok()
             });
 
             Stage {
                 pipeline,
                 bindings,
+                comparison_bindings,
+                shown_status: vec![],
                 chip,
-                size: (1200, 600),
-                debugger: Debugger::new(),
-                text_test: text,
-                text_test_2: text2,
+                rom_path: String::new(),
+                playlist: Playlist::new(&options.roms),
+                title: String::new(),
+                instructions_per_frame_override: options.instructions_per_frame,
+                default_instructions_per_frame: DEFAULT_INSTRUCTIONS_PER_FRAME,
+                quirk_overrides: options.quirks,
+                detect_quirks: true,
+                palette,
+                tone: Tone {
+                    waveform: options.waveform.unwrap_or(preferences.tone.waveform),
+                    frequency: options.tone_frequency.unwrap_or(preferences.tone.frequency),
+                    duty: options.duty.unwrap_or(preferences.tone.duty),
+                },
+                adjusting: Setting::default(),
+                scaling: options.scaling,
+                size: (width as i32, height as i32),
+                preferences,
+                debugger,
+                input,
+                status_text,
+                font,
+                message_text: None,
+                resume: None,
+                run_for_input: None,
+                browser: RomBrowser::new(font, &options.rom_dir),
+                cheats: CheatPanel::new(font),
+                memory_view: MemoryView::new(font),
+                keypad: Keypad::new(font),
+                controls: ControlBar::new(font),
+                input_overlay: InputOverlay::new(font),
+                stats_overlay: StatsOverlay::new(font),
+                timers_overlay: TimersOverlay::new(font),
+                draw_log_view: DrawLogView::new(font),
+                heatmap_view: HeatmapView::new(ctx, font),
+                last_panel: None,
+                dev_ui: DevUi::default(),
+                highlights,
+                show_collisions: false,
+                highlighted_draw: None,
+                toasts: Toasts::new(font),
+                script_hud: ScriptHud::new(font),
+                title_overlay: TitleOverlay::new(font),
+                help,
+                text_batcher: TextBatcher::new(ctx, font),
+                // Pausing would hold up the other player too
+                pause_in_background: options.pause_in_background && options.netplay.is_none(),
+                paused_in_background: false,
+                gdb: options.gdb_port.and_then(|port| {
+                    GdbServer::listen(port)
+                        .map_err(|e| eprintln!("Failed to listen for GDB on port {}: {}", port, e))
+                        .ok()
+                }),
+                // The debugger window talks to us through a control server, on
+                // whatever port is free if there isn't one already
+                control: options
+                    .control_port
+                    .or(options.debugger_window.then_some(0))
+                    .and_then(|port| {
+                        ControlServer::listen(port)
+                            .map_err(|e| {
+                                eprintln!(
+                                    "Failed to listen for control commands on port {}: {}",
+                                    port, e
+                                )
+                            })
+                            .ok()
+                    }),
+                dap: options.dap_port.and_then(|port| {
+                    DapServer::listen(port)
+                        .map_err(|e| eprintln!("Failed to listen for DAP on port {}: {}", port, e))
+                        .ok()
+                }),
+                spectators: options.spectate_port.and_then(|port| {
+                    SpectateServer::listen(port)
+                        .map_err(|e| {
+                            eprintln!("Failed to listen for spectators on port {}: {}", port, e)
+                        })
+                        .ok()
+                }),
+                recording: None,
+                runner: None,
+                threaded: options.threaded,
+                netplay: options.netplay.as_ref().and_then(|role| {
+                    Netplay::start(role)
+                        .map_err(|e| eprintln!("Failed to start netplay: {}", e))
+                        .ok()
+                }),
+                watcher: None,
+                user_shader,
             }
         };
 
+        let rom = stage.playlist.current().map(str::to_string);
+        if !rom.is_some_and(|rom| stage.open_rom(&rom)) {
+            // Nothing to run, so don't let the empty memory halt on opcode 0x0000
+            stage.chip.pause();
+        }
+        if let (Some(NetplayRole::Host(port)), Some(_)) = (&options.netplay, &stage.netplay) {
+            stage.notify(&format!("Waiting for a player to join on port {}", port));
+        }
+        if let Some(path) = &options.record {
+            stage.start_recording(Path::new(path));
+        }
+        let regions = regions::all(&stage.chip, &stage.debugger.regions);
+        for breakpoint in &options.breakpoints {
+            let message = match stage.chip.symbols().resolve(breakpoint) {
+                Some(address) => stage.debugger.add_breakpoint(&stage.chip, address),
+                None => match regions::find(&regions, breakpoint) {
+                    Some(region) => stage.debugger.add_region_breakpoint(region),
+                    None => {
+                        eprintln!("Unknown breakpoint location {}", breakpoint);
+                        continue;
+                    }
+                },
+            };
+            stage.notify(&message);
+        }
+        for watchpoint in &options.watchpoints {
+            let range = match stage.chip.symbols().resolve(watchpoint) {
+                Some(address) => address..address + 1,
+                None => match regions::find(&regions, watchpoint) {
+                    Some(region) => region.range.clone(),
+                    None => {
+                        eprintln!("Unknown watchpoint location {}", watchpoint);
+                        continue;
+                    }
+                },
+            };
+            let (first, last) = (range.start, range.end - 1);
+            let memory_len = stage.chip.memory().len();
+            for address in range.filter(|&address| address < memory_len) {
+                stage.debugger.add_watchpoint(address);
+            }
+            stage.notify(&format!(
+                "Watching {} ({:#06x}-{:#06x})",
+                watchpoint, first, last
+            ));
+        }
+        if let Some(control) = stage.control.as_ref().filter(|_| options.debugger_window) {
+            let address = format!("127.0.0.1:{}", control.port);
+            let spawned = std::env::current_exe().and_then(|exe| {
+                std::process::Command::new(exe)
+                    .args(["monitor", &address])
+                    .spawn()
+            });
+            if let Err(e) = spawned {
+                stage.notify(&format!("Failed to open the debugger window: {}", e));
+            }
+        }
         stage
     }
+
+    /// Reports something on stdout and in a toast.
+    pub fn notify(&mut self, message: &str) {
+        println!("{}", message);
+        self.toasts.push(message);
+    }
+
+    fn start_recording(&mut self, path: &Path) {
+        match Recording::start(path, self.tone) {
+            Ok(recording) => {
+                self.recording = Some(recording);
+                self.notify(&format!("Recording to {}", path.display()));
+            }
+            Err(e) => self.notify(&format!("Failed to start recording: {}", e)),
+        }
+    }
+
+    fn finish_recording(&mut self) {
+        let Some(recording) = self.recording.take() else {
+            return;
+        };
+        match recording.finish() {
+            Ok(path) => self.notify(&format!("Saved recording to {}", path.display())),
+            Err(e) => self.notify(&format!("Failed to save recording: {}", e)),
+        }
+    }
+
+    /// Converts window coordinates from input events, which have y down, to the y up
+    /// coordinates things are drawn in.
+    fn window_position(&self, x: f32, y: f32) -> Vec2 {
+        Vec2::new(x, self.size.1 as f32 - y)
+    }
+
+    /// Does what a click or drag on the control bar asks for.
+    fn control_bar_request(&mut self, request: controls::Request) {
+        match request {
+            controls::Request::Press(action) => self.input.press(action),
+            controls::Request::Speed(speed) => self.chip.instructions_per_frame = speed,
+            controls::Request::Scrub(position) => self.debugger.scrub_to(&mut self.chip, position),
+        }
+    }
+
+    /// Loads the ROM at `path`, reporting failures on screen instead of giving up.
+    /// Returns whether it was loaded; on failure the current program keeps running.
+    pub fn open_rom(&mut self, path: &str) -> bool {
+        match self.load_rom(path) {
+            Ok(()) => {
+                self.message_text = self.resume.is_some().then(|| {
+                    self.message(
+                        "Resume where you left off? Enter to resume, Esc to start over",
+                        Vec4::ONE,
+                    )
+                });
+                true
+            }
+            Err(e) => {
+                let message = format!("Failed to load {}: {}", path, e);
+                eprintln!("{}", message);
+                self.message_text = Some(self.message(&message, ERROR_COLOR));
+                false
+            }
+        }
+    }
+
+    fn message(&self, message: &str, color: Vec4) -> SDFText<'a> {
+        let mut text = SDFText::new(self.font, message);
+        text.align = Align {
+            horizontal: HAlign::Center,
+            vertical: VAlign::Top,
+        };
+        text.style = TextStyle {
+            color,
+            shadow_color: Vec4::new(0., 0., 0., 0.8),
+            shadow_offset: Vec2::new(3., -3.),
+            ..Default::default()
+        };
+        text
+    }
+
+    /// Replaces the running program with a fresh chip running the ROM at `path`,
+    /// saving the current debugger session and restoring the new ROM's one.
+    fn load_rom(&mut self, path: &str) -> Result<(), LoadError> {
+        let mut chip = Chip8::new();
+        chip.load(path)?;
+        // --ipf only replaces the speed, not the platform and quirks
+        let default_speed = self
+            .preferences
+            .instructions_per_frame
+            .unwrap_or(DEFAULT_INSTRUCTIONS_PER_FRAME);
+        let (instructions_per_frame, profile) =
+            romdb::apply_profile(&mut chip, self.detect_quirks, default_speed);
+        let instructions_per_frame = self
+            .instructions_per_frame_override
+            .unwrap_or(instructions_per_frame);
+        chip.quirks.apply(&self.quirk_overrides);
+        chip.instructions_per_frame = instructions_per_frame;
+        // Reloading the same ROM keeps it running if it was
+        let keep_running = self.rom_path == path && !self.chip.is_paused();
+        if self.debugger.is_enabled && !keep_running {
+            chip.pause();
+        }
+
+        if !self.rom_path.is_empty() {
+            self.save_session();
+            self.save_resume_state();
+        }
+        self.resume = None;
+        if self.rom_path != path && self.netplay.is_none() {
+            match autosave::load(chip.program()) {
+                Ok(state) => self.resume = state,
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        self.chip = chip;
+        self.highlighted_draw = None;
+        if self.rom_path != path {
+            self.watcher = if fetch::is_file(path) && self.netplay.is_none() {
+                FileWatcher::new(path)
+                    .map_err(|e| eprintln!("Not watching {} for changes: {}", path, e))
+                    .ok()
+            } else {
+                None
+            };
+        }
+        self.rom_path = path.to_string();
+        self.title = match self.playlist.select(path) {
+            Some(index) if self.playlist.len() > 1 => format!(
+                "{}/{} {}",
+                index + 1,
+                self.playlist.len(),
+                rom_title(&self.chip, path)
+            ),
+            _ => String::new(),
+        };
+        self.default_instructions_per_frame = instructions_per_frame;
+        self.debugger.reset();
+        self.debugger.restart_comparison(&self.chip);
+        self.cheats.reset();
+        self.memory_view.reset();
+        self.restore_session();
+        // Stdin can't be opened again later
+        if path != "-" {
+            self.browser.add_recent(path);
+        }
+        if self.threaded {
+            self.runner = Some(Runner::spawn(self.chip.clone()));
+        }
+        if let Some(profile) = profile {
+            self.notify(&profile);
+        }
+        Ok(())
+    }
+
+    /// Keeps where the chip is to offer resuming from next time, unless there's
+    /// nothing worth resuming.
+    pub fn save_resume_state(&self) {
+        let halted = matches!(self.chip.state(), ExecutionState::Halted(_));
+        if self.chip.program().is_empty() || self.chip.instruction_count() == 0 || halted {
+            return;
+        }
+        if let Err(e) = autosave::save(&self.chip) {
+            eprintln!("{}", e);
+        }
+    }
+
+    fn show_run_for_input(&mut self) {
+        let count = self.run_for_input.as_deref().unwrap_or_default();
+        self.message_text = Some(self.message(
+            &format!(
+                "Run for {}_ (Enter: instructions, F: frames, Esc: cancel)",
+                count
+            ),
+            Vec4::ONE,
+        ));
+    }
+
+    /// Takes typing while the count to run for is being entered.
+    fn run_for_key_down(&mut self, keycode: KeyCode) {
+        let Some(input) = &mut self.run_for_input else {
+            return;
+        };
+        let digit = match keycode {
+            KeyCode::Key0 | KeyCode::Kp0 => Some('0'),
+            KeyCode::Key1 | KeyCode::Kp1 => Some('1'),
+            KeyCode::Key2 | KeyCode::Kp2 => Some('2'),
+            KeyCode::Key3 | KeyCode::Kp3 => Some('3'),
+            KeyCode::Key4 | KeyCode::Kp4 => Some('4'),
+            KeyCode::Key5 | KeyCode::Kp5 => Some('5'),
+            KeyCode::Key6 | KeyCode::Kp6 => Some('6'),
+            KeyCode::Key7 | KeyCode::Kp7 => Some('7'),
+            KeyCode::Key8 | KeyCode::Kp8 => Some('8'),
+            KeyCode::Key9 | KeyCode::Kp9 => Some('9'),
+            _ => None,
+        };
+        let limit = match keycode {
+            _ if digit.is_some() => {
+                // Anything longer would overflow, and take forever anyway
+                if input.len() < 18 {
+                    input.extend(digit);
+                }
+                None
+            }
+            KeyCode::Backspace => {
+                input.pop();
+                None
+            }
+            KeyCode::Enter | KeyCode::KpEnter => {
+                Some(RunLimit::Instructions(input.parse().unwrap_or(0)))
+            }
+            KeyCode::F => Some(RunLimit::Frames(input.parse().unwrap_or(0))),
+            KeyCode::Escape => {
+                self.run_for_input = None;
+                self.message_text = None;
+                return;
+            }
+            _ => None,
+        };
+        match limit {
+            Some(limit) => {
+                self.run_for_input = None;
+                self.message_text = None;
+                self.debugger.run_for(&mut self.chip, limit);
+            }
+            None => self.show_run_for_input(),
+        }
+    }
+
+    fn resume_saved_state(&mut self) {
+        self.message_text = None;
+        let Some(state) = self.resume.take() else {
+            return;
+        };
+        match self.chip.restore_state(&state) {
+            Ok(()) => {
+                self.debugger.reset();
+                self.debugger.restart_comparison(&self.chip);
+                if self.threaded {
+                    self.runner = Some(Runner::spawn(self.chip.clone()));
+                }
+                self.notify("Resumed where you left off");
+            }
+            Err(e) => self.notify(&format!("Couldn't resume: {}", e)),
+        }
+    }
+
+    /// Loads the ROM `by` entries along the playlist, keeping it running if the
+    /// current one is.
+    fn switch_rom(&mut self, by: isize) {
+        let Some(path) = self.playlist.advance(by).map(str::to_string) else {
+            return;
+        };
+        let running = !self.chip.is_paused();
+        if self.open_rom(&path) && running && self.chip.is_paused() {
+            self.chip.resume();
+        }
+    }
+
+    /// Sessions are kept next to the ROM, so only ROMs loaded from files have them.
+    fn restore_session(&mut self) {
+        if !fetch::is_file(&self.rom_path) {
+            return;
+        }
+        match Session::load(&self.rom_path) {
+            Ok(Some(session)) => {
+                // An explicit --ipf wins over the speed the session was left at
+                let keep_speed = self.instructions_per_frame_override.is_some();
+                let message = self
+                    .debugger
+                    .restore_session(&session, &mut self.chip, keep_speed);
+                if session.last_pc + 2 <= self.chip.memory().len() {
+                    self.memory_view.go_to(session.last_pc);
+                }
+                self.notify(&message);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    /// Uploads the chip's display and the highlights over it, and the comparison
+    /// chip's display, recreating the textures if the resolution changed.
+    fn update_display_texture(&mut self, ctx: &mut Context) {
+        update_display_texture(ctx, &mut self.bindings.images[0], &self.chip.display);
+        if let Some(comparison) = &self.debugger.comparison {
+            let texture = &mut self.comparison_bindings.images[0];
+            update_display_texture(ctx, texture, &comparison.chip.display);
+        }
+        let (width, height) = self.chip.display.size();
+
+        let texture = &mut self.bindings.images[1];
+        if self.highlights.size() != (width, height) {
+            self.highlights = Highlights::new((width, height));
+            texture.delete();
+            *texture = highlight_texture(ctx, &self.highlights);
+        } else {
+            texture.update(ctx, &self.highlights.rgba());
+        }
+    }
+
+    /// Draws a display scaled into the part of the window `width` wide from `x`.
+    fn draw_display(
+        &self,
+        ctx: &mut Context,
+        bindings: &Bindings,
+        display: &Display,
+        (x, width): (f32, f32),
+        projection: Mat4,
+        view: Mat4,
+    ) {
+        let (display_width, display_height) = display.size();
+        let display_size = Vec2::new(display_width as f32, display_height as f32);
+        let model = Mat4::from_translation(Vec3::new(x, 0., 0.))
+            * self
+                .scaling
+                .model(Vec2::new(width, self.size.1 as f32), display_size);
+        let scale = Vec2::new(model.x_axis.x, model.y_axis.y) / display_size;
+        ctx.apply_pipeline(&self.pipeline);
+        ctx.apply_bindings(bindings);
+        ctx.apply_uniforms(&shader::Uniforms {
+            projection,
+            view,
+            model,
+            palette: self.palette.0,
+            texture_size: display_size,
+            pixel_scale: scale.min_element(),
+            filter_mode: self.scaling.filter.shader_mode(),
+            brightness: self.preferences.adjustments.brightness,
+            contrast: self.preferences.adjustments.contrast,
+            gamma: self.preferences.adjustments.gamma,
+        });
+        ctx.draw(0, 6, 1);
+    }
+
+    /// Marks the pixels a debugger step changed, replacing earlier highlights.
+    pub fn highlight_step(&mut self, diff: &StateDiff) {
+        self.highlights.clear();
+        let width = self.chip.display.width();
+        for change in &diff.display {
+            self.highlights
+                .mark(change.index % width, change.index / width, STEP_COLOR);
+        }
+    }
+
+    /// Marks the pixels the last sprite collided on, once per draw.
+    fn highlight_collisions(&mut self) {
+        let Some(draw) = self.chip.last_draw() else {
+            return;
+        };
+        if !draw.collided || self.highlighted_draw == Some(draw.instruction) {
+            return;
+        }
+        for &(x, y) in self.chip.collided_pixels() {
+            self.highlights.mark(x, y, COLLISION_COLOR);
+        }
+        self.highlighted_draw = Some(draw.instruction);
+    }
+
+    pub fn save_preferences(&mut self) {
+        self.preferences.palette = Some(
+            self.palette
+                .name()
+                .map_or_else(|| self.palette.to_hex(), str::to_string),
+        );
+        // Only a speed picked while running is kept, as the default for ROMs the
+        // database has no speed for; the database's and --ipf's are left out
+        if self.chip.instructions_per_frame != self.default_instructions_per_frame {
+            self.preferences.instructions_per_frame = Some(self.chip.instructions_per_frame);
+        }
+        self.preferences.save();
+    }
+
+    /// The ratio of the current instructions per frame to the ROM's default.
+    fn speed(&self) -> f32 {
+        self.chip.instructions_per_frame as f32 / self.default_instructions_per_frame as f32
+    }
+
+    /// The status line: the ROM's file name, its platform, the execution state and
+    /// the speed. miniquad 0.3 can't change the window title once the window is
+    /// open, so they're shown here instead of there.
+    fn status_line(&self) -> Vec<(String, Vec4)> {
+        let mut spans = vec![];
+        if let Some(name) = Path::new(&self.rom_path).file_name() {
+            spans.push((format!("{} | ", name.to_string_lossy()), Vec4::ONE));
+        }
+        spans.push((format!("{} | ", self.chip.mode().name()), Vec4::ONE));
+        spans.extend(status_spans(self.chip.state()));
+        spans.push((format!(" | x{:.2}", self.speed()), Vec4::ONE));
+        spans
+    }
+
+    pub fn save_session(&self) {
+        if !fetch::is_file(&self.rom_path) {
+            return;
+        }
+        if let Err(e) = self.debugger.session(&self.chip).save(&self.rom_path) {
+            eprintln!("{}", e);
+        }
+    }
 }
 
-fn keycode_to_index(keycode: KeyCode) -> Option<usize> {
-    match keycode {
-        KeyCode::Key1 => Some(1),
-        KeyCode::Key2 => Some(2),
-        KeyCode::Key3 => Some(3),
-        KeyCode::Key4 => Some(0xc),
-        KeyCode::Q => Some(4),
-        KeyCode::W => Some(5),
-        KeyCode::E => Some(6),
-        KeyCode::R => Some(0xd),
-        KeyCode::A => Some(7),
-        KeyCode::S => Some(8),
-        KeyCode::D => Some(9),
-        KeyCode::F => Some(0xe),
-        KeyCode::Z => Some(0xa),
-        KeyCode::X => Some(0),
-        KeyCode::C => Some(0xb),
-        KeyCode::V => Some(0xf),
-        _ => None,
+/// The ROM's title from the database, or else its file name.
+fn rom_title(chip: &Chip8, rom_path: &str) -> String {
+    match romdb::lookup(chip.program()) {
+        Some(info) => info.title,
+        None => Path::new(rom_path).file_name().map_or_else(
+            || rom_path.to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        ),
     }
 }
 
+fn display_pipeline(
+    ctx: &mut Context,
+    vertex: &str,
+    fragment: &str,
+) -> Result<Pipeline, ShaderError> {
+    let shader = Shader::new(ctx, vertex, fragment, shader::meta())?;
+    Ok(Pipeline::new(
+        ctx,
+        &[BufferLayout::default()],
+        &[
+            VertexAttribute::new("pos", VertexFormat::Float2),
+            VertexAttribute::new("uv", VertexFormat::Float2),
+        ],
+        shader,
+    ))
+}
+
+/// Builds the display pipeline from the user's shaders, reporting why if it can't.
+fn user_pipeline(ctx: &mut Context, user_shader: &UserShader) -> Option<Pipeline> {
+    let (vertex, fragment) = user_shader
+        .sources(shader::VERTEX, shader::FRAGMENT)
+        .map_err(|e| eprintln!("Failed to read shader {}", e))
+        .ok()?;
+    display_pipeline(ctx, &vertex, &fragment)
+        .map_err(|e| eprintln!("Failed to compile shaders: {}", e))
+        .ok()
+}
+
+fn display_texture(ctx: &mut Context, display: &Display) -> Texture {
+    Texture::from_data_and_format(
+        ctx,
+        display.pixels(),
+        TextureParams {
+            format: TextureFormat::Alpha,
+            wrap: TextureWrap::Clamp,
+            filter: FilterMode::Nearest,
+            width: display.width() as u32,
+            height: display.height() as u32,
+        },
+    )
+}
+
+/// Uploads `display` into `texture`, recreating it if the resolution changed.
+fn update_display_texture(ctx: &mut Context, texture: &mut Texture, display: &Display) {
+    if (texture.width as usize, texture.height as usize) != display.size() {
+        texture.delete();
+        *texture = display_texture(ctx, display);
+    } else {
+        texture.update(ctx, display.pixels());
+    }
+}
+
+fn highlight_texture(ctx: &mut Context, highlights: &Highlights) -> Texture {
+    let (width, height) = highlights.size();
+    Texture::from_data_and_format(
+        ctx,
+        &highlights.rgba(),
+        TextureParams {
+            format: TextureFormat::RGBA8,
+            wrap: TextureWrap::Clamp,
+            filter: FilterMode::Nearest,
+            width: width as u32,
+            height: height as u32,
+        },
+    )
+}
+
+/// The execution state for the status line, in red once halted.
+fn status_spans(state: ExecutionState) -> Vec<(String, Vec4)> {
+    match state {
+        ExecutionState::Running => vec![("Running".to_string(), Vec4::ONE)],
+        ExecutionState::Paused => vec![("Paused".to_string(), Vec4::ONE)],
+        ExecutionState::WaitingForKey(x) => {
+            vec![(format!("Waiting for key (V{:X})", x), Vec4::ONE)]
+        }
+        ExecutionState::Halted(error) => vec![
+            ("Halted: ".to_string(), ERROR_COLOR),
+            (error.to_string(), Vec4::ONE),
+        ],
+    }
+}
+
+const HUD_MARGIN: f32 = 20.;
+const MESSAGE_SCALE: f32 = 0.5;
+const ERROR_COLOR: Vec4 = Vec4::new(1., 0.3, 0.3, 1.);
+const COLLISION_COLOR: Vec3 = Vec3::new(1., 0.1, 0.1);
+const STEP_COLOR: Vec3 = Vec3::new(0.2, 1., 0.3);
+
 impl EventHandler for Stage<'_> {
     fn update(&mut self, ctx: &mut Context) {
         // return;
-        if !self.debugger.is_enabled {
+        if self.watcher.as_mut().is_some_and(|watcher| watcher.poll()) {
+            let path = self.rom_path.clone();
+            if self.open_rom(&path) {
+                self.notify(&format!("Reloaded {}", path));
+            }
+        }
+        if let Some(user_shader) = &mut self.user_shader {
+            if user_shader.poll() {
+                if let Some(pipeline) = user_pipeline(ctx, user_shader) {
+                    self.pipeline = pipeline;
+                    self.notify("Reloaded display shaders");
+                }
+            }
+        }
+        control::update(self);
+        if let Some(netplay) = &mut self.netplay {
+            match netplay.update(&mut self.chip) {
+                Ok(Some(message)) => self.notify(&message),
+                Ok(None) => {}
+                Err(e) => {
+                    // Carry on alone from where both chips were
+                    self.netplay = None;
+                    self.notify(&format!("Netplay stopped: {}", e));
+                }
+            }
+        } else if let Some(runner) = &mut self.runner {
+            let turbo = self.input.is_pressed(Action::FastForward);
+            runner.sync(&mut self.chip, turbo);
+        } else if !self.debugger.is_enabled {
             self.chip.step_with_time();
-            self.bindings.images[0].update(ctx, &self.chip.display);
-            return;
+        } else {
+            gdb::update(self);
+            dap::update(self);
+            debugger::update(self);
+        }
+        self.input.end_frame();
+        // Held while paused, so a step's changes stay visible until the next one
+        if !self.chip.is_paused() {
+            self.highlights.fade();
+        }
+        if self.show_collisions {
+            self.highlight_collisions();
+        }
+        self.update_display_texture(ctx);
+        if let Some(spectators) = &mut self.spectators {
+            spectators.update(&self.chip, self.palette);
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.update(&self.chip, &self.palette, self.preferences.volume);
+        }
+
+        let status = self.status_line();
+        if status != self.shown_status {
+            self.status_text.update_spans(&status);
+            self.shown_status = status;
         }
-        debugger::update(self, ctx);
+    }
+
+    fn quit_requested_event(&mut self, _ctx: &mut Context) {
+        self.finish_recording();
+        self.save_session();
+        self.save_resume_state();
+        self.save_preferences();
     }
 
     fn resize_event(&mut self, _ctx: &mut Context, width: f32, height: f32) {
         self.size = (width as i32, height as i32);
+        // Keep the windowed size to go back to
+        if !self.preferences.fullscreen {
+            self.preferences.window_width = self.size.0;
+            self.preferences.window_height = self.size.1;
+        }
     }
 
     fn key_down_event(
         &mut self,
-        _ctx: &mut Context,
+        ctx: &mut Context,
         keycode: KeyCode,
+        keymods: KeyMods,
+        repeat: bool,
+    ) {
+        if self.dev_ui.key_event(keycode, keymods, true) {
+            return;
+        }
+        if self.browser.is_open {
+            if self.input.action(keycode) == Some(Action::ToggleBrowser) {
+                self.browser.is_open = false;
+            } else if let Some(path) = self.browser.key_down_event(keycode) {
+                self.open_rom(&path);
+            }
+            return;
+        }
+        if self.resume.is_some() {
+            match keycode {
+                KeyCode::Enter => {
+                    self.resume_saved_state();
+                    return;
+                }
+                KeyCode::Escape => {
+                    self.resume = None;
+                    self.message_text = None;
+                    return;
+                }
+                _ => {}
+            }
+        }
+        if self.run_for_input.is_some() {
+            self.run_for_key_down(keycode);
+            return;
+        }
+        if self.cheats.is_open {
+            if self.input.action(keycode) == Some(Action::ToggleCheats) {
+                self.cheats.is_open = false;
+            } else if let Some(message) = self.cheats.key_down_event(keycode, &mut self.chip) {
+                self.notify(&message);
+            }
+            return;
+        }
+        if self.memory_view.is_open {
+            if self.input.action(keycode) == Some(Action::ToggleMemory) {
+                self.memory_view.is_open = false;
+            } else if let Some(message) = self
+                .memory_view
+                .key_down_event(keycode, keymods, &self.chip)
+            {
+                self.notify(&message);
+            }
+            return;
+        }
+        let action = self.input.key_down(keycode, repeat);
+        if let Some(panel) = action.and_then(Action::panel) {
+            self.last_panel = Some(panel);
+        }
+        match action {
+            Some(Action::ChipKey(key)) => self.chip.keys[key as usize] = true,
+            // Changing ROM alone would leave the other player behind
+            Some(Action::ToggleBrowser) if self.netplay.is_none() => self.browser.open(),
+            Some(Action::PreviousRom) if self.netplay.is_none() => self.switch_rom(-1),
+            Some(Action::NextRom) if self.netplay.is_none() => self.switch_rom(1),
+            // Freezing needs the debugger's stepping, which threaded chips don't use
+            Some(Action::ToggleCheats) if self.debugger.is_enabled => self.cheats.is_open = true,
+            Some(Action::ToggleMemory) => self.memory_view.is_open = true,
+            Some(Action::RunFor) if self.debugger.is_enabled => {
+                self.run_for_input = Some(String::new());
+                self.show_run_for_input();
+            }
+            Some(Action::ToggleKeypad) => self.keypad.is_visible = !self.keypad.is_visible,
+            Some(Action::ToggleInput) => {
+                self.input_overlay.is_visible = !self.input_overlay.is_visible
+            }
+            Some(Action::ToggleHelp) => self.help.is_visible = !self.help.is_visible,
+            Some(Action::ToggleStats) => {
+                self.stats_overlay.is_visible = !self.stats_overlay.is_visible
+            }
+            Some(Action::Screenshot) => {
+                match screenshot::save(&self.chip.display, &self.palette, &self.rom_path) {
+                    Ok(path) => self.notify(&format!("Saved screenshot to {}", path.display())),
+                    Err(e) => self.notify(&format!("Failed to save screenshot: {}", e)),
+                }
+            }
+            Some(Action::ToggleRecording) => {
+                if self.recording.is_some() {
+                    self.finish_recording();
+                } else {
+                    self.start_recording(&recording::default_path(&self.rom_path));
+                }
+            }
+            Some(Action::ToggleTimers) => {
+                self.timers_overlay.is_visible = !self.timers_overlay.is_visible
+            }
+            Some(Action::ToggleDrawLog) => {
+                self.draw_log_view.is_visible = !self.draw_log_view.is_visible
+            }
+            // Only the debugger's stepping counts accesses
+            Some(Action::ToggleHeatmap) if self.debugger.is_enabled => {
+                self.heatmap_view.is_visible = !self.heatmap_view.is_visible
+            }
+            Some(Action::ToggleDevUi) => {
+                if let Err(e) = self.dev_ui.toggle() {
+                    self.notify(&e);
+                }
+            }
+            Some(Action::MovePanel) => {
+                if let Some(panel) = self.last_panel {
+                    let message = self.preferences.layout.move_panel(panel);
+                    self.notify(&message);
+                }
+            }
+            Some(Action::ResizePanel) => {
+                if let Some(panel) = self.last_panel {
+                    let message = self.preferences.layout.resize_panel(panel);
+                    self.notify(&message);
+                }
+            }
+            Some(Action::ScrollLogBack) => self.draw_log_view.scroll_pages(1),
+            Some(Action::ScrollLogForward) => self.draw_log_view.scroll_pages(-1),
+            Some(Action::ToggleCollisions) => {
+                self.show_collisions = !self.show_collisions;
+                let state = if self.show_collisions { "on" } else { "off" };
+                self.notify(&format!("Collision highlighting: {}", state));
+            }
+            Some(Action::ToggleFullscreen) => {
+                self.preferences.fullscreen = !self.preferences.fullscreen;
+                ctx.set_fullscreen(self.preferences.fullscreen);
+            }
+            Some(Action::ToggleQuirkDetection) if self.netplay.is_none() => {
+                self.detect_quirks = !self.detect_quirks;
+                let state = if self.detect_quirks { "on" } else { "off" };
+                self.notify(&format!("Quirk detection: {}", state));
+                let path = self.rom_path.clone();
+                self.open_rom(&path);
+            }
+            Some(Action::CycleFilter) => {
+                self.scaling.filter = self.scaling.filter.next();
+                self.notify(&format!("Display filter: {:?}", self.scaling.filter));
+            }
+            Some(Action::CyclePalette) => {
+                let (name, palette) = self.palette.next_preset();
+                self.palette = palette;
+                self.notify(&format!("Palette: {}", name));
+            }
+            Some(Action::ChooseAdjustment) => {
+                self.adjusting = self.adjusting.next();
+                let message = self.preferences.adjustments.describe(self.adjusting);
+                self.notify(&message);
+            }
+            Some(Action::DecreaseAdjustment) => {
+                let message = self.preferences.adjustments.adjust(self.adjusting, false);
+                self.notify(&message);
+            }
+            Some(Action::IncreaseAdjustment) => {
+                let message = self.preferences.adjustments.adjust(self.adjusting, true);
+                self.notify(&message);
+            }
+            Some(Action::ResetAdjustments) => {
+                self.preferences.adjustments = Adjustments::default();
+                self.notify("Reset brightness, contrast and gamma");
+            }
+            Some(Action::ToggleMute) => {
+                let message = self.preferences.volume.toggle_mute();
+                self.notify(&message);
+            }
+            Some(Action::VolumeDown) => {
+                let message = self.preferences.volume.adjust(false);
+                self.notify(&message);
+            }
+            Some(Action::VolumeUp) => {
+                let message = self.preferences.volume.adjust(true);
+                self.notify(&message);
+            }
+            // The rest are the debugger's, checked in `update`
+            _ => {}
+        }
+    }
+
+    fn char_event(
+        &mut self,
+        _ctx: &mut Context,
+        character: char,
         _keymods: KeyMods,
         _repeat: bool,
     ) {
-        if let Some(index) = keycode_to_index(keycode) {
-            self.chip.keys[index] = true;
+        if self.dev_ui.char_event(character) {
+            return;
+        }
+        if self.memory_view.is_open && !self.browser.is_open {
+            self.memory_view.char_event(character);
+        }
+    }
+
+    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, keymods: KeyMods) {
+        // Released keys always reach the emulator, so none are left held down
+        self.dev_ui.key_event(keycode, keymods, false);
+        if let Some(Action::ChipKey(key)) = self.input.key_up(keycode) {
+            self.chip.keys[key as usize] = false;
         }
-        self.debugger.key_down_event(keycode);
     }
 
-    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymods: KeyMods) {
-        if let Some(index) = keycode_to_index(keycode) {
-            self.chip.keys[index] = false;
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+        if self.dev_ui.mouse_motion_event(x, y) {
+            return;
+        }
+        let position = self.window_position(x, y);
+        if let Some(request) = self.controls.pointer_moved(keypad::MOUSE_POINTER, position) {
+            self.control_bar_request(request);
+        }
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+        self.dev_ui.mouse_wheel_event(x, y);
+    }
+
+    // miniquad 0.3 only reports these on Windows and Android, and has no focus
+    // events at all, so switching to another window without minimizing keeps the
+    // chip running. The catch-up after the event loop stalls in the background is
+    // capped by `Chip8::limit_catch_up` instead. There's no live audio to mute, and
+    // recordings stop adding frames and sound while the chip is paused.
+    fn window_minimized_event(&mut self, _ctx: &mut Context) {
+        if self.pause_in_background && !self.chip.is_paused() {
+            self.chip.pause();
+            self.paused_in_background = true;
+        }
+    }
+
+    fn window_restored_event(&mut self, _ctx: &mut Context) {
+        if self.paused_in_background {
+            // Resuming resyncs the clock, so the time spent minimized isn't caught up
+            self.chip.resume();
+            self.paused_in_background = false;
+        }
+    }
+
+    fn mouse_button_down_event(&mut self, _ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        if self.dev_ui.mouse_button_event(button, x, y, true) {
+            return;
+        }
+        let position = self.window_position(x, y);
+        if let Some(request) = self.controls.press(keypad::MOUSE_POINTER, position) {
+            self.control_bar_request(request);
+            return;
+        }
+        self.keypad
+            .press(&mut self.chip, keypad::MOUSE_POINTER, position);
+    }
+
+    fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        self.dev_ui.mouse_button_event(button, x, y, false);
+        if let Some(action) = self.controls.release(keypad::MOUSE_POINTER) {
+            self.input.release(action);
+        }
+        self.keypad.release(&mut self.chip, keypad::MOUSE_POINTER);
+    }
+
+    /// Tracks each finger separately so several keys can be held at once. Touching
+    /// the window shows the keypad and the control bar, since there's no keyboard to
+    /// toggle them with.
+    fn touch_event(&mut self, _ctx: &mut Context, phase: TouchPhase, id: u64, x: f32, y: f32) {
+        let position = self.window_position(x, y);
+        match phase {
+            TouchPhase::Started => {
+                if let Some(request) = self.controls.press(id, position) {
+                    self.control_bar_request(request);
+                } else if !self.keypad.press(&mut self.chip, id, position) {
+                    self.keypad.is_visible = true;
+                }
+                self.controls.show();
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                if let Some(action) = self.controls.release(id) {
+                    self.input.release(action);
+                }
+                self.keypad.release(&mut self.chip, id);
+            }
+            TouchPhase::Moved => {
+                if let Some(request) = self.controls.pointer_moved(id, position) {
+                    self.control_bar_request(request);
+                }
+            }
         }
-        self.debugger.key_up_event(keycode);
     }
 
     fn draw(&mut self, ctx: &mut Context) {
@@ -181,25 +1303,107 @@ impl EventHandler for Stage<'_> {
             },
         )
         .inverse();
-        ctx.apply_pipeline(&self.pipeline);
-        ctx.apply_bindings(&self.bindings);
-        ctx.apply_uniforms(&shader::Uniforms {
+        // Side by side with the comparison chip, when there is one, on the right
+        let main_width = match self.debugger.comparison {
+            Some(_) => window_width / 2.,
+            None => window_width,
+        };
+        let main = (0., main_width);
+        self.draw_display(
+            ctx,
+            &self.bindings,
+            &self.chip.display,
+            main,
             projection,
             view,
-            model: Mat4::from_scale_rotation_translation(
-                Vec3::splat(f32::min(window_width / 64.0, window_height / 32.0)),
-                Quat::IDENTITY,
-                Vec3 {
-                    x: 1.,
-                    y: 0.,
-                    z: 0.,
-                },
-            ),
-        });
-        ctx.draw(0, 6, 1);
+        );
+        if let Some(comparison) = &self.debugger.comparison {
+            let display = &comparison.chip.display;
+            let right = (main_width, window_width - main_width);
+            self.draw_display(
+                ctx,
+                &self.comparison_bindings,
+                display,
+                right,
+                projection,
+                view,
+            );
+        }
 
-        self.text_test.draw(ctx, projection, view);
-        self.text_test_2.draw(ctx, projection, view);
+        self.status_text.model = Mat4::from_translation(Vec3 {
+            x: window_width - HUD_MARGIN,
+            y: HUD_MARGIN,
+            z: 0.,
+        });
+        self.text_batcher.add(&self.status_text);
+        if let Some(message_text) = &mut self.message_text {
+            message_text.set_max_width(Some((window_width - 2. * HUD_MARGIN) / MESSAGE_SCALE));
+            message_text.model = Mat4::from_translation(Vec3 {
+                x: window_width / 2.,
+                y: window_height - HUD_MARGIN,
+                z: 0.,
+            }) * Mat4::from_scale(Vec3::splat(MESSAGE_SCALE));
+            self.text_batcher.add(message_text);
+        }
+        self.keypad.draw(&mut self.text_batcher, &self.chip);
+        // Its buttons and sliders drive the debugger's stepping
+        if self.debugger.is_enabled {
+            let left = if self.keypad.is_visible {
+                keypad::WIDTH
+            } else {
+                0.
+            };
+            self.controls.draw(
+                &mut self.text_batcher,
+                &self.chip,
+                self.default_instructions_per_frame,
+                self.debugger.timeline(),
+                left,
+            );
+        }
+        // Panels on the same edge stack in the order they're drawn
+        let mut dock = Dock::new(&self.preferences.layout, window_width, window_height);
+        let speed = self.speed();
+        self.stats_overlay.draw(
+            &mut self.text_batcher,
+            &self.chip,
+            speed,
+            self.preferences.volume,
+            &mut dock,
+        );
+        self.timers_overlay
+            .draw(&mut self.text_batcher, &self.chip, &mut dock);
+        let regions = regions::all(&self.chip, &self.debugger.regions);
+        self.memory_view
+            .draw(&mut self.text_batcher, &self.chip, &regions, &mut dock);
+        self.input_overlay
+            .draw(&mut self.text_batcher, &self.chip, &mut dock);
+        self.draw_log_view
+            .draw(&mut self.text_batcher, &self.debugger.draw_log, &mut dock);
+        self.heatmap_view.draw(
+            ctx,
+            &mut self.text_batcher,
+            &self.debugger.heatmap,
+            &mut dock,
+            projection,
+            view,
+        );
+        if let Some(script) = &self.debugger.script {
+            self.script_hud.draw(&mut self.text_batcher, &script.hud());
+        }
+        self.title_overlay.draw(
+            &mut self.text_batcher,
+            &self.title,
+            window_width,
+            window_height,
+        );
+        self.toasts.draw(&mut self.text_batcher, window_width);
+        self.help.draw(&mut self.text_batcher, window_height);
+        self.browser.draw(&mut self.text_batcher, window_height);
+        self.cheats
+            .draw(&mut self.text_batcher, &self.chip, window_height);
+        self.text_batcher.draw(ctx, projection, view);
+        dev_ui::draw(ctx, self);
 
         ctx.end_render_pass();
 
@@ -215,12 +1419,19 @@ mod shader {
 
     pub fn meta() -> ShaderMeta {
         ShaderMeta {
-            images: vec!["tex".to_string()],
+            images: vec!["tex".to_string(), "highlight".to_string()],
             uniforms: UniformBlockLayout {
                 uniforms: vec![
                     UniformDesc::new("model", UniformType::Mat4),
                     UniformDesc::new("view", UniformType::Mat4),
                     UniformDesc::new("projection", UniformType::Mat4),
+                    UniformDesc::new("palette", UniformType::Float4).array(4),
+                    UniformDesc::new("texture_size", UniformType::Float2),
+                    UniformDesc::new("pixel_scale", UniformType::Float1),
+                    UniformDesc::new("filter_mode", UniformType::Float1),
+                    UniformDesc::new("brightness", UniformType::Float1),
+                    UniformDesc::new("contrast", UniformType::Float1),
+                    UniformDesc::new("gamma", UniformType::Float1),
                 ],
             },
         }
@@ -231,23 +1442,276 @@ mod shader {
         pub model: glam::Mat4,
         pub view: glam::Mat4,
         pub projection: glam::Mat4,
+        pub palette: [glam::Vec4; 4],
+        pub texture_size: glam::Vec2,
+        pub pixel_scale: f32,
+        pub filter_mode: f32,
+        pub brightness: f32,
+        pub contrast: f32,
+        pub gamma: f32,
+    }
+}
+
+/// Runs `flake asm`, returning the process exit code. Octo sources are compiled too.
+fn assemble(input: &str, output: &str) -> i32 {
+    let source = match std::fs::read_to_string(input) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", input, e);
+            return 1;
+        }
+    };
+    let result = if octo::is_source(input) {
+        octo::compile(&source)
+    } else {
+        asm::assemble(&source)
+    };
+    let assembled = match result {
+        Ok(assembled) => assembled,
+        Err(e) => {
+            eprintln!("{}: {}", input, e);
+            return 1;
+        }
+    };
+    if let Err(e) = std::fs::write(output, &assembled.rom) {
+        eprintln!("Failed to write {}: {}", output, e);
+        return 1;
+    }
+    println!("Assembled {} bytes into {}", assembled.rom.len(), output);
+    if !assembled.symbols.is_empty() {
+        if let Err(e) = assembled.symbols.save(output) {
+            eprintln!("{}", e);
+            return 1;
+        }
+    }
+    let mut lines = assembled.lines;
+    if !lines.is_empty() {
+        // Absolute, so debuggers find the source wherever they run from
+        lines.source = std::fs::canonicalize(input)
+            .map_or(input.to_string(), |path| path.display().to_string());
+        if let Err(e) = lines.save(output) {
+            eprintln!("{}", e);
+            return 1;
+        }
+    }
+    0
+}
+
+/// Runs `flake disasm`, returning the process exit code.
+fn disassemble(path: &str, output: Option<&str>) -> i32 {
+    let rom = match std::fs::read(path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path, e);
+            return 1;
+        }
+    };
+    let symbols = match Symbols::load(path) {
+        Ok(symbols) => symbols.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let listing = disasm::listing(&rom, &symbols);
+    match output {
+        Some(output) => {
+            if let Err(e) = std::fs::write(output, listing) {
+                eprintln!("Failed to write {}: {}", output, e);
+                return 1;
+            }
+        }
+        None => print!("{}", listing),
     }
+    0
+}
+
+/// Runs the ROM headless for `instructions` instructions, set up as it would be
+/// in the window, and prints a SHA-1 of the display and registers. Only the
+/// emulated time passes, so the hash is the same on every machine. Stops early,
+/// saying so, if the program halts or waits for a key.
+fn hash_after(options: &Options, instructions: u64) -> i32 {
+    let mut chip = Chip8::new();
+    chip.trace = false;
+    let playlist = Playlist::new(&options.roms);
+    let Some(rom) = playlist.current() else {
+        eprintln!("No ROMs to run");
+        return 1;
+    };
+    if let Err(e) = chip.load(rom) {
+        eprintln!("Failed to load {}: {}", rom, e);
+        return 1;
+    }
+    chip.seed_rng(0);
+    let (instructions_per_frame, _) =
+        romdb::apply_profile(&mut chip, true, DEFAULT_INSTRUCTIONS_PER_FRAME);
+    chip.instructions_per_frame = options
+        .instructions_per_frame
+        .unwrap_or(instructions_per_frame);
+    chip.quirks.apply(&options.quirks);
+
+    while chip.instruction_count() < instructions {
+        match chip.state() {
+            ExecutionState::Halted(_) | ExecutionState::WaitingForKey(_) => {
+                let status: String = status_spans(chip.state())
+                    .into_iter()
+                    .map(|(text, _)| text)
+                    .collect();
+                eprintln!(
+                    "Stopped after {} instructions: {}",
+                    chip.instruction_count(),
+                    status
+                );
+                break;
+            }
+            _ => chip.step_debug(),
+        }
+    }
+
+    let mut hash = sha1_smol::Sha1::new();
+    let (width, height) = chip.display.size();
+    hash.update(&(width as u16).to_le_bytes());
+    hash.update(&(height as u16).to_le_bytes());
+    hash.update(chip.display.pixels());
+    hash.update(&chip.v());
+    hash.update(&chip.i().to_le_bytes());
+    hash.update(&(chip.pc() as u16).to_le_bytes());
+    hash.update(&[chip.dt(), chip.st()]);
+    for &address in chip.stack() {
+        hash.update(&(address as u16).to_le_bytes());
+    }
+    println!("{}", hash.digest());
+    0
+}
+
+/// Traces `path` from a fresh chip with the RNG seeded to 0, so runs of the same
+/// ROM can be compared, and either writes the trace or diffs it against
+/// `reference`.
+fn trace_rom(
+    path: &str,
+    instructions: usize,
+    output: Option<&str>,
+    reference: Option<&str>,
+) -> i32 {
+    let mut chip = Chip8::new();
+    chip.trace = false;
+    if let Err(e) = chip.load(path) {
+        eprintln!("Failed to load {}: {}", path, e);
+        return 1;
+    }
+    chip.seed_rng(0);
+
+    if let Some(reference) = reference {
+        let expected = match std::fs::read_to_string(reference) {
+            Ok(text) => match trace::parse(&text) {
+                Ok(expected) => expected,
+                Err(e) => {
+                    eprintln!("{}: {}", reference, e);
+                    return 1;
+                }
+            },
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", reference, e);
+                return 1;
+            }
+        };
+        let actual = trace::record(&mut chip, expected.len());
+        return match trace::compare(&expected, &actual) {
+            Some(divergence) => {
+                println!("{}", divergence);
+                1
+            }
+            None => {
+                println!("Matched {} instructions", expected.len());
+                0
+            }
+        };
+    }
+
+    let text = trace::to_text(&trace::record(&mut chip, instructions));
+    match output {
+        Some(output) => {
+            if let Err(e) = std::fs::write(output, text) {
+                eprintln!("Failed to write {}: {}", output, e);
+                return 1;
+            }
+        }
+        None => print!("{}", text),
+    }
+    0
+}
+
+/// Runs the debugger window for the emulator whose control server is at
+/// `address`, until it closes.
+fn monitor(address: &str) {
+    let stream = match Monitor::connect(address) {
+        Ok(stream) => stream,
+        Err(e) => {
+            eprintln!("Failed to connect to {}: {}", address, e);
+            std::process::exit(1);
+        }
+    };
+    miniquad::start(
+        conf::Conf {
+            window_title: "flake debugger".to_string(),
+            window_width: 1100,
+            window_height: 600,
+            ..Default::default()
+        },
+        move |ctx| {
+            let font = Box::leak(Box::new(SDFFont::new(ctx, None)));
+            Box::new(Monitor::new(ctx, stream, font))
+        },
+    );
 }
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
+    let options = match cli::parse(&args) {
+        Ok(Command::Run(options)) => options,
+        Ok(Command::Asm { input, output }) => std::process::exit(assemble(&input, &output)),
+        Ok(Command::Disasm { rom, output }) => {
+            std::process::exit(disassemble(&rom, output.as_deref()))
+        }
+        Ok(Command::Trace {
+            rom,
+            instructions,
+            output,
+            reference,
+        }) => std::process::exit(trace_rom(
+            &rom,
+            instructions,
+            output.as_deref(),
+            reference.as_deref(),
+        )),
+        Ok(Command::Monitor { address }) => {
+            monitor(&address);
+            return;
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
 
+    if let Some(instructions) = options.hash_after {
+        std::process::exit(hash_after(&options, instructions));
+    }
+
+    let preferences = Preferences::load();
     miniquad::start(
         conf::Conf {
+            // miniquad 0.3 can't change the title once the window exists, so naming
+            // the ROM here would go stale on switching; the status line shows it
             window_title: "Flake".to_string(),
-            window_width: 1200,
-            window_height: 600,
+            window_width: preferences.window_width,
+            window_height: preferences.window_height,
+            fullscreen: preferences.fullscreen,
             ..Default::default()
         },
         move |ctx| {
-            let font = Box::leak(Box::new(SDFFont::new(ctx)));
-            let default = &String::from("roms/breakout.ch8");
-            Box::new(Stage::new(ctx, args.get(1).unwrap_or(default), font))
+            let font = Box::leak(Box::new(SDFFont::new(ctx, options.font.as_deref())));
+            Box::new(Stage::new(ctx, &options, preferences, font))
         },
     );
 }
@@ -0,0 +1,352 @@
+use crate::{
+    chip8::{Chip8, OpCodes},
+    disasm,
+    layout::{Dock, Panel},
+    regions::{self, Region},
+    sdf::{Align, SDFFont, SDFText, TextBatcher, TextStyle, VAlign},
+};
+use glam::Vec4;
+use miniquad::{KeyCode, KeyMods};
+
+const BYTES_PER_ROW: usize = 16;
+const VISIBLE_ROWS: usize = 12;
+const DISASSEMBLY_ROWS: usize = 10;
+const TEXT_SCALE: f32 = 0.3;
+
+const MATCH_COLOR: Vec4 = Vec4::new(1., 0.85, 0.2, 1.);
+const SELECTED_COLOR: Vec4 = Vec4::new(0.3, 1., 0.4, 1.);
+const PC_COLOR: Vec4 = Vec4::new(0.4, 0.8, 1., 1.);
+const HINT_COLOR: Vec4 = Vec4::new(0.6, 0.6, 0.6, 1.);
+
+/// Reads a search as bytes: hex digits with optional spaces, e.g. `F0 90`, or an
+/// ASCII string in double quotes, e.g. `"HI"`, where the closing quote is optional.
+pub fn parse_pattern(query: &str) -> Result<Vec<u8>, String> {
+    if let Some(text) = query.strip_prefix('"') {
+        let text = text.strip_suffix('"').unwrap_or(text);
+        if text.is_empty() || !text.is_ascii() {
+            return Err("expected ASCII text between the quotes".to_string());
+        }
+        return Ok(text.as_bytes().to_vec());
+    }
+    let digits: String = query.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) || !digits.is_ascii() {
+        return Err("expected whole bytes of hex, or text in quotes".to_string());
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&digits[i..i + 2], 16)
+                .map_err(|_| format!("'{}' is not a hex byte", &digits[i..i + 2]))
+        })
+        .collect()
+}
+
+/// Every address `pattern` starts at in `memory`.
+pub fn find(memory: &[u8], pattern: &[u8]) -> Vec<usize> {
+    if pattern.is_empty() {
+        return vec![];
+    }
+    memory
+        .windows(pattern.len())
+        .enumerate()
+        .filter(|(_, window)| *window == pattern)
+        .map(|(address, _)| address)
+        .collect()
+}
+
+/// A hex dump of memory that can be searched for bytes or text, with the
+/// disassembly at the program counter or at a found address underneath. Bytes in
+/// named regions are colored, with the names at the ends of their rows. Takes
+/// over the keyboard while open.
+pub struct MemoryView<'a> {
+    pub is_open: bool,
+    /// The first address shown, a multiple of `BYTES_PER_ROW`.
+    address: usize,
+    query: String,
+    /// The query the matches are for, to tell a new search from moving on.
+    searched: String,
+    pattern_len: usize,
+    matches: Vec<usize>,
+    /// Index into `matches`.
+    selected: usize,
+    /// Where the disassembly starts, or `None` to follow the program counter.
+    disassembly: Option<usize>,
+    text: SDFText<'a>,
+}
+
+impl<'a> MemoryView<'a> {
+    pub fn new(font: &'a SDFFont) -> MemoryView<'a> {
+        let mut text = SDFText::new(font, "");
+        text.align = Align {
+            vertical: VAlign::Top,
+            ..Default::default()
+        };
+        text.style = TextStyle {
+            outline_width: 0.15,
+            ..Default::default()
+        };
+        MemoryView {
+            is_open: false,
+            address: 0x200,
+            query: String::new(),
+            searched: String::new(),
+            pattern_len: 0,
+            matches: vec![],
+            selected: 0,
+            disassembly: None,
+            text,
+        }
+    }
+
+    /// Forgets the search and goes back to the start of the program, e.g. for a
+    /// new ROM.
+    pub fn reset(&mut self) {
+        self.address = 0x200;
+        self.query.clear();
+        self.searched.clear();
+        self.pattern_len = 0;
+        self.matches.clear();
+        self.selected = 0;
+        self.disassembly = None;
+    }
+
+    /// Scrolls to `address` and disassembles from there, e.g. where the program was
+    /// when the last session ended.
+    pub fn go_to(&mut self, address: usize) {
+        self.address = address - address % BYTES_PER_ROW;
+        self.disassembly = Some(address);
+    }
+
+    /// Adds typed text to the search.
+    pub fn char_event(&mut self, character: char) {
+        // Typed by the key that closes the view, on the way out
+        if character != '`' && !character.is_control() {
+            self.query.push(character);
+        }
+    }
+
+    /// Handles a key while open, returning a message for searches.
+    pub fn key_down_event(
+        &mut self,
+        keycode: KeyCode,
+        keymods: KeyMods,
+        chip: &Chip8,
+    ) -> Option<String> {
+        let memory_len = chip.memory().len();
+        let page = VISIBLE_ROWS * BYTES_PER_ROW;
+        match keycode {
+            KeyCode::Escape => self.is_open = false,
+            KeyCode::Backspace => {
+                self.query.pop();
+            }
+            KeyCode::Up => self.scroll_to(self.address.saturating_sub(BYTES_PER_ROW), chip),
+            KeyCode::Down => self.scroll_to(self.address + BYTES_PER_ROW, chip),
+            KeyCode::PageUp => self.scroll_to(self.address.saturating_sub(page), chip),
+            KeyCode::PageDown => self.scroll_to(self.address + page, chip),
+            KeyCode::Enter | KeyCode::KpEnter => {
+                let backwards = keymods.shift;
+                return Some(self.search(chip, backwards));
+            }
+            KeyCode::Tab => {
+                let address = self
+                    .matches
+                    .get(self.selected)
+                    .copied()
+                    .unwrap_or(self.address);
+                self.disassembly = Some(address.min(memory_len - 2));
+                return Some(format!("Disassembling from {:#06x}", address));
+            }
+            KeyCode::Home => {
+                self.disassembly = None;
+                return Some("Disassembling from PC".to_string());
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Searches for the query, or moves on to the next or previous match when it's
+    /// the same as last time.
+    fn search(&mut self, chip: &Chip8, backwards: bool) -> String {
+        let pattern = match parse_pattern(&self.query) {
+            Ok(pattern) => pattern,
+            Err(e) => return format!("Can't search: {}", e),
+        };
+        let previous = self.matches.get(self.selected).copied();
+        // Searched again every time, since memory changes as the program runs
+        self.matches = find(chip.memory(), &pattern);
+        self.pattern_len = pattern.len();
+        let is_new = self.query != self.searched;
+        self.searched = self.query.clone();
+        if self.matches.is_empty() {
+            return format!("No matches for {}", self.query);
+        }
+
+        let count = self.matches.len();
+        let from = if is_new { None } else { previous };
+        self.selected = match (from, backwards) {
+            // Starting from what's on screen
+            (None, false) => self
+                .matches
+                .iter()
+                .position(|&address| address >= self.address)
+                .unwrap_or(0),
+            (None, true) => self
+                .matches
+                .iter()
+                .rposition(|&address| address < self.address + VISIBLE_ROWS * BYTES_PER_ROW)
+                .unwrap_or(count - 1),
+            (Some(previous), false) => self
+                .matches
+                .iter()
+                .position(|&address| address > previous)
+                .unwrap_or(0),
+            (Some(previous), true) => self
+                .matches
+                .iter()
+                .rposition(|&address| address < previous)
+                .unwrap_or(count - 1),
+        };
+        let address = self.matches[self.selected];
+        self.reveal(address, chip);
+        format!(
+            "Match {} of {} for {} at {:#06x}",
+            self.selected + 1,
+            count,
+            self.query,
+            address
+        )
+    }
+
+    fn scroll_to(&mut self, address: usize, chip: &Chip8) {
+        let last_row = chip.memory().len() - VISIBLE_ROWS * BYTES_PER_ROW;
+        self.address = address.min(last_row) / BYTES_PER_ROW * BYTES_PER_ROW;
+    }
+
+    /// Scrolls just enough to show `address`.
+    fn reveal(&mut self, address: usize, chip: &Chip8) {
+        let shown = VISIBLE_ROWS * BYTES_PER_ROW;
+        if address < self.address || address >= self.address + shown {
+            self.scroll_to(address.saturating_sub(shown / 2), chip);
+        }
+    }
+
+    pub fn draw(
+        &mut self,
+        batcher: &mut TextBatcher,
+        chip: &Chip8,
+        regions: &[Region],
+        dock: &mut Dock,
+    ) {
+        if !self.is_open {
+            return;
+        }
+        // Memory changes while it runs, so this is laid out every frame
+        self.text.update_spans(&self.spans(chip, regions));
+        dock.place_text(Panel::Memory, &mut self.text, TEXT_SCALE);
+        batcher.add(&self.text);
+    }
+
+    /// The color of the byte at `address`, for whether it's part of a match or
+    /// else which region it's in.
+    fn byte_color(&self, address: usize, regions: &[Region]) -> Vec4 {
+        let within = |start: usize| (start..start + self.pattern_len).contains(&address);
+        if self.matches.get(self.selected).is_some_and(|&s| within(s)) {
+            SELECTED_COLOR
+        } else if self
+            .matches
+            .iter()
+            .take_while(|&&start| start <= address)
+            .any(|&start| within(start))
+        {
+            MATCH_COLOR
+        } else {
+            regions::at(regions, address).map_or(Vec4::ONE, |(_, color)| color)
+        }
+    }
+
+    fn spans(&self, chip: &Chip8, regions: &[Region]) -> Vec<(String, Vec4)> {
+        let memory = chip.memory();
+        let found = if self.searched.is_empty() {
+            String::new()
+        } else {
+            format!("   {} match(es)", self.matches.len())
+        };
+        let mut spans = vec![(
+            format!("Memory   Search: {}_{}\n", self.query, found),
+            Vec4::ONE,
+        )];
+        for row in (self.address..memory.len())
+            .step_by(BYTES_PER_ROW)
+            .take(VISIBLE_ROWS)
+        {
+            spans.push((format!("{:#06x} ", row), HINT_COLOR));
+            let bytes = row..row + BYTES_PER_ROW;
+            for address in bytes.clone() {
+                spans.push((
+                    format!(" {:02X}", memory[address]),
+                    self.byte_color(address, regions),
+                ));
+            }
+            spans.push(("  ".to_string(), Vec4::ONE));
+            let mut names: Vec<(&str, Vec4)> = vec![];
+            for address in bytes {
+                let byte = memory[address];
+                let character = if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                };
+                spans.push((character.to_string(), self.byte_color(address, regions)));
+                if let Some((region, color)) = regions::at(regions, address) {
+                    if !names.iter().any(|&(name, _)| name == region.name) {
+                        names.push((&region.name, color));
+                    }
+                }
+            }
+            for (name, color) in names {
+                spans.push((format!("  {}", name), color));
+            }
+            spans.push(("\n".to_string(), Vec4::ONE));
+        }
+
+        let start = self.disassembly.unwrap_or(chip.pc());
+        let from = match self.disassembly {
+            Some(address) => format!("{:#06x}", address),
+            None => "PC".to_string(),
+        };
+        spans.push((format!("\nDisassembly from {}\n", from), Vec4::ONE));
+        for address in (start..memory.len() - 1).step_by(2).take(DISASSEMBLY_ROWS) {
+            let opcode = u16::from_be_bytes([memory[address], memory[address + 1]]);
+            let op = OpCodes::try_from(opcode).unwrap();
+            if let Some(label) = chip.symbols().name(address) {
+                spans.push((format!("{}:\n", label), HINT_COLOR));
+            }
+            let color = if address == chip.pc() {
+                PC_COLOR
+            } else if self.matches.get(self.selected) == Some(&address) {
+                SELECTED_COLOR
+            } else {
+                Vec4::ONE
+            };
+            spans.push((
+                format!(
+                    "{} {:#06x}  {:04X}  {}\n",
+                    if address == chip.pc() { ">" } else { " " },
+                    address,
+                    opcode,
+                    disasm::mnemonic(op, chip.symbols())
+                ),
+                color,
+            ));
+        }
+        spans.push((
+            "Type hex bytes or \"text\" and Enter to find them, Enter again for the next\n\
+             match and Shift+Enter for the previous. Tab disassembles from the match,\n\
+             Home from PC. Arrows and Page Up/Down scroll."
+                .to_string(),
+            HINT_COLOR,
+        ));
+        spans
+    }
+}
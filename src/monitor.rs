@@ -0,0 +1,236 @@
+use crate::{
+    chip8::Chip8,
+    fetch,
+    layout::{Dock, Layout},
+    memory_view::MemoryView,
+    regions,
+    save_state::SaveState,
+    sdf::{Align, HAlign, SDFFont, SDFText, TextBatcher, TextStyle, VAlign},
+};
+use glam::{Mat4, Vec3};
+use miniquad::*;
+use serde_json::{json, Value};
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::TcpStream,
+};
+
+const TEXT_SCALE: f32 = 0.3;
+const MARGIN: f32 = 20.;
+/// Marks the responses to our own requests for the state.
+const SYNC_ID: &str = "sync";
+
+/// The debugger of another flake in a window of its own, kept up to date over
+/// its control server. miniquad only opens one window per process, so this runs
+/// as `flake monitor` alongside the emulator.
+pub struct Monitor<'a> {
+    stream: TcpStream,
+    /// What's been read that isn't a whole line yet.
+    received: Vec<u8>,
+    /// Whether a request for the state hasn't been answered yet.
+    syncing: bool,
+    /// A copy of the emulator's chip, for the memory view to look at.
+    chip: Chip8,
+    rom: String,
+    state: String,
+    message: String,
+    registers: SDFText<'a>,
+    memory_view: MemoryView<'a>,
+    text_batcher: TextBatcher<'a>,
+}
+
+impl<'a> Monitor<'a> {
+    pub fn connect(address: &str) -> io::Result<TcpStream> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nonblocking(true)?;
+        Ok(stream)
+    }
+
+    pub fn new(ctx: &mut Context, stream: TcpStream, font: &'a SDFFont) -> Monitor<'a> {
+        let mut registers = SDFText::new(font, "");
+        registers.align = Align {
+            horizontal: HAlign::Right,
+            vertical: VAlign::Top,
+        };
+        registers.style = TextStyle {
+            outline_width: 0.15,
+            ..Default::default()
+        };
+        let mut memory_view = MemoryView::new(font);
+        memory_view.is_open = true;
+        Monitor {
+            stream,
+            received: vec![],
+            syncing: false,
+            chip: Chip8::new(),
+            rom: String::new(),
+            state: String::new(),
+            message: String::new(),
+            registers,
+            memory_view,
+            text_batcher: TextBatcher::new(ctx, font),
+        }
+    }
+
+    fn send(&mut self, request: &Value) {
+        if let Err(e) = writeln!(self.stream, "{}", request) {
+            eprintln!("Failed to write to the emulator: {}", e);
+        }
+    }
+
+    /// Reads whatever has arrived, returning false once the emulator has gone.
+    fn receive(&mut self) -> bool {
+        let mut buffer = [0; 4096];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return false,
+                Ok(n) => self.received.extend_from_slice(&buffer[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    eprintln!("Lost the emulator: {}", e);
+                    return false;
+                }
+            }
+        }
+        while let Some(end) = self.received.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.received.drain(..=end).collect();
+            match serde_json::from_slice(&line) {
+                Ok(response) => self.handle(response),
+                Err(e) => eprintln!("Bad response from the emulator: {}", e),
+            }
+        }
+        true
+    }
+
+    fn handle(&mut self, response: Value) {
+        if response["ok"] != true {
+            self.message = format!("Error: {}", response["error"]);
+        }
+        if response["id"] != SYNC_ID {
+            return;
+        }
+        self.syncing = false;
+        let rom = response["rom"].as_str().unwrap_or_default();
+        if rom != self.rom {
+            self.rom = rom.to_string();
+            // Only for the symbols, since the state below overwrites the rest. Standard
+            // input and URLs can't be read again.
+            self.chip = Chip8::new();
+            if fetch::is_file(rom) {
+                if let Err(e) = self.chip.load(rom) {
+                    eprintln!("Failed to load {} for its symbols: {}", rom, e);
+                }
+            }
+            self.memory_view.reset();
+        }
+        self.state = response["state"].as_str().unwrap_or_default().to_string();
+        let restored = serde_json::from_value::<SaveState>(response["save_state"].clone())
+            .map_err(|e| e.to_string())
+            .and_then(|state| self.chip.restore_state(&state).map_err(|e| e.to_string()));
+        if let Err(e) = restored {
+            self.message = format!("Can't show the state: {}", e);
+        }
+    }
+
+    fn registers_text(&self) -> String {
+        let chip = &self.chip;
+        let mut text = format!(
+            "{}\n{}\n\nPC  {:#06x}\nI   {:#06x}\n",
+            self.rom,
+            self.state,
+            chip.pc(),
+            chip.i()
+        );
+        for (register, value) in chip.v().iter().enumerate() {
+            text += &format!("V{:X}  {:#04x}\n", register, value);
+        }
+        text += &format!(
+            "DT  {}\nST  {}\nTick  {}\n\nStack\n",
+            chip.dt(),
+            chip.st(),
+            chip.tick_count()
+        );
+        for address in chip.stack().iter().rev() {
+            text += &format!("{:#06x}\n", address);
+        }
+        text += "\nF5 pause/resume, F10 step\n";
+        text += &self.message;
+        text
+    }
+}
+
+impl<'a> EventHandler for Monitor<'a> {
+    fn update(&mut self, ctx: &mut Context) {
+        if !self.receive() {
+            ctx.quit();
+            return;
+        }
+        if !self.syncing {
+            self.send(&json!({ "command": "save_state", "id": SYNC_ID }));
+            self.syncing = true;
+        }
+    }
+
+    fn key_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        keycode: KeyCode,
+        keymods: KeyMods,
+        _repeat: bool,
+    ) {
+        match keycode {
+            KeyCode::F5 => {
+                let command = if self.state == "paused" {
+                    "resume"
+                } else {
+                    "pause"
+                };
+                self.send(&json!({ "command": command }));
+            }
+            KeyCode::F10 => self.send(&json!({ "command": "step" })),
+            _ => {
+                if let Some(message) = self
+                    .memory_view
+                    .key_down_event(keycode, keymods, &self.chip)
+                {
+                    self.message = message;
+                }
+            }
+        }
+    }
+
+    fn char_event(
+        &mut self,
+        _ctx: &mut Context,
+        character: char,
+        _keymods: KeyMods,
+        _repeat: bool,
+    ) {
+        self.memory_view.char_event(character);
+    }
+
+    fn draw(&mut self, ctx: &mut Context) {
+        ctx.begin_default_pass(PassAction::clear_color(0., 0., 0., 1.));
+        let (window_width, window_height) = ctx.screen_size();
+        let projection = Mat4::orthographic_rh_gl(0., window_width, 0., window_height, 10.0, -10.0);
+
+        self.registers.update_text(&self.registers_text());
+        self.registers.model = Mat4::from_translation(Vec3 {
+            x: window_width - MARGIN,
+            y: window_height - MARGIN,
+            z: 0.,
+        }) * Mat4::from_scale(Vec3::splat(TEXT_SCALE));
+        self.text_batcher.add(&self.registers);
+        // There's nothing else to show, so Escape doesn't close it
+        self.memory_view.is_open = true;
+        let regions = regions::all(&self.chip, &[]);
+        let layout = Layout::default();
+        let mut dock = Dock::new(&layout, window_width, window_height);
+        self.memory_view
+            .draw(&mut self.text_batcher, &self.chip, &regions, &mut dock);
+        self.text_batcher.draw(ctx, projection, Mat4::IDENTITY);
+
+        ctx.end_render_pass();
+        ctx.commit_frame();
+    }
+}
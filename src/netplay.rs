@@ -0,0 +1,274 @@
+//! Two player netplay over TCP. The chips run in lockstep: each side sends the
+//! keys it holds for every frame, and only runs a frame once the other side's keys
+//! for it have arrived, with both players' keys held. Since the chip is
+//! deterministic given its state, RNG seed and keys, and frames are counted in
+//! emulated cycles rather than wall time, the two stay identical.
+//!
+//! When the guest connects, the host sends it the chip's save state and an RNG
+//! seed for both to start from. The state's ROM hash makes sure both sides are
+//! running the same ROM.
+
+use flake::{
+    chip8::Chip8,
+    save_state::{self, SaveStateError},
+};
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+
+const MAGIC: [u8; 8] = *b"FLAKENET";
+/// Frames between a key being pressed and it taking effect, which is how long the
+/// other side has to receive it before having to wait.
+const INPUT_DELAY: u64 = 4;
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// Frames run at once to catch up after waiting on the other side. Any more time
+/// than that is dropped instead.
+const MAX_CATCH_UP: u64 = 6;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NetplayRole {
+    /// Wait for the other player on this port.
+    Host(u16),
+    /// Connect to a host at this address.
+    Join(String),
+}
+
+#[derive(Debug)]
+pub enum NetplayError {
+    IO(io::Error),
+    Disconnected,
+    /// The other end isn't a flake host.
+    NotAHost,
+    /// The host's state couldn't be started from, e.g. because it's for another ROM.
+    State(SaveStateError),
+}
+
+impl fmt::Display for NetplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetplayError::IO(e) => write!(f, "connection failed: {}", e),
+            NetplayError::Disconnected => write!(f, "the other player disconnected"),
+            NetplayError::NotAHost => write!(f, "not a flake netplay host"),
+            NetplayError::State(e) => write!(f, "can't start from the host's state: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for NetplayError {
+    fn from(error: io::Error) -> Self {
+        NetplayError::IO(error)
+    }
+}
+
+impl From<SaveStateError> for NetplayError {
+    fn from(error: SaveStateError) -> Self {
+        NetplayError::State(error)
+    }
+}
+
+fn key_mask(keys: &[bool; 16]) -> u16 {
+    keys.iter()
+        .enumerate()
+        .fold(0, |mask, (key, &held)| mask | (held as u16) << key)
+}
+
+fn keys_from_mask(mask: u16) -> [bool; 16] {
+    core::array::from_fn(|key| mask & (1 << key) != 0)
+}
+
+pub struct Netplay {
+    /// Set on the host until the guest connects.
+    listener: Option<TcpListener>,
+    stream: Option<TcpStream>,
+    /// Whether both chips have started from the same state.
+    started: bool,
+    received: Vec<u8>,
+    /// Keys for each frame not run yet, as sent by this side and the other.
+    local_keys: VecDeque<u16>,
+    remote_keys: VecDeque<u16>,
+    sent_frames: u64,
+    frame: u64,
+    /// When `start_frame` was due, for running frames at 60Hz.
+    start: Instant,
+    start_frame: u64,
+}
+
+impl Netplay {
+    pub fn start(role: &NetplayRole) -> io::Result<Netplay> {
+        let (listener, stream) = match role {
+            NetplayRole::Host(port) => {
+                let listener = TcpListener::bind(("0.0.0.0", *port))?;
+                listener.set_nonblocking(true)?;
+                (Some(listener), None)
+            }
+            NetplayRole::Join(address) => {
+                let stream = TcpStream::connect(address)?;
+                stream.set_nodelay(true)?;
+                stream.set_nonblocking(true)?;
+                (None, Some(stream))
+            }
+        };
+        Ok(Netplay {
+            listener,
+            stream,
+            started: false,
+            received: vec![],
+            local_keys: VecDeque::new(),
+            remote_keys: VecDeque::new(),
+            sent_frames: 0,
+            frame: 0,
+            start: Instant::now(),
+            start_frame: 0,
+        })
+    }
+
+    /// Runs the frames that are due and that both sides' keys have arrived for.
+    /// Returns a message to show when the game starts.
+    pub fn update(&mut self, chip: &mut Chip8) -> Result<Option<String>, NetplayError> {
+        if let Some(listener) = &self.listener {
+            let (stream, address) = match listener.accept() {
+                Ok(connection) => connection,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+            self.listener = None;
+            self.start_host(stream, chip)?;
+            return Ok(Some(format!("{} joined", address)));
+        }
+
+        self.receive()?;
+        if !self.started {
+            return Ok(self
+                .start_guest(chip)?
+                .then(|| "Joined the game".to_string()));
+        }
+        self.run_frames(chip)?;
+        Ok(None)
+    }
+
+    /// Sends the guest the state to start from, and starts from it too, since the
+    /// chip has more to it than the save state has.
+    fn start_host(&mut self, mut stream: TcpStream, chip: &mut Chip8) -> Result<(), NetplayError> {
+        let state = chip.save_state();
+        let seed = rand::random();
+        chip.restore_state(&state)?;
+        chip.seed_rng(seed);
+
+        let bytes = save_state::encode(&state, chip.program());
+        let mut message = Vec::from(MAGIC);
+        message.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        message.extend(bytes);
+        message.extend_from_slice(&seed.to_le_bytes());
+        stream.set_nodelay(true)?;
+        stream.set_nonblocking(false)?;
+        stream.write_all(&message)?;
+        stream.set_nonblocking(true)?;
+
+        self.stream = Some(stream);
+        self.begin();
+        Ok(())
+    }
+
+    /// Starts from the host's state once it has all arrived. Returns whether it has.
+    fn start_guest(&mut self, chip: &mut Chip8) -> Result<bool, NetplayError> {
+        let header_len = MAGIC.len() + 4;
+        let Some(header) = self.received.get(..header_len) else {
+            return Ok(false);
+        };
+        if header[..MAGIC.len()] != MAGIC {
+            return Err(NetplayError::NotAHost);
+        }
+        let state_len = u32::from_le_bytes(header[MAGIC.len()..].try_into().unwrap()) as usize;
+        let Some(seed) = self
+            .received
+            .get(header_len + state_len..header_len + state_len + 8)
+        else {
+            return Ok(false);
+        };
+        let seed = u64::from_le_bytes(seed.try_into().unwrap());
+        let state = save_state::decode(
+            &self.received[header_len..header_len + state_len],
+            chip.program(),
+        )?;
+        chip.restore_state(&state)?;
+        chip.seed_rng(seed);
+        self.received.drain(..header_len + state_len + 8);
+        self.begin();
+        Ok(true)
+    }
+
+    fn begin(&mut self) {
+        self.started = true;
+        self.start = Instant::now();
+        self.start_frame = 0;
+    }
+
+    /// Reads whatever has arrived without waiting for more.
+    fn receive(&mut self) -> Result<(), NetplayError> {
+        let Some(stream) = &mut self.stream else {
+            return Ok(());
+        };
+        let mut buffer = [0; 4096];
+        loop {
+            match stream.read(&mut buffer) {
+                Ok(0) => return Err(NetplayError::Disconnected),
+                Ok(n) => self.received.extend_from_slice(&buffer[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn run_frames(&mut self, chip: &mut Chip8) -> Result<(), NetplayError> {
+        // Neither side can run on while this one is paused, e.g. by a breakpoint
+        if chip.is_paused() {
+            return Ok(());
+        }
+        let mut due = self.start_frame
+            + (self.start.elapsed().as_nanos() / FRAME_DURATION.as_nanos()) as u64
+            + 1;
+        if due > self.frame + MAX_CATCH_UP {
+            self.start = Instant::now();
+            self.start_frame = self.frame;
+            due = self.frame + 1;
+        }
+
+        // `chip.keys` only ever holds the local keys outside of running a frame
+        let keys = key_mask(&chip.keys);
+        let stream = self.stream.as_mut().expect("started without a connection");
+        while self.sent_frames < due + INPUT_DELAY {
+            stream.write_all(&keys.to_le_bytes())?;
+            self.local_keys.push_back(keys);
+            self.sent_frames += 1;
+        }
+
+        let whole = self.received.len() / 2 * 2;
+        self.remote_keys.extend(
+            self.received
+                .drain(..whole)
+                .collect::<Vec<_>>()
+                .chunks(2)
+                .map(|mask| u16::from_le_bytes([mask[0], mask[1]])),
+        );
+
+        while self.frame < due {
+            let (Some(local), Some(remote)) = (self.local_keys.front(), self.remote_keys.front())
+            else {
+                break;
+            };
+            let held = chip.keys;
+            chip.keys = keys_from_mask(local | remote);
+            chip.step_frame();
+            chip.keys = held;
+            self.local_keys.pop_front();
+            self.remote_keys.pop_front();
+            self.frame += 1;
+        }
+        Ok(())
+    }
+}
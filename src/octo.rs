@@ -0,0 +1,533 @@
+use crate::{
+    asm::{AsmError, Assembled},
+    chip8::OpCodes,
+    source_map::SourceMap,
+    symbols::Symbols,
+};
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+const ORIGIN: usize = 0x200;
+const MEMORY_SIZE: usize = 4096;
+
+enum Flow {
+    /// `if ... begin`, holding the jump over the block.
+    Begin(usize),
+    /// `else`, holding the jump over the else block.
+    Else(usize),
+    /// `loop`, holding its start and the jumps out of it from `while`.
+    Loop { start: usize, exits: Vec<usize> },
+}
+
+enum Condition {
+    Equal(usize, Operand),
+    NotEqual(usize, Operand),
+    Key(usize),
+    NotKey(usize),
+}
+
+enum Operand {
+    V(usize),
+    Byte(u8),
+}
+
+/// Whether `filename` is Octo source rather than a binary ROM.
+#[cfg(feature = "std")]
+pub fn is_source(filename: &str) -> bool {
+    std::path::Path::new(filename)
+        .extension()
+        .is_some_and(|ext| ext == "8o")
+}
+
+/// Compiles Octo source (https://github.com/JohnEarnest/Octo) into a ROM loaded
+/// at 0x200. Covers labels, `:const`, `:alias`, `:org`, `:byte`, `:call`, the
+/// instruction statements and `if`/`loop` control flow, but not macros, `:calc`,
+/// `:next` or `:unpack`.
+pub fn compile(source: &str) -> Result<Assembled, AsmError> {
+    let tokens: Vec<(usize, &str)> = source
+        .lines()
+        .enumerate()
+        .flat_map(|(index, line)| {
+            let code = line.split('#').next().unwrap_or("");
+            code.split_whitespace().map(move |token| (index + 1, token))
+        })
+        .collect();
+
+    let mut compiler = Compiler {
+        tokens,
+        pos: 0,
+        line: 1,
+        rom: vec![],
+        here: ORIGIN,
+        labels: BTreeMap::new(),
+        consts: BTreeMap::new(),
+        aliases: BTreeMap::new(),
+        fixups: vec![],
+        flow: vec![],
+        lines: SourceMap::default(),
+    };
+    compiler.compile().map_err(|message| AsmError {
+        line: compiler.line,
+        message,
+    })?;
+    compiler.resolve_fixups()?;
+
+    let mut symbols = Symbols::default();
+    for (name, address) in compiler.labels {
+        symbols.insert(address, name);
+    }
+    Ok(Assembled {
+        rom: compiler.rom,
+        symbols,
+        lines: compiler.lines,
+    })
+}
+
+struct Compiler<'a> {
+    tokens: Vec<(usize, &'a str)>,
+    pos: usize,
+    line: usize,
+    rom: Vec<u8>,
+    here: usize,
+    labels: BTreeMap<&'a str, usize>,
+    consts: BTreeMap<&'a str, i64>,
+    aliases: BTreeMap<&'a str, usize>,
+    /// Instructions whose 12-bit address refers to a label defined later.
+    fixups: Vec<(usize, &'a str, usize)>,
+    flow: Vec<Flow>,
+    lines: SourceMap,
+}
+
+impl<'a> Compiler<'a> {
+    fn compile(&mut self) -> Result<(), String> {
+        // Octo programs start at `main`, which doesn't have to come first
+        if !matches!(self.tokens.as_slice(), [(_, ":"), (_, "main"), ..]) {
+            self.emit_address(OpCodes::Jmp(0), "main")?;
+        }
+        while self.pos < self.tokens.len() {
+            self.statement()?;
+        }
+        if !self.flow.is_empty() {
+            return Err("unterminated if or loop".to_string());
+        }
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<&'a str, String> {
+        let (line, token) = *self
+            .tokens
+            .get(self.pos)
+            .ok_or_else(|| "unexpected end of file".to_string())?;
+        self.pos += 1;
+        self.line = line;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(format!("expected '{}', found '{}'", expected, token));
+        }
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(|&(_, token)| token)
+    }
+
+    fn statement(&mut self) -> Result<(), String> {
+        let token = self.next()?;
+        match token {
+            ":" => {
+                let name = self.name()?;
+                if self.labels.insert(name, self.here).is_some() {
+                    return Err(format!("duplicate label '{}'", name));
+                }
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.number()?;
+                self.consts.insert(name, value);
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+            }
+            ":org" => {
+                let address = self.address_value()?;
+                if address < ORIGIN {
+                    return Err(format!(":org {:#x} is below {:#x}", address, ORIGIN));
+                }
+                self.here = address;
+            }
+            ":byte" => {
+                let byte = self.byte()?;
+                self.emit_byte(byte)?;
+            }
+            ":call" => self.emit_target(OpCodes::Call(0))?,
+            "clear" => self.emit(OpCodes::Cls)?,
+            "return" | ";" => self.emit(OpCodes::Ret)?,
+            "scroll-down" => {
+                let n = self.small(0xF)?;
+                self.emit(OpCodes::ScdN(n))?;
+            }
+            "scroll-right" => self.emit(OpCodes::Scr)?,
+            "scroll-left" => self.emit(OpCodes::Scl)?,
+            "lores" => self.emit(OpCodes::Low)?,
+            "hires" => self.emit(OpCodes::High)?,
+            "plane" => {
+                let n = self.small(3)?;
+                self.emit(OpCodes::PlaneN(n))?;
+            }
+            "jump" => self.emit_target(OpCodes::Jmp(0))?,
+            "jump0" => self.emit_target(OpCodes::JmpV0Nnn(0))?,
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let n = self.small(0xF)?;
+                self.emit(OpCodes::DrawVxVyN(x, y, n))?;
+            }
+            "save" => self.register_op(OpCodes::LdIVx)?,
+            "load" => self.register_op(OpCodes::LdVxI)?,
+            "saveflags" => self.register_op(OpCodes::LdRVx)?,
+            "loadflags" => self.register_op(OpCodes::LdVxR)?,
+            "bcd" => self.register_op(OpCodes::LdBVx)?,
+            "delay" => {
+                self.expect(":=")?;
+                self.register_op(OpCodes::LdDtVx)?;
+            }
+            "buzzer" => {
+                self.expect(":=")?;
+                self.register_op(OpCodes::LdStVx)?;
+            }
+            "pitch" => {
+                self.expect(":=")?;
+                self.register_op(OpCodes::LdPitchVx)?;
+            }
+            "i" => self.index_statement()?,
+            "if" => {
+                let condition = self.condition()?;
+                match self.next()? {
+                    "then" => self.emit_skip(&condition, false)?,
+                    "begin" => {
+                        self.emit_skip(&condition, true)?;
+                        let jump = self.here;
+                        self.emit(OpCodes::Jmp(0))?;
+                        self.flow.push(Flow::Begin(jump));
+                    }
+                    other => return Err(format!("expected 'then' or 'begin', found '{}'", other)),
+                }
+            }
+            "else" => match self.flow.pop() {
+                Some(Flow::Begin(jump)) => {
+                    let skip_else = self.here;
+                    self.emit(OpCodes::Jmp(0))?;
+                    self.patch(jump, self.here);
+                    self.flow.push(Flow::Else(skip_else));
+                }
+                _ => return Err("'else' without 'if ... begin'".to_string()),
+            },
+            "end" => match self.flow.pop() {
+                Some(Flow::Begin(jump)) | Some(Flow::Else(jump)) => self.patch(jump, self.here),
+                _ => return Err("'end' without 'if ... begin'".to_string()),
+            },
+            "loop" => self.flow.push(Flow::Loop {
+                start: self.here,
+                exits: vec![],
+            }),
+            "while" => {
+                let condition = self.condition()?;
+                self.emit_skip(&condition, true)?;
+                let exit = self.here;
+                self.emit(OpCodes::Jmp(0))?;
+                match self
+                    .flow
+                    .iter_mut()
+                    .rev()
+                    .find(|f| matches!(f, Flow::Loop { .. }))
+                {
+                    Some(Flow::Loop { exits, .. }) => exits.push(exit),
+                    _ => return Err("'while' outside of a loop".to_string()),
+                }
+            }
+            "again" => match self.flow.pop() {
+                Some(Flow::Loop { start, exits }) => {
+                    self.emit(OpCodes::Jmp(start))?;
+                    for exit in exits {
+                        self.patch(exit, self.here);
+                    }
+                }
+                _ => return Err("'again' without 'loop'".to_string()),
+            },
+            _ if token.starts_with(':') => {
+                return Err(format!("unsupported directive '{}'", token))
+            }
+            _ => {
+                if let Some(x) = self.to_register(token) {
+                    self.register_statement(x)?;
+                } else if let Some(value) = self.to_number(token) {
+                    let byte = to_byte(token, value)?;
+                    self.emit_byte(byte)?;
+                } else {
+                    // A bare name calls a subroutine, possibly defined further down
+                    self.emit_address(OpCodes::Call(0), token)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn register_statement(&mut self, x: usize) -> Result<(), String> {
+        let op = self.next()?;
+        let rhs = self.next()?;
+        let y = self.to_register(rhs);
+        let instruction = match (op, y) {
+            (":=", Some(y)) => OpCodes::LdVxVy(x, y),
+            (":=", None) => match rhs {
+                "random" => OpCodes::RndVxNn(x, self.byte()?),
+                "delay" => OpCodes::LdVxDt(x),
+                "key" => OpCodes::LdVxK(x),
+                _ => OpCodes::LdVxNn(x, self.rhs_byte(rhs)?),
+            },
+            ("+=", Some(y)) => OpCodes::AddVxVy(x, y),
+            ("+=", None) => OpCodes::AddVxNn(x, self.rhs_byte(rhs)?),
+            ("-=", Some(y)) => OpCodes::SubVxVy(x, y),
+            ("-=", None) => OpCodes::AddVxNn(x, self.rhs_byte(rhs)?.wrapping_neg()),
+            ("=-", Some(y)) => OpCodes::SubnVxVy(x, y),
+            ("|=", Some(y)) => OpCodes::OrVxVy(x, y),
+            ("&=", Some(y)) => OpCodes::AndVxVy(x, y),
+            ("^=", Some(y)) => OpCodes::XorVxVy(x, y),
+            (">>=", Some(y)) => OpCodes::ShrVxVy(x, y),
+            ("<<=", Some(y)) => OpCodes::ShlVxVy(x, y),
+            _ => return Err(format!("invalid statement 'v{:x} {} {}'", x, op, rhs)),
+        };
+        self.emit(instruction)
+    }
+
+    fn index_statement(&mut self) -> Result<(), String> {
+        match self.next()? {
+            ":=" => match self.peek() {
+                Some("hex") => {
+                    self.pos += 1;
+                    self.register_op(OpCodes::LdFVx)
+                }
+                Some("bighex") => {
+                    self.pos += 1;
+                    self.register_op(OpCodes::LdHfVx)
+                }
+                _ => {
+                    let target = self.next()?;
+                    self.emit_address(OpCodes::LdINn(0), target)
+                }
+            },
+            "+=" => self.register_op(OpCodes::AddIVx),
+            other => Err(format!("invalid statement 'i {}'", other)),
+        }
+    }
+
+    fn condition(&mut self) -> Result<Condition, String> {
+        let x = self.register()?;
+        let op = self.next()?;
+        Ok(match op {
+            "key" => Condition::Key(x),
+            "-key" => Condition::NotKey(x),
+            "==" | "!=" => {
+                let rhs = self.next()?;
+                let operand = match self.to_register(rhs) {
+                    Some(y) => Operand::V(y),
+                    None => Operand::Byte(self.rhs_byte(rhs)?),
+                };
+                if op == "==" {
+                    Condition::Equal(x, operand)
+                } else {
+                    Condition::NotEqual(x, operand)
+                }
+            }
+            _ => return Err(format!("unsupported condition '{}'", op)),
+        })
+    }
+
+    /// Emits the instruction that skips the next one when `condition` is `when`.
+    /// `then` skips its statement when the condition is false, while `begin` and
+    /// `while` skip the jump that follows them when it is true.
+    fn emit_skip(&mut self, condition: &Condition, when: bool) -> Result<(), String> {
+        // Whether to skip on equal (or pressed) rather than not equal (or not pressed)
+        let on_equal = match condition {
+            Condition::Equal(..) | Condition::Key(_) => when,
+            Condition::NotEqual(..) | Condition::NotKey(_) => !when,
+        };
+        let op = match condition {
+            Condition::Equal(x, operand) | Condition::NotEqual(x, operand) => {
+                match (operand, on_equal) {
+                    (Operand::V(y), true) => OpCodes::SeVxVy(*x, *y),
+                    (Operand::V(y), false) => OpCodes::SneVxVy(*x, *y),
+                    (Operand::Byte(nn), true) => OpCodes::SeVxNn(*x, *nn),
+                    (Operand::Byte(nn), false) => OpCodes::SneVxNn(*x, *nn),
+                }
+            }
+            Condition::Key(x) | Condition::NotKey(x) if on_equal => OpCodes::SkpVx(*x),
+            Condition::Key(x) | Condition::NotKey(x) => OpCodes::SknpVx(*x),
+        };
+        self.emit(op)
+    }
+
+    fn register_op(&mut self, op: fn(usize) -> OpCodes) -> Result<(), String> {
+        let x = self.register()?;
+        self.emit(op(x))
+    }
+
+    fn name(&mut self) -> Result<&'a str, String> {
+        let name = self.next()?;
+        if self.to_register(name).is_some() || self.to_number(name).is_some() {
+            return Err(format!("'{}' can't be used as a name", name));
+        }
+        Ok(name)
+    }
+
+    fn register(&mut self) -> Result<usize, String> {
+        let token = self.next()?;
+        self.to_register(token)
+            .ok_or_else(|| format!("expected a register, found '{}'", token))
+    }
+
+    fn to_register(&self, token: &str) -> Option<usize> {
+        if let Some(&x) = self.aliases.get(token) {
+            return Some(x);
+        }
+        let digit = token.strip_prefix(['v', 'V'])?;
+        if digit.len() != 1 {
+            return None;
+        }
+        usize::from_str_radix(digit, 16).ok()
+    }
+
+    fn to_number(&self, token: &str) -> Option<i64> {
+        if let Some(&value) = self.consts.get(token) {
+            return Some(value);
+        }
+        let (negative, digits) = match token.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, token),
+        };
+        let value = if let Some(hex) = digits.strip_prefix("0x") {
+            i64::from_str_radix(hex, 16).ok()?
+        } else if let Some(binary) = digits.strip_prefix("0b") {
+            i64::from_str_radix(binary, 2).ok()?
+        } else {
+            digits.parse().ok()?
+        };
+        Some(if negative { -value } else { value })
+    }
+
+    fn number(&mut self) -> Result<i64, String> {
+        let token = self.next()?;
+        self.to_number(token)
+            .or_else(|| self.labels.get(token).map(|&a| a as i64))
+            .ok_or_else(|| format!("expected a number, found '{}'", token))
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let token = self.next()?;
+        self.rhs_byte(token)
+    }
+
+    fn rhs_byte(&self, token: &str) -> Result<u8, String> {
+        let value = self
+            .to_number(token)
+            .ok_or_else(|| format!("expected a number, found '{}'", token))?;
+        to_byte(token, value)
+    }
+
+    fn small(&mut self, max: usize) -> Result<usize, String> {
+        let token = self.next()?;
+        match self.to_number(token) {
+            Some(value) if (0..=max as i64).contains(&value) => Ok(value as usize),
+            _ => Err(format!(
+                "expected a number up to {}, found '{}'",
+                max, token
+            )),
+        }
+    }
+
+    fn address_value(&mut self) -> Result<usize, String> {
+        let value = self.number()?;
+        if !(0..MEMORY_SIZE as i64).contains(&value) {
+            return Err(format!("address {:#x} is out of range", value));
+        }
+        Ok(value as usize)
+    }
+
+    fn emit_target(&mut self, op: OpCodes) -> Result<(), String> {
+        let target = self.next()?;
+        self.emit_address(op, target)
+    }
+
+    /// Emits `op` with its address set to `target`, patching it once the label is
+    /// known if it is defined further down.
+    fn emit_address(&mut self, op: OpCodes, target: &'a str) -> Result<(), String> {
+        let at = self.here;
+        self.emit(op)?;
+        match self.to_number(target) {
+            Some(value) if (0..MEMORY_SIZE as i64).contains(&value) => {
+                self.patch(at, value as usize)
+            }
+            Some(value) => return Err(format!("address {:#x} is out of range", value)),
+            None => match self.labels.get(target) {
+                Some(&address) => self.patch(at, address),
+                None => self.fixups.push((at, target, self.line)),
+            },
+        }
+        Ok(())
+    }
+
+    fn emit(&mut self, op: OpCodes) -> Result<(), String> {
+        self.lines.insert(self.here, self.line);
+        let [high, low] = u16::from(op).to_be_bytes();
+        self.emit_byte(high)?;
+        self.emit_byte(low)
+    }
+
+    fn emit_byte(&mut self, byte: u8) -> Result<(), String> {
+        if self.here >= MEMORY_SIZE {
+            return Err("program doesn't fit in memory".to_string());
+        }
+        let index = self.here - ORIGIN;
+        if self.rom.len() <= index {
+            self.rom.resize(index + 1, 0);
+        }
+        self.rom[index] = byte;
+        self.here += 1;
+        Ok(())
+    }
+
+    /// Sets the 12-bit address of the instruction at `at`.
+    fn patch(&mut self, at: usize, address: usize) {
+        let index = at - ORIGIN;
+        self.rom[index] = (self.rom[index] & 0xF0) | (address >> 8) as u8;
+        self.rom[index + 1] = address as u8;
+    }
+
+    fn resolve_fixups(&mut self) -> Result<(), AsmError> {
+        for (at, label, line) in core::mem::take(&mut self.fixups) {
+            let address = *self.labels.get(label).ok_or_else(|| AsmError {
+                line,
+                message: format!("undefined label '{}'", label),
+            })?;
+            self.patch(at, address);
+        }
+        Ok(())
+    }
+}
+
+fn to_byte(token: &str, value: i64) -> Result<u8, String> {
+    if !(-128..=255).contains(&value) {
+        return Err(format!("{} doesn't fit in a byte", token));
+    }
+    Ok(value as u8)
+}
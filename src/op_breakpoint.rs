@@ -0,0 +1,98 @@
+use crate::{
+    chip8::{Chip8, OpCodes},
+    disasm,
+    symbols::Symbols,
+};
+use std::{fmt, mem::Discriminant};
+
+/// A breakpoint on what the next instruction is rather than where it is, given as
+/// a mnemonic (`DRW`), a register written (`write:V7`), or an opcode with `_` for
+/// any digit (`F_55`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OpBreakpoint {
+    /// Any form of the instruction, e.g. every LD for `LD`.
+    Mnemonic {
+        name: String,
+        forms: Vec<Discriminant<OpCodes>>,
+    },
+    Writes(usize),
+    Pattern {
+        mask: u16,
+        value: u16,
+    },
+}
+
+impl OpBreakpoint {
+    pub fn parse(s: &str) -> Result<OpBreakpoint, String> {
+        if let Some(register) = s.strip_prefix("write:") {
+            return register
+                .strip_prefix(['V', 'v'])
+                .and_then(|x| usize::from_str_radix(x, 16).ok())
+                .filter(|&x| x < 16)
+                .map(OpBreakpoint::Writes)
+                .ok_or_else(|| format!("invalid register: {}", register));
+        }
+        if s.len() == 4 && s.chars().all(|c| c == '_' || c.is_ascii_hexdigit()) {
+            let (mask, value) = s.chars().fold((0, 0), |(mask, value), c| {
+                let digit = c.to_digit(16);
+                (
+                    mask << 4 | if digit.is_some() { 0xF } else { 0 },
+                    value << 4 | digit.unwrap_or(0) as u16,
+                )
+            });
+            return Ok(OpBreakpoint::Pattern { mask, value });
+        }
+
+        let name = s.to_ascii_uppercase();
+        let symbols = Symbols::default();
+        let mut forms = vec![];
+        for opcode in 0..=u16::MAX {
+            let op = OpCodes::try_from(opcode).unwrap();
+            let form = std::mem::discriminant(&op);
+            if !matches!(op, OpCodes::Unkn(_))
+                && !forms.contains(&form)
+                && disasm::mnemonic(op, &symbols).split(' ').next() == Some(&name)
+            {
+                forms.push(form);
+            }
+        }
+        if forms.is_empty() {
+            return Err(format!("unknown instruction: {}", s));
+        }
+        Ok(OpBreakpoint::Mnemonic { name, forms })
+    }
+
+    /// Whether the instruction about to run on `chip` matches.
+    pub fn matches(&self, chip: &Chip8) -> bool {
+        let opcode = chip.next_opcode();
+        match self {
+            OpBreakpoint::Mnemonic { forms, .. } => {
+                let op = OpCodes::try_from(opcode).unwrap();
+                forms.contains(&std::mem::discriminant(&op))
+            }
+            OpBreakpoint::Writes(register) => OpCodes::try_from(opcode)
+                .unwrap()
+                .writes_register(*register),
+            OpBreakpoint::Pattern { mask, value } => opcode & mask == *value,
+        }
+    }
+}
+
+impl fmt::Display for OpBreakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpBreakpoint::Mnemonic { name, .. } => write!(f, "{}", name),
+            OpBreakpoint::Writes(register) => write!(f, "write to V{:X}", register),
+            OpBreakpoint::Pattern { mask, value } => {
+                for shift in [12, 8, 4, 0] {
+                    if mask >> shift & 0xF == 0 {
+                        write!(f, "_")?;
+                    } else {
+                        write!(f, "{:X}", value >> shift & 0xF)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use glam::Vec4;
+
+/// Colors used for the 4 possible pixel values of a 2 bit-plane display: background,
+/// plane 1 only, plane 2 only, and both planes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette(pub [Vec4; 4]);
+
+impl Default for Palette {
+    fn default() -> Self {
+        PRESETS[0].1
+    }
+}
+
+/// Built-in palettes, switched between at runtime. They all keep their four colors
+/// distinguishable with protanopia, deuteranopia and tritanopia, as checked by
+/// simulating them with `simulate`, and the ones after the default are made of
+/// colors chosen for it.
+pub const PRESETS: [(&str, Palette); 4] = [
+    (
+        "default",
+        Palette([
+            Vec4::new(0.0, 0.0, 0.5, 1.0),
+            Vec4::new(1.0, 1.0, 0.5, 1.0),
+            Vec4::new(1.0, 0.4, 0.2, 1.0),
+            Vec4::new(0.3, 0.8, 1.0, 1.0),
+        ]),
+    ),
+    // From Okabe and Ito's Color Universal Design: black, orange, sky blue, yellow
+    (
+        "okabe-ito",
+        Palette([
+            Vec4::new(0.0, 0.0, 0.0, 1.0),
+            Vec4::new(0.902, 0.624, 0.0, 1.0),
+            Vec4::new(0.337, 0.706, 0.914, 1.0),
+            Vec4::new(0.941, 0.894, 0.259, 1.0),
+        ]),
+    ),
+    // From IBM's Design Library: black, gold, ultramarine, magenta
+    (
+        "ibm",
+        Palette([
+            Vec4::new(0.0, 0.0, 0.0, 1.0),
+            Vec4::new(1.0, 0.690, 0.0, 1.0),
+            Vec4::new(0.392, 0.561, 1.0, 1.0),
+            Vec4::new(0.863, 0.149, 0.498, 1.0),
+        ]),
+    ),
+    // Told apart by brightness alone, for any kind of color blindness
+    (
+        "grayscale",
+        Palette([
+            Vec4::new(0.0, 0.0, 0.0, 1.0),
+            Vec4::new(1.0, 1.0, 1.0, 1.0),
+            Vec4::new(0.376, 0.376, 0.376, 1.0),
+            Vec4::new(0.690, 0.690, 0.690, 1.0),
+        ]),
+    ),
+];
+
+/// Kinds of color vision deficiency `simulate` can show colors as seen with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Deficiency {
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+impl Deficiency {
+    pub const ALL: [Deficiency; 3] = [
+        Deficiency::Protanopia,
+        Deficiency::Deuteranopia,
+        Deficiency::Tritanopia,
+    ];
+
+    /// Machado, Oliveira and Fernandes' (2009) matrices for full severity, on linear
+    /// RGB.
+    fn matrix(self) -> [[f32; 3]; 3] {
+        match self {
+            Deficiency::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            Deficiency::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Deficiency::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        }
+    }
+}
+
+/// How `color`, in sRGB, looks with `deficiency`.
+pub fn simulate(color: Vec4, deficiency: Deficiency) -> Vec4 {
+    let to_linear = |c: f32| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let to_srgb = |c: f32| {
+        let c = c.clamp(0., 1.);
+        if c <= 0.0031308 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1. / 2.4) - 0.055
+        }
+    };
+    let linear = [color.x, color.y, color.z].map(to_linear);
+    let [r, g, b] = deficiency
+        .matrix()
+        .map(|row| to_srgb(row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2]));
+    Vec4::new(r, g, b, color.w)
+}
+
+impl Palette {
+    /// The name of the preset this is, if it's one of them.
+    pub fn name(self) -> Option<&'static str> {
+        PRESETS
+            .iter()
+            .find(|(_, preset)| *preset == self)
+            .map(|&(name, _)| name)
+    }
+
+    /// The preset after this one, or the first if it isn't one.
+    pub fn next_preset(self) -> (&'static str, Palette) {
+        let index = PRESETS.iter().position(|(_, preset)| *preset == self);
+        PRESETS[index.map_or(0, |i| (i + 1) % PRESETS.len())]
+    }
+
+    /// Parses the name of one of the `PRESETS`, or 4 comma separated `RRGGBB` hex
+    /// colors, e.g. `000000,ffffff,ff0000,0000ff`.
+    pub fn parse(s: &str) -> Result<Palette, String> {
+        if let Some(&(_, preset)) = PRESETS.iter().find(|(name, _)| *name == s) {
+            return Ok(preset);
+        }
+        let colors = s
+            .split(',')
+            .map(|hex| {
+                let hex = hex.trim().trim_start_matches('#');
+                match u32::from_str_radix(hex, 16) {
+                    Ok(rgb) if hex.len() == 6 => Ok(Vec4::new(
+                        ((rgb >> 16) & 0xFF) as f32 / 255.0,
+                        ((rgb >> 8) & 0xFF) as f32 / 255.0,
+                        (rgb & 0xFF) as f32 / 255.0,
+                        1.0,
+                    )),
+                    _ => Err(format!("invalid color: {}", hex)),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        colors
+            .try_into()
+            .map(Palette)
+            .map_err(|_| format!("palette needs exactly 4 colors: {}", s))
+    }
+
+    /// The inverse of `parse`.
+    pub fn to_hex(self) -> String {
+        self.0
+            .iter()
+            .map(|color| {
+                let [r, g, b] =
+                    [color.x, color.y, color.z].map(|c| (c.clamp(0., 1.) * 255.).round() as u8);
+                format!("{:02x}{:02x}{:02x}", r, g, b)
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
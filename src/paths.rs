@@ -0,0 +1,13 @@
+use std::{fs, path::PathBuf};
+
+/// Per-user directory for flake's own files (recent ROMs, preferences, ...), created on
+/// first use. Falls back to the working directory if the platform has no data directory.
+pub fn data_dir() -> PathBuf {
+    let dir = dirs::data_dir()
+        .map(|dir| dir.join("flake"))
+        .unwrap_or_else(|| PathBuf::from("."));
+    if let Err(e) = fs::create_dir_all(&dir) {
+        eprintln!("Failed to create {}: {}", dir.display(), e);
+    }
+    dir
+}
@@ -0,0 +1,94 @@
+//! What the core needs from whatever it runs on: the time, and somewhere to keep
+//! the flag registers. The `std` feature provides both; without it, embedders
+//! implement these traits over their own timers and storage.
+
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+
+/// A monotonic source of the current time, as the time since some fixed start.
+/// The chip schedules its instructions and timers against it.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Duration;
+}
+
+/// A clock that only moves when it's told to, for running headless, in tests and
+/// in replays, where the same input has to give the same result however fast
+/// the host is. Advance it and call `Chip8::step_until` with its new time to run
+/// exactly that much emulated time.
+#[cfg(target_has_atomic = "64")]
+#[derive(Debug, Default)]
+pub struct VirtualClock {
+    nanos: AtomicU64,
+}
+
+#[cfg(target_has_atomic = "64")]
+impl VirtualClock {
+    pub fn advance(&self, by: Duration) {
+        self.nanos
+            .fetch_add(by.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+#[cfg(target_has_atomic = "64")]
+impl Clock for VirtualClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+}
+
+/// Where the HP48 flag registers are persisted between runs, e.g. a file or
+/// EEPROM.
+pub trait FlagStorage: Send + Sync {
+    /// Fills `flags` with what was saved, leaving it alone if nothing was.
+    fn load(&self, flags: &mut [u8; 16]);
+    fn save(&self, flags: &[u8; 16]);
+}
+
+/// The system's monotonic clock, counting from when it was created.
+#[cfg(feature = "std")]
+pub struct SystemClock {
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock {
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Flags kept in a file, e.g. `roms/game.ch8.flags`.
+#[cfg(feature = "std")]
+pub struct FlagFile {
+    pub path: std::path::PathBuf,
+}
+
+#[cfg(feature = "std")]
+impl FlagStorage for FlagFile {
+    fn load(&self, flags: &mut [u8; 16]) {
+        match std::fs::read(&self.path) {
+            Ok(data) => {
+                let len = data.len().min(flags.len());
+                flags[..len].copy_from_slice(&data[..len]);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => eprintln!("Failed to read flags from {}: {}", self.path.display(), e),
+        }
+    }
+
+    fn save(&self, flags: &[u8; 16]) {
+        if let Err(e) = std::fs::write(&self.path, flags) {
+            eprintln!("Failed to save flags to {}: {}", self.path.display(), e);
+        }
+    }
+}
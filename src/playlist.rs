@@ -0,0 +1,51 @@
+use crate::browser;
+use std::path::Path;
+
+/// The ROMs given on the command line, with directories expanded to the ROMs in
+/// them, for stepping through a demo reel or a directory of test ROMs.
+pub struct Playlist {
+    roms: Vec<String>,
+    current: usize,
+}
+
+impl Playlist {
+    pub fn new(paths: &[String]) -> Playlist {
+        let mut roms = vec![];
+        for path in paths {
+            if Path::new(path).is_dir() {
+                match browser::list_roms(path) {
+                    Ok(files) => roms.extend(files),
+                    Err(e) => eprintln!("Failed to list {}: {}", path, e),
+                }
+            } else {
+                roms.push(path.clone());
+            }
+        }
+        Playlist { roms, current: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.roms.len()
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.roms.get(self.current).map(String::as_str)
+    }
+
+    /// Where `rom` is in the playlist, making it the current entry if it's there.
+    pub fn select(&mut self, rom: &str) -> Option<usize> {
+        let index = self.roms.iter().position(|entry| entry == rom)?;
+        self.current = index;
+        Some(index)
+    }
+
+    /// Moves `by` entries along, wrapping around at either end, and returns the
+    /// new current ROM.
+    pub fn advance(&mut self, by: isize) -> Option<&str> {
+        if self.roms.is_empty() {
+            return None;
+        }
+        self.current = (self.current as isize + by).rem_euclid(self.roms.len() as isize) as usize;
+        self.current()
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{
+    adjustments::Adjustments,
+    buzzer::{Tone, Volume},
+    layout::Layout,
+    paths,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+/// Settings remembered between runs, in `preferences.json` in the data directory.
+/// Command line options take precedence over them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Preferences {
+    /// Size of the window when it isn't fullscreen. miniquad can't report or set
+    /// the window position, so that isn't kept.
+    pub window_width: i32,
+    pub window_height: i32,
+    pub fullscreen: bool,
+    /// In the same format as `--palette`, a preset's name or hex colors.
+    pub palette: Option<String>,
+    /// Brightness, contrast and gamma of the display.
+    pub adjustments: Adjustments,
+    /// How the buzzer sounds in recordings.
+    pub tone: Tone,
+    /// How loud the buzzer is, and whether it's muted.
+    pub volume: Volume,
+    /// The speed last picked, which ROMs without one in the database run at. It
+    /// never overrides the database or quirk detection the way `--ipf` does.
+    pub instructions_per_frame: Option<u32>,
+    /// Where the debugger panels are docked and how big they are.
+    pub layout: Layout,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Preferences {
+            window_width: 1200,
+            window_height: 600,
+            fullscreen: false,
+            palette: None,
+            adjustments: Adjustments::default(),
+            tone: Tone::default(),
+            volume: Volume::default(),
+            instructions_per_frame: None,
+            layout: Layout::default(),
+        }
+    }
+}
+
+fn preferences_path() -> PathBuf {
+    paths::data_dir().join("preferences.json")
+}
+
+impl Preferences {
+    /// Falls back to the defaults when nothing has been saved yet or the file is
+    /// unreadable.
+    pub fn load() -> Preferences {
+        let path = preferences_path();
+        match fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                eprintln!("Ignoring {}: {}", path.display(), e);
+                Preferences::default()
+            }),
+            Err(_) => Preferences::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        let path = preferences_path();
+        let result = serde_json::to_string_pretty(self)
+            .map_err(|e| e.to_string())
+            .and_then(|data| fs::write(&path, data).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("Failed to save {}: {}", path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::chip8::Modes;
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+use serde::{Deserialize, Serialize};
+
+/// Behaviours that differ between CHIP-8 interpreters. Each platform in `Modes`
+/// has a default profile, but individual quirks can be toggled for ROMs that
+/// expect a mix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quirks {
+    /// 8XY6/8XYE load VY into VX before shifting (COSMAC VIP) instead of shifting VX in place.
+    pub shift_uses_vy: bool,
+    /// FX0A completes once a key is pressed and then released (COSMAC VIP) instead of
+    /// as soon as any key is held.
+    pub key_wait_release: bool,
+    /// DXYN wraps the pixels of sprites that cross a screen edge around to the opposite
+    /// edge instead of clipping them. The start coordinate always wraps.
+    pub wrap_sprites: bool,
+    /// BNNN is read as BXNN and jumps to XNN + VX (CHIP-48/SUPER-CHIP) instead of NNN + V0.
+    pub jump_uses_vx: bool,
+    /// Most return addresses the stack holds before CALL halts: 12 on the COSMAC VIP,
+    /// 16 on the HP48 interpreters.
+    pub stack_depth: usize,
+    /// Accesses from I past the end of memory wrap around to the start instead of
+    /// halting.
+    pub wrap_memory: bool,
+    /// FX29/FX30 halt when VX has no character in the font instead of using its low
+    /// nibble.
+    pub strict_font: bool,
+}
+
+impl Quirks {
+    pub fn for_mode(mode: Modes) -> Quirks {
+        match mode {
+            Modes::Chip8 => Quirks {
+                shift_uses_vy: true,
+                key_wait_release: true,
+                wrap_sprites: false,
+                jump_uses_vx: false,
+                stack_depth: 12,
+                wrap_memory: false,
+                strict_font: false,
+            },
+            Modes::Chip48 | Modes::SuperChip => Quirks {
+                shift_uses_vy: false,
+                key_wait_release: false,
+                wrap_sprites: false,
+                jump_uses_vx: true,
+                stack_depth: 16,
+                wrap_memory: false,
+                strict_font: false,
+            },
+        }
+    }
+
+    pub fn apply(&mut self, overrides: &QuirkOverrides) {
+        let QuirkOverrides {
+            shift_uses_vy,
+            key_wait_release,
+            wrap_sprites,
+            jump_uses_vx,
+            stack_depth,
+            wrap_memory,
+            strict_font,
+        } = *overrides;
+        self.shift_uses_vy = shift_uses_vy.unwrap_or(self.shift_uses_vy);
+        self.key_wait_release = key_wait_release.unwrap_or(self.key_wait_release);
+        self.wrap_sprites = wrap_sprites.unwrap_or(self.wrap_sprites);
+        self.jump_uses_vx = jump_uses_vx.unwrap_or(self.jump_uses_vx);
+        self.stack_depth = stack_depth.unwrap_or(self.stack_depth);
+        self.wrap_memory = wrap_memory.unwrap_or(self.wrap_memory);
+        self.strict_font = strict_font.unwrap_or(self.strict_font);
+    }
+}
+
+/// Individual quirks to change from a platform's profile, as found in the ROM database.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuirkOverrides {
+    pub shift_uses_vy: Option<bool>,
+    pub key_wait_release: Option<bool>,
+    pub wrap_sprites: Option<bool>,
+    pub jump_uses_vx: Option<bool>,
+    pub stack_depth: Option<usize>,
+    pub wrap_memory: Option<bool>,
+    pub strict_font: Option<bool>,
+}
+
+impl QuirkOverrides {
+    /// Parses comma separated `name=value` pairs, named as in the ROM database, e.g.
+    /// `shift_uses_vy=false,stack_depth=16`.
+    pub fn parse(s: &str) -> Result<QuirkOverrides, String> {
+        let mut fields = serde_json::Map::new();
+        for pair in s.split(',').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("invalid quirk {}, expected name=value", pair))?;
+            let value = serde_json::from_str(value)
+                .map_err(|_| format!("invalid value for {}: {}", name, value))?;
+            fields.insert(name.to_string(), value);
+        }
+        serde_json::from_value(fields.into()).map_err(|e| format!("invalid quirks: {}", e))
+    }
+}
@@ -0,0 +1,317 @@
+use crate::{
+    buzzer::{Buzzer, Tone, Volume},
+    chip8::Chip8,
+    display::HIRES,
+    palette::Palette,
+    paths, screenshot,
+};
+use image::{imageops, RgbaImage};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Sender},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+const FRAME_RATE: u32 = 60;
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / FRAME_RATE as u64);
+const SAMPLE_RATE: u32 = 44100;
+const SAMPLES_PER_FRAME: u32 = SAMPLE_RATE / FRAME_RATE;
+/// Of full scale, so the buzzer isn't unpleasantly loud.
+const VOLUME: f32 = 0.25;
+/// How many image pixels each display pixel becomes in image sequences. Videos
+/// are scaled by ffmpeg instead, which is faster.
+const IMAGE_SCALE: u32 = 4;
+const VIDEO_SCALE: u32 = 8;
+/// Extensions recorded as a video through ffmpeg rather than as images.
+const VIDEO_EXTENSIONS: [&str; 6] = ["mp4", "mkv", "webm", "mov", "avi", "gif"];
+
+enum Sink {
+    /// A directory of numbered PNGs, with the audio next to them.
+    Images(PathBuf),
+    /// Raw frames piped into ffmpeg, which writes `video`. The audio is added once
+    /// it's complete.
+    Ffmpeg {
+        process: Child,
+        video: PathBuf,
+        output: PathBuf,
+    },
+}
+
+/// Records what's shown as a video of the display at 60 frames a second, with an
+/// audio track of the beeper, for sharing runs. Frames are taken in real time
+/// while the chip runs, so pauses are cut out but fast forwarding isn't.
+pub struct Recording {
+    frames: Option<Sender<RgbaImage>>,
+    writer: Option<JoinHandle<io::Result<()>>>,
+    sink: Sink,
+    audio: Vec<i16>,
+    buzzer: Buzzer,
+    /// Time since the last frame, while running.
+    elapsed: Duration,
+    last_update: Option<Instant>,
+}
+
+impl Recording {
+    /// Starts recording to `path`: a video if it has a video extension, which
+    /// needs ffmpeg, or otherwise a directory of PNGs and a WAV, with the beeper
+    /// sounding like `tone`.
+    pub fn start(path: &Path, tone: Tone) -> io::Result<Recording> {
+        let (sink, stdin) = if is_video(path) {
+            if let Some(directory) = path.parent() {
+                fs::create_dir_all(directory)?;
+            }
+            let video = if has_audio(path) {
+                path.with_extension(format!(
+                    "video.{}",
+                    path.extension().unwrap_or_default().to_string_lossy()
+                ))
+            } else {
+                path.to_path_buf()
+            };
+            let (width, height) = HIRES;
+            let mut process = ffmpeg()
+                .args(["-f", "rawvideo", "-pixel_format", "rgba"])
+                .args(["-video_size", &format!("{}x{}", width, height)])
+                .args(["-framerate", &FRAME_RATE.to_string(), "-i", "-"])
+                .args([
+                    "-vf",
+                    &format!("scale=iw*{0}:ih*{0}:flags=neighbor", VIDEO_SCALE),
+                ])
+                .args(pixel_format(path))
+                .arg(&video)
+                .stdin(Stdio::piped())
+                .spawn()
+                .map_err(|e| io::Error::new(e.kind(), format!("failed to run ffmpeg: {}", e)))?;
+            let stdin = process.stdin.take();
+            let sink = Sink::Ffmpeg {
+                process,
+                video,
+                output: path.to_path_buf(),
+            };
+            (sink, stdin)
+        } else {
+            fs::create_dir_all(path)?;
+            (Sink::Images(path.to_path_buf()), None)
+        };
+
+        let (frames, receiver) = mpsc::channel();
+        let directory = match &sink {
+            Sink::Images(directory) => Some(directory.clone()),
+            Sink::Ffmpeg { .. } => None,
+        };
+        // Encoding can take longer than a frame, so it's kept off the main thread
+        let writer = thread::Builder::new()
+            .name("recording".to_string())
+            .spawn(move || write_frames(receiver, directory, stdin))?;
+        Ok(Recording {
+            frames: Some(frames),
+            writer: Some(writer),
+            sink,
+            audio: vec![],
+            buzzer: Buzzer::new(tone),
+            elapsed: Duration::ZERO,
+            last_update: None,
+        })
+    }
+
+    /// Adds the frames due since the last update, all showing `chip` as it is now,
+    /// with the buzzer at `volume`.
+    pub fn update(&mut self, chip: &Chip8, palette: &Palette, volume: Volume) {
+        self.buzzer.volume = volume;
+        if chip.is_paused() {
+            self.last_update = None;
+            return;
+        }
+        let now = Instant::now();
+        // Start with a frame, so there's something to show for short recordings
+        self.elapsed += self
+            .last_update
+            .map_or(FRAME_DURATION, |last_update| now - last_update);
+        self.last_update = Some(now);
+
+        let mut image = None;
+        while self.elapsed >= FRAME_DURATION {
+            self.elapsed -= FRAME_DURATION;
+            let image = image.get_or_insert_with(|| frame(chip, palette));
+            if let Some(frames) = &self.frames {
+                let _ = frames.send(image.clone());
+            }
+            self.add_audio(chip);
+        }
+    }
+
+    /// A frame's worth of the buzzer.
+    fn add_audio(&mut self, chip: &Chip8) {
+        for _ in 0..SAMPLES_PER_FRAME {
+            let sample = self.buzzer.next_sample(chip, SAMPLE_RATE as f32) * VOLUME;
+            self.audio.push((sample * i16::MAX as f32) as i16);
+        }
+    }
+
+    /// Finishes writing the frames and adds the audio, returning where the
+    /// recording is.
+    pub fn finish(mut self) -> io::Result<PathBuf> {
+        // Closing the channel ends the writer, which closes ffmpeg's input
+        self.frames = None;
+        if let Some(writer) = self.writer.take() {
+            writer
+                .join()
+                .map_err(|_| io::Error::other("recording thread panicked"))??;
+        }
+        match self.sink {
+            Sink::Images(directory) => {
+                write_wav(&directory.join("audio.wav"), &self.audio)?;
+                Ok(directory)
+            }
+            Sink::Ffmpeg {
+                mut process,
+                video,
+                output,
+            } => {
+                check(process.wait()?)?;
+                if video == output {
+                    return Ok(output);
+                }
+                let audio = output.with_extension("wav");
+                write_wav(&audio, &self.audio)?;
+                let status = ffmpeg()
+                    .arg("-i")
+                    .arg(&video)
+                    .arg("-i")
+                    .arg(&audio)
+                    .args(["-c:v", "copy", "-shortest"])
+                    .arg(&output)
+                    .status();
+                let _ = fs::remove_file(&audio);
+                check(status?)?;
+                fs::remove_file(&video)?;
+                Ok(output)
+            }
+        }
+    }
+}
+
+/// Where recordings go when no path is given: the recordings directory, named
+/// after the ROM and the time, as a video if ffmpeg is installed and as images
+/// otherwise.
+pub fn default_path(rom_path: &str) -> PathBuf {
+    let name = format!(
+        "{}-{}",
+        screenshot::rom_name(rom_path),
+        screenshot::timestamp()
+    );
+    let directory = paths::data_dir().join("recordings");
+    let has_ffmpeg = Command::new("ffmpeg")
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if has_ffmpeg {
+        directory.join(format!("{}.mp4", name))
+    } else {
+        directory.join(name)
+    }
+}
+
+fn is_video(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        VIDEO_EXTENSIONS
+            .iter()
+            .any(|video| extension.eq_ignore_ascii_case(video))
+    })
+}
+
+/// Whether the format can hold sound, which GIFs can't.
+fn has_audio(path: &Path) -> bool {
+    !path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"))
+}
+
+/// Most players only handle 4:2:0 chroma, which ffmpeg doesn't pick for RGB input
+/// by default.
+fn pixel_format(path: &Path) -> &'static [&'static str] {
+    if has_audio(path) {
+        &["-pix_fmt", "yuv420p"]
+    } else {
+        &[]
+    }
+}
+
+fn ffmpeg() -> Command {
+    let mut command = Command::new("ffmpeg");
+    command.args(["-y", "-loglevel", "error"]);
+    command
+}
+
+fn check(status: std::process::ExitStatus) -> io::Result<()> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("ffmpeg failed: {}", status)))
+    }
+}
+
+/// The display at the size of the largest resolution, so that switching
+/// resolution doesn't change the size of the video.
+fn frame(chip: &Chip8, palette: &Palette) -> RgbaImage {
+    let (width, height) = HIRES;
+    imageops::resize(
+        &screenshot::image(&chip.display, palette),
+        width as u32,
+        height as u32,
+        imageops::FilterType::Nearest,
+    )
+}
+
+fn write_frames(
+    frames: mpsc::Receiver<RgbaImage>,
+    directory: Option<PathBuf>,
+    mut stdin: Option<ChildStdin>,
+) -> io::Result<()> {
+    for (index, image) in frames.into_iter().enumerate() {
+        if let Some(stdin) = &mut stdin {
+            stdin.write_all(image.as_raw())?;
+        }
+        if let Some(directory) = &directory {
+            let image = imageops::resize(
+                &image,
+                image.width() * IMAGE_SCALE,
+                image.height() * IMAGE_SCALE,
+                imageops::FilterType::Nearest,
+            );
+            image
+                .save(directory.join(format!("frame{:06}.png", index)))
+                .map_err(io::Error::other)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes mono 16-bit PCM.
+fn write_wav(path: &Path, samples: &[i16]) -> io::Result<()> {
+    let data_len = samples.len() as u32 * 2;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, one channel
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+    fs::write(path, bytes)
+}
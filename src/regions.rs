@@ -0,0 +1,90 @@
+use crate::chip8::Chip8;
+use glam::Vec4;
+use std::ops::Range;
+
+/// Colors regions are shown in, in turn.
+const COLORS: [Vec4; 6] = [
+    Vec4::new(1., 0.6, 0.8, 1.),
+    Vec4::new(0.6, 0.8, 1., 1.),
+    Vec4::new(1., 0.75, 0.4, 1.),
+    Vec4::new(0.7, 1., 0.6, 1.),
+    Vec4::new(0.85, 0.7, 1., 1.),
+    Vec4::new(0.5, 1., 0.9, 1.),
+];
+
+/// A named range of memory, e.g. a level's map or the player's variables.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub name: String,
+    pub range: Range<usize>,
+}
+
+impl Region {
+    /// Reads `<name>=<start>-<end>`, with the end included and both in hex, e.g.
+    /// `map=0x300-0x3ff`.
+    pub fn parse(s: &str) -> Result<Region, String> {
+        let error = || format!("expected <name>=<start>-<end>, found '{}'", s);
+        let (name, range) = s.split_once('=').ok_or_else(error)?;
+        let (start, end) = range.split_once('-').ok_or_else(error)?;
+        let address = |text: &str| {
+            usize::from_str_radix(text.trim().trim_start_matches("0x"), 16)
+                .map_err(|_| format!("invalid address '{}'", text))
+        };
+        let (start, end) = (address(start)?, address(end)?);
+        let name = name.trim();
+        if name.is_empty() || end < start {
+            return Err(error());
+        }
+        Ok(Region {
+            name: name.to_string(),
+            range: start..end + 1,
+        })
+    }
+}
+
+/// The regions every program has: the fonts, the ROM, and where each call on the
+/// stack returns to, innermost first as `return0`.
+pub fn builtin(chip: &Chip8) -> Vec<Region> {
+    let mut regions = vec![
+        Region {
+            name: "font".to_string(),
+            range: chip.font_range(),
+        },
+        Region {
+            name: "big_font".to_string(),
+            range: chip.big_font_range(),
+        },
+        Region {
+            name: "program".to_string(),
+            range: chip.program_range(),
+        },
+    ];
+    for (depth, &address) in chip.stack().iter().rev().enumerate() {
+        regions.push(Region {
+            name: format!("return{}", depth),
+            range: address..address + 2,
+        });
+    }
+    regions
+}
+
+/// The user's regions followed by the built-in ones, so that the user's come
+/// first when they overlap.
+pub fn all(chip: &Chip8, user: &[Region]) -> Vec<Region> {
+    let mut regions = user.to_vec();
+    regions.extend(builtin(chip));
+    regions
+}
+
+pub fn find<'r>(regions: &'r [Region], name: &str) -> Option<&'r Region> {
+    regions.iter().find(|region| region.name == name)
+}
+
+/// The first region `address` is in, with its color.
+pub fn at(regions: &[Region], address: usize) -> Option<(&Region, Vec4)> {
+    regions
+        .iter()
+        .enumerate()
+        .find(|(_, region)| region.range.contains(&address))
+        .map(|(index, region)| (region, COLORS[index % COLORS.len()]))
+}
@@ -0,0 +1,53 @@
+use crate::{
+    chip8::{Chip8, Modes},
+    detect,
+    quirks::QuirkOverrides,
+};
+use serde::Deserialize;
+
+const DATABASE: &str = include_str!("../assets/roms.json");
+
+/// What is known about a ROM, and how it should be run.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct RomInfo {
+    pub sha1: String,
+    pub title: String,
+    pub platform: Modes,
+    #[serde(default)]
+    pub quirks: QuirkOverrides,
+    pub instructions_per_frame: Option<u32>,
+}
+
+pub fn sha1(rom: &[u8]) -> String {
+    sha1_smol::Sha1::from(rom).digest().to_string()
+}
+
+pub fn lookup(rom: &[u8]) -> Option<RomInfo> {
+    let entries: Vec<RomInfo> = serde_json::from_str(DATABASE).expect("invalid ROM database");
+    let hash = sha1(rom);
+    entries.into_iter().find(|entry| entry.sha1 == hash)
+}
+
+/// Sets up the platform and quirks for a ROM found in the database, or guessed from
+/// its code if `detect` is set, returning the speed it should run at and what was
+/// guessed. ROMs without a speed in the database run at `default_speed`.
+pub fn apply_profile(chip: &mut Chip8, detect: bool, default_speed: u32) -> (u32, Option<String>) {
+    if let Some(info) = lookup(chip.program()) {
+        eprintln!("Detected {} ({:?})", info.title, info.platform);
+        chip.set_mode(info.platform);
+        chip.quirks.apply(&info.quirks);
+        let instructions_per_frame = info.instructions_per_frame.unwrap_or(default_speed);
+        return (instructions_per_frame, None);
+    }
+    eprintln!("Unknown ROM {}", sha1(chip.program()));
+    if !detect {
+        return (default_speed, None);
+    }
+    let detection = detect::detect(chip.program());
+    if detection.reasons.is_empty() {
+        return (default_speed, None);
+    }
+    chip.set_mode(detection.platform);
+    chip.quirks.apply(&detection.quirks);
+    (default_speed, Some(detection.summary()))
+}
@@ -0,0 +1,136 @@
+use crate::chip8::Chip8;
+use std::{
+    sync::mpsc::{channel, sync_channel, Receiver, Sender, SyncSender, TryRecvError},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// How often a copy of the chip is sent back for drawing.
+const SNAPSHOT_INTERVAL: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// Sleep between steps when running in real time.
+const IDLE_SLEEP: Duration = Duration::from_millis(1);
+/// Time spent emulating flat out between checks for commands while in turbo.
+const TURBO_SLICE: Duration = Duration::from_millis(10);
+
+enum RunnerCommand {
+    Keys([bool; 16]),
+    Pause,
+    Resume,
+    Turbo(bool),
+    Stop,
+}
+
+/// Runs a chip in real time on its own thread, so slow rendering can't affect
+/// emulation timing. Key and run state changes go in, snapshots of the chip come
+/// out at 60Hz. There's no debugger support, since it needs to step the chip itself.
+pub struct Runner {
+    commands: Sender<RunnerCommand>,
+    snapshots: Receiver<Chip8>,
+    thread: Option<JoinHandle<()>>,
+    sent_keys: [bool; 16],
+    sent_paused: bool,
+    sent_turbo: bool,
+}
+
+impl Runner {
+    pub fn spawn(chip: Chip8) -> Runner {
+        let (commands, command_receiver) = channel();
+        // The renderer only wants the newest snapshot, so don't queue more than one
+        let (snapshot_sender, snapshots) = sync_channel(1);
+        let sent_keys = chip.keys;
+        let sent_paused = chip.is_paused();
+        let thread = thread::Builder::new()
+            .name("emulation".to_string())
+            .spawn(move || run(chip, command_receiver, snapshot_sender))
+            .expect("failed to start the emulation thread");
+        Runner {
+            commands,
+            snapshots,
+            thread: Some(thread),
+            sent_keys,
+            sent_paused,
+            sent_turbo: false,
+        }
+    }
+
+    /// Forwards changes to the keys and pause state of `chip`, then replaces the
+    /// rest of it with the latest snapshot, if there's a new one.
+    pub fn sync(&mut self, chip: &mut Chip8, turbo: bool) {
+        if chip.keys != self.sent_keys {
+            self.sent_keys = chip.keys;
+            self.send(RunnerCommand::Keys(chip.keys));
+        }
+        if chip.is_paused() != self.sent_paused {
+            self.sent_paused = chip.is_paused();
+            self.send(if self.sent_paused {
+                RunnerCommand::Pause
+            } else {
+                RunnerCommand::Resume
+            });
+        }
+        if turbo != self.sent_turbo {
+            self.sent_turbo = turbo;
+            self.send(RunnerCommand::Turbo(turbo));
+        }
+
+        if let Some(snapshot) = self.snapshots.try_iter().last() {
+            // Keys pressed since the snapshot was taken haven't reached the thread yet
+            let keys = chip.keys;
+            chip.clone_from(&snapshot);
+            chip.keys = keys;
+        }
+    }
+
+    fn send(&self, command: RunnerCommand) {
+        // The thread only exits once told to stop, so this can't fail before `drop`
+        let _ = self.commands.send(command);
+    }
+}
+
+impl Drop for Runner {
+    fn drop(&mut self) {
+        self.send(RunnerCommand::Stop);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn run(mut chip: Chip8, commands: Receiver<RunnerCommand>, snapshots: SyncSender<Chip8>) {
+    let mut turbo = false;
+    let mut last_snapshot = Instant::now();
+    loop {
+        loop {
+            match commands.try_recv() {
+                Ok(RunnerCommand::Keys(keys)) => chip.keys = keys,
+                Ok(RunnerCommand::Pause) => chip.pause(),
+                Ok(RunnerCommand::Resume) => chip.resume(),
+                Ok(RunnerCommand::Turbo(on)) => {
+                    turbo = on;
+                    chip.resync_clock();
+                }
+                Ok(RunnerCommand::Stop) | Err(TryRecvError::Disconnected) => return,
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        if turbo && !chip.is_paused() {
+            let end = Instant::now() + TURBO_SLICE;
+            while Instant::now() < end {
+                chip.step_debug();
+            }
+            chip.resync_clock();
+        } else {
+            chip.step_with_time();
+        }
+
+        if last_snapshot.elapsed() >= SNAPSHOT_INTERVAL {
+            // A full channel means the last snapshot hasn't been picked up yet
+            let _ = snapshots.try_send(chip.clone());
+            last_snapshot = Instant::now();
+        }
+        if !turbo {
+            thread::sleep(IDLE_SLEEP);
+        }
+    }
+}
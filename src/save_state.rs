@@ -0,0 +1,151 @@
+//! The machine state that outlives a run, for saving to disk and resuming later.
+//! Run control, debugger bookkeeping and the RNG aren't part of it.
+//!
+//! On disk a state is `MAGIC`, the format version as a little-endian u16, the
+//! SHA-1 of the ROM it belongs to, and then the state as JSON. The header lets a
+//! state from another ROM or a newer flake be refused instead of corrupting the
+//! chip, and older versions are migrated as they're read.
+
+use crate::{
+    chip8::{Modes, Timing},
+    display::Display,
+    quirks::Quirks,
+};
+use alloc::{string::String, vec::Vec};
+use core::{fmt, time::Duration};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const MAGIC: [u8; 8] = *b"FLAKESAV";
+/// Bumped whenever `SaveState` changes, with a step added to `migrate`.
+pub const VERSION: u16 = 3;
+const HEADER_LEN: usize = MAGIC.len() + 2 + 20;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveState {
+    pub memory: Vec<u8>,
+    pub display: Display,
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: usize,
+    pub stack: Vec<usize>,
+    pub dt: u8,
+    pub st: u8,
+    pub pitch: u8,
+    pub flags: [u8; 16],
+    pub mode: Modes,
+    pub quirks: Quirks,
+    pub timing: Timing,
+    pub font_address: usize,
+    pub instructions_per_frame: u32,
+    pub instruction_count: u64,
+    pub tick_count: u64,
+    /// The register an unfinished LD VX, K is loading.
+    pub key_wait: Option<usize>,
+    /// When the next instruction and timer decrement are due, from whichever
+    /// comes first.
+    pub next_tick: Duration,
+    pub next_timers_tick: Duration,
+}
+
+#[derive(Debug)]
+pub enum SaveStateError {
+    /// Missing the header, so not something `encode` wrote.
+    NotASaveState,
+    /// Written by a newer flake.
+    UnsupportedVersion(u16),
+    /// Saved while running a different ROM.
+    WrongRom,
+    Parse(serde_json::Error),
+    /// The state couldn't have come from a chip, e.g. memory of the wrong size.
+    Invalid(String),
+}
+
+impl fmt::Display for SaveStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveStateError::NotASaveState => write!(f, "not a flake save state"),
+            SaveStateError::UnsupportedVersion(version) => write!(
+                f,
+                "save state is version {}, but this flake only reads up to {}",
+                version, VERSION
+            ),
+            SaveStateError::WrongRom => write!(f, "save state is for a different ROM"),
+            SaveStateError::Parse(e) => write!(f, "failed to parse save state: {}", e),
+            SaveStateError::Invalid(reason) => write!(f, "invalid save state: {}", reason),
+        }
+    }
+}
+
+impl From<serde_json::Error> for SaveStateError {
+    fn from(error: serde_json::Error) -> Self {
+        SaveStateError::Parse(error)
+    }
+}
+
+fn rom_hash(program: &[u8]) -> [u8; 20] {
+    sha1_smol::Sha1::from(program).digest().bytes()
+}
+
+/// `state` with the header for the current version and `program`.
+pub fn encode(state: &SaveState, program: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::from(MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&rom_hash(program));
+    bytes.extend(serde_json::to_vec(state).expect("save states are always serializable"));
+    bytes
+}
+
+/// Reads a state written by `encode` while `program` was loaded, from this or an
+/// older version. Bare JSON, as saved before the header existed, is read as
+/// version 0, which has no ROM hash to check.
+pub fn decode(bytes: &[u8], program: &[u8]) -> Result<SaveState, SaveStateError> {
+    let (version, payload) = if bytes.first() == Some(&b'{') {
+        (0, bytes)
+    } else {
+        let header = bytes
+            .get(..HEADER_LEN)
+            .ok_or(SaveStateError::NotASaveState)?;
+        if header[..MAGIC.len()] != MAGIC {
+            return Err(SaveStateError::NotASaveState);
+        }
+        let version = u16::from_le_bytes([header[8], header[9]]);
+        if version > VERSION {
+            return Err(SaveStateError::UnsupportedVersion(version));
+        }
+        if header[10..] != rom_hash(program) {
+            return Err(SaveStateError::WrongRom);
+        }
+        (version, &bytes[HEADER_LEN..])
+    };
+    let value = migrate(version, serde_json::from_slice(payload)?)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Brings the JSON of a state from `version` up to `VERSION`, one version at a
+/// time.
+fn migrate(version: u16, mut value: Value) -> Result<Value, SaveStateError> {
+    match version {
+        // Version 1 only added the header
+        0 => migrate(1, value),
+        // Version 2 added the schedule, which older states start afresh
+        1 => {
+            if let Value::Object(fields) = &mut value {
+                let zero = serde_json::to_value(Duration::ZERO)?;
+                fields.insert("next_tick".into(), zero.clone());
+                fields.insert("next_timers_tick".into(), zero);
+            }
+            migrate(2, value)
+        }
+        // Version 3 added the tick count, which can't be less than the instructions
+        2 => {
+            if let Value::Object(fields) = &mut value {
+                let instructions = fields.get("instruction_count").cloned();
+                fields.insert("tick_count".into(), instructions.unwrap_or(0.into()));
+            }
+            migrate(3, value)
+        }
+        VERSION => Ok(value),
+        _ => Err(SaveStateError::UnsupportedVersion(version)),
+    }
+}
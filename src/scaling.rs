@@ -0,0 +1,102 @@
+use glam::{Mat4, Quat, Vec2, Vec3};
+
+/// How the display is fitted into the window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScaleMode {
+    /// As large as fits while keeping the aspect ratio, centered with bars on the
+    /// sides that don't fill.
+    #[default]
+    Fit,
+    /// Like `Fit`, but only whole multiples of the display resolution, so every
+    /// pixel is the same size.
+    Integer,
+    /// Fills the window, ignoring the aspect ratio.
+    Stretch,
+}
+
+impl ScaleMode {
+    pub fn parse(s: &str) -> Result<ScaleMode, String> {
+        match s {
+            "fit" => Ok(ScaleMode::Fit),
+            "integer" => Ok(ScaleMode::Integer),
+            "stretch" => Ok(ScaleMode::Stretch),
+            _ => Err(format!(
+                "invalid scale mode: {} (expected fit, integer or stretch)",
+                s
+            )),
+        }
+    }
+}
+
+/// How display pixels are smoothed when scaled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DisplayFilter {
+    /// Hard edged pixels, which shimmer at non-integer scales.
+    #[default]
+    Nearest,
+    Linear,
+    /// Hard edged pixels with a one pixel wide blend at their edges, so all pixels
+    /// look the same size at any scale.
+    SharpBilinear,
+}
+
+impl DisplayFilter {
+    pub fn parse(s: &str) -> Result<DisplayFilter, String> {
+        match s {
+            "nearest" => Ok(DisplayFilter::Nearest),
+            "linear" => Ok(DisplayFilter::Linear),
+            "sharp" => Ok(DisplayFilter::SharpBilinear),
+            _ => Err(format!(
+                "invalid filter: {} (expected nearest, linear or sharp)",
+                s
+            )),
+        }
+    }
+
+    pub fn next(self) -> DisplayFilter {
+        match self {
+            DisplayFilter::Nearest => DisplayFilter::Linear,
+            DisplayFilter::Linear => DisplayFilter::SharpBilinear,
+            DisplayFilter::SharpBilinear => DisplayFilter::Nearest,
+        }
+    }
+
+    /// The value of the display shader's `filter_mode` uniform.
+    pub fn shader_mode(self) -> f32 {
+        match self {
+            DisplayFilter::Nearest => 0.,
+            DisplayFilter::Linear => 1.,
+            DisplayFilter::SharpBilinear => 2.,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DisplayScaling {
+    pub mode: ScaleMode,
+    /// Space kept clear around the display, in window pixels.
+    pub margin: f32,
+    pub filter: DisplayFilter,
+}
+
+impl DisplayScaling {
+    /// Transforms the unit quad to where the display goes in a window of `window`
+    /// pixels, for a display of `display` pixels.
+    pub fn model(&self, window: Vec2, display: Vec2) -> Mat4 {
+        let available = (window - 2. * self.margin).max(Vec2::ZERO);
+        let fit = f32::min(available.x / display.x, available.y / display.y);
+        let size = match self.mode {
+            ScaleMode::Fit => display * fit,
+            // Shrinks below 1x rather than overflowing tiny windows
+            ScaleMode::Integer if fit >= 1. => display * fit.floor(),
+            ScaleMode::Integer => display * fit,
+            ScaleMode::Stretch => available,
+        };
+        let origin = (window - size) / 2.;
+        Mat4::from_scale_rotation_translation(
+            size.extend(1.),
+            Quat::IDENTITY,
+            Vec3::new(origin.x.round(), origin.y.round(), 0.),
+        )
+    }
+}
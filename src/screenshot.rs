@@ -0,0 +1,57 @@
+use crate::{display::Display, palette::Palette, paths};
+use image::{imageops, ImageResult, Rgba, RgbaImage};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// How many image pixels each display pixel becomes.
+const SCALE: u32 = 8;
+
+/// The display at one image pixel per display pixel, in `palette`'s colors.
+pub fn image(display: &Display, palette: &Palette) -> RgbaImage {
+    let (width, height) = display.size();
+    let colors = palette.0.map(|color| {
+        let [r, g, b, a] = color
+            .to_array()
+            .map(|c| (c.clamp(0., 1.) * 255.).round() as u8);
+        Rgba([r, g, b, a])
+    });
+    RgbaImage::from_fn(width as u32, height as u32, |x, y| {
+        let index = display.pixels()[y as usize * width + x as usize];
+        colors[index as usize & 3]
+    })
+}
+
+/// `rom_path`'s file name without the extension, for naming files saved from it.
+pub fn rom_name(rom_path: &str) -> String {
+    Path::new(rom_path)
+        .file_stem()
+        .map_or("flake".into(), |stem| stem.to_string_lossy().into_owned())
+}
+
+/// Saves the display as a PNG in the screenshots directory, named after the ROM
+/// and the time, returning where it went.
+pub fn save(display: &Display, palette: &Palette, rom_path: &str) -> ImageResult<PathBuf> {
+    let (width, height) = display.size();
+    let image = imageops::resize(
+        &image(display, palette),
+        width as u32 * SCALE,
+        height as u32 * SCALE,
+        imageops::FilterType::Nearest,
+    );
+
+    let directory = paths::data_dir().join("screenshots");
+    fs::create_dir_all(&directory)?;
+    let path = directory.join(format!("{}-{}.png", rom_name(rom_path), timestamp()));
+    image.save(&path)?;
+    Ok(path)
+}
+
+/// Seconds since the epoch, to tell apart files saved from the same ROM.
+pub fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
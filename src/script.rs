@@ -0,0 +1,255 @@
+use crate::chip8::{Chip8, MemoryAccess};
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, Map, Scope, AST};
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+/// Operations a single hook call may take before it's stopped, so a runaway loop
+/// doesn't hang the emulator.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// What the functions registered on the engine work on. Hooks see a copy of the
+/// chip, which is copied back if they changed it.
+struct Host {
+    chip: Chip8,
+    changed: bool,
+    pause: bool,
+    hud: Vec<String>,
+}
+
+type Result<T> = std::result::Result<T, Box<EvalAltResult>>;
+
+fn out_of_range(what: &str, value: i64) -> Box<EvalAltResult> {
+    format!("{} out of range: {}", what, value).into()
+}
+
+fn index(value: i64, len: usize, what: &str) -> Result<usize> {
+    usize::try_from(value)
+        .ok()
+        .filter(|&index| index < len)
+        .ok_or_else(|| out_of_range(what, value))
+}
+
+fn byte(value: i64) -> Result<u8> {
+    u8::try_from(value).map_err(|_| out_of_range("byte", value))
+}
+
+/// A Rhai script run alongside the program, for cheats, bots and research.
+/// Scripts define any of `on_frame()`, `on_instruction(pc)` and
+/// `on_memory(address, length, write)`, which are called with `this` bound to a
+/// map that keeps its contents between calls. See `register_api` for what they
+/// can call.
+pub struct Script {
+    pub path: String,
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    state: Dynamic,
+    host: Rc<RefCell<Host>>,
+    on_frame: bool,
+    on_instruction: bool,
+    on_memory: bool,
+}
+
+impl Script {
+    pub fn load(path: &str) -> std::result::Result<Script, String> {
+        let host = Rc::new(RefCell::new(Host {
+            chip: Chip8::new(),
+            changed: false,
+            pause: false,
+            hud: vec![],
+        }));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        register_api(&mut engine, &host);
+        let ast = engine
+            .compile_file(PathBuf::from(path))
+            .map_err(|e| format!("{}: {}", path, e))?;
+        let defines = |name: &str, params: usize| {
+            ast.iter_functions()
+                .any(|f| f.name == name && f.params.len() == params)
+        };
+        let (on_frame, on_instruction, on_memory) = (
+            defines("on_frame", 0),
+            defines("on_instruction", 1),
+            defines("on_memory", 3),
+        );
+        let mut scope = Scope::new();
+        // Top level statements run once, e.g. to print instructions
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| format!("{}: {}", path, e))?;
+        Ok(Script {
+            path: path.to_string(),
+            engine,
+            ast,
+            scope,
+            state: Dynamic::from(Map::new()),
+            host,
+            on_frame,
+            on_instruction,
+            on_memory,
+        })
+    }
+
+    /// Lines the script has shown with `hud` since the last frame.
+    pub fn hud(&self) -> Vec<String> {
+        self.host.borrow().hud.clone()
+    }
+
+    fn call(&mut self, chip: &mut Chip8, name: &str, args: impl FuncArgs) -> Result<()> {
+        self.host.borrow_mut().chip.clone_from(chip);
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.state);
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut self.scope,
+            &self.ast,
+            name,
+            args,
+        );
+        let mut host = self.host.borrow_mut();
+        if host.changed {
+            chip.clone_from(&host.chip);
+            host.changed = false;
+        }
+        result.map(|_| ())
+    }
+
+    /// Runs `on_frame`, starting a new HUD. Returns whether the script asked to
+    /// pause, or what went wrong.
+    pub fn frame(&mut self, chip: &mut Chip8) -> std::result::Result<bool, String> {
+        self.host.borrow_mut().hud.clear();
+        if self.on_frame {
+            self.call(chip, "on_frame", ())
+                .map_err(|e| format!("Script error in on_frame: {}", e))?;
+        }
+        Ok(self.take_pause())
+    }
+
+    /// Runs the hooks for an instruction that just ran at `pc`, returning like
+    /// `frame`.
+    pub fn step(&mut self, chip: &mut Chip8, pc: usize) -> std::result::Result<bool, String> {
+        if self.on_instruction {
+            self.call(chip, "on_instruction", (pc as i64,))
+                .map_err(|e| format!("Script error in on_instruction: {}", e))?;
+        }
+        if let (true, Some(access)) = (self.on_memory, chip.last_access()) {
+            let MemoryAccess {
+                address,
+                length,
+                write,
+            } = access;
+            self.call(chip, "on_memory", (address as i64, length as i64, write))
+                .map_err(|e| format!("Script error in on_memory: {}", e))?;
+        }
+        Ok(self.take_pause())
+    }
+
+    fn take_pause(&mut self) -> bool {
+        std::mem::take(&mut self.host.borrow_mut().pause)
+    }
+}
+
+/// Registers the functions scripts use to read and change the chip:
+/// `peek(address)`, `poke(address, value)`, `v(x)`, `set_v(x, value)`, `i()`,
+/// `set_i(value)`, `pc()`, `set_pc(value)`, `dt()`, `set_dt(value)`, `st()`,
+/// `set_st(value)`, `key(k)`, `press(k)`, `release(k)`, `hud(text)` and `pause()`.
+fn register_api(engine: &mut Engine, host: &Rc<RefCell<Host>>) {
+    let read = |f: fn(&Chip8) -> i64| {
+        let host = host.clone();
+        move || f(&host.borrow().chip)
+    };
+    engine.register_fn("i", read(|chip| chip.i() as i64));
+    engine.register_fn("pc", read(|chip| chip.pc() as i64));
+    engine.register_fn("dt", read(|chip| chip.dt() as i64));
+    engine.register_fn("st", read(|chip| chip.st() as i64));
+
+    let write = |f: fn(&mut Chip8, i64) -> Result<()>| {
+        let host = host.clone();
+        move |value: i64| -> Result<()> {
+            let mut host = host.borrow_mut();
+            f(&mut host.chip, value)?;
+            host.changed = true;
+            Ok(())
+        }
+    };
+    engine.register_fn(
+        "set_i",
+        write(|chip, value| {
+            let i = u16::try_from(value).map_err(|_| out_of_range("I", value))?;
+            chip.set_i(i);
+            Ok(())
+        }),
+    );
+    engine.register_fn(
+        "set_pc",
+        write(|chip, value| {
+            chip.set_pc(index(value, chip.memory().len(), "PC")?);
+            Ok(())
+        }),
+    );
+    engine.register_fn(
+        "set_dt",
+        write(|chip, value| {
+            chip.set_dt(byte(value)?);
+            Ok(())
+        }),
+    );
+    engine.register_fn(
+        "set_st",
+        write(|chip, value| {
+            chip.set_st(byte(value)?);
+            Ok(())
+        }),
+    );
+    engine.register_fn(
+        "press",
+        write(|chip, key| {
+            chip.keys[index(key, 16, "key")?] = true;
+            Ok(())
+        }),
+    );
+    engine.register_fn(
+        "release",
+        write(|chip, key| {
+            chip.keys[index(key, 16, "key")?] = false;
+            Ok(())
+        }),
+    );
+
+    let host_for = host.clone();
+    engine.register_fn("peek", move |address: i64| -> Result<i64> {
+        let host = host_for.borrow();
+        let memory = host.chip.memory();
+        Ok(memory[index(address, memory.len(), "address")?] as i64)
+    });
+    let host_for = host.clone();
+    engine.register_fn("poke", move |address: i64, value: i64| -> Result<()> {
+        let mut host = host_for.borrow_mut();
+        let address = index(address, host.chip.memory().len(), "address")?;
+        host.chip.write_memory(address, &[byte(value)?]);
+        host.changed = true;
+        Ok(())
+    });
+    let host_for = host.clone();
+    engine.register_fn("v", move |x: i64| -> Result<i64> {
+        Ok(host_for.borrow().chip.v()[index(x, 16, "register")?] as i64)
+    });
+    let host_for = host.clone();
+    engine.register_fn("set_v", move |x: i64, value: i64| -> Result<()> {
+        let mut host = host_for.borrow_mut();
+        host.chip.set_v(index(x, 16, "register")?, byte(value)?);
+        host.changed = true;
+        Ok(())
+    });
+    let host_for = host.clone();
+    engine.register_fn("key", move |key: i64| -> Result<bool> {
+        Ok(host_for.borrow().chip.keys[index(key, 16, "key")?])
+    });
+    let host_for = host.clone();
+    engine.register_fn("hud", move |text: &str| {
+        host_for.borrow_mut().hud.push(text.to_string());
+    });
+    let host_for = host.clone();
+    engine.register_fn("pause", move || host_for.borrow_mut().pause = true);
+}
@@ -1,18 +1,59 @@
-use std::{collections::HashMap, fs::File, io::BufReader, path::Path};
-
-use glam::{Mat4, Quat, Vec3};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use glam::{Mat4, Quat, Vec3, Vec4};
 use image::{EncodableLayout, RgbaImage};
 use miniquad::*;
 
+/// A piece of text laid out with an `SDFFont`, drawn through a `TextBatcher`.
 pub struct SDFText<'a> {
-    bindings: Bindings,
     font: &'a SDFFont,
+    text: String,
+    /// End byte index and color of each span of `text`.
+    colors: Vec<(usize, Vec4)>,
+    /// Width in font pixels past which lines wrap at the last space.
+    max_width: Option<f32>,
+    vertices: Vec<Vertex>,
+    bounds: Bounds,
     pub model: Mat4,
+    pub style: TextStyle,
+    pub align: Align,
+}
+
+/// How an `SDFText` is shaded. Widths are in distance field units, where the
+/// glyph edge sits at 0.5, so an `outline_width` of 0.2 is a thick outline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextStyle {
+    pub color: Vec4,
+    pub outline_color: Vec4,
+    pub outline_width: f32,
+    /// How far either side of the edge is anti-aliased.
+    pub softness: f32,
+    pub shadow_color: Vec4,
+    /// In font pixels, with y up.
+    pub shadow_offset: Vec2,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle {
+            color: Vec4::ONE,
+            outline_color: Vec4::new(0., 0., 0., 1.),
+            outline_width: 0.,
+            softness: 0.1,
+            shadow_color: Vec4::ZERO,
+            shadow_offset: Vec2::ZERO,
+        }
+    }
 }
 
 pub struct SDFFont {
     pipeline: Pipeline,
-    glyphs: HashMap<char, GlyphInfo>,
+    glyphs: GlyphSet,
     texture: Texture,
 }
 
@@ -23,6 +64,38 @@ use glam::Vec2;
 struct Vertex {
     pos: Vec2,
     uv: Vec2,
+    /// Multiplies the style's color, so spans can be colored differently.
+    color: Vec4,
+}
+
+struct GlyphSet {
+    glyphs: HashMap<char, GlyphInfo>,
+    /// Distance between the baselines of consecutive lines.
+    line_height: f32,
+    /// Distance from the top of a line to its baseline.
+    base: f32,
+    /// Extra advance between specific pairs of characters.
+    kernings: HashMap<(char, char), f32>,
+    /// Characters missing from the atlas that have already been reported.
+    warned: RefCell<HashSet<char>>,
+}
+
+/// Drawn in place of characters missing from the atlas.
+const FALLBACK_GLYPH: char = '?';
+/// Width of a tab, in spaces.
+const TAB_WIDTH: f32 = 4.;
+
+impl GlyphSet {
+    /// The glyph for `c`, or the fallback glyph (reporting `c` the first time) if the
+    /// atlas doesn't have it.
+    fn get(&self, c: char) -> Option<&GlyphInfo> {
+        self.glyphs.get(&c).or_else(|| {
+            if self.warned.borrow_mut().insert(c) {
+                eprintln!("Font has no glyph for {:?}, using {:?}", c, FALLBACK_GLYPH);
+            }
+            self.glyphs.get(&FALLBACK_GLYPH)
+        })
+    }
 }
 
 struct GlyphInfo {
@@ -56,11 +129,20 @@ struct BMFontJSONCommon {
     base: f32,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+struct BMFontJSONKerning {
+    first: u32,
+    second: u32,
+    amount: f32,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct BMFontJSON {
     pages: Vec<String>,
     chars: Vec<BMFontJSONGlyphInfo>,
     common: BMFontJSONCommon,
+    #[serde(default)]
+    kernings: Vec<BMFontJSONKerning>,
 }
 
 #[derive(Debug)]
@@ -68,6 +150,18 @@ enum FontLoadError {
     IO(std::io::Error),
     Parse(serde_json::Error),
     Image(image::ImageError),
+    /// Parsed, but not usable as a font.
+    Invalid(String),
+}
+impl std::fmt::Display for FontLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FontLoadError::IO(e) => write!(f, "failed to read font: {}", e),
+            FontLoadError::Parse(e) => write!(f, "failed to parse font: {}", e),
+            FontLoadError::Image(e) => write!(f, "failed to load font atlas: {}", e),
+            FontLoadError::Invalid(reason) => write!(f, "invalid font: {}", reason),
+        }
+    }
 }
 impl From<std::io::Error> for FontLoadError {
     fn from(error: std::io::Error) -> Self {
@@ -85,19 +179,31 @@ impl From<image::ImageError> for FontLoadError {
     }
 }
 
-fn load_font(filename: &str) -> Result<(RgbaImage, HashMap<char, GlyphInfo>), FontLoadError> {
-    let reader = BufReader::new(File::open(filename)?);
-    let data: BMFontJSON = serde_json::from_reader(reader)?;
+/// The font built into the binary, used unless another one is given on the command line.
+const DEFAULT_FONT_JSON: &[u8] = include_bytes!("../assets/roboto-bold.json");
+const DEFAULT_FONT_ATLAS: &[u8] = include_bytes!("../assets/roboto-bold.png");
+
+/// Loads a BMFont JSON file, along with the atlas image it names next to it.
+fn load_font(filename: &str) -> Result<(RgbaImage, GlyphSet), FontLoadError> {
+    let json = fs::read(filename)?;
+    let data: BMFontJSON = serde_json::from_slice(&json)?;
+    let page = data
+        .pages
+        .first()
+        .ok_or_else(|| FontLoadError::Invalid("no atlas pages".to_string()))?;
     let path = match Path::new(filename).parent() {
-        Some(parent) => parent.join(data.pages[0].clone()),
+        Some(parent) => parent.join(page),
         None => {
             return Result::Err(
                 std::io::Error::new(std::io::ErrorKind::NotFound, "filename must be a file").into(),
             )
         }
     };
+    parse_font(data, &fs::read(path)?)
+}
 
-    let sdf_texture = image::open(path)?;
+fn parse_font(data: BMFontJSON, atlas: &[u8]) -> Result<(RgbaImage, GlyphSet), FontLoadError> {
+    let sdf_texture = image::load_from_memory(atlas)?;
 
     let texture_size = Vec2 {
         x: sdf_texture.width() as f32,
@@ -112,9 +218,11 @@ fn load_font(filename: &str) -> Result<(RgbaImage, HashMap<char, GlyphInfo>), Fo
                 x: info.width,
                 y: info.height,
             };
-            (
-                // TODO nice way to return an error result from this?
-                char::from_u32(info.id).expect("Invalid character id"),
+            let character = char::from_u32(info.id).ok_or_else(|| {
+                FontLoadError::Invalid(format!("invalid character id {}", info.id))
+            })?;
+            Ok((
+                character,
                 GlyphInfo {
                     size,
                     uv: Vec2 {
@@ -128,48 +236,227 @@ fn load_font(filename: &str) -> Result<(RgbaImage, HashMap<char, GlyphInfo>), Fo
                         y: data.common.base - (size.y + info.yoffset),
                     },
                 },
-            )
+            ))
+        })
+        .collect::<Result<_, FontLoadError>>()?;
+
+    let kernings = data
+        .kernings
+        .iter()
+        .filter_map(|k| {
+            let pair = (char::from_u32(k.first)?, char::from_u32(k.second)?);
+            Some((pair, k.amount))
         })
         .collect();
 
-    Ok((sdf_texture.into_rgba8(), map))
+    Ok((
+        sdf_texture.into_rgba8(),
+        GlyphSet {
+            glyphs: map,
+            line_height: data.common.line_height,
+            base: data.common.base,
+            kernings,
+            warned: RefCell::default(),
+        },
+    ))
 }
 
 #[rustfmt::skip]
-fn make_quad(info: &GlyphInfo, buf :&mut [Vertex], offset : Vec2) {
-    buf[0] = Vertex { pos : Vec2::splat(0.0) + offset,                uv: Vec2 { x: info.uv.x, y: info.uv.y + info.uv_size.y } };
-    buf[1] = Vertex { pos : Vec2 { x:  info.size.x, y: 0. } + offset, uv:  info.uv + info.uv_size  };
-    buf[2] = Vertex { pos : info.size + offset,                       uv: Vec2 { x: info.uv.x + info.uv_size.x, y: info.uv.y } };
-    buf[3] = Vertex { pos : Vec2 { x: 0., y:  info.size.y} + offset,  uv: info.uv };
-}
-
-fn make_mesh(glyphs: &HashMap<char, GlyphInfo>, text: &str) -> (Vec<Vertex>, Vec<u16>) {
-    let num_chars = text.chars().count();
-    let mut vertices = vec![Default::default(); num_chars * 4];
-
-    let mut x_offset = 0.0;
-    text.chars().enumerate().for_each(|(i, c)| {
-        let info = glyphs.get(&c).unwrap();
-        make_quad(
-            info,
-            &mut vertices[i * 4..i * 4 + 4],
-            Vec2 { x: x_offset, y: 0. } + info.offset,
-        );
-        x_offset += info.x_advance;
-    });
+fn make_quad(info: &GlyphInfo, buf :&mut [Vertex], offset : Vec2, color: Vec4) {
+    buf[0] = Vertex { pos : Vec2::splat(0.0) + offset,                uv: Vec2 { x: info.uv.x, y: info.uv.y + info.uv_size.y }, color };
+    buf[1] = Vertex { pos : Vec2 { x:  info.size.x, y: 0. } + offset, uv:  info.uv + info.uv_size, color };
+    buf[2] = Vertex { pos : info.size + offset,                       uv: Vec2 { x: info.uv.x + info.uv_size.x, y: info.uv.y }, color };
+    buf[3] = Vertex { pos : Vec2 { x: 0., y:  info.size.y} + offset,  uv: info.uv, color };
+}
+
+/// The area a piece of text covers, in font pixels relative to the first line's
+/// baseline. Horizontally it spans the pen advance of the longest line and
+/// vertically the full line height of every line.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Bounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Bounds {
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
 
-    let mut indices = vec![0; num_chars * 6];
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VAlign {
+    Top,
+    #[default]
+    Baseline,
+    Bottom,
+}
+
+/// Which point of an `SDFText`'s bounds sits at the origin of its model matrix.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Align {
+    pub horizontal: HAlign,
+    pub vertical: VAlign,
+}
 
+impl Align {
+    fn anchor(&self, bounds: &Bounds) -> Vec2 {
+        Vec2 {
+            x: match self.horizontal {
+                HAlign::Left => bounds.min.x,
+                HAlign::Center => (bounds.min.x + bounds.max.x) / 2.,
+                HAlign::Right => bounds.max.x,
+            },
+            y: match self.vertical {
+                VAlign::Top => bounds.max.y,
+                VAlign::Baseline => 0.,
+                VAlign::Bottom => bounds.min.y,
+            },
+        }
+    }
+}
+
+/// Lays out `text` starting with the first line's baseline at the origin, calling
+/// `place` with the byte index of each glyph's character, the glyph, and where its
+/// quad goes. Each newline moves down by the
+/// font's line height, and characters the font lacks are drawn as `FALLBACK_GLYPH`.
+fn layout<'a>(
+    glyphs: &'a GlyphSet,
+    text: &str,
+    mut place: impl FnMut(usize, &'a GlyphInfo, Vec2),
+) -> Bounds {
+    let mut width: f32 = 0.;
+    let mut cursor = Vec2::ZERO;
+    let mut previous = None;
+    for (index, c) in text.char_indices() {
+        match c {
+            '\n' => {
+                width = width.max(cursor.x);
+                cursor = Vec2 {
+                    x: 0.,
+                    y: cursor.y - glyphs.line_height,
+                };
+                previous = None;
+                continue;
+            }
+            '\t' => {
+                let space = glyphs.get(' ').map_or(0., |info| info.x_advance);
+                cursor.x += space * TAB_WIDTH;
+                previous = None;
+                continue;
+            }
+            '\r' => continue,
+            _ => {}
+        }
+        if let Some(amount) = previous.and_then(|p| glyphs.kernings.get(&(p, c))) {
+            cursor.x += amount;
+        }
+        previous = Some(c);
+        let Some(info) = glyphs.get(c) else {
+            continue;
+        };
+        place(index, info, cursor + info.offset);
+        cursor.x += info.x_advance;
+    }
+
+    Bounds {
+        min: Vec2 {
+            x: 0.,
+            y: cursor.y + glyphs.base - glyphs.line_height,
+        },
+        max: Vec2 {
+            x: width.max(cursor.x),
+            y: glyphs.base,
+        },
+    }
+}
+
+/// Breaks each line of `text` at the last space that keeps it within `max_width`.
+/// Words wider than `max_width` on their own are left to overflow.
+fn wrap(glyphs: &GlyphSet, text: &str, max_width: f32) -> String {
+    let width = |text: &str| layout(glyphs, text, |_, _, _| {}).max.x;
+    let space = width(" ");
+    let mut wrapped = String::with_capacity(text.len());
+    for (index, line) in text.split('\n').enumerate() {
+        if index > 0 {
+            wrapped.push('\n');
+        }
+        let mut line_width = 0.;
+        for (index, word) in line.split(' ').enumerate() {
+            let word_width = width(word);
+            if index > 0 {
+                if line_width > 0. && line_width + space + word_width > max_width {
+                    wrapped.push('\n');
+                    line_width = 0.;
+                } else {
+                    wrapped.push(' ');
+                    line_width += space;
+                }
+            }
+            wrapped.push_str(word);
+            line_width += word_width;
+        }
+    }
+    wrapped
+}
+
+/// Fills `vertices` with four per glyph, replacing what was there, wrapping lines
+/// longer than `max_width` if it's given. `colors` holds the end byte index and
+/// color of each run of `text`.
+fn make_mesh(
+    glyphs: &GlyphSet,
+    text: &str,
+    colors: &[(usize, Vec4)],
+    max_width: Option<f32>,
+    vertices: &mut Vec<Vertex>,
+) -> Bounds {
+    vertices.clear();
+    let wrapped;
+    let text = match max_width {
+        Some(max_width) => {
+            wrapped = wrap(glyphs, text, max_width);
+            &wrapped
+        }
+        None => text,
+    };
+    // Wrapping only swaps spaces for newlines, so byte indices still line up
+    let mut runs = colors.iter().peekable();
+    layout(glyphs, text, |index, info, offset| {
+        while runs.next_if(|(end, _)| index >= *end).is_some() {}
+        let color = runs.peek().map_or(Vec4::ONE, |(_, color)| *color);
+        let mut quad = [Vertex::default(); 4];
+        make_quad(info, &mut quad, offset, color);
+        vertices.extend_from_slice(&quad);
+    })
+}
+
+/// Two triangles per glyph quad, for `glyphs` quads.
+fn make_indices(glyphs: usize) -> Vec<u16> {
+    let mut indices = vec![0; glyphs * 6];
     indices.chunks_exact_mut(6).enumerate().for_each(|(i, v)| {
         let o: u16 = 4 * i as u16;
         v.copy_from_slice(&[0, 1, 2, 0, 2, 3].map(|n| n + o));
     });
-
-    (vertices, indices)
+    indices
 }
 
 impl SDFFont {
-    pub fn new(ctx: &mut Context) -> Self {
+    /// The bounds `text` would have, without building a mesh for it.
+    pub fn measure(&self, text: &str) -> Bounds {
+        layout(&self.glyphs, text, |_, _, _| {})
+    }
+
+    /// Loads the BMFont JSON at `path`, falling back to the built-in font when it's
+    /// `None` or fails to load.
+    pub fn new(ctx: &mut Context, path: Option<&str>) -> Self {
         let shader = Shader::new(ctx, shader::VERTEX, shader::FRAGMENT, shader::meta()).unwrap();
         let pipeline = Pipeline::with_params(
             ctx,
@@ -177,20 +464,30 @@ impl SDFFont {
             &[
                 VertexAttribute::new("pos", VertexFormat::Float2),
                 VertexAttribute::new("uv", VertexFormat::Float2),
+                VertexAttribute::new("tint", VertexFormat::Float4),
             ],
             shader,
             PipelineParams {
                 color_blend: Some(BlendState::new(
                     Equation::Add,
-                    BlendFactor::Value(BlendValue::SourceColor),
+                    BlendFactor::One,
                     BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
                 )),
                 ..Default::default()
             },
         );
 
-        let (sdf_texture, glyphs) =
-            load_font("./assets/roboto-bold.json").expect("failed to load font");
+        let loaded = path.and_then(|path| {
+            load_font(path)
+                .map_err(|e| eprintln!("Using the built-in font, {}: {}", path, e))
+                .ok()
+        });
+        let (sdf_texture, glyphs) = loaded.unwrap_or_else(|| {
+            serde_json::from_slice(DEFAULT_FONT_JSON)
+                .map_err(FontLoadError::from)
+                .and_then(|data| parse_font(data, DEFAULT_FONT_ATLAS))
+                .expect("built-in font is valid")
+        });
 
         let texture = Texture::from_data_and_format(
             ctx,
@@ -213,46 +510,170 @@ impl SDFFont {
 }
 
 impl<'a> SDFText<'a> {
-    pub fn new(ctx: &mut GraphicsContext, font: &'a SDFFont, text: &str) -> SDFText<'a> {
-        let (vertices, indices) = make_mesh(&font.glyphs, text);
-
-        let bindings = Bindings {
-            index_buffer: Buffer::immutable(ctx, BufferType::IndexBuffer, &indices),
-            vertex_buffers: vec![Buffer::immutable(ctx, BufferType::VertexBuffer, &vertices)],
-            images: vec![font.texture],
-        };
-
+    pub fn new(font: &'a SDFFont, text: &str) -> SDFText<'a> {
         let model = Mat4::from_scale_rotation_translation(
             Vec3::splat(1.0),
             Quat::from_rotation_z(0.0),
             Vec3::new(50.0, 50.0, 0.0),
         );
 
-        SDFText {
-            bindings,
+        let mut sdf_text = SDFText {
             font,
+            text: String::new(),
+            colors: vec![],
+            max_width: None,
+            vertices: vec![],
+            bounds: Bounds::default(),
             model,
+            style: TextStyle::default(),
+            align: Align::default(),
+        };
+        sdf_text.update_text(text);
+        sdf_text
+    }
+
+    pub fn update_text(&mut self, text: &str) {
+        self.update_spans(&[(text, Vec4::ONE)]);
+    }
+
+    /// Replaces the text with `spans` drawn one after another, each in its own color
+    /// (multiplied by `style.color`).
+    pub fn update_spans<S: AsRef<str>>(&mut self, spans: &[(S, Vec4)]) {
+        self.text.clear();
+        self.colors.clear();
+        for (text, color) in spans {
+            self.text.push_str(text.as_ref());
+            self.colors.push((self.text.len(), *color));
+        }
+        self.relayout();
+    }
+
+    fn relayout(&mut self) {
+        self.bounds = make_mesh(
+            &self.font.glyphs,
+            &self.text,
+            &self.colors,
+            self.max_width,
+            &mut self.vertices,
+        );
+    }
+
+    /// The size of the laid out text in font pixels, before `model` is applied.
+    pub fn size(&self) -> Vec2 {
+        self.bounds.size()
+    }
+
+    /// Wraps the text to `max_width` font pixels, or stops wrapping it if `None`.
+    pub fn set_max_width(&mut self, max_width: Option<f32>) {
+        if self.max_width != max_width {
+            self.max_width = max_width;
+            self.relayout();
         }
     }
-    pub fn update_text(&mut self, ctx: &mut Context, text: String) {
-        let (vertices, indices) = make_mesh(&self.font.glyphs, &text);
-        self.bindings.index_buffer = Buffer::immutable(ctx, BufferType::IndexBuffer, &indices);
-        self.bindings.vertex_buffers =
-            vec![Buffer::immutable(ctx, BufferType::VertexBuffer, &vertices)];
+}
+
+/// Glyphs of buffer space a `TextBatcher` starts with.
+const MIN_CAPACITY: usize = 256;
+
+/// Collects the glyphs of every `SDFText` drawn in a frame into one streaming
+/// vertex buffer, then draws them with as few draw calls as possible: one per run
+/// of consecutively added texts that share a style.
+pub struct TextBatcher<'a> {
+    font: &'a SDFFont,
+    bindings: Bindings,
+    /// How many glyphs the buffers in `bindings` have room for.
+    capacity: usize,
+    vertices: Vec<Vertex>,
+    /// Styles and how many glyphs use them, in the order they were added.
+    runs: Vec<(TextStyle, usize)>,
+}
+
+impl<'a> TextBatcher<'a> {
+    pub fn new(ctx: &mut Context, font: &'a SDFFont) -> TextBatcher<'a> {
+        let (index_buffer, vertex_buffer) = glyph_buffers(ctx, MIN_CAPACITY);
+        TextBatcher {
+            font,
+            bindings: Bindings {
+                index_buffer,
+                vertex_buffers: vec![vertex_buffer],
+                images: vec![font.texture],
+            },
+            capacity: MIN_CAPACITY,
+            vertices: vec![],
+            runs: vec![],
+        }
     }
 
-    pub fn draw(&self, ctx: &mut Context, projection: Mat4, view: Mat4) {
+    /// Queues `text` for the next `draw`, baking its model matrix and alignment
+    /// into the vertices.
+    pub fn add(&mut self, text: &SDFText) {
+        debug_assert!(std::ptr::eq(text.font, self.font));
+        let anchor = text.align.anchor(&text.bounds);
+        let transform = text.model * Mat4::from_translation(-anchor.extend(0.));
+        self.vertices.extend(text.vertices.iter().map(|v| Vertex {
+            pos: transform.transform_point3(v.pos.extend(0.)).truncate(),
+            ..*v
+        }));
+
+        let glyphs = text.vertices.len() / 4;
+        match self.runs.last_mut() {
+            Some((style, count)) if *style == text.style => *count += glyphs,
+            _ => self.runs.push((text.style, glyphs)),
+        }
+    }
+
+    /// Draws everything added since the last call.
+    pub fn draw(&mut self, ctx: &mut Context, projection: Mat4, view: Mat4) {
+        let glyphs = self.vertices.len() / 4;
+        if glyphs > self.capacity {
+            self.bindings.index_buffer.delete();
+            self.bindings.vertex_buffers[0].delete();
+            self.capacity = glyphs.next_power_of_two();
+            let (index_buffer, vertex_buffer) = glyph_buffers(ctx, self.capacity);
+            self.bindings.index_buffer = index_buffer;
+            self.bindings.vertex_buffers = vec![vertex_buffer];
+        }
+        self.bindings.vertex_buffers[0].update(ctx, &self.vertices);
+
         ctx.apply_pipeline(&self.font.pipeline);
         ctx.apply_bindings(&self.bindings);
-        ctx.apply_uniforms(&shader::Uniforms {
-            model: self.model,
-            view,
-            projection,
-        });
-        ctx.draw(0, self.bindings.index_buffer.size() as i32, 1);
+        let texture_size = Vec2::new(
+            self.font.texture.width as f32,
+            self.font.texture.height as f32,
+        );
+        let mut first = 0;
+        for (style, count) in self.runs.drain(..) {
+            ctx.apply_uniforms(&shader::Uniforms {
+                model: Mat4::IDENTITY,
+                view,
+                projection,
+                color: style.color,
+                outline_color: style.outline_color,
+                shadow_color: style.shadow_color,
+                // Texture v runs down the atlas while y runs up the screen
+                shadow_offset: style.shadow_offset * Vec2::new(1., -1.) / texture_size,
+                outline_width: style.outline_width,
+                softness: style.softness,
+            });
+            ctx.draw(first as i32 * 6, count as i32 * 6, 1);
+            first += count;
+        }
+        self.vertices.clear();
     }
 }
 
+/// Buffers for `capacity` glyphs, with the index buffer already filled in since
+/// every quad uses the same pattern.
+fn glyph_buffers(ctx: &mut Context, capacity: usize) -> (Buffer, Buffer) {
+    let index_buffer = Buffer::immutable(ctx, BufferType::IndexBuffer, &make_indices(capacity));
+    let vertex_buffer = Buffer::stream(
+        ctx,
+        BufferType::VertexBuffer,
+        capacity * 4 * std::mem::size_of::<Vertex>(),
+    );
+    (index_buffer, vertex_buffer)
+}
+
 mod shader {
     use miniquad::*;
 
@@ -267,6 +688,12 @@ mod shader {
                     UniformDesc::new("model", UniformType::Mat4),
                     UniformDesc::new("view", UniformType::Mat4),
                     UniformDesc::new("projection", UniformType::Mat4),
+                    UniformDesc::new("color", UniformType::Float4),
+                    UniformDesc::new("outline_color", UniformType::Float4),
+                    UniformDesc::new("shadow_color", UniformType::Float4),
+                    UniformDesc::new("shadow_offset", UniformType::Float2),
+                    UniformDesc::new("outline_width", UniformType::Float1),
+                    UniformDesc::new("softness", UniformType::Float1),
                 ],
             },
         }
@@ -277,5 +704,18 @@ mod shader {
         pub model: glam::Mat4,
         pub view: glam::Mat4,
         pub projection: glam::Mat4,
+        pub color: glam::Vec4,
+        pub outline_color: glam::Vec4,
+        pub shadow_color: glam::Vec4,
+        pub shadow_offset: glam::Vec2,
+        pub outline_width: f32,
+        pub softness: f32,
+    }
+}
+
+impl Drop for TextBatcher<'_> {
+    fn drop(&mut self) {
+        self.bindings.index_buffer.delete();
+        self.bindings.vertex_buffers[0].delete();
     }
 }
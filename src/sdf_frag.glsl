@@ -1,23 +1,31 @@
 #version 100
 precision lowp float;
 varying lowp vec2 texcoord;
+varying lowp vec4 span_color;
 uniform sampler2D tex;
+uniform vec4 color;
+uniform vec4 outline_color;
+uniform vec4 shadow_color;
+uniform vec2 shadow_offset;
+uniform float outline_width;
+uniform float softness;
 
-
-float cubicPulse( float c, float w, float x )
-{
-    if( x>c+w ) return 0.0;
-    x = abs(x - c);
-    if( x>w ) return 0.0;
-    x /= w;
-    return 1.0 - x*x*(3.0-2.0*x);
+// Coverage of the shape grown outwards by `grow`, in distance field units
+float coverage(float d, float grow) {
+    float edge = 0.5 - grow;
+    return smoothstep(edge - softness, edge + softness, d);
 }
 
 void main() {
-    float d = texture2D(tex, vec2(texcoord.x, texcoord.y)).a;
-    float c = smoothstep(0.4,0.6, d);
-    vec3 ch = vec3(c,c,c);
-    float outline = cubicPulse(0.5, 0.1, d);
-    gl_FragColor.rgb = mix(ch, vec3(1.,0.,0.), outline);
-    gl_FragColor.a = c;
+    float d = texture2D(tex, texcoord).a;
+    vec4 body = mix(outline_color, color * span_color, coverage(d, 0.0));
+    body.a *= coverage(d, outline_width);
+    body.rgb *= body.a;
+
+    float shadow_d = texture2D(tex, texcoord - shadow_offset).a;
+    vec4 shadow = shadow_color * coverage(shadow_d, outline_width);
+    shadow.rgb *= shadow_color.a;
+
+    // Premultiplied alpha, text over its shadow
+    gl_FragColor = body + shadow * (1.0 - body.a);
 }
@@ -1,11 +1,14 @@
 #version 100
 attribute vec2 pos;
 attribute vec2 uv;
+attribute vec4 tint;
 uniform mat4 projection;
 uniform mat4 view;
 uniform mat4 model;
 varying lowp vec2 texcoord;
+varying lowp vec4 span_color;
 void main() {
     gl_Position = projection * view * model * vec4(pos, 0, 1);
     texcoord = uv;
+    span_color = tint;
 }
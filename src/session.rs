@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+
+/// Debugger setup persisted next to a ROM so it survives restarting the emulator.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Session {
+    pub breakpoints: Vec<usize>,
+    pub watchpoints: Vec<usize>,
+    pub instructions_per_frame: u32,
+    pub last_pc: usize,
+}
+
+#[derive(Debug)]
+pub enum SessionError {
+    IO(std::io::Error),
+    Parse(serde_json::Error),
+}
+impl std::fmt::Display for SessionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionError::IO(e) => write!(f, "failed to access session file: {}", e),
+            SessionError::Parse(e) => write!(f, "failed to parse session file: {}", e),
+        }
+    }
+}
+impl From<std::io::Error> for SessionError {
+    fn from(error: std::io::Error) -> Self {
+        SessionError::IO(error)
+    }
+}
+impl From<serde_json::Error> for SessionError {
+    fn from(error: serde_json::Error) -> Self {
+        SessionError::Parse(error)
+    }
+}
+
+/// The sidecar file for a ROM, e.g. `roms/breakout.ch8.session.json`.
+pub fn sidecar_path(rom_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.session.json", rom_path))
+}
+
+impl Session {
+    /// Returns `Ok(None)` when no session has been saved for this ROM yet.
+    pub fn load(rom_path: &str) -> Result<Option<Session>, SessionError> {
+        let file = match File::open(sidecar_path(rom_path)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+    }
+
+    pub fn save(&self, rom_path: &str) -> Result<(), SessionError> {
+        let writer = BufWriter::new(File::create(sidecar_path(rom_path))?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+}
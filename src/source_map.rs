@@ -0,0 +1,125 @@
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+};
+use core::fmt;
+#[cfg(feature = "std")]
+use std::{fs, path::PathBuf};
+
+/// Which source line each instruction in a ROM was assembled from, so debuggers
+/// can set breakpoints by line. Saved next to assembled ROMs as a `.map` file with
+/// a `source <path>` line followed by one `<address> <line>` pair per line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// The file the lines are in, empty if it isn't known.
+    pub source: String,
+    lines: BTreeMap<usize, usize>,
+}
+
+#[derive(Debug)]
+pub enum SourceMapError {
+    #[cfg(feature = "std")]
+    IO(std::io::Error),
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+impl fmt::Display for SourceMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            SourceMapError::IO(e) => write!(f, "failed to access source map: {}", e),
+            SourceMapError::Parse { line, message } => {
+                write!(f, "failed to parse source map: line {}: {}", line, message)
+            }
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl From<std::io::Error> for SourceMapError {
+    fn from(error: std::io::Error) -> Self {
+        SourceMapError::IO(error)
+    }
+}
+
+/// The sidecar file for a ROM, e.g. `roms/game.ch8.map`.
+#[cfg(feature = "std")]
+pub fn sidecar_path(rom_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.map", rom_path))
+}
+
+impl SourceMap {
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Records the instruction at `address` as coming from `line`, 1-based.
+    pub fn insert(&mut self, address: usize, line: usize) {
+        self.lines.insert(address, line);
+    }
+
+    pub fn line(&self, address: usize) -> Option<usize> {
+        self.lines.get(&address).copied()
+    }
+
+    /// The first instruction from `line`, or from the next line with any, along
+    /// with the line it's on.
+    pub fn address(&self, line: usize) -> Option<(usize, usize)> {
+        self.lines
+            .iter()
+            .filter(|(_, &at)| at >= line)
+            .min_by_key(|(&address, &at)| (at, address))
+            .map(|(&address, &at)| (address, at))
+    }
+
+    pub fn parse(text: &str) -> Result<SourceMap, SourceMapError> {
+        let mut map = SourceMap::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| SourceMapError::Parse {
+                line: index + 1,
+                message,
+            };
+            if let Some(source) = line.strip_prefix("source ") {
+                map.source = source.trim().to_string();
+                continue;
+            }
+            let (address, source_line) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| error(format!("expected '<address> <line>', found '{}'", line)))?;
+            let address = usize::from_str_radix(address.trim_start_matches("0x"), 16)
+                .map_err(|_| error(format!("invalid address '{}'", address)))?;
+            let source_line = source_line
+                .trim()
+                .parse()
+                .map_err(|_| error(format!("invalid line '{}'", source_line.trim())))?;
+            map.insert(address, source_line);
+        }
+        Ok(map)
+    }
+
+    /// Returns `Ok(None)` when the ROM has no source map.
+    #[cfg(feature = "std")]
+    pub fn load(rom_path: &str) -> Result<Option<SourceMap>, SourceMapError> {
+        match fs::read_to_string(sidecar_path(rom_path)) {
+            Ok(text) => Ok(Some(SourceMap::parse(&text)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn save(&self, rom_path: &str) -> Result<(), SourceMapError> {
+        let mut text = format!("source {}\n", self.source);
+        for (address, line) in &self.lines {
+            text.push_str(&format!("{:#05x} {}\n", address, line));
+        }
+        fs::write(sidecar_path(rom_path), text)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{chip8::Chip8, display::Display, palette::Palette};
+use serde_json::json;
+use std::{
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+use tungstenite::{protocol::WebSocketConfig, Message, WebSocket};
+
+/// The page served to browsers, which connects back for the stream.
+const PAGE: &str = include_str!("../assets/spectate.html");
+/// How long a connection has to send its request before it's dropped.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Spectators that fall this far behind are dropped rather than queued for.
+const MAX_BACKLOG: usize = 1 << 20;
+
+/// What a spectator has been sent, so only changes go out.
+struct Spectator {
+    socket: WebSocket<TcpStream>,
+    display: Option<Display>,
+    palette: Option<String>,
+    beeping: bool,
+}
+
+impl Spectator {
+    /// Sends whatever has changed since last time.
+    fn update(&mut self, chip: &Chip8, palette: &str) -> tungstenite::Result<()> {
+        // Read anything sent, which answers pings and closes
+        loop {
+            match self.socket.read() {
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        if self.palette.as_deref() != Some(palette) {
+            let colors: Vec<_> = palette
+                .split(',')
+                .map(|color| format!("#{}", color))
+                .collect();
+            self.send(Message::text(json!({ "palette": colors }).to_string()))?;
+            self.palette = Some(palette.to_string());
+        }
+        if self.display.as_ref() != Some(&chip.display) {
+            let (width, height) = chip.display.size();
+            let mut frame = Vec::with_capacity(4 + width * height);
+            frame.extend_from_slice(&(width as u16).to_le_bytes());
+            frame.extend_from_slice(&(height as u16).to_le_bytes());
+            frame.extend_from_slice(chip.display.pixels());
+            self.send(Message::binary(frame))?;
+            self.display = Some(chip.display.clone());
+        }
+        if self.beeping != chip.is_sound_playing() {
+            self.beeping = chip.is_sound_playing();
+            self.send(Message::text(json!({ "beep": self.beeping }).to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Queues `message` when the connection can't take it yet, which is fine up
+    /// to `MAX_BACKLOG`.
+    fn send(&mut self, message: Message) -> tungstenite::Result<()> {
+        match self.socket.send(message) {
+            Err(tungstenite::Error::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            result => result,
+        }
+    }
+}
+
+/// Streams the display and the beeper to web pages, for watching a run live from
+/// other machines, e.g. while teaching. Browsers opening the port get a page that
+/// connects back over WebSocket, which is sent the palette and beeper changes as
+/// JSON and each changed frame as binary: width and height as little-endian
+/// u16s, then a byte per pixel as in `Display`.
+pub struct SpectateServer {
+    incoming: Receiver<WebSocket<TcpStream>>,
+    spectators: Vec<Spectator>,
+}
+
+impl SpectateServer {
+    pub fn listen(port: u16) -> io::Result<SpectateServer> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        println!("Streaming to spectators on http://localhost:{}", port);
+        let (sender, incoming) = mpsc::channel();
+        thread::spawn(move || serve(listener, sender));
+        Ok(SpectateServer {
+            incoming,
+            spectators: vec![],
+        })
+    }
+
+    pub fn update(&mut self, chip: &Chip8, palette: Palette) {
+        for socket in self.incoming.try_iter() {
+            self.spectators.push(Spectator {
+                socket,
+                display: None,
+                palette: None,
+                beeping: false,
+            });
+        }
+        if self.spectators.is_empty() {
+            return;
+        }
+        let palette = palette.to_hex();
+        self.spectators
+            .retain_mut(|spectator| match spectator.update(chip, &palette) {
+                Ok(()) => true,
+                Err(tungstenite::Error::ConnectionClosed) => false,
+                Err(e) => {
+                    eprintln!("Dropped spectator: {}", e);
+                    false
+                }
+            });
+    }
+}
+
+/// Accepts any number of connections, each handled on a thread of its own until
+/// it's either been sent the page or become a spectator.
+fn serve(listener: TcpListener, sender: Sender<WebSocket<TcpStream>>) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Spectator connection failed: {}", e);
+                continue;
+            }
+        };
+        let sender = sender.clone();
+        thread::spawn(move || {
+            if let Err(e) = accept(stream, &sender) {
+                eprintln!("Spectator connection failed: {}", e);
+            }
+        });
+    }
+}
+
+fn accept(mut stream: TcpStream, sender: &Sender<WebSocket<TcpStream>>) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut request = [0; 4096];
+    let mut len = 0;
+    // Wait for the whole header without consuming it, since the WebSocket
+    // handshake needs to read it too
+    for _ in 0..50 {
+        len = stream.peek(&mut request)?;
+        if len == 0 || len == request.len() || request[..len].windows(4).any(|w| w == b"\r\n\r\n") {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    let header = String::from_utf8_lossy(&request[..len]).to_ascii_lowercase();
+
+    if header.contains("upgrade: websocket") {
+        let config = WebSocketConfig::default().max_write_buffer_size(MAX_BACKLOG);
+        let socket =
+            tungstenite::accept_with_config(stream, Some(config)).map_err(|e| match e {
+                tungstenite::HandshakeError::Failure(e) => io::Error::other(e),
+                tungstenite::HandshakeError::Interrupted(_) => {
+                    io::Error::new(io::ErrorKind::TimedOut, "handshake timed out")
+                }
+            })?;
+        socket.get_ref().set_read_timeout(None)?;
+        socket.get_ref().set_nonblocking(true)?;
+        let _ = sender.send(socket);
+        return Ok(());
+    }
+
+    stream.read_exact(&mut request[..len])?;
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        PAGE.len()
+    )?;
+    stream.write_all(PAGE.as_bytes())?;
+    stream.shutdown(std::net::Shutdown::Write)
+}
@@ -0,0 +1,128 @@
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+};
+use core::fmt;
+#[cfg(feature = "std")]
+use std::{fs, path::PathBuf};
+
+/// Names for addresses in a ROM, from the assembler or a hand-written `.sym` file
+/// next to the ROM with one `<address> <name>` pair per line, e.g. `0x206 main_loop`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Symbols {
+    names: BTreeMap<usize, String>,
+}
+
+#[derive(Debug)]
+pub enum SymbolsError {
+    #[cfg(feature = "std")]
+    IO(std::io::Error),
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+impl fmt::Display for SymbolsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            SymbolsError::IO(e) => write!(f, "failed to access symbols file: {}", e),
+            SymbolsError::Parse { line, message } => {
+                write!(
+                    f,
+                    "failed to parse symbols file: line {}: {}",
+                    line, message
+                )
+            }
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl From<std::io::Error> for SymbolsError {
+    fn from(error: std::io::Error) -> Self {
+        SymbolsError::IO(error)
+    }
+}
+
+/// The sidecar file for a ROM, e.g. `roms/game.ch8.sym`.
+#[cfg(feature = "std")]
+pub fn sidecar_path(rom_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.sym", rom_path))
+}
+
+impl Symbols {
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn insert(&mut self, address: usize, name: &str) {
+        self.names.insert(address, name.to_string());
+    }
+
+    /// Adds every symbol from `other`, replacing names for the same address.
+    pub fn extend(&mut self, other: Symbols) {
+        self.names.extend(other.names);
+    }
+
+    pub fn name(&self, address: usize) -> Option<&str> {
+        self.names.get(&address).map(String::as_str)
+    }
+
+    pub fn address(&self, name: &str) -> Option<usize> {
+        self.names
+            .iter()
+            .find(|(_, n)| n.as_str() == name)
+            .map(|(&address, _)| address)
+    }
+
+    /// Resolves a symbol name or a `0x` hex address.
+    pub fn resolve(&self, text: &str) -> Option<usize> {
+        match text.strip_prefix("0x") {
+            Some(hex) => usize::from_str_radix(hex, 16).ok(),
+            None => self.address(text),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Symbols, SymbolsError> {
+        let mut symbols = Symbols::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split(['#', ';']).next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| SymbolsError::Parse {
+                line: index + 1,
+                message,
+            };
+            let (address, name) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| error(format!("expected '<address> <name>', found '{}'", line)))?;
+            let address = usize::from_str_radix(address.trim_start_matches("0x"), 16)
+                .map_err(|_| error(format!("invalid address '{}'", address)))?;
+            symbols.insert(address, name.trim());
+        }
+        Ok(symbols)
+    }
+
+    /// Returns `Ok(None)` when the ROM has no symbols file.
+    #[cfg(feature = "std")]
+    pub fn load(rom_path: &str) -> Result<Option<Symbols>, SymbolsError> {
+        match fs::read_to_string(sidecar_path(rom_path)) {
+            Ok(text) => Ok(Some(Symbols::parse(&text)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn save(&self, rom_path: &str) -> Result<(), SymbolsError> {
+        let text: String = self
+            .names
+            .iter()
+            .map(|(address, name)| format!("{:#05x} {}\n", address, name))
+            .collect();
+        fs::write(sidecar_path(rom_path), text)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+//! Execution traces, for checking flake against other emulators one instruction
+//! at a time. The format is plain text that's easy to produce from anything: one
+//! line per instruction, holding the state just before it ran.
+//!
+//! ```text
+//! # cycle pc opcode v0 v1 v2 v3 v4 v5 v6 v7 v8 v9 va vb vc vd ve vf i
+//! 0 200 00E0 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 0000
+//! ```
+//!
+//! The cycle counts instructions from 0 in decimal, and everything else is hex.
+//! Blank lines and lines starting with `#` are skipped.
+
+use crate::chip8::{Chip8, ExecutionState};
+use alloc::{format, string::String, vec::Vec};
+use core::fmt::{self, Write};
+
+pub const HEADER: &str = "# cycle pc opcode v0 v1 v2 v3 v4 v5 v6 v7 v8 v9 va vb vc vd ve vf i";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub cycle: u64,
+    pub pc: usize,
+    pub opcode: u16,
+    pub v: [u8; 16],
+    pub i: u16,
+}
+
+impl TraceEntry {
+    /// The instruction `chip` is about to run.
+    pub fn capture(chip: &Chip8) -> TraceEntry {
+        let memory = chip.memory();
+        let byte = |address: usize| memory.get(address).copied().unwrap_or(0);
+        TraceEntry {
+            cycle: chip.instruction_count(),
+            pc: chip.pc(),
+            opcode: u16::from_be_bytes([byte(chip.pc()), byte(chip.pc() + 1)]),
+            v: chip.v(),
+            i: chip.i(),
+        }
+    }
+
+    pub fn parse(line: &str) -> Result<TraceEntry, String> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 20 {
+            return Err(format!("expected 20 fields, found {}", fields.len()));
+        }
+        let hex = |index: usize| {
+            u32::from_str_radix(fields[index], 16)
+                .map_err(|_| format!("'{}' is not a hex number", fields[index]))
+        };
+        let cycle = fields[0]
+            .parse()
+            .map_err(|_| format!("'{}' is not a cycle number", fields[0]))?;
+        let mut v = [0; 16];
+        for (x, value) in v.iter_mut().enumerate() {
+            *value = u8::try_from(hex(3 + x)?).map_err(|_| format!("V{:X} is out of range", x))?;
+        }
+        Ok(TraceEntry {
+            cycle,
+            pc: hex(1)? as usize,
+            opcode: u16::try_from(hex(2)?).map_err(|_| String::from("opcode is out of range"))?,
+            v,
+            i: u16::try_from(hex(19)?).map_err(|_| String::from("I is out of range"))?,
+        })
+    }
+
+    /// Names of the fields that aren't the same in `other`.
+    pub fn differences(&self, other: &TraceEntry) -> Vec<String> {
+        let mut names = Vec::new();
+        if self.cycle != other.cycle {
+            names.push("cycle".into());
+        }
+        if self.pc != other.pc {
+            names.push("pc".into());
+        }
+        if self.opcode != other.opcode {
+            names.push("opcode".into());
+        }
+        for x in 0..16 {
+            if self.v[x] != other.v[x] {
+                names.push(format!("v{:x}", x));
+            }
+        }
+        if self.i != other.i {
+            names.push("i".into());
+        }
+        names
+    }
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:03X} {:04X}", self.cycle, self.pc, self.opcode)?;
+        for value in self.v {
+            write!(f, " {:02X}", value)?;
+        }
+        write!(f, " {:04X}", self.i)
+    }
+}
+
+#[derive(Debug)]
+pub struct TraceError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+pub fn parse(text: &str) -> Result<Vec<TraceEntry>, TraceError> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(index, line)| {
+            TraceEntry::parse(line).map_err(|message| TraceError {
+                line: index + 1,
+                message,
+            })
+        })
+        .collect()
+}
+
+pub fn to_text(entries: &[TraceEntry]) -> String {
+    let mut text = String::from(HEADER);
+    text.push('\n');
+    for entry in entries {
+        let _ = writeln!(text, "{}", entry);
+    }
+    text
+}
+
+/// Runs `chip` for up to `instructions` instructions, ticking the timers in
+/// between as usual, and stops early if it halts or waits for a key.
+pub fn record(chip: &mut Chip8, instructions: usize) -> Vec<TraceEntry> {
+    let mut entries = Vec::new();
+    while entries.len() < instructions
+        && !matches!(
+            chip.state(),
+            ExecutionState::Halted(_) | ExecutionState::WaitingForKey(_)
+        )
+    {
+        let entry = TraceEntry::capture(chip);
+        chip.step_debug();
+        if chip.instruction_count() != entry.cycle {
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+/// The first place two traces disagree. One side is missing when its trace
+/// ended first.
+#[derive(Debug)]
+pub struct Divergence {
+    pub index: usize,
+    pub expected: Option<TraceEntry>,
+    pub actual: Option<TraceEntry>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Traces diverge at instruction {}", self.index)?;
+        match self.expected {
+            Some(entry) => writeln!(f, "  expected: {}", entry)?,
+            None => writeln!(f, "  expected: (end of trace)")?,
+        }
+        match self.actual {
+            Some(entry) => write!(f, "  actual:   {}", entry)?,
+            None => write!(f, "  actual:   (end of trace)")?,
+        }
+        if let (Some(expected), Some(actual)) = (self.expected, self.actual) {
+            write!(
+                f,
+                "\n  differs in: {}",
+                expected.differences(&actual).join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub fn compare(expected: &[TraceEntry], actual: &[TraceEntry]) -> Option<Divergence> {
+    (0..expected.len().max(actual.len())).find_map(|index| {
+        let (e, a) = (expected.get(index), actual.get(index));
+        (e != a).then(|| Divergence {
+            index,
+            expected: e.copied(),
+            actual: a.copied(),
+        })
+    })
+}
@@ -0,0 +1,63 @@
+use crate::watcher::FileWatcher;
+use std::{fs, io};
+
+/// Display shaders loaded from files instead of the built-in ones, watched so
+/// they can be reloaded when edited. They get the same `pos`/`uv` attributes,
+/// `tex` and `highlight` textures and uniforms as the built-in shaders in
+/// `vert.glsl` and `frag.glsl`.
+pub struct UserShader {
+    vertex_path: Option<String>,
+    fragment_path: Option<String>,
+    watchers: Vec<FileWatcher>,
+}
+
+impl UserShader {
+    /// Returns `None` when neither shader is overridden.
+    pub fn new(vertex_path: Option<&str>, fragment_path: Option<&str>) -> Option<UserShader> {
+        if vertex_path.is_none() && fragment_path.is_none() {
+            return None;
+        }
+        let watchers = [vertex_path, fragment_path]
+            .into_iter()
+            .flatten()
+            .filter_map(|path| {
+                FileWatcher::new(path)
+                    .map_err(|e| eprintln!("Not watching {} for changes: {}", path, e))
+                    .ok()
+            })
+            .collect();
+        Some(UserShader {
+            vertex_path: vertex_path.map(str::to_string),
+            fragment_path: fragment_path.map(str::to_string),
+            watchers,
+        })
+    }
+
+    /// The vertex and fragment sources, using `default_vertex` or
+    /// `default_fragment` for whichever isn't overridden.
+    pub fn sources(
+        &self,
+        default_vertex: &str,
+        default_fragment: &str,
+    ) -> io::Result<(String, String)> {
+        let read = |path: &Option<String>, default: &str| match path {
+            Some(path) => fs::read_to_string(path)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e))),
+            None => Ok(default.to_string()),
+        };
+        Ok((
+            read(&self.vertex_path, default_vertex)?,
+            read(&self.fragment_path, default_fragment)?,
+        ))
+    }
+
+    /// Returns true once either file has changed and the changes have settled.
+    pub fn poll(&mut self) -> bool {
+        // Poll every watcher so none are left with stale events
+        let mut changed = false;
+        for watcher in &mut self.watchers {
+            changed |= watcher.poll();
+        }
+        changed
+    }
+}
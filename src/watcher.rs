@@ -0,0 +1,68 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    time::{Duration, Instant},
+};
+
+/// Editors and assemblers often write a file in several steps, so wait for changes
+/// to settle before reloading.
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// Watches a file, like the loaded ROM, for changes on disk.
+pub struct FileWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    file_name: PathBuf,
+    reload_at: Option<Instant>,
+}
+
+impl FileWatcher {
+    pub fn new(file_path: &str) -> notify::Result<FileWatcher> {
+        let path = Path::new(file_path);
+        let (tx, events) = channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        // Watch the directory rather than the file, so that replacing the file
+        // (as many editors do when saving) keeps being noticed.
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        watcher.watch(directory, RecursiveMode::NonRecursive)?;
+
+        Ok(FileWatcher {
+            _watcher: watcher,
+            events,
+            file_name: path.file_name().map(PathBuf::from).unwrap_or_default(),
+            reload_at: None,
+        })
+    }
+
+    /// Returns true once the file has changed and the changes have settled.
+    pub fn poll(&mut self) -> bool {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Ok(event) if self.concerns_file(&event) => {
+                    self.reload_at = Some(Instant::now() + SETTLE_TIME);
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Watch error: {}", e),
+            }
+        }
+        match self.reload_at {
+            Some(at) if Instant::now() >= at => {
+                self.reload_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn concerns_file(&self, event: &Event) -> bool {
+        matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+            && event
+                .paths
+                .iter()
+                .any(|path| path.file_name() == Some(self.file_name.as_os_str()))
+    }
+}
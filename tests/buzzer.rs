@@ -0,0 +1,91 @@
+//! The buzzer's waveforms, as the recordings and the libretro core play them.
+
+use flake::{
+    buzzer::{Buzzer, Tone, Volume, Waveform},
+    chip8::Chip8,
+    platform::VirtualClock,
+};
+use std::sync::Arc;
+
+/// A chip looping on the spot with the sound timer running.
+fn beeping() -> Chip8 {
+    let mut chip = Chip8::with_clock(Arc::new(VirtualClock::default()));
+    chip.trace = false;
+    chip.load_bytes(&[0x12, 0x00]).unwrap();
+    chip.set_st(10);
+    chip.step_debug();
+    assert!(chip.is_sound_playing());
+    chip
+}
+
+/// A cycle of `tone` at 16 samples a cycle.
+fn cycle(tone: Tone) -> Vec<f32> {
+    let chip = beeping();
+    let mut buzzer = Buzzer::new(tone);
+    (0..16)
+        .map(|_| buzzer.next_sample(&chip, tone.frequency * 16.))
+        .collect()
+}
+
+#[test]
+fn silent_without_the_sound_timer() {
+    let chip = Chip8::with_clock(Arc::new(VirtualClock::default()));
+    let mut buzzer = Buzzer::default();
+    assert!((0..100).all(|_| buzzer.next_sample(&chip, 44100.) == 0.));
+}
+
+#[test]
+fn duty_cycle() {
+    for (duty, high) in [(0.5, 8), (0.25, 4), (0.125, 2)] {
+        let samples = cycle(Tone {
+            duty,
+            ..Tone::default()
+        });
+        assert_eq!(samples.iter().filter(|&&s| s == 1.).count(), high);
+        assert_eq!(samples.iter().filter(|&&s| s == -1.).count(), 16 - high);
+    }
+}
+
+#[test]
+fn waveforms_stay_in_range() {
+    for waveform in [Waveform::Square, Waveform::Sine, Waveform::Triangle] {
+        let samples = cycle(Tone {
+            waveform,
+            duty: 0.3,
+            ..Tone::default()
+        });
+        assert!(
+            samples.iter().all(|s| (-1. ..=1.).contains(s)),
+            "{:?}",
+            waveform
+        );
+        assert!(samples.iter().any(|&s| s > 0.5) && samples.iter().any(|&s| s < -0.5));
+    }
+}
+
+#[test]
+fn volume() {
+    let chip = beeping();
+    let mut buzzer = Buzzer::default();
+    buzzer.volume.adjust(false);
+    assert_eq!(buzzer.next_sample(&chip, 44100.), 0.9);
+    assert_eq!(buzzer.volume.toggle_mute(), "Muted");
+    assert_eq!(buzzer.next_sample(&chip, 44100.), 0.);
+    assert_eq!(buzzer.volume.adjust(true), "Volume 100%");
+    assert_eq!(buzzer.volume.adjust(true), "Volume 100%");
+
+    let mut volume = Volume::default();
+    for _ in 0..20 {
+        volume.adjust(false);
+    }
+    assert_eq!(volume.gain(), 0.);
+}
+
+#[test]
+fn parsing() {
+    assert_eq!(Waveform::parse("triangle"), Ok(Waveform::Triangle));
+    assert!(Waveform::parse("sawtooth").is_err());
+    assert!(Tone::parse_duty("0.25").is_ok());
+    assert!(Tone::parse_duty("1").is_err());
+    assert!(Tone::parse_frequency("0").is_err());
+}
@@ -0,0 +1,41 @@
+//! Round trips through the decoder, the disassembler and the assembler, over
+//! every possible instruction word.
+
+use flake::{asm, chip8::OpCodes, disasm, symbols::Symbols};
+
+fn words() -> impl Iterator<Item = u16> {
+    0..=u16::MAX
+}
+
+/// Anything decoded as an instruction encodes back to the same word, so no bits
+/// are ignored by a mask that's too loose.
+#[test]
+fn decode_encode() {
+    for word in words() {
+        let op = OpCodes::try_from(word).unwrap();
+        if !matches!(op, OpCodes::Unkn(_)) {
+            assert_eq!(u16::from(op), word, "{:04X} decoded as {:?}", word, op);
+        }
+        assert_eq!(OpCodes::try_from(u16::from(op)), Ok(op), "{:?}", op);
+    }
+}
+
+/// Disassembling and assembling gives back the original word, unknown ones
+/// included since they're written as `.word`.
+#[test]
+fn disassemble_assemble() {
+    let symbols = Symbols::default();
+    for word in words() {
+        let op = OpCodes::try_from(word).unwrap();
+        let text = disasm::mnemonic(op, &symbols);
+        let assembled =
+            asm::assemble(&text).unwrap_or_else(|e| panic!("{:04X} as '{}': {}", word, text, e));
+        assert_eq!(
+            assembled.rom,
+            word.to_be_bytes(),
+            "{:04X} as '{}'",
+            word,
+            text
+        );
+    }
+}
@@ -0,0 +1,39 @@
+//! Checks the bundled ROMs against the golden traces in `tests/traces`, failing
+//! at the first instruction where they diverge. Each `<rom>.trace` is traced
+//! from `roms/<rom>` the way `flake trace` does it, so a trace exported from
+//! another emulator in the `trace` module's format can be dropped in to check
+//! flake against that emulator.
+
+use flake::{chip8::Chip8, platform::VirtualClock, trace};
+use std::{fs, path::Path, sync::Arc};
+
+fn check(path: &Path) {
+    let rom = path.file_stem().unwrap().to_str().unwrap();
+    let text = fs::read_to_string(path).unwrap();
+    let expected = trace::parse(&text).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+
+    let mut chip = Chip8::with_clock(Arc::new(VirtualClock::default()));
+    chip.trace = false;
+    chip.load_bytes(&fs::read(format!("roms/{}", rom)).unwrap())
+        .unwrap();
+    chip.seed_rng(0);
+    let actual = trace::record(&mut chip, expected.len());
+
+    if let Some(divergence) = trace::compare(&expected, &actual) {
+        panic!("{}: {}", rom, divergence);
+    }
+}
+
+#[test]
+fn bundled_roms() {
+    let mut paths: Vec<_> = fs::read_dir("tests/traces")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "trace"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+    for path in paths {
+        check(&path);
+    }
+}
@@ -0,0 +1,661 @@
+//! One or more cases for every instruction the interpreter knows, run a tick at a
+//! time against a fresh chip.
+
+use flake::{
+    chip8::{Chip8, Chip8Error, ExecutionState, OpCodes, DEFAULT_FONT_ADDRESS},
+    platform::VirtualClock,
+};
+use std::sync::Arc;
+
+/// A chip running `program` from 0x200.
+fn chip(program: &[u16]) -> Chip8 {
+    let mut chip = Chip8::with_clock(Arc::new(VirtualClock::default()));
+    chip.trace = false;
+    let rom: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    chip.load_bytes(&rom).unwrap();
+    chip
+}
+
+fn run(chip: &mut Chip8, instructions: usize) {
+    for _ in 0..instructions {
+        chip.tick();
+    }
+}
+
+fn pixel(chip: &Chip8, x: usize, y: usize) -> u8 {
+    chip.display.pixels()[y * chip.display.width() + x]
+}
+
+/// Runs a DXYN at PC that draws `row` as an 8x1 sprite at (x, y), from 0x300.
+fn draw_row(chip: &mut Chip8, x: u8, y: u8, row: u8) {
+    chip.write_memory(0x300, &[row]);
+    chip.set_i(0x300);
+    chip.set_v(0xA, x);
+    chip.set_v(0xB, y);
+    chip.write_memory(chip.pc(), &0xDAB1u16.to_be_bytes());
+    chip.tick();
+}
+
+/// An 8XYN instruction with V1 = `vx`, V2 = `vy` and VF = 0xAA beforehand.
+struct AluCase {
+    op: u16,
+    vx: u8,
+    vy: u8,
+    result: u8,
+    vf: u8,
+}
+
+#[test]
+fn arithmetic_and_logic() {
+    #[rustfmt::skip]
+    let cases = [
+        // LD, OR, AND and XOR leave VF alone
+        AluCase { op: 0x8120, vx: 0x12, vy: 0x34, result: 0x34, vf: 0xAA },
+        AluCase { op: 0x8121, vx: 0b1100, vy: 0b1010, result: 0b1110, vf: 0xAA },
+        AluCase { op: 0x8122, vx: 0b1100, vy: 0b1010, result: 0b1000, vf: 0xAA },
+        AluCase { op: 0x8123, vx: 0b1100, vy: 0b1010, result: 0b0110, vf: 0xAA },
+        // ADD sets VF to the carry
+        AluCase { op: 0x8124, vx: 0x10, vy: 0x20, result: 0x30, vf: 0 },
+        AluCase { op: 0x8124, vx: 0xFF, vy: 0x01, result: 0x00, vf: 1 },
+        AluCase { op: 0x8124, vx: 0xFF, vy: 0xFF, result: 0xFE, vf: 1 },
+        // SUB and SUBN set VF when there's no borrow, including when equal
+        AluCase { op: 0x8125, vx: 0x30, vy: 0x10, result: 0x20, vf: 1 },
+        AluCase { op: 0x8125, vx: 0x10, vy: 0x10, result: 0x00, vf: 1 },
+        AluCase { op: 0x8125, vx: 0x10, vy: 0x30, result: 0xE0, vf: 0 },
+        AluCase { op: 0x8127, vx: 0x10, vy: 0x30, result: 0x20, vf: 1 },
+        AluCase { op: 0x8127, vx: 0x30, vy: 0x30, result: 0x00, vf: 1 },
+        AluCase { op: 0x8127, vx: 0x30, vy: 0x10, result: 0xE0, vf: 0 },
+    ];
+    for case in cases {
+        let mut chip = chip(&[case.op]);
+        chip.set_v(1, case.vx);
+        chip.set_v(2, case.vy);
+        chip.set_v(0xF, 0xAA);
+        chip.tick();
+        let v = chip.v();
+        assert_eq!((v[1], v[0xF]), (case.result, case.vf), "{:04X}", case.op);
+        assert_eq!(v[2], case.vy, "{:04X} changed VY", case.op);
+    }
+}
+
+/// When VF is the destination, the flag is written after the result and wins.
+#[test]
+fn flag_overwrites_result_in_vf() {
+    // (instruction, VF, V1, VF afterwards)
+    let cases = [
+        (0x8F14, 0xFF, 0x01, 1),
+        (0x8F14, 0x01, 0x01, 0),
+        (0x8F15, 0x05, 0x03, 1),
+        (0x8F15, 0x03, 0x05, 0),
+        (0x8F17, 0x03, 0x05, 1),
+        (0x8F17, 0x05, 0x03, 0),
+    ];
+    for (op, vf, v1, expected) in cases {
+        let mut chip = chip(&[op]);
+        chip.set_v(0xF, vf);
+        chip.set_v(1, v1);
+        chip.tick();
+        assert_eq!(chip.v()[0xF], expected, "{:04X} with VF={:#04x}", op, vf);
+    }
+}
+
+/// VF as the source is read before the flag is written.
+#[test]
+fn vf_as_source() {
+    let mut chip = chip(&[0x81F4, 0x82F5]);
+    chip.set_v(1, 0x10);
+    chip.set_v(2, 0x50);
+    chip.set_v(0xF, 0x20);
+    chip.tick();
+    assert_eq!(chip.v()[1], 0x30);
+    assert_eq!(chip.v()[0xF], 0);
+    chip.set_v(0xF, 0x20);
+    chip.tick();
+    assert_eq!(chip.v()[2], 0x30);
+    assert_eq!(chip.v()[0xF], 1);
+}
+
+#[test]
+fn shifts() {
+    // (instruction, shift_uses_vy, V1, V2, V1 afterwards, VF afterwards)
+    let cases = [
+        (0x8126, false, 0b0000_0101, 0xFF, 0b0000_0010, 1),
+        (0x8126, false, 0b0000_0100, 0xFF, 0b0000_0010, 0),
+        (0x8126, true, 0xFF, 0b0000_0101, 0b0000_0010, 1),
+        (0x812E, false, 0b1000_0001, 0x00, 0b0000_0010, 1),
+        (0x812E, false, 0b0100_0000, 0x00, 0b1000_0000, 0),
+        (0x812E, true, 0x00, 0b1000_0001, 0b0000_0010, 1),
+    ];
+    for (op, shift_uses_vy, v1, v2, result, vf) in cases {
+        let mut chip = chip(&[op]);
+        chip.quirks.shift_uses_vy = shift_uses_vy;
+        chip.set_v(1, v1);
+        chip.set_v(2, v2);
+        chip.tick();
+        assert_eq!(
+            (chip.v()[1], chip.v()[0xF]),
+            (result, vf),
+            "{:04X} with shift_uses_vy={}",
+            op,
+            shift_uses_vy
+        );
+    }
+
+    // Shifting VF itself leaves just the flag
+    for (op, vf, expected) in [(0x8FF6, 0b11, 1), (0x8FFE, 0b0111_1111, 0)] {
+        let mut chip = chip(&[op]);
+        chip.quirks.shift_uses_vy = false;
+        chip.set_v(0xF, vf);
+        chip.tick();
+        assert_eq!(chip.v()[0xF], expected, "{:04X}", op);
+    }
+}
+
+#[test]
+fn load_and_add_immediate() {
+    let mut chip = chip(&[0x6A42, 0x7A10, 0x7AFF, 0x7F01]);
+    chip.set_v(0xF, 0x07);
+    run(&mut chip, 2);
+    assert_eq!(chip.v()[0xA], 0x52);
+    // 7XNN wraps without touching VF, even when VF is X
+    chip.tick();
+    assert_eq!(chip.v()[0xA], 0x51);
+    assert_eq!(chip.v()[0xF], 0x07);
+    chip.tick();
+    assert_eq!(chip.v()[0xF], 0x08);
+}
+
+#[test]
+fn skips() {
+    // (instruction, V1, V2, whether it skips)
+    let cases = [
+        (0x3142, 0x42, 0, true),
+        (0x3142, 0x43, 0, false),
+        (0x4142, 0x42, 0, false),
+        (0x4142, 0x43, 0, true),
+        (0x5120, 0x07, 0x07, true),
+        (0x5120, 0x07, 0x08, false),
+        (0x9120, 0x07, 0x07, false),
+        (0x9120, 0x07, 0x08, true),
+    ];
+    for (op, v1, v2, skips) in cases {
+        let mut chip = chip(&[op]);
+        chip.set_v(1, v1);
+        chip.set_v(2, v2);
+        chip.tick();
+        let expected = if skips { 0x204 } else { 0x202 };
+        assert_eq!(chip.pc(), expected, "{:04X} with V1={} V2={}", op, v1, v2);
+    }
+
+    // Skipping the last instruction in memory halts at the skip
+    let mut chip = chip(&[0x00E0]);
+    chip.write_memory(0xFFC, &[0x30, 0x00, 0x00, 0xE0]);
+    chip.set_pc(0xFFC);
+    chip.tick();
+    assert_eq!(
+        chip.state(),
+        ExecutionState::Halted(Chip8Error::JumpOutOfBounds {
+            addr: 0xFFC,
+            target: 0x1000
+        })
+    );
+}
+
+#[test]
+fn key_skips() {
+    // Only VX's low nibble picks the key
+    for (op, v1, held, skips) in [
+        (0xE19E, 0xC, true, true),
+        (0xE19E, 0xC, false, false),
+        (0xE1A1, 0xC, true, false),
+        (0xE1A1, 0xC, false, true),
+        (0xE19E, 0xFC, true, true),
+        (0xE1A1, 0xFC, true, false),
+    ] {
+        let mut chip = chip(&[op]);
+        chip.set_v(1, v1);
+        chip.keys[0xC] = held;
+        chip.tick();
+        let expected = if skips { 0x204 } else { 0x202 };
+        assert_eq!(
+            chip.pc(),
+            expected,
+            "{:04X} with V1={:02X} and the key held={}",
+            op,
+            v1,
+            held
+        );
+    }
+}
+
+#[test]
+fn jumps_calls_and_returns() {
+    let mut chip = chip(&[0x1206, 0x0000, 0x00EE, 0x2204]);
+    chip.tick();
+    assert_eq!(chip.pc(), 0x206);
+    chip.tick();
+    assert_eq!(chip.pc(), 0x204);
+    assert_eq!(chip.stack(), [0x208]);
+    chip.tick();
+    assert_eq!(chip.pc(), 0x208);
+    assert!(chip.stack().is_empty());
+}
+
+#[test]
+fn jump_with_offset() {
+    let mut chip = chip(&[0xB310]);
+    chip.quirks.jump_uses_vx = false;
+    chip.set_v(0, 0x04);
+    chip.set_v(3, 0x20);
+    chip.tick();
+    assert_eq!(chip.pc(), 0x314);
+
+    // BXNN adds VX, X being the top nibble of the address
+    let mut chip = self::chip(&[0xB310]);
+    chip.quirks.jump_uses_vx = true;
+    chip.set_v(0, 0x04);
+    chip.set_v(3, 0x20);
+    chip.tick();
+    assert_eq!(chip.pc(), 0x330);
+
+    // The jump halts where it is rather than leaving the PC past the end of memory
+    let mut chip = self::chip(&[0x60FF, 0xBFFF]);
+    chip.quirks.jump_uses_vx = false;
+    run(&mut chip, 3);
+    assert_eq!(
+        chip.state(),
+        ExecutionState::Halted(Chip8Error::JumpOutOfBounds {
+            addr: 0x202,
+            target: 0x10FE
+        })
+    );
+}
+
+#[test]
+fn fetch_past_end_of_memory_halts() {
+    // Only the first byte of the instruction at 0xFFF is in memory
+    let mut chip = chip(&[0x1FFF]);
+    run(&mut chip, 2);
+    assert_eq!(
+        chip.state(),
+        ExecutionState::Halted(Chip8Error::PcOutOfBounds { addr: 0xFFF })
+    );
+}
+
+#[test]
+fn stack_limits() {
+    let mut chip = chip(&[0x2202, 0x2204, 0x0000]);
+    chip.quirks.stack_depth = 1;
+    run(&mut chip, 2);
+    assert_eq!(
+        chip.state(),
+        ExecutionState::Halted(Chip8Error::StackOverflow {
+            addr: 0x202,
+            depth: 1
+        })
+    );
+    assert_eq!(chip.pc(), 0x202);
+
+    let mut chip = self::chip(&[0x00EE]);
+    chip.tick();
+    assert_eq!(
+        chip.state(),
+        ExecutionState::Halted(Chip8Error::StackUnderflow { addr: 0x200 })
+    );
+}
+
+#[test]
+fn unknown_opcodes_halt() {
+    for op in [0x0123, 0x8008, 0xE000, 0xF0FF, 0xF401] {
+        let mut chip = chip(&[op]);
+        chip.tick();
+        assert_eq!(
+            chip.state(),
+            ExecutionState::Halted(Chip8Error::UnknownOpcode {
+                addr: 0x200,
+                opcode: op
+            }),
+            "{:04X}",
+            op
+        );
+        assert_eq!(chip.pc(), 0x200);
+        // A halted chip stays put
+        chip.tick();
+        assert_eq!(chip.pc(), 0x200);
+    }
+}
+
+#[test]
+fn index_register() {
+    let mut chip = chip(&[0xA123, 0xF11E, 0xF11E]);
+    chip.set_v(1, 0x10);
+    chip.set_v(0xF, 0x07);
+    run(&mut chip, 2);
+    assert_eq!(chip.i(), 0x133);
+    // FX1E leaves VF alone, even past the end of memory
+    chip.set_i(0xFFFF);
+    chip.tick();
+    assert_eq!(chip.i(), 0x000F);
+    assert_eq!(chip.v()[0xF], 0x07);
+}
+
+#[test]
+fn random_numbers_are_masked() {
+    let mut chip = chip(&[0xC100, 0xC20F]);
+    chip.set_v(1, 0xFF);
+    run(&mut chip, 2);
+    assert_eq!(chip.v()[1], 0);
+    assert!(chip.v()[2] <= 0x0F);
+
+    // The same seed draws the same numbers
+    let draws = |seed| {
+        let mut chip = self::chip(&[0xC1FF, 0xC2FF, 0xC3FF]);
+        chip.seed_rng(seed);
+        run(&mut chip, 3);
+        chip.v()
+    };
+    assert_eq!(draws(7), draws(7));
+}
+
+#[test]
+fn timers() {
+    let mut chip = chip(&[0xF115, 0xF218, 0xF307]);
+    chip.set_v(1, 0x30);
+    chip.set_v(2, 0x40);
+    run(&mut chip, 2);
+    assert_eq!((chip.dt(), chip.st()), (0x30, 0x40));
+    chip.set_dt(0x12);
+    chip.tick();
+    assert_eq!(chip.v()[3], 0x12);
+}
+
+#[test]
+fn pitch() {
+    let mut chip = chip(&[0xF13A]);
+    assert_eq!(chip.pitch_ratio(), 1.);
+    chip.set_v(1, 112);
+    chip.tick();
+    assert_eq!(chip.pitch_ratio(), 2.);
+}
+
+#[test]
+fn wait_for_key_press() {
+    let mut chip = chip(&[0xF50A, 0x0000]);
+    chip.quirks.key_wait_release = false;
+    run(&mut chip, 3);
+    assert_eq!(chip.state(), ExecutionState::WaitingForKey(5));
+    assert_eq!(chip.pc(), 0x202);
+    chip.keys[0xB] = true;
+    chip.tick();
+    assert_eq!(chip.state(), ExecutionState::Running);
+    assert_eq!(chip.v()[5], 0xB);
+    assert_eq!(chip.last_key_wait(), Some((5, 0xB)));
+}
+
+#[test]
+fn wait_for_key_release() {
+    let mut chip = chip(&[0xF50A]);
+    chip.quirks.key_wait_release = true;
+    chip.tick();
+    chip.keys[0x3] = true;
+    run(&mut chip, 2);
+    assert_eq!(chip.state(), ExecutionState::WaitingForKey(5));
+    chip.keys[0x3] = false;
+    chip.tick();
+    assert_eq!(chip.state(), ExecutionState::Running);
+    assert_eq!(chip.v()[5], 0x3);
+}
+
+#[test]
+fn store_and_load_registers() {
+    let mut chip = chip(&[0xF255, 0xF265]);
+    chip.set_i(0x400);
+    for x in 0..4 {
+        chip.set_v(x, 0x10 + x as u8);
+    }
+    chip.tick();
+    assert_eq!(chip.memory()[0x400..0x404], [0x10, 0x11, 0x12, 0x00]);
+    assert_eq!(chip.i(), 0x400);
+
+    chip.write_memory(0x400, &[0xA0, 0xA1, 0xA2, 0xA3]);
+    chip.tick();
+    assert_eq!(chip.v()[..4], [0xA0, 0xA1, 0xA2, 0x13]);
+    assert_eq!(chip.i(), 0x400);
+}
+
+#[test]
+fn memory_bounds() {
+    let mut chip = chip(&[0xF155]);
+    chip.set_i(0xFFF);
+    chip.tick();
+    assert_eq!(
+        chip.state(),
+        ExecutionState::Halted(Chip8Error::MemoryOutOfBounds {
+            addr: 0x200,
+            i: 0xFFF,
+            len: 2
+        })
+    );
+
+    let mut chip = self::chip(&[0xF155]);
+    chip.quirks.wrap_memory = true;
+    chip.set_i(0xFFF);
+    chip.set_v(0, 0x11);
+    chip.set_v(1, 0x22);
+    chip.tick();
+    assert_eq!(chip.state(), ExecutionState::Running);
+    assert_eq!((chip.memory()[0xFFF], chip.memory()[0]), (0x11, 0x22));
+}
+
+#[test]
+fn binary_coded_decimal() {
+    for (value, digits) in [
+        (0, [0, 0, 0]),
+        (7, [0, 0, 7]),
+        (42, [0, 4, 2]),
+        (255, [2, 5, 5]),
+    ] {
+        let mut chip = chip(&[0xF133]);
+        chip.set_i(0x400);
+        chip.set_v(1, value);
+        chip.tick();
+        assert_eq!(chip.memory()[0x400..0x403], digits, "{}", value);
+    }
+}
+
+#[test]
+fn font_characters() {
+    let mut chip = chip(&[0xF129, 0xF130]);
+    chip.set_v(1, 0x7);
+    chip.tick();
+    assert_eq!(chip.i() as usize, DEFAULT_FONT_ADDRESS + 7 * 5);
+    // The big font follows the 16 small characters
+    chip.tick();
+    assert_eq!(chip.i() as usize, DEFAULT_FONT_ADDRESS + 16 * 5 + 7 * 10);
+
+    // Only the low nibble counts, unless the font is strict
+    let mut chip = self::chip(&[0xF129]);
+    chip.set_v(1, 0x17);
+    chip.tick();
+    assert_eq!(chip.i() as usize, DEFAULT_FONT_ADDRESS + 7 * 5);
+
+    for (op, digit) in [(0xF129, 0x10), (0xF130, 0x0A)] {
+        let mut chip = self::chip(&[op]);
+        chip.quirks.strict_font = true;
+        chip.set_v(1, digit);
+        chip.tick();
+        assert_eq!(
+            chip.state(),
+            ExecutionState::Halted(Chip8Error::InvalidFontDigit { addr: 0x200, digit }),
+            "{:04X}",
+            op
+        );
+    }
+}
+
+#[test]
+fn flag_registers() {
+    let mut chip = chip(&[0xF275, 0x6000, 0x6100, 0x6200, 0xF185]);
+    for x in 0..3 {
+        chip.set_v(x, 0xF0 + x as u8);
+    }
+    run(&mut chip, 5);
+    assert_eq!(chip.v()[..3], [0xF0, 0xF1, 0x00]);
+}
+
+#[test]
+fn draw_sprite() {
+    let mut chip = chip(&[0x0000]);
+    chip.set_v(0xF, 0x55);
+    draw_row(&mut chip, 2, 3, 0b1010_0000);
+    assert_eq!(
+        (pixel(&chip, 2, 3), pixel(&chip, 3, 3), pixel(&chip, 4, 3)),
+        (1, 0, 1)
+    );
+    assert_eq!(chip.v()[0xF], 0);
+    let draw = chip.last_draw().unwrap();
+    assert_eq!(
+        (draw.x, draw.y, draw.height, draw.collided),
+        (2, 3, 1, false)
+    );
+
+    // Drawing over a lit pixel turns it off and sets VF
+    draw_row(&mut chip, 2, 3, 0b1000_0000);
+    assert_eq!(pixel(&chip, 2, 3), 0);
+    assert_eq!(chip.v()[0xF], 1);
+    assert_eq!(chip.collided_pixels(), [(2, 3)]);
+}
+
+#[test]
+fn draw_position_wraps_and_sprites_clip() {
+    // The start wraps onto the display, then pixels past the edge are clipped
+    let mut chip = chip(&[0x0000]);
+    chip.quirks.wrap_sprites = false;
+    draw_row(&mut chip, 64 + 60, 32 + 1, 0xFF);
+    assert_eq!((pixel(&chip, 60, 1), pixel(&chip, 63, 1)), (1, 1));
+    assert_eq!(pixel(&chip, 0, 1), 0);
+
+    let mut chip = self::chip(&[0x0000]);
+    chip.quirks.wrap_sprites = true;
+    draw_row(&mut chip, 60, 1, 0xFF);
+    assert_eq!(
+        (pixel(&chip, 63, 1), pixel(&chip, 0, 1), pixel(&chip, 3, 1)),
+        (1, 1, 1)
+    );
+}
+
+#[test]
+fn large_sprites() {
+    let mut chip = chip(&[0x00FF, 0xD120]);
+    chip.write_memory(0x300, &[0xFF; 32]);
+    chip.set_i(0x300);
+    run(&mut chip, 2);
+    assert_eq!(chip.display.size(), (128, 64));
+    assert_eq!(
+        (
+            pixel(&chip, 15, 15),
+            pixel(&chip, 16, 15),
+            pixel(&chip, 15, 16)
+        ),
+        (1, 0, 0)
+    );
+    assert_eq!(chip.last_draw().unwrap().height, 16);
+}
+
+#[test]
+fn clear_and_resolution() {
+    let mut chip = chip(&[0x00E0, 0x00FF, 0x00FE]);
+    draw_row(&mut chip, 0, 0, 0xFF);
+    chip.set_pc(0x200);
+    chip.tick();
+    assert!(chip.display.pixels().iter().all(|&p| p == 0));
+    chip.tick();
+    assert_eq!(chip.display.size(), (128, 64));
+    chip.tick();
+    assert_eq!(chip.display.size(), (64, 32));
+}
+
+#[test]
+fn scrolling() {
+    // (instruction, where the pixel at (8, 8) ends up)
+    for (op, (x, y)) in [(0x00C3, (8, 11)), (0x00FB, (12, 8)), (0x00FC, (4, 8))] {
+        let mut chip = chip(&[0x0000, op]);
+        draw_row(&mut chip, 8, 8, 0b1000_0000);
+        chip.tick();
+        assert_eq!(pixel(&chip, 8, 8), 0, "{:04X}", op);
+        assert_eq!(pixel(&chip, x, y), 1, "{:04X}", op);
+    }
+}
+
+#[test]
+fn planes() {
+    let mut chip = chip(&[0x0000, 0xF201, 0x0000, 0xF301]);
+    draw_row(&mut chip, 0, 0, 0b1000_0000);
+    chip.tick();
+    assert_eq!(chip.display.selected_planes, 2);
+    draw_row(&mut chip, 0, 0, 0b1000_0000);
+    assert_eq!(pixel(&chip, 0, 0), 0b11);
+
+    // With both planes selected the sprite has a row for each
+    chip.tick();
+    chip.write_memory(0x300, &[0b1000_0000, 0b0100_0000]);
+    chip.set_i(0x300);
+    chip.write_memory(chip.pc(), &0xDAB1u16.to_be_bytes());
+    chip.set_v(0xA, 4);
+    chip.set_v(0xB, 0);
+    chip.tick();
+    assert_eq!((pixel(&chip, 4, 0), pixel(&chip, 5, 0)), (0b01, 0b10));
+}
+
+/// Every instruction decodes to the variant these tests exercise it through.
+#[test]
+fn decoding() {
+    #[rustfmt::skip]
+    let cases = [
+        (0x0123, OpCodes::Unkn(0x0123)), (0x00E0, OpCodes::Cls), (0x00EE, OpCodes::Ret),
+        (0x00C3, OpCodes::ScdN(3)), (0x00FB, OpCodes::Scr), (0x00FC, OpCodes::Scl),
+        (0x00FE, OpCodes::Low), (0x00FF, OpCodes::High), (0x1234, OpCodes::Jmp(0x234)),
+        (0x2345, OpCodes::Call(0x345)), (0x3142, OpCodes::SeVxNn(1, 0x42)),
+        (0x4142, OpCodes::SneVxNn(1, 0x42)), (0x5120, OpCodes::SeVxVy(1, 2)),
+        (0x6A42, OpCodes::LdVxNn(0xA, 0x42)), (0x7A10, OpCodes::AddVxNn(0xA, 0x10)),
+        (0x8120, OpCodes::LdVxVy(1, 2)), (0x8121, OpCodes::OrVxVy(1, 2)),
+        (0x8122, OpCodes::AndVxVy(1, 2)), (0x8123, OpCodes::XorVxVy(1, 2)),
+        (0x8124, OpCodes::AddVxVy(1, 2)), (0x8125, OpCodes::SubVxVy(1, 2)),
+        (0x8126, OpCodes::ShrVxVy(1, 2)), (0x8127, OpCodes::SubnVxVy(1, 2)),
+        (0x812E, OpCodes::ShlVxVy(1, 2)), (0x9120, OpCodes::SneVxVy(1, 2)),
+        (0xA123, OpCodes::LdINn(0x123)), (0xB310, OpCodes::JmpV0Nnn(0x310)),
+        (0xC20F, OpCodes::RndVxNn(2, 0x0F)), (0xDAB1, OpCodes::DrawVxVyN(0xA, 0xB, 1)),
+        (0xE19E, OpCodes::SkpVx(1)), (0xE1A1, OpCodes::SknpVx(1)),
+        (0xF307, OpCodes::LdVxDt(3)), (0xF50A, OpCodes::LdVxK(5)),
+        (0xF115, OpCodes::LdDtVx(1)), (0xF218, OpCodes::LdStVx(2)),
+        (0xF11E, OpCodes::AddIVx(1)), (0xF129, OpCodes::LdFVx(1)),
+        (0xF130, OpCodes::LdHfVx(1)), (0xF133, OpCodes::LdBVx(1)),
+        (0xF255, OpCodes::LdIVx(2)), (0xF265, OpCodes::LdVxI(2)),
+        (0xF201, OpCodes::PlaneN(2)), (0xF275, OpCodes::LdRVx(2)),
+        (0xF185, OpCodes::LdVxR(1)), (0xF13A, OpCodes::LdPitchVx(1)),
+    ];
+    for (word, op) in cases {
+        assert_eq!(OpCodes::try_from(word), Ok(op), "{:04X}", word);
+        assert_eq!(u16::from(op), word, "{:?}", op);
+    }
+}
+
+/// Registers an instruction isn't said to write are left alone, so breakpoints on
+/// writes to a register can't miss any.
+#[test]
+fn writes_register_covers_every_write() {
+    for word in 0..=u16::MAX {
+        let mut chip = chip(&[word]);
+        // Distinct, and all valid keys for SKP and SKNP
+        for x in 0..16 {
+            chip.set_v(x, (x as u8 * 7 + 3) & 0xF);
+        }
+        chip.set_i(0x300);
+        let before = chip.v();
+        chip.tick();
+        let op = OpCodes::try_from(word).unwrap();
+        for x in (0..16).filter(|&x| !op.writes_register(x)) {
+            assert_eq!(chip.v()[x], before[x], "{:04X} changed V{:X}", word, x);
+        }
+    }
+}
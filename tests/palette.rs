@@ -0,0 +1,51 @@
+//! The built-in palettes have to keep every pair of colors apart, as seen
+//! normally and with each simulated color vision deficiency.
+
+#![cfg(feature = "std")]
+
+use flake::palette::{simulate, Deficiency, Palette, PRESETS};
+use glam::Vec4;
+
+/// The least distance in sRGB between any two of a palette's colors, out of a most
+/// of about 1.7 between black and white.
+const MIN_DISTANCE: f32 = 0.3;
+
+fn closest_pair(colors: [Vec4; 4]) -> f32 {
+    let mut closest = f32::MAX;
+    for a in 0..4 {
+        for b in a + 1..4 {
+            closest = closest.min(colors[a].truncate().distance(colors[b].truncate()));
+        }
+    }
+    closest
+}
+
+#[test]
+fn presets_stay_distinguishable() {
+    for (name, palette) in PRESETS {
+        assert!(closest_pair(palette.0) > MIN_DISTANCE, "{}", name);
+        for deficiency in Deficiency::ALL {
+            let seen = palette.0.map(|color| simulate(color, deficiency));
+            let closest = closest_pair(seen);
+            assert!(
+                closest > MIN_DISTANCE,
+                "{} with {:?}: {}",
+                name,
+                deficiency,
+                closest
+            );
+        }
+    }
+}
+
+#[test]
+fn presets_parse_by_name() {
+    for (name, palette) in PRESETS {
+        assert_eq!(Palette::parse(name), Ok(palette));
+        assert_eq!(palette.name(), Some(name));
+    }
+    assert_eq!(Palette::default().next_preset().0, "okabe-ito");
+    let custom = Palette::parse("000000,ffffff,ff0000,0000ff").unwrap();
+    assert_eq!(custom.name(), None);
+    assert_eq!(custom.next_preset().0, "default");
+}
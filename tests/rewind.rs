@@ -0,0 +1,39 @@
+//! Rewinding to an earlier snapshot, as the debugger's history does, and then
+//! running on has to replay the original timeline, however long was spent
+//! rewinding.
+
+use flake::{
+    chip8::Chip8,
+    platform::{Clock, VirtualClock},
+};
+use std::{sync::Arc, time::Duration};
+
+const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+fn run_frames(chip: &mut Chip8, clock: &VirtualClock, frames: usize) {
+    for _ in 0..frames {
+        clock.advance(FRAME);
+        chip.step_until(clock.now());
+    }
+}
+
+#[test]
+fn rewinding_replays_the_original_timeline() {
+    let clock = Arc::new(VirtualClock::default());
+    let mut chip = Chip8::with_clock(clock.clone());
+    chip.trace = false;
+    chip.load_bytes(include_bytes!("../roms/test_opcode.ch8"))
+        .unwrap();
+    run_frames(&mut chip, &clock, 5);
+    let snapshot = chip.clone();
+    run_frames(&mut chip, &clock, 30);
+    let expected = chip.save_state();
+
+    // Time spent scrubbing back through the history
+    clock.advance(Duration::from_secs(10));
+    chip.rewind_to(&snapshot);
+    assert_eq!(chip.instruction_count(), snapshot.instruction_count());
+    assert_eq!(chip.tick_count(), snapshot.tick_count());
+    run_frames(&mut chip, &clock, 30);
+    assert_eq!(chip.save_state(), expected);
+}
@@ -0,0 +1,115 @@
+//! Save states through the on-disk container: round trips, and the mismatches
+//! that have to be refused rather than loaded into the chip.
+
+use flake::{
+    chip8::Chip8,
+    platform::VirtualClock,
+    save_state::{self, SaveStateError, VERSION},
+};
+use std::sync::Arc;
+
+const BREAKOUT: &[u8] = include_bytes!("../roms/breakout.ch8");
+const IBM: &[u8] = include_bytes!("../roms/ibm.ch8");
+
+fn new_chip() -> Chip8 {
+    Chip8::with_clock(Arc::new(VirtualClock::default()))
+}
+
+/// Breakout a few thousand steps in, stopped at its first key wait.
+fn running_chip() -> Chip8 {
+    let mut chip = new_chip();
+    chip.trace = false;
+    chip.load_bytes(BREAKOUT).unwrap();
+    for _ in 0..3000 {
+        chip.step_debug();
+    }
+    chip
+}
+
+#[test]
+fn round_trip() {
+    let chip = running_chip();
+    let bytes = save_state::encode(&chip.save_state(), BREAKOUT);
+    assert!(bytes.starts_with(&save_state::MAGIC));
+
+    let mut restored = new_chip();
+    restored.load_bytes(BREAKOUT).unwrap();
+    let state = save_state::decode(&bytes, BREAKOUT).unwrap();
+    restored.restore_state(&state).unwrap();
+    assert_eq!(restored.save_state(), chip.save_state());
+    assert_eq!(restored.display, chip.display);
+    assert_eq!(restored.is_waiting_for_key(), chip.is_waiting_for_key());
+}
+
+/// States from before the header, which also lack the schedule added in version 2
+/// and the tick count added in version 3.
+#[test]
+fn migrates_bare_json() {
+    let chip = running_chip();
+    let mut json = serde_json::to_value(chip.save_state()).unwrap();
+    let fields = json.as_object_mut().unwrap();
+    fields.remove("next_tick");
+    fields.remove("next_timers_tick");
+    fields.remove("tick_count");
+    let state = save_state::decode(&serde_json::to_vec(&json).unwrap(), BREAKOUT).unwrap();
+
+    let mut expected = chip.save_state();
+    expected.next_tick = Default::default();
+    expected.next_timers_tick = Default::default();
+    expected.tick_count = expected.instruction_count;
+    assert_eq!(state, expected);
+}
+
+#[test]
+fn refuses_other_roms() {
+    let bytes = save_state::encode(&running_chip().save_state(), BREAKOUT);
+    assert!(matches!(
+        save_state::decode(&bytes, IBM),
+        Err(SaveStateError::WrongRom)
+    ));
+}
+
+#[test]
+fn refuses_newer_versions() {
+    let mut bytes = save_state::encode(&running_chip().save_state(), BREAKOUT);
+    bytes[8..10].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    assert!(matches!(
+        save_state::decode(&bytes, BREAKOUT),
+        Err(SaveStateError::UnsupportedVersion(version)) if version == VERSION + 1
+    ));
+}
+
+#[test]
+fn refuses_other_files() {
+    for bytes in [&b""[..], b"FLAKESAV", BREAKOUT] {
+        assert!(matches!(
+            save_state::decode(bytes, BREAKOUT),
+            Err(SaveStateError::NotASaveState)
+        ));
+    }
+}
+
+/// A state that decodes but doesn't fit the chip, e.g. from a build with more
+/// memory, leaves the chip as it was.
+#[test]
+fn refuses_states_that_dont_fit() {
+    let mut chip = running_chip();
+    let before = chip.save_state();
+
+    let mut state = before.clone();
+    state.memory.resize(0x10000, 0);
+    assert!(matches!(
+        chip.restore_state(&state),
+        Err(SaveStateError::Invalid(_))
+    ));
+
+    let mut state = before.clone();
+    state.pc = 0x1000;
+    assert!(chip.restore_state(&state).is_err());
+
+    let mut state = before.clone();
+    state.instructions_per_frame = 0;
+    assert!(chip.restore_state(&state).is_err());
+
+    assert_eq!(chip.save_state(), before);
+}